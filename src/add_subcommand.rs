@@ -1,28 +1,60 @@
 use std::{
 	env,
 	error::Error,
-	ffi::{OsStr, OsString},
-	fs::{self, File},
-	io::{self, Write as _},
-	os::unix::ffi::OsStringExt as _,
+	ffi::OsString,
+	fs, io,
 	path::{Path, PathBuf},
-	process::{self, Command},
+	process::ExitCode,
 };
 
-use crate::{dirs, AddArgs};
+use crate::{
+	atomic_file,
+	detect::Language,
+	dirs::Dirs,
+	git,
+	lock::{FileLock, DATA_LOCK},
+	parse::{self, toml_util::toml_string_escape},
+	rename_subcommand,
+	sandbox::{Command as SandboxCommand, LogConfig},
+	AddArgs, AddTarget, EditorPreset,
+};
 
 type ModResult<T> = Result<T, Box<dyn Error>>;
+pub fn run(args: AddArgs, dirs: &dyn Dirs) -> ModResult<()> {
+	if args.recursive {
+		return run_recursive(&args, dirs);
+	}
 
-pub fn run(args: AddArgs) -> ModResult<()> {
-	let project_path = args.project_path.canonicalize().map_err(|err| {
+	// NOTE: without a path, the root of the enclosing git worktree is added
+	//       and named after the remote of the repository
+	let (project_path, remote_name) = match &args.project_path {
+		Some(project_path) => (project_path.clone(), None),
+		None => {
+			let current_dir = env::current_dir()
+				.map_err(|err| format!("Failed to determine the current directory:\n  {err}"))?;
+			let worktree_root = (!args.no_git)
+				.then(|| git::find_worktree_root(&current_dir))
+				.flatten();
+			match worktree_root {
+				Some(worktree_root) => {
+					let remote_name = git::get_remote_name(&worktree_root);
+					(worktree_root, remote_name)
+				}
+				None => (current_dir, None),
+			}
+		}
+	};
+	let project_path = project_path.canonicalize().map_err(|err| {
 		format!(
 			"Failed to canonicalize the project path `{}`: {err}",
-			args.project_path.display()
+			project_path.display()
 		)
 	})?;
 
 	let project_name = if let Some(name) = &args.project_name {
 		name
+	} else if let Some(name) = &remote_name {
+		name
 	} else {
 		get_project_name_from_path(&project_path).ok_or(concat!(
 			"Failed to determine a project name from the path.\n",
@@ -30,8 +62,186 @@ pub fn run(args: AddArgs) -> ModResult<()> {
 		))?
 	};
 
-	let project_file_contents = if project_path.is_file() {
-		let project_dir = normalize_path_prefix(project_path.parent().unwrap());
+	let project_file_contents = get_project_file_contents(&project_path, &args, dirs)?;
+
+	// NOTE: the lock is released before the editor is launched
+	let data_lock = FileLock::acquire(DATA_LOCK, dirs)?;
+	let projects_dir = create_projects_dir(&args.target, dirs)?;
+
+	let project_filename = projects_dir.join(format!("{project_name}.toml"));
+	let result = if is_project_name_taken(project_name, dirs)? {
+		Err(io::ErrorKind::AlreadyExists.into())
+	} else {
+		atomic_file::write_new(&project_filename, project_file_contents)
+	};
+	result.map_err(|err| {
+		if err.kind() == io::ErrorKind::AlreadyExists {
+			concat!(
+				"Failed to add the project, because a project with the same name already exists.\n",
+				"  NOTE: Use option '--name' to specify a different name."
+			)
+			.to_string()
+		} else {
+			format!(
+				"Failed to create the project file `{}`:\n  {err}",
+				project_filename.display()
+			)
+		}
+	})?;
+	drop(data_lock);
+
+	launch_editor(&project_filename, dirs)?;
+
+	Ok(())
+}
+// add a project for each repository below PATH without a project file
+fn run_recursive(args: &AddArgs, dirs: &dyn Dirs) -> ModResult<()> {
+	let root_dir = args.project_path.as_ref().unwrap();
+	let root_dir = root_dir.canonicalize().map_err(|err| {
+		format!(
+			"Failed to canonicalize the path `{}`: {err}",
+			root_dir.display()
+		)
+	})?;
+	let mut repos = Vec::new();
+	find_repos(&root_dir, args.depth, &args.markers, &mut repos);
+
+	let existing_project_dirs = get_existing_project_dirs(dirs)?;
+	let data_lock = FileLock::acquire(DATA_LOCK, dirs)?;
+	let projects_dir = create_projects_dir(&args.target, dirs)?;
+	let (mut created_count, mut skipped_count) = (0, 0);
+	for repo in repos {
+		let is_existing = [repo.clone(), normalize_path_prefix(&repo, dirs)]
+			.iter()
+			.any(|dir| existing_project_dirs.contains(dir));
+		if is_existing {
+			println!(
+				"Skipped `{}`, because a project with this directory already exists",
+				repo.display()
+			);
+			skipped_count += 1;
+			continue;
+		}
+
+		let remote_name = (!args.no_git)
+			.then(|| git::get_remote_name(&repo))
+			.flatten();
+		let Some(project_name) = remote_name
+			.as_deref()
+			.or_else(|| get_project_name_from_path(&repo))
+		else {
+			println!(
+				"Skipped `{}`, because no project name could be determined",
+				repo.display()
+			);
+			skipped_count += 1;
+			continue;
+		};
+		let project_file_contents = get_project_file_contents(&repo, args, dirs)?;
+		let project_filename = projects_dir.join(format!("{project_name}.toml"));
+		let result = if is_project_name_taken(project_name, dirs)? {
+			Err(io::ErrorKind::AlreadyExists.into())
+		} else {
+			atomic_file::write_new(&project_filename, project_file_contents)
+		};
+		match result {
+			Ok(()) => {
+				println!(
+					"Created `{}` for `{}`",
+					project_filename.display(),
+					repo.display()
+				);
+				created_count += 1;
+			}
+			Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+				println!(
+					"Skipped `{}`, because a project named `{project_name}` already exists",
+					repo.display()
+				);
+				skipped_count += 1;
+			}
+			Err(err) => {
+				return Err(
+					format!(
+						"Failed to create the project file `{}`:\n  {err}",
+						project_filename.display()
+					)
+					.into(),
+				)
+			}
+		}
+	}
+	drop(data_lock);
+
+	println!("{created_count} created, {skipped_count} skipped");
+	Ok(())
+}
+// directories containing one of 'markers', searched up to 'depth' levels below 'dir'
+// NOTE: repositories are not searched for nested repositories,
+//       and hidden directories and symlinks are skipped
+fn find_repos(dir: &Path, depth: usize, markers: &[String], repos: &mut Vec<PathBuf>) {
+	if markers.iter().any(|marker| dir.join(marker).exists()) {
+		repos.push(dir.to_path_buf());
+		return;
+	}
+	if depth == 0 {
+		return;
+	}
+	let Ok(dir_iter) = fs::read_dir(dir) else {
+		return;
+	};
+	let mut subdirs = dir_iter
+		.filter_map(Result::ok)
+		.filter(|entry| entry.file_type().is_ok_and(|ty| ty.is_dir()))
+		.filter(|entry| !entry.file_name().as_encoded_bytes().starts_with(b"."))
+		.map(|entry| entry.path())
+		.collect::<Vec<_>>();
+	subdirs.sort();
+	for subdir in subdirs {
+		find_repos(&subdir, depth - 1, markers, repos);
+	}
+}
+// the 'project-dir' of all project files, as written in the files
+// NOTE: the paths are not resolved, so they are compared with
+//       the paths normalized by 'normalize_path_prefix'
+fn get_existing_project_dirs(dirs: &dyn Dirs) -> ModResult<Vec<PathBuf>> {
+	let mut project_dirs = Vec::new();
+	for projects_dir in get_searched_projects_dirs(dirs)? {
+		for file in rename_subcommand::get_toml_files(&projects_dir) {
+			let Ok(contents) = fs::read_to_string(&file) else {
+				continue;
+			};
+			let Ok(value) = toml_span::parse(&contents) else {
+				continue;
+			};
+			let project_dir = value
+				.as_table()
+				.and_then(|table| table.get("project-dir"))
+				.and_then(|value| value.as_str());
+			if let Some(project_dir) = project_dir {
+				project_dirs.push(PathBuf::from(project_dir));
+			}
+		}
+	}
+	Ok(project_dirs)
+}
+// the contents of a project file for 'project_path' (a directory or a file)
+fn get_project_file_contents(
+	project_path: &Path,
+	args: &AddArgs,
+	dirs: &dyn Dirs,
+) -> ModResult<String> {
+	let project_dir = if project_path.is_file() {
+		project_path.parent().unwrap()
+	} else {
+		project_path
+	};
+	let language = (!args.no_detect)
+		.then(|| Language::detect(project_dir))
+		.flatten();
+
+	let mut project_file_contents = if project_path.is_file() {
+		let project_dir = normalize_path_prefix(project_path.parent().unwrap(), dirs);
 		let project_dir = project_dir.to_str().ok_or_else(|| {
 			format!(
 				concat!(
@@ -59,7 +269,7 @@ pub fn run(args: AddArgs) -> ModResult<()> {
 			toml_string_escape(project_file)
 		)
 	} else {
-		let project_dir = normalize_path_prefix(&project_path);
+		let project_dir = normalize_path_prefix(project_path, dirs);
 		let project_dir = project_dir.to_str().ok_or_else(|| {
 			format!(
 				concat!(
@@ -73,36 +283,130 @@ pub fn run(args: AddArgs) -> ModResult<()> {
 		format!("project-dir = {}", toml_string_escape(project_dir))
 	};
 
-	let projects_dir = dirs::get_skeld_data_dir()
-		.map_err(|err| format!("Failed to determine the skeld data directory:\n  {err}"))?
-		.join("projects");
+	// NOTE: the include file of the language comes first,
+	//       so that the explicitly specified ones can build on it
+	let mut includes = Vec::new();
+	if let Some(language) = language {
+		project_file_contents += &format!("\nicon = {}", toml_string_escape(language.icon()));
+		includes.extend(language.default_include(dirs).map(str::to_string));
+	}
+	for include in &args.includes {
+		if parse::canonicalize_include_path(include.as_str(), dirs).is_err() {
+			return Err(
+				format!(
+					concat!(
+						"Failed to add the project, because the include file `{}` could not be found.\n",
+						"  NOTE: Include files are searched in `<SKELD-DATA>/include`."
+					),
+					include
+				)
+				.into(),
+			);
+		}
+		if !includes.contains(include) {
+			includes.push(include.clone());
+		}
+	}
+	if !includes.is_empty() {
+		let includes = includes
+			.iter()
+			.map(|include| toml_string_escape(include))
+			.collect::<Vec<_>>();
+		project_file_contents += &format!("\ninclude = [{}]", includes.join(", "));
+	}
+
+	if let Some(preset) = args.editor {
+		let (cmd_with_file, cmd_without_file, detach) = get_editor_preset(preset);
+		let toml_array = |cmd: &[&str]| {
+			let elements = cmd
+				.iter()
+				.map(|arg| toml_string_escape(arg))
+				.collect::<Vec<_>>();
+			format!("[{}]", elements.join(", "))
+		};
+		project_file_contents += &format!(
+			"\n\n[editor]\ncmd-with-file = {}\ncmd-without-file = {}\ndetach = {detach}",
+			toml_array(cmd_with_file),
+			toml_array(cmd_without_file),
+		);
+	}
+
+	Ok(format!("{project_file_contents}\n"))
+}
+// NOTE: a directory outside of the skeld directories is accepted,
+//       but its projects are not shown by skeld
+fn create_projects_dir(target: &AddTarget, dirs: &dyn Dirs) -> ModResult<PathBuf> {
+	let projects_dir = match target {
+		AddTarget::Config => dirs
+			.get_skeld_config_dir()
+			.map_err(|err| format!("Failed to determine the skeld config directory:\n  {err}"))?
+			.join("projects"),
+		AddTarget::Data => dirs
+			.get_skeld_data_dir()
+			.map_err(|err| format!("Failed to determine the skeld data directory:\n  {err}"))?
+			.join("projects"),
+		AddTarget::Dir(dir) => {
+			let dir = env::current_dir()
+				.map(|cwd| cwd.join(dir))
+				.unwrap_or(dir.clone());
+			let is_searched = get_searched_projects_dirs(dirs)?.contains(&dir);
+			if !is_searched {
+				eprintln!(
+					concat!(
+						"Warning: Projects in `{}` are not shown by skeld, which only reads the\n",
+						"  `projects` directories in the skeld config and data directories."
+					),
+					dir.display()
+				);
+			}
+			dir
+		}
+	};
 	fs::create_dir_all(&projects_dir).map_err(|err| {
 		format!(
 			"Failed to create the skeld projects directory `{}`:\n  {err}",
 			projects_dir.display()
 		)
 	})?;
-
-	let project_filename = projects_dir.join(format!("{project_name}.toml"));
-	let mut project_file = File::create_new(&project_filename).map_err(|err| {
-		if err.kind() == io::ErrorKind::AlreadyExists {
-			concat!(
-				"Failed to add the project, because a project with the same name already exists.\n",
-				"  NOTE: Use option '--name' to specify a different name."
-			)
-			.to_string()
-		} else {
-			format!(
-				"Failed to create the project file `{}`:\n  {err}",
-				project_filename.display()
-			)
-		}
-	})?;
-	writeln!(project_file, "{project_file_contents}").unwrap();
-
-	launch_editor(&project_filename)?;
-
-	Ok(())
+	Ok(projects_dir)
+}
+// the directories searched for project files
+fn get_searched_projects_dirs(dirs: &dyn Dirs) -> ModResult<Vec<PathBuf>> {
+	let data_dirs = dirs
+		.get_skeld_data_dirs()
+		.map_err(|err| format!("Failed to determine the skeld data directories:\n  {err}"))?;
+	Ok(
+		data_dirs
+			.into_iter()
+			.map(|dir| dir.join("projects"))
+			.collect(),
+	)
+}
+// whether a project named 'name' exists in any of the searched directories
+// NOTE: projects of the same name would conflict, even in different directories
+fn is_project_name_taken(name: &str, dirs: &dyn Dirs) -> ModResult<bool> {
+	Ok(
+		get_searched_projects_dirs(dirs)?
+			.iter()
+			.any(|dir| dir.join(format!("{name}.toml")).exists()),
+	)
+}
+// (cmd-with-file, cmd-without-file, detach) of the editor
+fn get_editor_preset(
+	preset: EditorPreset,
+) -> (&'static [&'static str], &'static [&'static str], bool) {
+	match preset {
+		EditorPreset::Nvim => (&["nvim", "$(FILE)"], &["nvim", "."], false),
+		EditorPreset::Vim => (&["vim", "$(FILE)"], &["vim", "."], false),
+		EditorPreset::Helix => (&["hx", "$(FILE)"], &["hx", "."], false),
+		EditorPreset::Emacs => (&["emacs", "-nw", "$(FILE)"], &["emacs", "-nw", "."], false),
+		EditorPreset::Code => (
+			&["code", "--wait", "$(FILE)"],
+			&["code", "--wait", "."],
+			true,
+		),
+		EditorPreset::Zed => (&["zed", "--wait", "$(FILE)"], &["zed", "--wait", "."], true),
+	}
 }
 fn get_project_name_from_path(path: &Path) -> Option<&str> {
 	let basename = path.file_name()?.to_str()?;
@@ -118,7 +422,7 @@ fn get_project_name_from_path(path: &Path) -> Option<&str> {
 	Some(project_name)
 }
 // use known path prefixes like '~'
-fn normalize_path_prefix(path: impl AsRef<Path>) -> PathBuf {
+pub fn normalize_path_prefix(path: impl AsRef<Path>, dirs: &dyn Dirs) -> PathBuf {
 	let path = path.as_ref();
 
 	let handle_prefix = |prefix: Option<PathBuf>, replacement: &str| {
@@ -132,68 +436,44 @@ fn normalize_path_prefix(path: impl AsRef<Path>) -> PathBuf {
 		}
 	};
 
-	if let Some(path) = handle_prefix(dirs::get_xdg_config_dir().ok(), "$(CONFIG)") {
+	if let Some(path) = handle_prefix(dirs.get_xdg_config_dir().ok(), "$(CONFIG)") {
 		path
-	} else if let Some(path) = handle_prefix(dirs::get_xdg_cache_dir().ok(), "$(CACHE)") {
+	} else if let Some(path) = handle_prefix(dirs.get_xdg_cache_dir().ok(), "$(CACHE)") {
 		path
-	} else if let Some(path) = handle_prefix(dirs::get_xdg_data_dir().ok(), "$(DATA)") {
+	} else if let Some(path) = handle_prefix(dirs.get_xdg_data_dir().ok(), "$(DATA)") {
 		path
-	} else if let Some(path) = handle_prefix(dirs::get_xdg_state_dir().ok(), "$(STATE)") {
+	} else if let Some(path) = handle_prefix(dirs.get_xdg_state_dir().ok(), "$(STATE)") {
 		path
-	} else if let Some(path) = handle_prefix(dirs::get_home_dir().ok(), "~") {
+	} else if let Some(path) = handle_prefix(dirs.get_home_dir().ok(), "~") {
 		path
 	} else {
 		path.to_path_buf()
 	}
 }
-fn toml_string_escape(str: &str) -> String {
-	let escaped_str = str
-		.chars()
-		.map(|char| match char {
-			'\x08' => "\\b".to_string(),
-			'\t' => "\\t".to_string(),
-			'\n' => "\\n".to_string(),
-			'\x0c' => "\\f".to_string(),
-			'\r' => "\\r".to_string(),
-			'\\' => "\\\\".to_string(),
-			'"' => "\\\"".to_string(),
-			ch if ch.is_ascii_graphic() || ch == ' ' => ch.to_string(),
-			ch => format!("\\U{:08X}", ch as u32),
-		})
-		.collect::<String>();
-	format!("\"{escaped_str}\"")
-}
 
-fn launch_editor(file: impl AsRef<Path>) -> ModResult<()> {
-	let mut editor_cmd = get_editor();
-	editor_cmd.push(" ");
-	editor_cmd.push(shell_string_escape(file.as_ref().as_os_str()));
-
-	let editor_output = Command::new("sh")
-		.arg("-c")
-		.arg(&editor_cmd)
-		// NOTE: This won't work if the editor uses stderr to display its tui,
-		//       but that should be unlikely.
-		.stderr(process::Stdio::piped())
-		.spawn()
-		.expect("failed to execute `sh`")
-		.wait_with_output()
-		.unwrap();
-
-	if !editor_output.status.success() {
-		let editor_stderr = String::from_utf8_lossy(&editor_output.stderr);
-		let trimmed_stderr = editor_stderr
-			.trim()
-			.strip_prefix("sh: line 1: ")
-			.unwrap_or(&editor_stderr);
-		return Err(
-			format!(
-				"Failed to execute the editor command `{}`:\n  {}",
-				editor_cmd.to_string_lossy(),
-				trimmed_stderr.replace('\n', "\n  ")
-			)
-			.into(),
-		);
+// NOTE: like git, the editor command is interpreted by the shell,
+//       so that it may contain arguments (e.g. `code --wait`);
+//       the file is passed as a positional argument and needs no escaping
+fn launch_editor(file: &Path, dirs: &dyn Dirs) -> ModResult<()> {
+	let editor_cmd = get_editor().to_string_lossy().into_owned();
+	let command = SandboxCommand {
+		name: "edit".to_string(),
+		cmd: vec![
+			"sh".to_string(),
+			"-c".to_string(),
+			format!(r#"{editor_cmd} "$@""#),
+			"sh".to_string(),
+			file.to_string_lossy().into_owned(),
+		],
+		working_dir: file.parent().unwrap().to_path_buf(),
+		detach: false,
+		terminal: None,
+	};
+	let exit_code = command
+		.run(&LogConfig::default(), dirs)
+		.map_err(|err| format!("Failed to execute the editor command `{editor_cmd}`:\n  {err}"))?;
+	if exit_code != ExitCode::SUCCESS {
+		return Err(format!("The editor command `{editor_cmd}` failed").into());
 	}
 
 	Ok(())
@@ -217,17 +497,3 @@ fn get_editor() -> OsString {
 		"vi".into()
 	}
 }
-// escape string for POSIX-compliant shells
-fn shell_string_escape(str: &OsStr) -> OsString {
-	let mut escaped_bytes = Vec::new();
-	escaped_bytes.push(b'"');
-	escaped_bytes.extend(str.as_encoded_bytes().iter().flat_map(|&byte| match byte {
-		b'$' => vec![b'\\', b'$'],
-		b'`' => vec![b'\\', b'`'],
-		b'\\' => vec![b'\\', b'\\'],
-		b'"' => vec![b'\\', b'"'],
-		_ => vec![byte],
-	}));
-	escaped_bytes.push(b'"');
-	OsString::from_vec(escaped_bytes)
-}
@@ -0,0 +1,59 @@
+use std::{
+	ffi::OsString,
+	fs::{self, File},
+	io::{self, Write as _},
+	path::{Path, PathBuf},
+	process,
+};
+
+// write 'contents' to a temporary file next to 'path', which then replaces 'path',
+// so that a crash never leaves a truncated file behind
+// NOTE: if 'path' is a symlink, its target is replaced instead
+pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+	let path = match fs::canonicalize(path.as_ref()) {
+		Ok(target_path) => target_path,
+		Err(err) if err.kind() == io::ErrorKind::NotFound => path.as_ref().to_path_buf(),
+		Err(err) => return Err(err),
+	};
+	let temp_path = write_temp_file(&path, contents.as_ref())?;
+	fs::rename(&temp_path, &path).inspect_err(|_| {
+		let _ = fs::remove_file(&temp_path);
+	})?;
+	sync_parent_dir(&path)
+}
+// like 'write', but fails with 'AlreadyExists' if 'path' exists
+pub fn write_new(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+	let path = path.as_ref();
+	let temp_path = write_temp_file(path, contents.as_ref())?;
+	// NOTE: in contrast to 'rename', 'hard_link' does not replace an existing file
+	let link_result = fs::hard_link(&temp_path, path);
+	let _ = fs::remove_file(&temp_path);
+	link_result?;
+	sync_parent_dir(path)
+}
+fn write_temp_file(path: &Path, contents: &[u8]) -> io::Result<PathBuf> {
+	// NOTE: the temporary file is hidden and lacks the '.toml' extension,
+	//       so that it is never picked up as a project or bookmark
+	let mut temp_name = OsString::from(".");
+	temp_name.push(path.file_name().unwrap_or_default());
+	temp_name.push(format!(".tmp-{}", process::id()));
+	let temp_path = path.with_file_name(temp_name);
+
+	let write_contents = || -> io::Result<()> {
+		let mut file = File::create(&temp_path)?;
+		file.write_all(contents)?;
+		file.sync_all()
+	};
+	write_contents().inspect_err(|_| {
+		let _ = fs::remove_file(&temp_path);
+	})?;
+	Ok(temp_path)
+}
+// persist the new directory entry
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+	let parent_dir = match path.parent() {
+		Some(dir) if !dir.as_os_str().is_empty() => dir,
+		_ => Path::new("."),
+	};
+	File::open(parent_dir)?.sync_all()
+}
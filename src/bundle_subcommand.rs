@@ -0,0 +1,356 @@
+use std::{
+	collections::{btree_map, BTreeMap},
+	fs,
+	ops::Range,
+	path::{Component, Path, PathBuf},
+};
+
+use toml_span::value::ValueInner;
+
+use crate::{
+	add_subcommand, atomic_file,
+	dirs::Dirs,
+	lock::{FileLock, DATA_LOCK},
+	parse::{self, ParseContext, ProjectDataFuture},
+	rename_subcommand, trust, ExportArgs, GenericResult, ImportArgs,
+};
+
+// NOTE: bundles are tar archives containing the files
+//       relative to the skeld data directory
+pub fn run_export(args: ExportArgs, parse_ctx: &mut ParseContext) -> GenericResult<()> {
+	let dirs = parse_ctx.dirs.clone();
+	let mut selected_files = Vec::new();
+	for bookmark in parse_ctx.get_bookmarks()? {
+		if args.names.is_empty() || args.names.contains(&bookmark.name) {
+			let ProjectDataFuture::Bookmark(path) = bookmark.project_data else {
+				unreachable!();
+			};
+			selected_files.push((
+				bookmark.name,
+				Path::new("bookmarks").join(path.file_name().unwrap()),
+				path,
+			));
+		}
+	}
+	for project in parse_ctx.get_projects()? {
+		if args.names.is_empty() || args.names.contains(&project.name) {
+			let ProjectDataFuture::Project(path) = project.project_data else {
+				unreachable!();
+			};
+			selected_files.push((
+				project.name,
+				Path::new("projects").join(path.file_name().unwrap()),
+				path,
+			));
+		}
+	}
+	if let Some(unknown) = args.names.iter().find(|name| {
+		!selected_files
+			.iter()
+			.any(|(selected, ..)| selected == *name)
+	}) {
+		return Err(format!("Unknown project or bookmark `{unknown}`").into());
+	}
+
+	// bundle_files: archive path -> host path
+	let mut bundle_files = selected_files
+		.into_iter()
+		.map(|(_, archive_path, path)| (archive_path, path))
+		.collect::<BTreeMap<_, _>>();
+	// NOTE: include files are added until there are no new references
+	let mut unprocessed_files = bundle_files.values().cloned().collect::<Vec<_>>();
+	while let Some(file) = unprocessed_files.pop() {
+		let contents = read_file(&file)?;
+		for include in get_includes(&file, &contents, &*dirs)? {
+			if let Some(archive_path) = get_include_archive_path(&include, &*dirs)? {
+				if let btree_map::Entry::Vacant(entry) = bundle_files.entry(archive_path) {
+					entry.insert(include.clone());
+					unprocessed_files.push(include);
+				}
+			}
+		}
+	}
+
+	let mut archive = Vec::new();
+	for (archive_path, path) in &bundle_files {
+		let contents = read_file(path)?;
+		let contents = normalize_paths(path, &contents, &*dirs)?;
+		tar::append_file(&mut archive, archive_path, contents.as_bytes())?;
+		println!("Exported `{}`", path.display());
+	}
+	tar::finish(&mut archive);
+
+	atomic_file::write(&args.output, archive).map_err(|err| {
+		format!(
+			"Failed to write the bundle `{}`:\n  {err}",
+			args.output.display()
+		)
+	})?;
+	Ok(())
+}
+pub fn run_import(args: ImportArgs, dirs: &dyn Dirs) -> GenericResult<()> {
+	let archive = fs::read(&args.bundle).map_err(|err| {
+		format!(
+			"Failed to read the bundle `{}`:\n  {err}",
+			args.bundle.display()
+		)
+	})?;
+	let entries = tar::read_files(&archive).map_err(|err| {
+		format!(
+			"Failed to read the bundle `{}`:\n  {err}",
+			args.bundle.display()
+		)
+	})?;
+
+	let data_dir = dirs
+		.get_skeld_data_dir()
+		.map_err(|err| format!("Failed to determine the skeld data directory:\n  {err}"))?;
+	let _data_lock = FileLock::acquire(DATA_LOCK, dirs)?;
+	// NOTE: all files are checked before anything is written,
+	//       so that a bundle is either imported completely or not at all
+	for (archive_path, _) in &entries {
+		let is_valid_path = Path::new(archive_path)
+			.components()
+			.all(|comp| matches!(comp, Component::Normal(_)))
+			&& ["projects/", "bookmarks/", "include/"]
+				.iter()
+				.any(|dir| archive_path.starts_with(dir))
+			&& archive_path.ends_with(".toml");
+		if !is_valid_path {
+			return Err(format!("The bundle contains the invalid file `{archive_path}`").into());
+		}
+		let path = data_dir.join(archive_path);
+		if path.exists() && !args.force {
+			return Err(
+				format!(
+					concat!(
+						"The file `{}` already exists.\n",
+						"  NOTE: Use option '--force' to overwrite existing files."
+					),
+					path.display()
+				)
+				.into(),
+			);
+		}
+	}
+
+	for (archive_path, contents) in entries {
+		let path = data_dir.join(archive_path);
+		let parent_dir = path.parent().unwrap();
+		fs::create_dir_all(parent_dir).map_err(|err| {
+			format!(
+				"Failed to create the directory `{}`:\n  {err}",
+				parent_dir.display()
+			)
+		})?;
+		atomic_file::write(&path, contents)
+			.map_err(|err| format!("Failed to write file `{}`: {err}", path.display()))?;
+		// NOTE: bundles are not written by the user, so their
+		//       sandbox-weakening options have to be confirmed
+		trust::mark_imported(&path, dirs)?;
+		println!("Imported `{}`", path.display());
+	}
+	Ok(())
+}
+
+// returns the paths of all files included by 'file'
+fn get_includes(file: &Path, contents: &str, dirs: &dyn Dirs) -> GenericResult<Vec<PathBuf>> {
+	let value = parse_file(file, contents)?;
+	let mut include_entries = Vec::new();
+	rename_subcommand::find_include_entries(&value, &mut include_entries);
+	Ok(
+		include_entries
+			.into_iter()
+			.filter_map(|(_, include)| parse::canonicalize_include_path(include, dirs).ok())
+			.collect(),
+	)
+}
+// NOTE: only files in an include directory can be exported,
+//       as they are found on other machines by their relative path
+fn get_include_archive_path(include: &Path, dirs: &dyn Dirs) -> GenericResult<Option<PathBuf>> {
+	let skeld_data_dirs = dirs
+		.get_skeld_data_dirs()
+		.map_err(|err| format!("Failed to determine the skeld data directories:\n  {err}"))?;
+	Ok(skeld_data_dirs.into_iter().find_map(|data_dir| {
+		let relative_path = include.strip_prefix(data_dir.join("include")).ok()?;
+		Some(Path::new("include").join(relative_path))
+	}))
+}
+// replace machine-specific paths with placeholders like '~' and make includes
+// of files in an include directory relative
+fn normalize_paths(file: &Path, contents: &str, dirs: &dyn Dirs) -> GenericResult<String> {
+	let value = parse_file(file, contents)?;
+	let mut include_entries = Vec::new();
+	rename_subcommand::find_include_entries(&value, &mut include_entries);
+	let mut strings = Vec::new();
+	find_path_strings(&value, false, &mut strings);
+
+	let mut replacements = Vec::new();
+	for (span, string) in strings {
+		let is_include = include_entries
+			.iter()
+			.any(|(include_span, _)| include_span == &span);
+		let relative_include = parse::canonicalize_include_path(string, dirs)
+			.ok()
+			.filter(|_| is_include && string.starts_with('/'))
+			.map(|path| get_include_archive_path(&path, dirs))
+			.transpose()?
+			.flatten();
+		let new_string = if let Some(include) = relative_include {
+			let include = include.strip_prefix("include").unwrap().with_extension("");
+			include.to_string_lossy().into_owned()
+		} else if string.starts_with('/') {
+			let path = add_subcommand::normalize_path_prefix(string, dirs);
+			path.to_string_lossy().into_owned()
+		} else {
+			continue;
+		};
+		if new_string != string {
+			replacements.push((span, new_string));
+		}
+	}
+
+	let replacements = replacements
+		.iter()
+		.map(|(span, new)| (span.clone(), new.as_str()))
+		.collect::<Vec<_>>();
+	Ok(rename_subcommand::replace_strings(contents, &replacements))
+}
+// 'is_path' is whether strings in 'value' are paths
+fn find_path_strings<'a>(
+	value: &'a toml_span::Value<'a>,
+	is_path: bool,
+	strings: &mut Vec<(Range<usize>, &'a str)>,
+) {
+	// NOTE: other strings (e.g. commands) are left untouched,
+	//       even if they contain something that looks like a path
+	const PATH_OPTIONS: &[&str] = &[
+		"project-dir",
+		"private-home",
+		"initial-file",
+		"include",
+		"whitelist-rw",
+		"whitelist-ro",
+		"whitelist-cow",
+		"whitelist-dev",
+		"whitelist-ln",
+		"add-tmpfs",
+		"blacklist",
+		"src",
+		"dest",
+		"working-dir",
+	];
+	match value.as_ref() {
+		ValueInner::String(string) if is_path => {
+			strings.push((value.span.start..value.span.end, string));
+		}
+		ValueInner::Table(table) => {
+			for (key, value) in table {
+				find_path_strings(value, PATH_OPTIONS.contains(&&*key.name), strings);
+			}
+		}
+		ValueInner::Array(array) => {
+			for value in array {
+				find_path_strings(value, is_path, strings);
+			}
+		}
+		_ => (),
+	}
+}
+
+fn read_file(path: &Path) -> GenericResult<String> {
+	fs::read_to_string(path)
+		.map_err(|err| format!("Failed to read file `{}`: {err}", path.display()).into())
+}
+fn parse_file<'a>(path: &Path, contents: &'a str) -> GenericResult<toml_span::Value<'a>> {
+	toml_span::parse(contents)
+		.map_err(|err| format!("Failed to parse `{}`: {err}", path.display()).into())
+}
+
+// minimal implementation of the ustar format, only supporting regular files
+mod tar {
+	use std::path::Path;
+
+	const BLOCK_SIZE: usize = 512;
+
+	pub fn append_file(archive: &mut Vec<u8>, path: &Path, contents: &[u8]) -> Result<(), String> {
+		let path = path.to_string_lossy();
+		if path.len() > 100 {
+			return Err(format!(
+				"The path `{path}` is too long to be stored in a bundle"
+			));
+		}
+
+		let mut header = [0u8; BLOCK_SIZE];
+		header[..path.len()].copy_from_slice(path.as_bytes());
+		write_octal(&mut header[100..108], 0o644);
+		write_octal(&mut header[108..116], 0);
+		write_octal(&mut header[116..124], 0);
+		write_octal(&mut header[124..136], contents.len() as u64);
+		write_octal(&mut header[136..148], 0);
+		header[156] = b'0';
+		header[257..263].copy_from_slice(b"ustar\0");
+		header[263..265].copy_from_slice(b"00");
+		// NOTE: the checksum is calculated with the checksum field set to spaces
+		header[148..156].fill(b' ');
+		let checksum = header.iter().map(|&byte| u64::from(byte)).sum();
+		write_octal(&mut header[148..155], checksum);
+
+		archive.extend_from_slice(&header);
+		archive.extend_from_slice(contents);
+		archive.resize(archive.len().next_multiple_of(BLOCK_SIZE), 0);
+		Ok(())
+	}
+	pub fn finish(archive: &mut Vec<u8>) {
+		archive.resize(archive.len() + 2 * BLOCK_SIZE, 0);
+	}
+	// read_files(..) -> Vec<(path, contents)>
+	pub fn read_files(archive: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+		let mut files = Vec::new();
+		let mut offset = 0;
+		while let Some(header) = archive.get(offset..offset + BLOCK_SIZE) {
+			// NOTE: the archive ends with empty blocks
+			if header.iter().all(|&byte| byte == 0) {
+				break;
+			}
+			let size = read_octal(&header[124..136]).ok_or("invalid file size")? as usize;
+			let contents_start = offset + BLOCK_SIZE;
+			let contents = archive
+				.get(contents_start..contents_start + size)
+				.ok_or("unexpected end of the archive")?;
+			offset = contents_start + size.next_multiple_of(BLOCK_SIZE);
+
+			let name = read_string(&header[..100]);
+			let prefix = read_string(&header[345..500]);
+			let path = if prefix.is_empty() {
+				name
+			} else {
+				format!("{prefix}/{name}")
+			};
+			match header[156] {
+				b'0' | b'\0' => files.push((path, contents.to_vec())),
+				// NOTE: directories are created as needed
+				b'5' => (),
+				_ => return Err(format!("`{path}` is not a regular file")),
+			}
+		}
+		Ok(files)
+	}
+
+	fn write_octal(field: &mut [u8], value: u64) {
+		let digits = format!("{value:0width$o}", width = field.len() - 1);
+		field[..digits.len()].copy_from_slice(digits.as_bytes());
+		field[digits.len()] = 0;
+	}
+	fn read_octal(field: &[u8]) -> Option<u64> {
+		let str = read_string(field);
+		u64::from_str_radix(str.trim(), 8).ok()
+	}
+	fn read_string(field: &[u8]) -> String {
+		let end = field
+			.iter()
+			.position(|&byte| byte == 0)
+			.unwrap_or(field.len());
+		String::from_utf8_lossy(&field[..end]).into_owned()
+	}
+}
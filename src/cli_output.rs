@@ -0,0 +1,94 @@
+use std::io;
+
+use crossterm::{
+	style::{Color, Stylize as _},
+	tty::IsTty as _,
+};
+use unicode_width::UnicodeWidthStr as _;
+
+use crate::ColorMode;
+
+// NOTE: columns are separated by two spaces
+const COLUMN_GAP: &str = "  ";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+	Left,
+	Right,
+}
+pub struct Cell {
+	text: String,
+	color: Option<Color>,
+}
+impl Cell {
+	pub fn plain(text: impl Into<String>) -> Self {
+		Self {
+			text: text.into(),
+			color: None,
+		}
+	}
+	pub fn colored(text: impl Into<String>, color: Color) -> Self {
+		Self {
+			text: text.into(),
+			color: Some(color),
+		}
+	}
+}
+// table printed to stdout with aligned columns
+pub struct Table {
+	alignments: Vec<Align>,
+	rows: Vec<Vec<Cell>>,
+}
+impl Table {
+	pub fn new(alignments: &[Align]) -> Self {
+		Self {
+			alignments: alignments.to_vec(),
+			rows: Vec::new(),
+		}
+	}
+	// NOTE: rows may have fewer cells than there are columns
+	pub fn add_row(&mut self, row: Vec<Cell>) {
+		assert!(row.len() <= self.alignments.len());
+		self.rows.push(row);
+	}
+	pub fn print(&self, color: ColorMode) {
+		let use_color = stdout_color(color);
+		let column_widths = (0..self.alignments.len())
+			.map(|i| {
+				self
+					.rows
+					.iter()
+					.filter_map(|row| row.get(i))
+					.map(|cell| cell.text.width())
+					.max()
+					.unwrap_or_default()
+			})
+			.collect::<Vec<_>>();
+
+		for row in &self.rows {
+			let mut line = String::new();
+			for (i, cell) in row.iter().enumerate() {
+				if i != 0 {
+					line += COLUMN_GAP;
+				}
+				let padding = " ".repeat(column_widths[i] - cell.text.width());
+				let text = match cell.color {
+					Some(color) if use_color => cell.text.as_str().with(color).to_string(),
+					_ => cell.text.clone(),
+				};
+				// NOTE: the last cell of a row is not padded on the right,
+				//       so that there is no trailing whitespace
+				match self.alignments[i] {
+					Align::Left if i == row.len() - 1 => line += &text,
+					Align::Left => line += &format!("{text}{padding}"),
+					Align::Right => line += &format!("{padding}{text}"),
+				}
+			}
+			println!("{line}");
+		}
+	}
+}
+// whether the output to stdout should be colored
+pub fn stdout_color(color: ColorMode) -> bool {
+	color.use_color(io::stdout().is_tty())
+}
@@ -0,0 +1,191 @@
+use std::{
+	env,
+	error::Error,
+	ffi::OsString,
+	io::{self, IsTerminal as _},
+	path::Path,
+};
+
+use crate::{
+	dirs::Dirs,
+	sandbox::{Command, EnvVarWhitelist, LaunchPlan, SandboxParameters, VirtualFSEntryType},
+};
+
+// alternative launch backend, that runs the command inside a container
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContainerParameters {
+	pub image: String,
+	pub engine: ContainerEngine,
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ContainerEngine {
+	Podman,
+	Docker,
+}
+impl ContainerEngine {
+	pub fn program(self) -> &'static str {
+		match self {
+			Self::Podman => "podman",
+			Self::Docker => "docker",
+		}
+	}
+}
+
+impl ContainerParameters {
+	// NOTE: the whitelists of 'sandbox_params' are mapped to
+	//       volumes and environment variables of the container
+	pub fn plan_cmd(
+		&self,
+		sandbox_params: &SandboxParameters,
+		command: Command,
+		dirs: &dyn Dirs,
+	) -> Result<LaunchPlan, Box<dyn Error>> {
+		assert!(!command.cmd.is_empty());
+
+		let mut engine_args = self.get_engine_args(sandbox_params, &command, dirs)?;
+		engine_args.push(self.image.clone().into());
+		engine_args.extend(command.cmd.iter().map(OsString::from));
+
+		Ok(LaunchPlan {
+			program: self.engine.program().into(),
+			args: engine_args,
+			working_dir: None,
+			detach: command.is_detached(),
+			restrict_tty: false,
+			seccomp_filter: None,
+			nofile_limit: None,
+			track_running: false,
+			not_found_note: Some(match self.engine {
+				ContainerEngine::Podman => {
+					"\n  NOTE: Make sure `podman` is installed and available in `$PATH`."
+				}
+				ContainerEngine::Docker => {
+					"\n  NOTE: Make sure `docker` is installed and available in `$PATH`."
+				}
+			}),
+			name: command.name,
+			terminal: command.terminal,
+		})
+	}
+
+	fn get_engine_args(
+		&self,
+		sandbox_params: &SandboxParameters,
+		command: &Command,
+		dirs: &dyn Dirs,
+	) -> Result<Vec<OsString>, Box<dyn Error>> {
+		let mut args: Vec<OsString> = vec!["run".into(), "--rm".into()];
+		if !command.detach {
+			args.push("--interactive".into());
+			if command.terminal.is_some() || io::stdin().is_terminal() {
+				args.push("--tty".into());
+			}
+		}
+
+		// files created in mounted directories should belong to the user
+		match self.engine {
+			ContainerEngine::Podman => args.push("--userns=keep-id".into()),
+			ContainerEngine::Docker => {
+				let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+				args.extend_from_slice(&["--user".into(), format!("{uid}:{gid}").into()]);
+			}
+		}
+
+		assert!(command.working_dir.is_absolute());
+		args.extend_from_slice(&["--workdir".into(), command.working_dir.clone().into()]);
+
+		args.append(&mut get_envvar_args(&sandbox_params.envvar_whitelist));
+
+		if let Some(private_home) = &sandbox_params.private_home {
+			let home_dir = dirs
+				.get_home_dir()
+				.map_err(|err| format!("Failed to determine the home directory:\n  {err}"))?;
+			args.append(&mut volume_args(private_home, &home_dir, ""));
+		}
+		args.append(&mut self.get_virtual_fs_args(sandbox_params)?);
+
+		Ok(args)
+	}
+	fn get_virtual_fs_args(
+		&self,
+		sandbox_params: &SandboxParameters,
+	) -> Result<Vec<OsString>, String> {
+		let mut args = Vec::new();
+		for (path, ty, source) in sandbox_params.fs_tree.flatten() {
+			let source = source.unwrap_or_else(|| path.clone());
+			let mut path_args = match ty {
+				// mirror the semantics of '--*-bind-try' of the bwrap sandbox
+				VirtualFSEntryType::AllowDev
+				| VirtualFSEntryType::ReadWrite
+				| VirtualFSEntryType::ReadOnly
+				| VirtualFSEntryType::CopyOnWrite
+					if !source.exists() =>
+				{
+					Vec::new()
+				}
+				VirtualFSEntryType::AllowDev if source == path && path.starts_with("/dev") => {
+					vec!["--device".into(), path.into()]
+				}
+				VirtualFSEntryType::AllowDev | VirtualFSEntryType::ReadWrite => {
+					volume_args(&source, &path, "")
+				}
+				VirtualFSEntryType::ReadOnly => volume_args(&source, &path, ":ro"),
+				VirtualFSEntryType::CopyOnWrite => match self.engine {
+					ContainerEngine::Podman => volume_args(&source, &path, ":O"),
+					ContainerEngine::Docker => {
+						return Err(format!(
+							concat!(
+								"The copy-on-write path `{}` is not supported with docker.",
+								"\n  NOTE: Use podman or whitelist the path read-only instead.",
+							),
+							path.display()
+						));
+					}
+				},
+				// NOTE: the image provides its own file system layout
+				VirtualFSEntryType::Symlink => Vec::new(),
+				VirtualFSEntryType::Tmpfs => vec!["--tmpfs".into(), path.into()],
+				VirtualFSEntryType::Deny if path.is_dir() => {
+					let mut tmpfs_arg = path.into_os_string();
+					tmpfs_arg.push(":ro");
+					vec!["--tmpfs".into(), tmpfs_arg]
+				}
+				VirtualFSEntryType::Deny if path.exists() => {
+					volume_args(Path::new("/dev/null"), &path, ":ro")
+				}
+				VirtualFSEntryType::Deny => Vec::new(),
+			};
+			args.append(&mut path_args);
+		}
+		Ok(args)
+	}
+}
+// 'options' is appended to the volume specification (e.g. ":ro")
+fn volume_args(source: &Path, dest: &Path, options: &str) -> Vec<OsString> {
+	let mut volume_spec = source.as_os_str().to_owned();
+	volume_spec.push(":");
+	volume_spec.push(dest);
+	volume_spec.push(options);
+	vec!["--volume".into(), volume_spec]
+}
+// NOTE: 'PATH' is never transferred, as the image has its own layout
+fn get_envvar_args(envvar_whitelist: &EnvVarWhitelist) -> Vec<OsString> {
+	let envvars = match envvar_whitelist {
+		EnvVarWhitelist::All => env::vars_os().collect::<Vec<_>>(),
+		EnvVarWhitelist::List(list) => list
+			.iter()
+			.filter_map(|name| Some((name.clone(), env::var_os(name)?)))
+			.collect(),
+	};
+	let mut args = Vec::new();
+	for (name, value) in envvars {
+		if name == "PATH" {
+			continue;
+		}
+		let mut envvar_arg = name;
+		envvar_arg.push("=");
+		envvar_arg.push(value);
+		args.extend_from_slice(&["--env".into(), envvar_arg]);
+	}
+	args
+}
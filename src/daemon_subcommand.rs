@@ -0,0 +1,302 @@
+use std::{
+	collections::HashMap,
+	fs,
+	io::{self, BufRead as _, BufReader, Read as _, Write as _},
+	net::Shutdown,
+	os::unix::net::{UnixListener, UnixStream},
+	path::{Path, PathBuf},
+	process::{self, ExitCode},
+	sync::Arc,
+	time::SystemTime,
+};
+
+use nix::{
+	sys::wait::{self, WaitPidFlag, WaitStatus},
+	unistd::{self, ForkResult},
+};
+
+use crate::{
+	dirs::Dirs,
+	open_stats,
+	parse::{FileDatabase, ParseContext, ProjectDataFuture},
+	sandbox::{LaunchPlan, LogConfig},
+	watch::ConfigWatcher,
+	ColorMode, CtlArgs, CtlCommand, GenericResult, GlobalConfig,
+};
+
+// the protocol is line-based: the client sends a single request line,
+// the daemon responds with a status line (`ok` or `error`) followed by the output
+pub fn run(
+	file_database: &mut FileDatabase,
+	dirs: Arc<dyn Dirs>,
+	color: ColorMode,
+) -> GenericResult<ExitCode> {
+	let socket_path = get_socket_path(&*dirs)?;
+	let listener = bind_socket(&socket_path)?;
+
+	let mut parse_ctx = ParseContext::new(file_database, dirs, color);
+	// NOTE: stdin of the daemon is not connected to the client
+	parse_ctx.disable_trust_prompt();
+	let mut state = DaemonState::load(&mut parse_ctx)?;
+	// NOTE: without a watcher, changes are only picked up with `skeld ctl reload`
+	let watcher = ConfigWatcher::new(&*parse_ctx.dirs).ok();
+	println!("Listening on `{}`", socket_path.display());
+
+	for stream in listener.incoming() {
+		// NOTE: a failed connection should not stop the daemon
+		let Ok(stream) = stream else {
+			continue;
+		};
+		// NOTE: changes are applied lazily before the next request
+		if watcher.as_ref().is_some_and(ConfigWatcher::has_changed) {
+			// NOTE: if the reload fails, the previous state is kept
+			let reload_result = parse_ctx.reload(DaemonState::load, |err, files| err.print(files, color));
+			if let Ok(new_state) = reload_result {
+				state = new_state;
+			}
+		}
+		handle_client(stream, &mut state, &mut parse_ctx);
+		reap_children();
+	}
+	Ok(ExitCode::SUCCESS)
+}
+pub fn run_client(args: CtlArgs, dirs: &dyn Dirs) -> GenericResult<ExitCode> {
+	let socket_path = get_socket_path(dirs)?;
+	let mut stream = UnixStream::connect(&socket_path).map_err(|err| {
+		format!(
+			concat!(
+				"Failed to connect to the skeld daemon at `{}`: {}\n",
+				"  NOTE: The daemon can be started with `skeld daemon`."
+			),
+			socket_path.display(),
+			err
+		)
+	})?;
+
+	let request = match args.command {
+		CtlCommand::List => "list".to_string(),
+		CtlCommand::Open { name } => format!("open {name}"),
+		CtlCommand::Reload => "reload".to_string(),
+	};
+	let mut communicate = || -> io::Result<String> {
+		writeln!(stream, "{request}")?;
+		stream.shutdown(Shutdown::Write)?;
+		let mut response = String::new();
+		stream.read_to_string(&mut response)?;
+		Ok(response)
+	};
+	let response =
+		communicate().map_err(|err| format!("Failed to communicate with the skeld daemon: {err}"))?;
+
+	match response.split_once('\n') {
+		Some(("ok", output)) => {
+			print!("{output}");
+			Ok(ExitCode::SUCCESS)
+		}
+		Some(("error", output)) => {
+			eprint!("{output}");
+			Ok(ExitCode::FAILURE)
+		}
+		_ => Err("Received an invalid response from the skeld daemon".into()),
+	}
+}
+
+fn get_socket_path(dirs: &dyn Dirs) -> GenericResult<PathBuf> {
+	let runtime_dir = dirs
+		.get_xdg_runtime_dir()
+		.map_err(|err| format!("Failed to determine the runtime directory:\n  {err}"))?;
+	Ok(runtime_dir.join("skeld.sock"))
+}
+fn bind_socket(socket_path: &Path) -> GenericResult<UnixListener> {
+	if socket_path.exists() {
+		if UnixStream::connect(socket_path).is_ok() {
+			return Err(
+				format!(
+					"A skeld daemon is already listening on `{}`",
+					socket_path.display()
+				)
+				.into(),
+			);
+		}
+		// NOTE: the socket is left over from a daemon that has not terminated cleanly
+		let _ = fs::remove_file(socket_path);
+	}
+	let listener = UnixListener::bind(socket_path).map_err(|err| {
+		format!(
+			"Failed to bind the socket `{}`: {err}",
+			socket_path.display()
+		)
+	})?;
+	Ok(listener)
+}
+
+struct DaemonState {
+	config: GlobalConfig,
+	// (name, project), bookmarks come first
+	entries: Vec<(String, ProjectDataFuture)>,
+	// launch plans of opened projects by name,
+	// which are reused until one of their files changes
+	launch_plans: HashMap<String, CachedLaunchPlan>,
+}
+impl DaemonState {
+	fn load(parse_ctx: &mut ParseContext) -> GenericResult<Self> {
+		let config = parse_ctx.get_global_config()?;
+		let bookmarks = parse_ctx
+			.get_bookmarks()?
+			.into_iter()
+			.map(|data| (data.name, data.project_data));
+		let projects = parse_ctx
+			.get_projects()?
+			.into_iter()
+			.map(|data| (data.name, data.project_data));
+		Ok(Self {
+			config,
+			entries: bookmarks.chain(projects).collect(),
+			launch_plans: HashMap::new(),
+		})
+	}
+	fn get_launch_plan(
+		&mut self,
+		name: &str,
+		parse_ctx: &mut ParseContext,
+	) -> GenericResult<LaunchPlan> {
+		if let Some(cached_plan) = self.launch_plans.get(name) {
+			if !cached_plan.is_outdated() {
+				return Ok(cached_plan.plan.clone());
+			}
+		}
+		let project_data = self
+			.get_project(name)?
+			.clone()
+			.load(self.config.global_project_data.clone(), parse_ctx)?;
+		let source_files = project_data
+			.source_files
+			.iter()
+			.map(|path| (path.clone(), get_modification_time(path)))
+			.collect();
+		let plan = project_data
+			.plan_launch(&*parse_ctx.dirs)
+			.map_err(|err| err.to_string())?;
+		let cached_plan = CachedLaunchPlan {
+			plan: plan.clone(),
+			source_files,
+		};
+		self.launch_plans.insert(name.to_string(), cached_plan);
+		Ok(plan)
+	}
+	fn get_project(&self, name: &str) -> GenericResult<&ProjectDataFuture> {
+		let (_, project) = self
+			.entries
+			.iter()
+			.find(|(entry_name, _)| entry_name == name)
+			.ok_or_else(|| format!("Unknown project `{name}`"))?;
+		Ok(project)
+	}
+}
+struct CachedLaunchPlan {
+	plan: LaunchPlan,
+	// (path, modification time or None if it does not exist)
+	source_files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+impl CachedLaunchPlan {
+	fn is_outdated(&self) -> bool {
+		self
+			.source_files
+			.iter()
+			.any(|(path, time)| get_modification_time(path) != *time)
+	}
+}
+fn get_modification_time(path: &Path) -> Option<SystemTime> {
+	fs::metadata(path)
+		.and_then(|metadata| metadata.modified())
+		.ok()
+}
+
+fn handle_client(stream: UnixStream, state: &mut DaemonState, parse_ctx: &mut ParseContext) {
+	let mut request = String::new();
+	if BufReader::new(&stream).read_line(&mut request).is_err() {
+		return;
+	}
+	let response = match execute_request(request.trim_end(), state, parse_ctx) {
+		Ok(output) => format!("ok\n{output}"),
+		Err(err) => {
+			let mut output = err.to_plain_string(parse_ctx.file_database);
+			if !output.ends_with('\n') {
+				output.push('\n');
+			}
+			format!("error\n{output}")
+		}
+	};
+	// NOTE: the client may have already disconnected
+	let _ = (&stream).write_all(response.as_bytes());
+}
+fn execute_request(
+	request: &str,
+	state: &mut DaemonState,
+	parse_ctx: &mut ParseContext,
+) -> GenericResult<String> {
+	let (command, argument) = request.split_once(' ').unwrap_or((request, ""));
+	match command {
+		"list" => Ok(
+			state
+				.entries
+				.iter()
+				.map(|(name, _)| format!("{name}\n"))
+				.collect(),
+		),
+		"open" => {
+			let plan = state.get_launch_plan(argument, parse_ctx)?;
+			open_in_child(plan, &state.config.log_config, &*parse_ctx.dirs)?;
+			// NOTE: the project has been opened nevertheless
+			let state_key = state.get_project(argument)?.state_key();
+			if let Err(err) = open_stats::record_open(&state_key, argument, &*parse_ctx.dirs) {
+				eprintln!("{err}");
+			}
+			Ok(format!("Opened `{argument}`\n"))
+		}
+		"reload" => {
+			// NOTE: if the reload fails, the previous state is kept
+			*state = parse_ctx.reload(DaemonState::load, |err, files| err.to_plain_string(files))?;
+			Ok(format!("Loaded {} projects\n", state.entries.len()))
+		}
+		_ => {
+			Err(format!("Unknown command `{command}`, expected `list`, `open <NAME>` or `reload`").into())
+		}
+	}
+}
+fn open_in_child(plan: LaunchPlan, log_config: &LogConfig, dirs: &dyn Dirs) -> GenericResult<()> {
+	// NOTE: the daemon has no terminal to pass on to the editor
+	if !plan.detach {
+		return Err(
+			format!(
+				"The editor of `{}` is not detached, so it cannot be opened by the daemon",
+				plan.name
+			)
+			.into(),
+		);
+	}
+
+	// SAFETY: the daemon is single-threaded
+	match unsafe { unistd::fork() } {
+		Ok(ForkResult::Child) => {
+			let exit_code = match plan.execute(log_config, dirs) {
+				Ok(_) => 0,
+				Err(err) => {
+					eprintln!("{err}");
+					1
+				}
+			};
+			process::exit(exit_code);
+		}
+		Ok(ForkResult::Parent { .. }) => Ok(()),
+		Err(err) => Err(format!("Failed to fork the daemon: {err}").into()),
+	}
+}
+fn reap_children() {
+	loop {
+		match wait::waitpid(None, Some(WaitPidFlag::WNOHANG)) {
+			Ok(WaitStatus::StillAlive) | Err(_) => break,
+			Ok(_) => (),
+		}
+	}
+}
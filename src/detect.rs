@@ -0,0 +1,58 @@
+use std::path::Path;
+
+use crate::dirs::Dirs;
+
+// language of a project, detected by the manifest in its directory
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+	Rust,
+	JavaScript,
+	Go,
+	Python,
+}
+impl Language {
+	// (language, manifest file), in the order they are checked
+	const MANIFESTS: [(Self, &'static str); 4] = [
+		(Self::Rust, "Cargo.toml"),
+		(Self::Go, "go.mod"),
+		(Self::Python, "pyproject.toml"),
+		(Self::JavaScript, "package.json"),
+	];
+
+	// NOTE: only the top level of 'project_dir' is inspected, so that
+	//       nested projects (e.g. a website of a rust crate) are ignored
+	pub fn detect(project_dir: impl AsRef<Path>) -> Option<Self> {
+		let project_dir = project_dir.as_ref();
+		Self::MANIFESTS
+			.into_iter()
+			.find(|(_, manifest)| project_dir.join(manifest).is_file())
+			.map(|(language, _)| language)
+	}
+	// name of the include file that is used by default, e.g. `rust.toml`
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::Rust => "rust",
+			Self::JavaScript => "javascript",
+			Self::Go => "go",
+			Self::Python => "python",
+		}
+	}
+	// nerd font glyph
+	pub fn icon(self) -> &'static str {
+		match self {
+			Self::Rust => "\u{e7a8}",
+			Self::JavaScript => "\u{e74e}",
+			Self::Go => "\u{e627}",
+			Self::Python => "\u{e73c}",
+		}
+	}
+	// the include file `<SKELD-DATA>/include/<name>.toml`,
+	// if it exists in any of the skeld data directories
+	pub fn default_include(self, dirs: &dyn Dirs) -> Option<&'static str> {
+		let data_dirs = dirs.get_skeld_data_dirs().ok()?;
+		data_dirs
+			.iter()
+			.any(|dir| dir.join(format!("include/{}.toml", self.name())).is_file())
+			.then_some(self.name())
+	}
+}
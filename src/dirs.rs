@@ -6,7 +6,7 @@ use std::{
 };
 
 #[allow(clippy::enum_variant_names)]
-#[derive(Debug, derive_more::Display)]
+#[derive(Clone, Debug, derive_more::Display)]
 pub enum Error {
 	#[display("home directory could not be determined")]
 	UnknownHomeDir,
@@ -20,23 +20,139 @@ pub enum Error {
 		dir.display()
 	)]
 	RelativeXdgBaseDir { varname: String, dir: PathBuf },
+	#[display("`XDG_RUNTIME_DIR` is not set")]
+	UnknownRuntimeDir,
 }
 impl std::error::Error for Error {}
 type ModResult<T> = Result<T, Error>;
 
-pub fn get_xdg_config_dir() -> ModResult<PathBuf> {
-	get_xdg_base_dir("XDG_CONFIG_HOME", ".config")
+// lookup of the directories used by skeld
+// NOTE: only the base directories have to be provided,
+//       the skeld directories are derived from them
+pub trait Dirs: Send + Sync {
+	fn get_home_dir(&self) -> ModResult<PathBuf>;
+	fn get_xdg_config_dir(&self) -> ModResult<PathBuf>;
+	fn get_xdg_cache_dir(&self) -> ModResult<PathBuf>;
+	fn get_xdg_data_dir(&self) -> ModResult<PathBuf>;
+	fn get_xdg_state_dir(&self) -> ModResult<PathBuf>;
+	// NOTE: there is no fallback for the runtime directory
+	fn get_xdg_runtime_dir(&self) -> ModResult<PathBuf>;
+	fn get_xdg_system_data_dirs(&self) -> Vec<PathBuf>;
+
+	fn get_skeld_config_dir(&self) -> ModResult<PathBuf> {
+		Ok(self.get_xdg_config_dir()?.join("skeld"))
+	}
+	fn get_skeld_data_dir(&self) -> ModResult<PathBuf> {
+		Ok(self.get_xdg_data_dir()?.join("skeld"))
+	}
+	// NOTE: system-wide directories come last
+	fn get_skeld_data_dirs(&self) -> ModResult<Vec<PathBuf>> {
+		let mut dirs = vec![self.get_skeld_config_dir()?, self.get_skeld_data_dir()?];
+		dirs.append(&mut self.get_skeld_system_data_dirs());
+		Ok(dirs)
+	}
+	// files in these directories are not controlled by the user
+	fn get_skeld_system_data_dirs(&self) -> Vec<PathBuf> {
+		self
+			.get_xdg_system_data_dirs()
+			.into_iter()
+			.map(|dir| dir.join("skeld"))
+			.collect()
+	}
+	fn get_skeld_state_dir(&self) -> ModResult<PathBuf> {
+		Ok(self.get_xdg_state_dir()?.join("skeld"))
+	}
 }
-pub fn get_xdg_cache_dir() -> ModResult<PathBuf> {
-	get_xdg_base_dir("XDG_CACHE_HOME", ".cache")
+
+// skeld directories given on the command line (e.g. `--data-dir`),
+// which replace the directories derived from the environment
+#[derive(Clone, Debug, Default)]
+pub struct DirOverrides {
+	pub data_dir: Option<PathBuf>,
+	pub state_dir: Option<PathBuf>,
 }
-pub fn get_xdg_data_dir() -> ModResult<PathBuf> {
-	get_xdg_base_dir("XDG_DATA_HOME", ".local/share")
+
+// directories according to the XDG Base Directory Specification
+// NOTE: the environment is only read once on creation
+pub struct XdgDirs {
+	home_dir: ModResult<PathBuf>,
+	config_dir: ModResult<PathBuf>,
+	cache_dir: ModResult<PathBuf>,
+	data_dir: ModResult<PathBuf>,
+	state_dir: ModResult<PathBuf>,
+	runtime_dir: ModResult<PathBuf>,
+	system_data_dirs: Vec<PathBuf>,
+	overrides: DirOverrides,
 }
-pub fn get_xdg_state_dir() -> ModResult<PathBuf> {
-	get_xdg_base_dir("XDG_STATE_HOME", ".local/state")
+impl XdgDirs {
+	pub fn new(overrides: DirOverrides) -> Self {
+		let home_dir = read_home_dir();
+		let base_dir = |env_var, fallback| read_xdg_base_dir(env_var, fallback, &home_dir);
+		Self {
+			config_dir: base_dir("XDG_CONFIG_HOME", ".config"),
+			cache_dir: base_dir("XDG_CACHE_HOME", ".cache"),
+			data_dir: base_dir("XDG_DATA_HOME", ".local/share"),
+			state_dir: base_dir("XDG_STATE_HOME", ".local/state"),
+			runtime_dir: read_xdg_runtime_dir(),
+			system_data_dirs: read_xdg_system_data_dirs(),
+			home_dir,
+			overrides,
+		}
+	}
+}
+impl Dirs for XdgDirs {
+	fn get_home_dir(&self) -> ModResult<PathBuf> {
+		self.home_dir.clone()
+	}
+	fn get_xdg_config_dir(&self) -> ModResult<PathBuf> {
+		self.config_dir.clone()
+	}
+	fn get_xdg_cache_dir(&self) -> ModResult<PathBuf> {
+		self.cache_dir.clone()
+	}
+	fn get_xdg_data_dir(&self) -> ModResult<PathBuf> {
+		self.data_dir.clone()
+	}
+	fn get_xdg_state_dir(&self) -> ModResult<PathBuf> {
+		self.state_dir.clone()
+	}
+	fn get_xdg_runtime_dir(&self) -> ModResult<PathBuf> {
+		self.runtime_dir.clone()
+	}
+	fn get_xdg_system_data_dirs(&self) -> Vec<PathBuf> {
+		self.system_data_dirs.clone()
+	}
+
+	fn get_skeld_data_dir(&self) -> ModResult<PathBuf> {
+		if let Some(data_dir) = &self.overrides.data_dir {
+			return Ok(data_dir.clone());
+		}
+		Ok(self.get_xdg_data_dir()?.join("skeld"))
+	}
+	fn get_skeld_state_dir(&self) -> ModResult<PathBuf> {
+		if let Some(state_dir) = &self.overrides.state_dir {
+			return Ok(state_dir.clone());
+		}
+		Ok(self.get_xdg_state_dir()?.join("skeld"))
+	}
 }
-fn get_xdg_base_dir(env_var: &str, fallback: &str) -> ModResult<PathBuf> {
+fn read_home_dir() -> ModResult<PathBuf> {
+	let home_dir_path = match env::var_os("HOME") {
+		Some(val) if !val.is_empty() => val.into(),
+		_ => get_home_dir_from_passwd().ok_or(Error::UnknownHomeDir)?,
+	};
+
+	if home_dir_path.is_relative() {
+		return Err(Error::RelativeHomeDir { dir: home_dir_path });
+	}
+
+	Ok(home_dir_path)
+}
+fn read_xdg_base_dir(
+	env_var: &str,
+	fallback: &str,
+	home_dir: &ModResult<PathBuf>,
+) -> ModResult<PathBuf> {
 	match env::var_os(env_var) {
 		Some(env_var_val) if !env_var_val.is_empty() => {
 			let path: PathBuf = env_var_val.into();
@@ -49,34 +165,33 @@ fn get_xdg_base_dir(env_var: &str, fallback: &str) -> ModResult<PathBuf> {
 				Ok(path)
 			}
 		}
-		_ => Ok(get_home_dir()?.join(fallback)),
+		_ => Ok(home_dir.clone()?.join(fallback)),
 	}
 }
-
-pub fn get_skeld_config_dir() -> ModResult<PathBuf> {
-	Ok(get_xdg_config_dir()?.join("skeld"))
-}
-pub fn get_skeld_data_dir() -> ModResult<PathBuf> {
-	Ok(get_xdg_data_dir()?.join("skeld"))
-}
-pub fn get_skeld_data_dirs() -> ModResult<Vec<PathBuf>> {
-	Ok(vec![get_skeld_config_dir()?, get_skeld_data_dir()?])
-}
-pub fn get_skeld_state_dir() -> ModResult<PathBuf> {
-	Ok(get_xdg_state_dir()?.join("skeld"))
+fn read_xdg_runtime_dir() -> ModResult<PathBuf> {
+	match env::var_os("XDG_RUNTIME_DIR") {
+		Some(env_var_val) if !env_var_val.is_empty() => {
+			let path: PathBuf = env_var_val.into();
+			if path.is_relative() {
+				Err(Error::RelativeXdgBaseDir {
+					varname: "XDG_RUNTIME_DIR".to_string(),
+					dir: path,
+				})
+			} else {
+				Ok(path)
+			}
+		}
+		_ => Err(Error::UnknownRuntimeDir),
+	}
 }
-
-pub fn get_home_dir() -> ModResult<PathBuf> {
-	let home_dir_path = match env::var_os("HOME") {
-		Some(val) if !val.is_empty() => val.into(),
-		_ => get_home_dir_from_passwd().ok_or(Error::UnknownHomeDir)?,
-	};
-
-	if home_dir_path.is_relative() {
-		return Err(Error::RelativeHomeDir { dir: home_dir_path });
+fn read_xdg_system_data_dirs() -> Vec<PathBuf> {
+	match env::var_os("XDG_DATA_DIRS") {
+		Some(env_var_val) if !env_var_val.is_empty() => env::split_paths(&env_var_val)
+			// NOTE: relative paths are invalid and should be ignored according to the spec
+			.filter(|path| path.is_absolute())
+			.collect(),
+		_ => vec!["/usr/local/share".into(), "/usr/share".into()],
 	}
-
-	Ok(home_dir_path)
 }
 fn get_home_dir_from_passwd() -> Option<PathBuf> {
 	let passwd_ptr = unsafe { libc::getpwuid(libc::getuid()) };
@@ -1,9 +1,9 @@
 use std::{convert::From, error::Error, io};
 
 use codespan_reporting::term::{self, termcolor};
-use crossterm::tty::IsTty as _;
+use crossterm::{terminal, tty::IsTty as _};
 
-use crate::parse::FileDatabase;
+use crate::{parse::FileDatabase, ColorMode};
 
 pub use crate::parse::Diagnostic;
 
@@ -15,22 +15,90 @@ pub enum GenericError {
 }
 pub type GenericResult<T> = Result<T, GenericError>;
 
+// exit codes of skeld, apart from the exit code of the editor,
+// which is forwarded if the editor has been started
+// NOTE: the codes of sysexits.h are used, as editors rarely return them
+pub mod exit_code {
+	// invalid configuration, project or bookmark files
+	pub const CONFIG_ERROR: u8 = 78;
+	// the selected project could not be prepared for opening
+	pub const PROJECT_ERROR: u8 = 65;
+	// the editor or command could not be started
+	pub const LAUNCH_ERROR: u8 = 69;
+	// the user aborted the selection (like SIGINT in shells)
+	pub const USER_ABORT: u8 = 130;
+}
+// error together with the exit code of skeld
+#[derive(Debug)]
+pub struct ExitError {
+	pub error: GenericError,
+	pub code: u8,
+}
+// NOTE: errors without a specific exit code use the generic code 1
+impl<E: Into<GenericError>> From<E> for ExitError {
+	fn from(error: E) -> Self {
+		Self {
+			error: error.into(),
+			code: 1,
+		}
+	}
+}
+pub trait WithExitCode<T> {
+	fn with_exit_code(self, code: u8) -> Result<T, ExitError>;
+}
+impl<T, E: Into<GenericError>> WithExitCode<T> for Result<T, E> {
+	fn with_exit_code(self, code: u8) -> Result<T, ExitError> {
+		self.map_err(|error| ExitError {
+			error: error.into(),
+			code,
+		})
+	}
+}
+
 impl GenericError {
-	pub fn print(&self, files: &FileDatabase) {
+	pub fn print(&self, files: &FileDatabase, color: ColorMode) {
 		match self {
-			GenericError::Diagnostic(diag) => Self::print_diagnostic(diag, files),
+			GenericError::Diagnostic(diag) => Self::print_diagnostic(diag, files, color),
 			GenericError::Generic(msg) => eprintln!("{msg}"),
 		}
 	}
-	fn print_diagnostic(diag: &Diagnostic, files: &FileDatabase) {
-		let color_choice = if io::stderr().is_tty() {
-			termcolor::ColorChoice::Auto
+	// renders the error without colors, e.g. to send it to another process
+	pub fn to_plain_string(&self, files: &FileDatabase) -> String {
+		match self {
+			GenericError::Diagnostic(diag) => {
+				let mut writer = termcolor::NoColor::new(Vec::new());
+				let config = term::Config::default();
+				match term::emit(&mut writer, &config, files, diag) {
+					Ok(()) => String::from_utf8_lossy(&writer.into_inner()).into_owned(),
+					Err(_) => format!("error: {}", diag.message),
+				}
+			}
+			GenericError::Generic(msg) => msg.to_string(),
+		}
+	}
+	fn print_diagnostic(diag: &Diagnostic, files: &FileDatabase, color: ColorMode) {
+		let is_tty = io::stderr().is_tty();
+		let color_choice = if color.use_color(is_tty) {
+			termcolor::ColorChoice::Always
 		} else {
 			termcolor::ColorChoice::Never
 		};
 		let writer = termcolor::StandardStream::stderr(color_choice);
 		let config = term::Config::default();
-		let writer_error = term::emit(&mut writer.lock(), &config, files, diag);
+
+		let mut diag = diag.clone();
+		if let Some((width, _)) = terminal::size().ok().filter(|_| is_tty) {
+			let width = usize::from(width);
+			// NOTE: notes are indented by 4 columns (`  = `)
+			diag.notes = diag
+				.notes
+				.iter()
+				.map(|note| wrap_text(note, width.saturating_sub(4)))
+				.collect();
+			// NOTE: the message is prefixed with `error: `
+			diag.message = wrap_text(&diag.message, width.saturating_sub(7));
+		}
+		let writer_error = term::emit(&mut writer.lock(), &config, files, &diag);
 		if writer_error.is_err() {
 			eprintln!(
 				"Failed to pretty-print error, here is the raw version:\nerror: {}",
@@ -40,6 +108,29 @@ impl GenericError {
 	}
 }
 
+// wraps lines longer than 'width' at whitespace
+fn wrap_text(text: &str, width: usize) -> String {
+	let mut wrapped_lines = Vec::new();
+	for line in text.lines() {
+		let indent_len = line.len() - line.trim_start().len();
+		let indent = &line[..indent_len];
+		let mut current_line = indent.to_string();
+		for word in line.split_whitespace() {
+			let is_line_empty = current_line.len() == indent_len;
+			if !is_line_empty && current_line.len() + 1 + word.len() > width {
+				wrapped_lines.push(current_line);
+				// NOTE: continuation lines keep the indentation
+				current_line = indent.to_string();
+			} else if !is_line_empty {
+				current_line.push(' ');
+			}
+			current_line.push_str(word);
+		}
+		wrapped_lines.push(current_line);
+	}
+	wrapped_lines.join("\n")
+}
+
 impl From<&str> for GenericError {
 	fn from(value: &str) -> Self {
 		Self::Generic(value.into())
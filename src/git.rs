@@ -0,0 +1,72 @@
+use std::{
+	fs,
+	path::{Path, PathBuf},
+};
+
+// root of the git worktree containing 'dir', found by walking up to the
+// first directory with a `.git` entry (a directory, or a file for linked
+// worktrees and submodules)
+pub fn find_worktree_root(dir: impl AsRef<Path>) -> Option<PathBuf> {
+	dir
+		.as_ref()
+		.ancestors()
+		.find(|dir| dir.join(".git").exists())
+		.map(Path::to_path_buf)
+}
+
+// name of the repository derived from the url of the remote 'origin'
+// (or of the first remote), e.g. `skeld` for `git@github.com:hacrvlq/skeld.git`
+pub fn get_remote_name(worktree_root: impl AsRef<Path>) -> Option<String> {
+	let config_path = get_common_dir(worktree_root.as_ref())?.join("config");
+	let config = fs::read_to_string(config_path).ok()?;
+	let remote_urls = get_remote_urls(&config);
+	let (_, url) = remote_urls
+		.iter()
+		.find(|(remote, _)| remote == "origin")
+		.or(remote_urls.first())?;
+
+	let url = url.trim_end_matches('/');
+	let url = url.strip_suffix(".git").unwrap_or(url);
+	// NOTE: scp-like urls (e.g. `host:repo`) have no slash before the name
+	let name = url.rsplit(['/', ':']).next()?;
+	(!name.is_empty()).then(|| name.to_string())
+}
+// the directory with the config of the repository
+// NOTE: in linked worktrees, `.git` is a file pointing to the git directory,
+//       which in turn points to the common directory of the repository
+fn get_common_dir(worktree_root: &Path) -> Option<PathBuf> {
+	let dot_git = worktree_root.join(".git");
+	if dot_git.is_dir() {
+		return Some(dot_git);
+	}
+
+	let contents = fs::read_to_string(&dot_git).ok()?;
+	let git_dir = worktree_root.join(contents.trim().strip_prefix("gitdir:")?.trim());
+	match fs::read_to_string(git_dir.join("commondir")) {
+		Ok(common_dir) => Some(git_dir.join(common_dir.trim())),
+		Err(_) => Some(git_dir),
+	}
+}
+// (remote name, url) of all remotes in the git config 'config'
+// NOTE: only the subset of the git config syntax used by `git remote add`
+//       is supported
+fn get_remote_urls(config: &str) -> Vec<(String, String)> {
+	let mut remote_urls = Vec::new();
+	let mut current_remote = None;
+	for line in config.lines().map(str::trim) {
+		if line.starts_with('[') {
+			current_remote = line
+				.strip_prefix("[remote \"")
+				.and_then(|line| line.strip_suffix("\"]"))
+				.map(str::to_string);
+		} else if let Some(remote) = &current_remote {
+			let Some((key, value)) = line.split_once('=') else {
+				continue;
+			};
+			if key.trim() == "url" {
+				remote_urls.push((remote.clone(), value.trim().to_string()));
+			}
+		}
+	}
+	remote_urls
+}
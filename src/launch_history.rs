@@ -0,0 +1,143 @@
+use std::{
+	fs::{self, OpenOptions},
+	io::{self, Write as _},
+	os::unix::process::ExitStatusExt as _,
+	path::PathBuf,
+	process::ExitStatus,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{atomic_file, dirs::Dirs, lock::FileLock};
+
+// NOTE: older entries are removed when the history grows beyond this size
+const MAX_HISTORY_LEN: usize = 100;
+
+// launch of a detached command, whose exit status is otherwise only visible in the logfile
+pub struct Launch {
+	pub name: String,
+	// seconds since the unix epoch
+	pub start_time: u64,
+	pub status: LaunchStatus,
+	pub logfile: PathBuf,
+}
+#[derive(Clone, Copy)]
+pub enum LaunchStatus {
+	// NOTE: also the case if the supervising process has been killed
+	Running,
+	Exited(i32),
+	Signaled(i32),
+}
+impl LaunchStatus {
+	pub fn from_exit_status(status: ExitStatus) -> Self {
+		match status.code() {
+			Some(code) => Self::Exited(code),
+			None => Self::Signaled(status.signal().unwrap_or_default()),
+		}
+	}
+	pub fn success(self) -> bool {
+		matches!(self, Self::Exited(0))
+	}
+}
+
+pub fn current_time() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|time| time.as_secs())
+		.unwrap_or_default()
+}
+
+pub fn record(launch: &Launch, dirs: &dyn Dirs) -> Result<(), String> {
+	let history_path = get_history_path(dirs)?;
+	let status = match launch.status {
+		LaunchStatus::Running => "running".to_string(),
+		LaunchStatus::Exited(code) => format!("exit:{code}"),
+		LaunchStatus::Signaled(signal) => format!("signal:{signal}"),
+	};
+	// NOTE: tabs and newlines are the separators of the history file
+	let name = launch.name.replace(['\t', '\n'], " ");
+	let entry = format!(
+		"{}\t{name}\t{status}\t{}\n",
+		launch.start_time,
+		launch.logfile.display()
+	);
+
+	let _history_lock = FileLock::acquire("launches", dirs)?;
+	let append_entry = || -> io::Result<()> {
+		fs::create_dir_all(history_path.parent().unwrap())?;
+		OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(&history_path)?
+			.write_all(entry.as_bytes())
+	};
+	append_entry().map_err(|err| {
+		format!(
+			"Failed to record the launch in `{}`: {err}",
+			history_path.display()
+		)
+	})?;
+
+	// errors are silently ignored, as the history is still usable
+	if let Ok(contents) = fs::read_to_string(&history_path) {
+		let lines = contents.lines().collect::<Vec<_>>();
+		if lines.len() > 2 * MAX_HISTORY_LEN {
+			let mut trimmed_contents = lines[lines.len() - MAX_HISTORY_LEN..].join("\n");
+			trimmed_contents.push('\n');
+			let _ = atomic_file::write(&history_path, trimmed_contents);
+		}
+	}
+	Ok(())
+}
+// returns the recorded launches, starting with the oldest
+pub fn read(dirs: &dyn Dirs) -> Result<Vec<Launch>, String> {
+	let history_path = get_history_path(dirs)?;
+	let contents = match fs::read_to_string(&history_path) {
+		Ok(contents) => contents,
+		Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(err) => {
+			return Err(format!(
+				"Failed to read the launch history `{}`: {err}",
+				history_path.display()
+			))
+		}
+	};
+	// NOTE: malformed entries are skipped
+	let mut launches: Vec<Launch> = Vec::new();
+	for launch in contents.lines().filter_map(parse_entry) {
+		// the final status of a launch is recorded as a separate entry
+		let prev_entry = launches.iter_mut().find(|prev_launch| {
+			prev_launch.start_time == launch.start_time
+				&& prev_launch.name == launch.name
+				&& prev_launch.logfile == launch.logfile
+		});
+		match prev_entry {
+			Some(prev_launch) => prev_launch.status = launch.status,
+			None => launches.push(launch),
+		}
+	}
+	Ok(launches)
+}
+fn parse_entry(line: &str) -> Option<Launch> {
+	let mut fields = line.splitn(4, '\t');
+	let start_time = fields.next()?.parse().ok()?;
+	let name = fields.next()?.to_string();
+	let status = match fields.next()?.split_once(':').unwrap_or(("running", "")) {
+		("running", _) => LaunchStatus::Running,
+		("exit", code) => LaunchStatus::Exited(code.parse().ok()?),
+		("signal", signal) => LaunchStatus::Signaled(signal.parse().ok()?),
+		_ => return None,
+	};
+	let logfile = fields.next()?.into();
+	Some(Launch {
+		name,
+		start_time,
+		status,
+		logfile,
+	})
+}
+fn get_history_path(dirs: &dyn Dirs) -> Result<PathBuf, String> {
+	let state_dir = dirs
+		.get_skeld_state_dir()
+		.map_err(|err| format!("Failed to determine the skeld state directory:\n  {err}"))?;
+	Ok(state_dir.join("launches"))
+}
@@ -0,0 +1,51 @@
+use std::{
+	fs::{self, File},
+	path::PathBuf,
+};
+
+use nix::fcntl::{Flock, FlockArg};
+
+use crate::dirs::Dirs;
+
+// lock held while files in the skeld data directory are created or modified
+pub const DATA_LOCK: &str = "data";
+
+// advisory lock, that serializes modifications of shared files
+// between concurrent skeld processes
+// NOTE: the lock is released when this is dropped
+pub struct FileLock {
+	_lock: Flock<File>,
+}
+impl FileLock {
+	// blocks until the lock named 'name' is acquired
+	pub fn acquire(name: &str, dirs: &dyn Dirs) -> Result<Self, String> {
+		let lock_path = get_lock_path(name, dirs)?;
+		let open_lock_file = || {
+			fs::create_dir_all(lock_path.parent().unwrap())?;
+			File::options()
+				.create(true)
+				.truncate(false)
+				.write(true)
+				.open(&lock_path)
+		};
+		let lock_file = open_lock_file().map_err(|err| {
+			format!(
+				"Failed to open the lock file `{}`: {err}",
+				lock_path.display()
+			)
+		})?;
+		let lock = Flock::lock(lock_file, FlockArg::LockExclusive).map_err(|(_, err)| {
+			format!(
+				"Failed to lock the lock file `{}`: {err}",
+				lock_path.display()
+			)
+		})?;
+		Ok(Self { _lock: lock })
+	}
+}
+fn get_lock_path(name: &str, dirs: &dyn Dirs) -> Result<PathBuf, String> {
+	let state_dir = dirs
+		.get_skeld_state_dir()
+		.map_err(|err| format!("Failed to determine the skeld state directory:\n  {err}"))?;
+	Ok(state_dir.join("locks").join(format!("{name}.lock")))
+}
@@ -0,0 +1,124 @@
+use std::{
+	error::Error,
+	fs::File,
+	io::{self, Read as _, Seek as _, SeekFrom, Write as _},
+	os::unix::fs::MetadataExt as _,
+	path::{Path, PathBuf},
+	thread,
+	time::Duration,
+};
+
+use crate::{
+	dirs::Dirs,
+	launch_history::{self, Launch},
+	AttachArgs, LogsArgs,
+};
+
+type ModResult<T> = Result<T, Box<dyn Error>>;
+
+pub fn run(args: LogsArgs, dirs: &dyn Dirs) -> ModResult<()> {
+	let latest_launch = find_latest_launch(args.name.as_deref(), dirs)?;
+	let (logfile, pos) = print_logfile(&latest_launch.logfile, args.lines)?;
+	if !args.follow {
+		return Ok(());
+	}
+	follow(latest_launch.logfile, logfile, pos, None, dirs)
+}
+// like `logs -f`, but also follows later launches of 'name'
+pub fn attach(args: AttachArgs, dirs: &dyn Dirs) -> ModResult<()> {
+	let latest_launch = find_latest_launch(Some(&args.name), dirs)?;
+	let (logfile, pos) = print_logfile(&latest_launch.logfile, args.lines)?;
+	follow(latest_launch.logfile, logfile, pos, Some(&args.name), dirs)
+}
+
+fn find_latest_launch(name: Option<&str>, dirs: &dyn Dirs) -> ModResult<Launch> {
+	let launches = launch_history::read(dirs)?;
+	let latest_launch = launches
+		.into_iter()
+		.rev()
+		.find(|launch| name.map_or(true, |name| launch.name == name));
+	latest_launch.ok_or_else(|| match name {
+		Some(name) => format!("No detached launch of `{name}` has been recorded").into(),
+		None => "No detached launches have been recorded yet".into(),
+	})
+}
+fn open_logfile(path: &Path) -> ModResult<File> {
+	File::open(path)
+		.map_err(|err| format!("Failed to open the logfile `{}`: {err}", path.display()).into())
+}
+// print the last 'lines' lines of the logfile, returns it with the read position
+fn print_logfile(path: &Path, lines: usize) -> ModResult<(File, u64)> {
+	let mut logfile = open_logfile(path)?;
+	let mut contents = Vec::new();
+	logfile.read_to_end(&mut contents)?;
+
+	let mut stdout = io::stdout();
+	stdout.write_all(get_last_lines(&contents, lines))?;
+	stdout.flush()?;
+	Ok((logfile, contents.len() as u64))
+}
+// keep printing new output of 'logfile' (starting at 'pos'), until interrupted
+// NOTE: if the logfile at 'path' is replaced (e.g. removed and created again),
+//       the new file is followed; with 'name', the logfile of a later
+//       launch of 'name' is followed as well
+fn follow(
+	mut path: PathBuf,
+	mut logfile: File,
+	mut pos: u64,
+	name: Option<&str>,
+	dirs: &dyn Dirs,
+) -> ModResult<()> {
+	let mut stdout = io::stdout();
+	loop {
+		thread::sleep(Duration::from_millis(500));
+		// NOTE: the logfile may have been truncated
+		if logfile.metadata()?.len() < pos {
+			pos = 0;
+		}
+		logfile.seek(SeekFrom::Start(pos))?;
+		let mut new_contents = Vec::new();
+		pos += logfile.read_to_end(&mut new_contents)? as u64;
+		stdout.write_all(&new_contents)?;
+		stdout.flush()?;
+
+		// NOTE: the remaining output of the previous logfile has been printed
+		let latest_logfile = name
+			.and_then(|name| find_latest_launch(Some(name), dirs).ok())
+			.map(|launch| launch.logfile)
+			.filter(|latest_logfile| *latest_logfile != path);
+		if let Some(latest_logfile) = latest_logfile {
+			eprintln!("skeld: following `{}`", latest_logfile.display());
+			path = latest_logfile;
+		} else if !is_replaced(&path, &logfile) {
+			continue;
+		}
+		// NOTE: if the new logfile cannot be opened (yet), it is tried again
+		if let Ok(new_logfile) = File::open(&path) {
+			(logfile, pos) = (new_logfile, 0);
+		}
+	}
+}
+// whether the file at 'path' is not 'file' anymore (if it exists)
+fn is_replaced(path: &Path, file: &File) -> bool {
+	match (path.metadata(), file.metadata()) {
+		(Ok(new), Ok(old)) => (new.dev(), new.ino()) != (old.dev(), old.ino()),
+		_ => false,
+	}
+}
+fn get_last_lines(contents: &[u8], count: usize) -> &[u8] {
+	if count == 0 {
+		return &[];
+	}
+	// NOTE: a trailing newline does not start a new line
+	let trimmed_len = contents
+		.strip_suffix(b"\n")
+		.map_or(contents.len(), <[u8]>::len);
+	let start = contents[..trimmed_len]
+		.iter()
+		.enumerate()
+		.rev()
+		.filter(|(_, byte)| **byte == b'\n')
+		.nth(count.saturating_sub(1))
+		.map_or(0, |(idx, _)| idx + 1);
+	&contents[start..]
+}
@@ -1,16 +1,38 @@
 mod add_subcommand;
+mod atomic_file;
+mod bundle_subcommand;
+mod cli_output;
+mod container;
+mod daemon_subcommand;
+mod detect;
 mod dirs;
 mod error;
+mod git;
+mod launch_history;
+mod lock;
+mod logs_subcommand;
+mod migrate_subcommand;
+mod open_stats;
 mod parse;
 mod project;
+mod rename_subcommand;
+mod running;
 mod sandbox;
+mod shell_init_subcommand;
+mod signals;
+mod stats_subcommand;
+mod status_subcommand;
+mod trust;
 mod ui_subcommand;
+mod watch;
 
-use std::{path::PathBuf, process::ExitCode};
+use std::{env, path::PathBuf, process::ExitCode, sync::Arc};
 
 use clap::Parser as _;
 
 use crate::{
+	dirs::Dirs,
+	error::{exit_code, ExitError, WithExitCode as _},
 	parse::ParseContext,
 	ui_subcommand::{tui, CommandData},
 };
@@ -24,55 +46,437 @@ pub const DOCS_URL: &str = "https://github.com/hacrvlq/skeld/blob/v0.3.0/docs/DO
 struct CliArgs {
 	#[command(subcommand)]
 	subcommand: CliSubcommands,
+	#[arg(long = "color", id = "WHEN", global = true, default_value = "auto")]
+	/// When to use colors
+	color: ColorMode,
+	#[arg(long, global = true, conflicts_with = "WHEN")]
+	/// Never use colors (same as `--color=never`)
+	no_color: bool,
+	#[arg(long = "data-dir", id = "DATA-DIR", global = true)]
+	/// Use DATA-DIR instead of `$XDG_DATA_HOME/skeld`
+	data_dir: Option<PathBuf>,
+	#[arg(long = "state-dir", id = "STATE-DIR", global = true)]
+	/// Use STATE-DIR instead of `$XDG_STATE_HOME/skeld`
+	state_dir: Option<PathBuf>,
+}
+impl CliArgs {
+	fn color(&self) -> ColorMode {
+		if self.no_color {
+			ColorMode::Never
+		} else {
+			self.color
+		}
+	}
+}
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum ColorMode {
+	/// Use colors if the output is a terminal and neither `NO_COLOR` nor `CLICOLOR_FORCE` is set
+	Auto,
+	Always,
+	Never,
+}
+impl ColorMode {
+	// 'is_tty' indicates whether the output in question is a terminal
+	pub fn use_color(self, is_tty: bool) -> bool {
+		let env_var_set = |name| env::var_os(name).is_some_and(|val| !val.is_empty() && val != "0");
+		match self {
+			Self::Always => true,
+			Self::Never => false,
+			// NOTE: 'NO_COLOR' takes precedence over 'CLICOLOR_FORCE'
+			Self::Auto if env_var_set("NO_COLOR") => false,
+			Self::Auto if env_var_set("CLICOLOR_FORCE") => true,
+			Self::Auto => is_tty && env::var_os("TERM").map_or(true, |term| term != "dumb"),
+		}
+	}
 }
 #[derive(clap::Subcommand)]
 enum CliSubcommands {
 	/// Open the skeld tui
-	Ui,
+	Ui(UiArgs),
 	/// Add a project
 	Add(AddArgs),
+	/// Keep projects loaded and listen for commands on `$XDG_RUNTIME_DIR/skeld.sock`
+	Daemon,
+	/// Send a command to a running daemon
+	Ctl(CtlArgs),
+	/// Show the exit status of recent detached launches
+	Status(StatusArgs),
+	/// Show the logfile of the latest detached launch
+	Logs(LogsArgs),
+	/// Follow the output of the detached launches of a project or command
+	Attach(AttachArgs),
+	/// Show how often each project has been opened
+	Stats,
+	/// Rename a project or bookmark and update references to its file
+	Rename(RenameArgs),
+	/// Package projects, bookmarks and their include files into a bundle
+	Export(ExportArgs),
+	/// Add the files of a bundle created with `skeld export`
+	Import(ImportArgs),
+	/// Update the configuration, project and bookmark files to the current format
+	Migrate,
+	/// Inspect the configuration
+	Config(ConfigArgs),
+	/// Print a shell function that changes into the project chosen with the cd key
+	ShellInit {
+		#[arg(id = "SHELL")]
+		shell: Shell,
+	},
+}
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Shell {
+	Bash,
+	Zsh,
+	Fish,
+}
+
+#[derive(clap::Parser)]
+struct UiArgs {
+	#[arg(
+		long = "print-selection",
+		id = "FIELD",
+		num_args = 0..=1,
+		default_missing_value = "path"
+	)]
+	/// Print the selected project instead of opening it
+	print_selection: Option<PrintSelection>,
+	#[arg(long = "stdin-projects")]
+	/// Read additional projects as lines of `name<TAB>path` from stdin
+	stdin_projects: bool,
+	#[arg(long = "show-shadowed")]
+	/// Also list projects shadowed by projects of the same name
+	show_shadowed: bool,
+	#[arg(long = "resume")]
+	/// Open the most recently opened project instead of showing the tui
+	resume: bool,
+	#[arg(long = "quiet", short = 'q')]
+	/// Do not print a notice when detaching from the terminal
+	quiet: bool,
+	#[arg(long = "cd-file", id = "PATH")]
+	/// Enable the cd key, which writes the project directory to PATH and exits
+	cd_file: Option<PathBuf>,
+}
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum PrintSelection {
+	/// Name of the project
+	Name,
+	/// Project directory
+	Path,
 }
 
 #[derive(clap::Parser)]
 struct AddArgs {
 	#[arg(id = "PATH")]
-	/// Path to the project
-	project_path: PathBuf,
+	/// Path to the project (default: the root of the git worktree containing the current
+	/// directory, or the current directory)
+	project_path: Option<PathBuf>,
 	#[arg(long = "name", id = "NAME")]
-	/// Use this name instead of the name derived from the path
+	/// Use this name instead of the name derived from the path (or the git remote)
 	project_name: Option<String>,
+	#[arg(long)]
+	/// Without PATH, add the current directory even inside a git worktree;
+	/// with '--recursive', do not name the projects after their git remote
+	no_git: bool,
+	#[arg(long, requires = "PATH", conflicts_with = "NAME")]
+	/// Add a project for each repository below PATH that has no project file yet
+	recursive: bool,
+	#[arg(long, id = "DEPTH", default_value_t = 2, requires = "recursive")]
+	/// Number of directory levels below PATH that are searched for repositories
+	depth: usize,
+	#[arg(
+		long = "marker",
+		id = "MARKER",
+		default_value = ".git",
+		requires = "recursive"
+	)]
+	/// File or directory that marks a repository, can be repeated
+	markers: Vec<String>,
+	#[arg(long)]
+	/// Do not set the icon and include file based on the detected language
+	no_detect: bool,
+	#[arg(long = "include", id = "INCLUDE")]
+	/// Include this file (like 'include' in the project file), can be repeated
+	includes: Vec<String>,
+	#[arg(long, id = "PRESET")]
+	/// Set the editor of the project instead of relying on the configuration
+	editor: Option<EditorPreset>,
+	#[arg(long = "to", id = "TARGET", default_value = "data", value_parser = parse_add_target)]
+	/// Where the project file is created: 'config' or 'data' (the projects directory
+	/// in the skeld config or data directory) or the path of a directory
+	target: AddTarget,
+}
+#[derive(Clone)]
+enum AddTarget {
+	Config,
+	Data,
+	Dir(PathBuf),
+}
+fn parse_add_target(value: &str) -> Result<AddTarget, String> {
+	match value {
+		"config" => Ok(AddTarget::Config),
+		"data" => Ok(AddTarget::Data),
+		"" => Err("the target must not be empty".to_string()),
+		path => Ok(AddTarget::Dir(path.into())),
+	}
+}
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum EditorPreset {
+	Nvim,
+	Vim,
+	Helix,
+	Emacs,
+	Code,
+	Zed,
+}
+
+#[derive(clap::Parser)]
+struct RenameArgs {
+	#[arg(id = "OLD")]
+	/// Current name of the project or bookmark
+	old_name: String,
+	#[arg(id = "NEW")]
+	/// New name
+	new_name: String,
+}
+
+#[derive(clap::Parser)]
+struct ExportArgs {
+	#[arg(id = "NAME")]
+	/// Projects and bookmarks to export (default: all)
+	names: Vec<String>,
+	#[arg(short = 'o', long = "output", id = "FILE")]
+	/// Path of the created bundle
+	output: PathBuf,
+}
+
+#[derive(clap::Parser)]
+struct ImportArgs {
+	#[arg(id = "FILE")]
+	/// Path to the bundle
+	bundle: PathBuf,
+	#[arg(long = "force")]
+	/// Overwrite existing files
+	force: bool,
+}
+
+#[derive(clap::Parser)]
+struct StatusArgs {
+	#[arg(short = 'n', long = "count", id = "N", default_value_t = 10)]
+	/// Number of launches to show
+	count: usize,
+}
+
+#[derive(clap::Parser)]
+struct LogsArgs {
+	#[arg(id = "NAME")]
+	/// Only consider launches of this project or command
+	name: Option<String>,
+	#[arg(short = 'n', long = "lines", id = "N", default_value_t = 20)]
+	/// Number of lines to show
+	lines: usize,
+	#[arg(short = 'f', long = "follow")]
+	/// Keep printing new output
+	follow: bool,
+}
+
+#[derive(clap::Parser)]
+struct AttachArgs {
+	#[arg(id = "NAME")]
+	/// Name of the project or command
+	name: String,
+	#[arg(short = 'n', long = "lines", id = "N", default_value_t = 20)]
+	/// Number of previous lines to show
+	lines: usize,
+}
+
+#[derive(clap::Parser)]
+struct ConfigArgs {
+	#[command(subcommand)]
+	command: ConfigCommand,
+}
+#[derive(clap::Subcommand)]
+enum ConfigCommand {
+	/// Print the merged configuration, annotated with the origin of each value
+	Dump {
+		#[arg(id = "NAME")]
+		/// Also merge the project data of this project or bookmark
+		name: Option<String>,
+	},
+}
+
+#[derive(clap::Parser)]
+struct CtlArgs {
+	#[command(subcommand)]
+	command: CtlCommand,
+}
+#[derive(clap::Subcommand)]
+enum CtlCommand {
+	/// List the names of all projects and bookmarks
+	List,
+	/// Open a project or bookmark
+	Open {
+		#[arg(id = "NAME")]
+		/// Name of the project or bookmark
+		name: String,
+	},
+	/// Reload the configuration and all projects
+	Reload,
 }
 
 fn main() -> ExitCode {
+	let args = CliArgs::parse();
+	let color = args.color();
+	// NOTE: relative paths are resolved now, as the working directory
+	//       differs e.g. inside of the sandbox
+	let make_absolute = |dir: PathBuf| env::current_dir().unwrap_or_default().join(dir);
+	let dirs: Arc<dyn Dirs> = Arc::new(dirs::XdgDirs::new(dirs::DirOverrides {
+		data_dir: args.data_dir.clone().map(make_absolute),
+		state_dir: args.state_dir.clone().map(make_absolute),
+	}));
 	let mut file_database = parse::FileDatabase::new();
 
-	match try_main(&mut file_database) {
+	match try_main(args, dirs, &mut file_database) {
 		Ok(code) => code,
-		Err(err) => {
-			err.print(&file_database);
-			ExitCode::FAILURE
+		Err(ExitError { error, code }) => {
+			error.print(&file_database, color);
+			code.into()
 		}
 	}
 }
-fn try_main(file_database: &mut parse::FileDatabase) -> GenericResult<ExitCode> {
-	let args = CliArgs::parse();
+fn try_main(
+	args: CliArgs,
+	dirs: Arc<dyn Dirs>,
+	file_database: &mut parse::FileDatabase,
+) -> Result<ExitCode, ExitError> {
+	let color = args.color();
 
-	let mut parse_ctx = ParseContext { file_database };
-	let config = parse_ctx.get_global_config()?;
+	// NOTE: these subcommands do not use the config parsed below
+	match args.subcommand {
+		CliSubcommands::Daemon => return Ok(daemon_subcommand::run(file_database, dirs, color)?),
+		CliSubcommands::Ctl(args) => return Ok(daemon_subcommand::run_client(args, &*dirs)?),
+		CliSubcommands::Status(args) => {
+			status_subcommand::run(args, color, &*dirs)?;
+			return Ok(ExitCode::SUCCESS);
+		}
+		CliSubcommands::Logs(args) => {
+			logs_subcommand::run(args, &*dirs)?;
+			return Ok(ExitCode::SUCCESS);
+		}
+		CliSubcommands::Attach(args) => {
+			logs_subcommand::attach(args, &*dirs)?;
+			return Ok(ExitCode::SUCCESS);
+		}
+		CliSubcommands::Stats => {
+			stats_subcommand::run(color, &*dirs)?;
+			return Ok(ExitCode::SUCCESS);
+		}
+		CliSubcommands::Rename(args) => {
+			rename_subcommand::run(args, &*dirs)?;
+			return Ok(ExitCode::SUCCESS);
+		}
+		CliSubcommands::Import(args) => {
+			bundle_subcommand::run_import(args, &*dirs)?;
+			return Ok(ExitCode::SUCCESS);
+		}
+		CliSubcommands::Migrate => {
+			migrate_subcommand::run(&*dirs)?;
+			return Ok(ExitCode::SUCCESS);
+		}
+		CliSubcommands::ShellInit { shell } => {
+			shell_init_subcommand::run(shell);
+			return Ok(ExitCode::SUCCESS);
+		}
+		_ => (),
+	}
+
+	let mut parse_ctx = ParseContext::new(file_database, dirs.clone(), color);
+	let config = parse_ctx
+		.get_global_config()
+		.with_exit_code(exit_code::CONFIG_ERROR)?;
 
 	match args.subcommand {
-		CliSubcommands::Ui => ui_subcommand::run(&mut parse_ctx, config),
+		CliSubcommands::Ui(args) => ui_subcommand::run(&mut parse_ctx, config, args, color),
 		CliSubcommands::Add(args) => {
-			add_subcommand::run(args)?;
+			add_subcommand::run(args, &*dirs)?;
+			Ok(ExitCode::SUCCESS)
+		}
+		CliSubcommands::Export(args) => {
+			bundle_subcommand::run_export(args, &mut parse_ctx)?;
+			Ok(ExitCode::SUCCESS)
+		}
+		CliSubcommands::Config(ConfigArgs {
+			command: ConfigCommand::Dump { name },
+		}) => {
+			let dump = parse_ctx
+				.dump_config(&config, name.as_deref())
+				.with_exit_code(exit_code::CONFIG_ERROR)?;
+			print!("{dump}");
 			Ok(ExitCode::SUCCESS)
 		}
+		CliSubcommands::Daemon
+		| CliSubcommands::Ctl(_)
+		| CliSubcommands::Status(_)
+		| CliSubcommands::Logs(_)
+		| CliSubcommands::Attach(_)
+		| CliSubcommands::Stats
+		| CliSubcommands::Rename(_)
+		| CliSubcommands::Import(_)
+		| CliSubcommands::Migrate
+		| CliSubcommands::ShellInit { .. } => unreachable!(),
 	}
 }
 
 pub struct GlobalConfig {
 	pub banner: String,
 	pub colorscheme: tui::Colorscheme,
-	pub disable_help_text: bool,
+	// whether the footer bar is shown in the tui
+	pub footer: bool,
+	// whether the icons of projects and bookmarks are shown in the tui
+	pub icons: bool,
+	pub layout: tui::Layout,
+	pub spacing: tui::Spacing,
+	// glyph shown in front of the selected button in the tui
+	pub selection_marker: String,
+	// whether the tui is rendered below the prompt instead of on the alternate screen
+	pub inline: bool,
+	// maximum number of lines of the tui in inline mode
+	pub max_height: u16,
+	pub project_sort: open_stats::ProjectSort,
+	// whether the most recently opened project is opened on start
+	pub resume_on_start: bool,
+	// key to edit the project file of the selected project
+	// (empty if disabled)
+	pub edit_keybind: String,
+	// key to copy the project directory of the selected project
+	// (empty if disabled)
+	pub yank_keybind: String,
+	// key to change into the project directory with `skeld ui --cd-file`
+	pub cd_keybind: String,
+	// key to open the action menu of the selected project
+	pub actions_keybind: String,
+	// key to open a shell in the sandbox of the selected project
+	pub shell_keybind: String,
+	// key to stop the running instances of the selected project
+	pub kill_keybind: String,
+	// keys to select the first/last button of the tui
+	pub move_top_keybind: String,
+	pub move_bottom_keybind: String,
+	// keys to select the first button of the next/previous section of the tui
+	pub next_section_keybind: String,
+	pub prev_section_keybind: String,
+	// whether the selection wraps around at the first/last button
+	pub wrap_navigation: bool,
+	// whether the escape key quits the tui
+	pub quit_on_escape: bool,
+	// command of that shell (default: '$SHELL')
+	pub shell_cmd: Option<Vec<String>>,
+	// whether the project file is edited inside a sandbox
+	pub sandbox_edit: bool,
+	// maximum time between the clicks of a double click
+	pub double_click_interval: std::time::Duration,
 	pub commands: Vec<CommandData>,
 	pub global_project_data: parse::PrelimParseState,
+	pub log_config: sandbox::LogConfig,
+	// options that apply to the parsing of projects
+	pub parse_options: parse::ParseOptions,
 }
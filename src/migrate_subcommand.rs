@@ -0,0 +1,102 @@
+use std::{fs, path::Path};
+
+use crate::{
+	atomic_file,
+	dirs::Dirs,
+	parse::migration::{self, FileKind},
+	rename_subcommand, GenericResult,
+};
+
+// rewrite all files of the user, so that they no longer rely on the translation
+// of outdated options
+pub fn run(dirs: &dyn Dirs) -> GenericResult<()> {
+	// NOTE: system-wide files are not modified
+	let user_dirs = [dirs.get_skeld_config_dir(), dirs.get_skeld_data_dir()]
+		.into_iter()
+		.collect::<Result<Vec<_>, _>>()
+		.map_err(|err| format!("Failed to determine the skeld data directories:\n  {err}"))?;
+
+	let mut files = Vec::new();
+	for user_dir in &user_dirs {
+		let config_file = user_dir.join("config.toml");
+		if config_file.is_file() {
+			files.push((config_file, FileKind::Config));
+		}
+		for (subdir, kind) in [
+			("projects", FileKind::ProjectData),
+			("bookmarks", FileKind::Bookmark),
+			("include", FileKind::ProjectData),
+		] {
+			let dir_files = rename_subcommand::get_toml_files(&user_dir.join(subdir));
+			files.extend(dir_files.into_iter().map(|file| (file, kind)));
+		}
+	}
+
+	let mut is_manual_migration_required = false;
+	for (file, kind) in files {
+		is_manual_migration_required |= !migrate_file(&file, kind)?;
+	}
+	if is_manual_migration_required {
+		return Err("Some files have to be updated manually".into());
+	}
+	Ok(())
+}
+// returns false if the file has to be updated manually
+fn migrate_file(file: &Path, kind: FileKind) -> GenericResult<bool> {
+	let contents = fs::read_to_string(file)
+		.map_err(|err| format!("Failed to read file `{}`: {err}", file.display()))?;
+	let Ok(mut value) = toml_span::parse(&contents) else {
+		println!("Skipped `{}`, because it is not valid toml", file.display());
+		return Ok(true);
+	};
+	let result = match migration::migrate(&mut value, kind, &contents) {
+		Ok(result) => result,
+		Err((span, msg)) => {
+			let line = get_line_number(&contents, span.start);
+			println!(
+				"Skipped `{}`, because of an invalid config version in line {line}: {msg}",
+				file.display()
+			);
+			return Ok(false);
+		}
+	};
+	if result.version == migration::CONFIG_VERSION {
+		return Ok(true);
+	}
+
+	println!("Migrating `{}`", file.display());
+	let mut edits = Vec::new();
+	let mut is_complete = true;
+	for change in result.changes {
+		let line = get_line_number(&contents, change.span.start);
+		if let Some(edit) = change.edit {
+			println!(
+				"  line {line}: `{}` {}",
+				change.old_option, change.description
+			);
+			edits.push(edit);
+		} else {
+			println!(
+				"  line {line}: `{}` has to be updated manually ({})",
+				change.old_option, change.description
+			);
+			is_complete = false;
+		}
+	}
+	// NOTE: the version is only increased if all changes were made,
+	//       so that the remaining options are still translated
+	if is_complete {
+		let version = migration::CONFIG_VERSION.to_string();
+		match result.version_span {
+			Some(span) => edits.push((span.start..span.end, version)),
+			None => edits.push((0..0, format!("config-version = {version}\n"))),
+		}
+	}
+
+	atomic_file::write(file, migration::apply_edits(&contents, &edits))
+		.map_err(|err| format!("Failed to write file `{}`: {err}", file.display()))?;
+	Ok(is_complete)
+}
+fn get_line_number(contents: &str, pos: usize) -> usize {
+	contents[..pos].matches('\n').count() + 1
+}
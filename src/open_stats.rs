@@ -0,0 +1,134 @@
+use std::{fs, io, path::PathBuf};
+
+use crate::{atomic_file, dirs::Dirs, launch_history::current_time, lock::FileLock};
+
+// NOTE: the weight of an open is halved after this many seconds (one week)
+const FRECENCY_HALF_LIFE: f64 = 7.0 * 24.0 * 60.0 * 60.0;
+
+// ordering of the projects in the tui
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProjectSort {
+	Name,
+	// most recently opened first
+	Recent,
+	// most frequently and recently opened first
+	Frecency,
+}
+
+// how often a project (or bookmark) has been opened
+pub struct ProjectStats {
+	// see 'ProjectDataFuture::state_key'
+	// NOTE: None for entries of older versions, which are keyed by the name
+	state_key: Option<String>,
+	// name at the time of the last open
+	pub name: String,
+	pub open_count: u64,
+	// seconds since the unix epoch
+	pub last_open: u64,
+	// frecency at the time of the last open
+	score: f64,
+}
+impl ProjectStats {
+	pub fn is_entry_of(&self, state_key: &str, name: &str) -> bool {
+		match &self.state_key {
+			Some(entry_key) => entry_key == state_key,
+			None => self.name == name,
+		}
+	}
+	// open count, where each open is decayed by its age
+	pub fn frecency(&self, now: u64) -> f64 {
+		let elapsed_time = now.saturating_sub(self.last_open) as f64;
+		self.score * 0.5f64.powf(elapsed_time / FRECENCY_HALF_LIFE)
+	}
+}
+
+pub fn record_open(state_key: &str, name: &str, dirs: &dyn Dirs) -> Result<(), String> {
+	let stats_path = get_stats_path(dirs)?;
+	// NOTE: tabs and newlines are the separators of the stats file
+	let state_key = state_key.replace(['\t', '\n'], " ");
+	let name = name.replace(['\t', '\n'], " ");
+	let now = current_time();
+
+	let _stats_lock = FileLock::acquire("opens", dirs)?;
+	let mut stats = read(dirs)?;
+	match stats
+		.iter_mut()
+		.find(|entry| entry.is_entry_of(&state_key, &name))
+	{
+		Some(entry) => {
+			entry.state_key = Some(state_key);
+			entry.name = name;
+			entry.score = entry.frecency(now) + 1.0;
+			entry.open_count += 1;
+			entry.last_open = now;
+		}
+		None => stats.push(ProjectStats {
+			state_key: Some(state_key),
+			name,
+			open_count: 1,
+			last_open: now,
+			score: 1.0,
+		}),
+	}
+
+	let contents = stats
+		.iter()
+		.map(|entry| {
+			let state_key = entry.state_key.as_deref().unwrap_or_default();
+			format!(
+				"{}\t{}\t{}\t{}\t{state_key}\n",
+				entry.name, entry.open_count, entry.last_open, entry.score
+			)
+		})
+		.collect::<String>();
+	let write_stats = || -> io::Result<()> {
+		fs::create_dir_all(stats_path.parent().unwrap())?;
+		atomic_file::write(&stats_path, contents)
+	};
+	write_stats().map_err(|err| {
+		format!(
+			"Failed to record the open in `{}`: {err}",
+			stats_path.display()
+		)
+	})
+}
+pub fn read(dirs: &dyn Dirs) -> Result<Vec<ProjectStats>, String> {
+	let stats_path = get_stats_path(dirs)?;
+	let contents = match fs::read_to_string(&stats_path) {
+		Ok(contents) => contents,
+		Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(err) => {
+			return Err(format!(
+				"Failed to read the project stats `{}`: {err}",
+				stats_path.display()
+			))
+		}
+	};
+	// NOTE: malformed entries are skipped
+	Ok(contents.lines().filter_map(parse_entry).collect())
+}
+fn parse_entry(line: &str) -> Option<ProjectStats> {
+	let mut fields = line.split('\t');
+	let name = fields.next()?.to_string();
+	let open_count = fields.next()?.parse().ok()?;
+	let last_open = fields.next()?.parse().ok()?;
+	let score = fields.next()?.parse().ok()?;
+	// NOTE: the state key has been appended later
+	let state_key = fields
+		.next()
+		.filter(|key| !key.is_empty())
+		.map(str::to_string);
+	Some(ProjectStats {
+		state_key,
+		name,
+		open_count,
+		last_open,
+		score,
+	})
+}
+fn get_stats_path(dirs: &dyn Dirs) -> Result<PathBuf, String> {
+	let state_dir = dirs
+		.get_skeld_state_dir()
+		.map_err(|err| format!("Failed to determine the skeld state directory:\n  {err}"))?;
+	Ok(state_dir.join("opens"))
+}
@@ -1,16 +1,21 @@
-use std::path::Path;
+use std::{fs, path::Path, sync::Arc, time::Duration};
 
 use super::{
 	lib::{
 		self as parse_lib, diagnostics, ArrayOption, BaseOption, BoolOption, ConfigOption, Diagnostic,
-		StringOption, TomlKey, TomlValue,
+		DurationOption, MergePolicy, PathBufOption, StringOption, TomlKey, TomlTable, TomlValue,
 	},
-	path,
+	migration::FileKind,
+	path::{self, LateVariable},
 	project_data::{self, ProjectDataOption},
 	ModResult, ParseContext,
 };
 use crate::{
-	ui_subcommand::{tui, Command, CommandData},
+	dirs::Dirs,
+	open_stats::ProjectSort,
+	project::ReopenPolicy,
+	sandbox::{LogConfig, SandboxFallback},
+	ui_subcommand::{figlet, tui, Command, CommandData},
 	GlobalConfig,
 };
 
@@ -30,15 +35,88 @@ const DEFAULT_COLORSCHEME: tui::Colorscheme = tui::Colorscheme {
 	heading: tui::Color::Reset,
 	keybind: tui::Color::Reset,
 	button_label: tui::Color::Reset,
-	background: tui::Color::Reset,
+	background: Some(tui::Color::Reset),
+	footer: tui::Color::Reset,
+	footer_background: tui::Color::Reset,
+	selected_border: None,
+	selected_keybind: None,
+	selected_label: None,
+	selected_background: None,
+	sections: Vec::new(),
 };
-pub fn default_config() -> GlobalConfig {
+const DEFAULT_BANNER_FONT: &str = "block";
+const DEFAULT_EDIT_KEYBIND: &str = "e";
+const DEFAULT_YANK_KEYBIND: &str = "y";
+const DEFAULT_CD_KEYBIND: &str = "d";
+const DEFAULT_ACTIONS_KEYBIND: &str = "a";
+const DEFAULT_SHELL_KEYBIND: &str = "s";
+const DEFAULT_KILL_KEYBIND: &str = "x";
+const DEFAULT_MOVE_TOP_KEYBIND: &str = "gg";
+const DEFAULT_MOVE_BOTTOM_KEYBIND: &str = "G";
+const DEFAULT_NEXT_SECTION_KEYBIND: &str = "}";
+const DEFAULT_PREV_SECTION_KEYBIND: &str = "{";
+const DEFAULT_MAX_HEIGHT: u16 = 20;
+const DEFAULT_DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(500);
+const DEFAULT_MAX_INCLUDE_DEPTH: usize = 32;
+// options of the config file that determine how projects are parsed and opened
+#[derive(Clone, Copy)]
+pub struct ParseOptions {
+	pub max_include_depth: usize,
+	// resolution of conflicting definitions in project data
+	pub merge_policy: MergePolicy,
+	// whether '.skeld.toml' files in project directories are loaded
+	pub project_local_config: bool,
+	// whether whitelisted paths that do not exist are reported
+	pub warn_missing_paths: bool,
+	pub sandbox_fallback: SandboxFallback,
+	pub systemd_scope: bool,
+	pub reopen_policy: ReopenPolicy,
+}
+impl Default for ParseOptions {
+	fn default() -> Self {
+		Self {
+			max_include_depth: DEFAULT_MAX_INCLUDE_DEPTH,
+			merge_policy: MergePolicy::default(),
+			project_local_config: false,
+			warn_missing_paths: false,
+			sandbox_fallback: SandboxFallback::default(),
+			systemd_scope: true,
+			reopen_policy: ReopenPolicy::default(),
+		}
+	}
+}
+pub fn default_config(dirs: Arc<dyn Dirs>) -> GlobalConfig {
 	GlobalConfig {
 		banner: DEFAULT_BANNER.to_string(),
 		colorscheme: DEFAULT_COLORSCHEME,
-		disable_help_text: false,
+		footer: true,
+		icons: false,
+		layout: tui::Layout::Boxes,
+		spacing: tui::Spacing::default(),
+		selection_marker: String::new(),
+		inline: false,
+		max_height: DEFAULT_MAX_HEIGHT,
+		project_sort: ProjectSort::Name,
+		resume_on_start: false,
+		edit_keybind: DEFAULT_EDIT_KEYBIND.to_string(),
+		yank_keybind: DEFAULT_YANK_KEYBIND.to_string(),
+		cd_keybind: DEFAULT_CD_KEYBIND.to_string(),
+		actions_keybind: DEFAULT_ACTIONS_KEYBIND.to_string(),
+		shell_keybind: DEFAULT_SHELL_KEYBIND.to_string(),
+		kill_keybind: DEFAULT_KILL_KEYBIND.to_string(),
+		move_top_keybind: DEFAULT_MOVE_TOP_KEYBIND.to_string(),
+		move_bottom_keybind: DEFAULT_MOVE_BOTTOM_KEYBIND.to_string(),
+		next_section_keybind: DEFAULT_NEXT_SECTION_KEYBIND.to_string(),
+		prev_section_keybind: DEFAULT_PREV_SECTION_KEYBIND.to_string(),
+		wrap_navigation: false,
+		quit_on_escape: false,
+		shell_cmd: None,
+		sandbox_edit: false,
+		double_click_interval: DEFAULT_DOUBLE_CLICK_INTERVAL,
 		commands: Vec::new(),
-		global_project_data: project_data::PrelimParseState::empty(),
+		global_project_data: project_data::PrelimParseState::empty(dirs),
+		log_config: LogConfig::default(),
+		parse_options: ParseOptions::default(),
 	}
 }
 
@@ -46,50 +124,511 @@ pub fn parse_config_file(
 	path: impl AsRef<Path>,
 	ctx: &mut ParseContext,
 ) -> ModResult<GlobalConfig> {
-	let mut outlivers = (None, None);
-	let parsed_contents =
-		parse_lib::parse_toml_file(path.as_ref(), ctx.file_database, &mut outlivers)?;
-
-	let mut global_project_data =
-		ProjectDataOption::new("project", project_data::PrelimParseState::empty(), ctx);
-	let mut commands = ArrayOption::new("commands", false, parse_command_data);
+	let mut global_project_data = project_data::PrelimParseState::empty(ctx.dirs.clone());
+	let mut parse_options = ParseOptions::default();
+	let dirs = ctx.dirs.clone();
+	let mut commands = ArrayOption::new("commands", false, move |value| {
+		parse_command_data(value, &dirs)
+	});
 	let mut colorscheme = ColorschemeOption::new();
 	let mut banner = StringOption::new("banner");
-	let mut disable_help_text = BoolOption::new("disable-help");
-	parse_lib::parse_table!(
-		&parsed_contents => [
-			global_project_data,
-			commands,
-			colorscheme,
-			banner,
-			disable_help_text
-		],
-		docs-pref: "configuration",
-	)?;
+	let mut banner_text = StringOption::new("banner-text");
+	let dirs = ctx.dirs.clone();
+	let mut banner_font =
+		BaseOption::new("banner-font", move |value| parse_banner_font(value, &*dirs));
+	let mut footer = BoolOption::new("footer");
+	let mut icons = BoolOption::new("icons");
+	let mut layout = BaseOption::new("layout", parse_layout);
+	let mut center_vertically = BoolOption::new("center-vertically");
+	let mut top_margin = BaseOption::new("top-margin", parse_line_count);
+	let mut section_spacing = BaseOption::new("section-spacing", parse_line_count);
+	let mut button_spacing = BaseOption::new("button-spacing", parse_line_count);
+	let mut selection_marker = StringOption::new("selection-marker");
+	let mut inline = BoolOption::new("inline");
+	let mut max_height = BaseOption::new("max-height", parse_max_height);
+	let mut project_sort = BaseOption::new("project-sort", parse_project_sort);
+	let mut resume_on_start = BoolOption::new("resume-on-start");
+	let mut edit_keybind = StringOption::new("edit-keybind");
+	let mut yank_keybind = StringOption::new("yank-keybind");
+	let mut cd_keybind = StringOption::new("cd-keybind");
+	let mut actions_keybind = StringOption::new("actions-keybind");
+	let mut shell_keybind = StringOption::new("shell-keybind");
+	let mut kill_keybind = StringOption::new("kill-keybind");
+	let mut move_top_keybind = StringOption::new("move-top-keybind");
+	let mut move_bottom_keybind = StringOption::new("move-bottom-keybind");
+	let mut next_section_keybind = StringOption::new("next-section-keybind");
+	let mut prev_section_keybind = StringOption::new("prev-section-keybind");
+	let mut wrap_navigation = BoolOption::new("wrap-navigation");
+	let mut quit_on_escape = BoolOption::new("quit-on-escape");
+	let mut shell_cmd = ArrayOption::new("shell-cmd", false, |raw_value| {
+		Ok(raw_value.as_str()?.to_string())
+	});
+	let mut sandbox_edit = BoolOption::new("sandbox-edit");
+	let mut double_click_interval = DurationOption::new("double-click-interval");
+	let mut project_local_config = BoolOption::new("project-local-config");
+	let mut warn_missing_paths = BoolOption::new("warn-missing-paths");
+	let mut sandbox_fallback = BaseOption::new("sandbox-fallback", parse_sandbox_fallback);
+	let mut systemd_scope = BoolOption::new("systemd-scope");
+	let mut reopen_policy = BaseOption::new("reopen-policy", parse_reopen_policy);
+	let dirs = ctx.dirs.clone();
+	let mut log_config = BaseOption::new("logs", move |value| parse_log_config(value, &dirs));
+
+	// (file, include entry referencing it, include chain)
+	// NOTE: includes are pushed in reverse order, so that they are parsed in order
+	let mut pending_files = vec![(path.as_ref().to_path_buf(), None, Vec::new())];
+	let mut parsed_files = Vec::new();
+	while let Some((file, origin, mut include_stack)) = pending_files.pop() {
+		let canonical_file = fs::canonicalize(&file).unwrap_or_else(|_| file.clone());
+		// NOTE: files included multiple times are only parsed once
+		if parsed_files.contains(&canonical_file) {
+			continue;
+		}
+		parsed_files.push(canonical_file.clone());
+
+		let mut outlivers = (None, None);
+		let parsed_contents = ctx
+			.parse_toml_file(&file, FileKind::Config, &mut outlivers)
+			.map_err(|err| match &origin {
+				Some(origin) => project_data::add_include_label(err, origin),
+				None => err,
+			})?;
+		let is_included = origin.is_some();
+		if let Some(origin) = origin {
+			ctx.record_include_origin(parsed_contents.loc().file, origin);
+		} else {
+			preparse_options(&parsed_contents, &mut parse_options)?;
+			// NOTE: the global project data is parsed with these options
+			ctx.options = parse_options;
+		}
+
+		let dirs = ctx.dirs.clone();
+		let mut project_option = ProjectDataOption::new("project", global_project_data, ctx);
+		let mut include_option = ArrayOption::new("include", false, move |raw_value| {
+			let value = raw_value.as_str()?;
+			let include_path = path::canonicalize_config_include_path(value, &*dirs)
+				.map_err(|err| diagnostics::failed_canonicalization(raw_value, &err))?;
+			Ok((include_path, raw_value.loc().clone()))
+		});
+		// NOTE: these are preparsed in the config file itself
+		let mut max_include_depth = BaseOption::new("max-include-depth", parse_max_include_depth);
+		let mut merge_policy = BaseOption::new("merge-policy", parse_merge_policy);
+		parse_lib::parse_table!(
+			&parsed_contents => [
+				project_option,
+				include_option,
+				commands,
+				colorscheme,
+				banner,
+				banner_text,
+				banner_font,
+				footer,
+				icons,
+				layout,
+				center_vertically,
+				top_margin,
+				section_spacing,
+				button_spacing,
+				selection_marker,
+				inline,
+				max_height,
+				project_sort,
+				resume_on_start,
+				edit_keybind,
+				yank_keybind,
+				cd_keybind,
+				actions_keybind,
+				shell_keybind,
+				kill_keybind,
+				move_top_keybind,
+				move_bottom_keybind,
+				next_section_keybind,
+				prev_section_keybind,
+				wrap_navigation,
+				quit_on_escape,
+				shell_cmd,
+				sandbox_edit,
+				double_click_interval,
+				project_local_config,
+				warn_missing_paths,
+				sandbox_fallback,
+				systemd_scope,
+				reopen_policy,
+				log_config,
+				max_include_depth,
+				merge_policy
+			],
+			docs-pref: "configuration",
+		)?;
+		global_project_data = project_option.get_value();
+		if is_included {
+			let preparsed_options = [
+				(
+					"max-include-depth",
+					max_include_depth.get_value_with_loc().map(|(_, loc)| loc),
+				),
+				(
+					"merge-policy",
+					merge_policy.get_value_with_loc().map(|(_, loc)| loc),
+				),
+			];
+			for (name, loc) in preparsed_options {
+				if let Some(loc) = loc {
+					return Err(preparsed_option_in_include(name, &loc).into());
+				}
+			}
+		}
+
+		include_stack.push(canonical_file);
+		let includes = include_option.get_value().unwrap_or_default();
+		for (include_path, origin) in includes.into_iter().rev() {
+			let canonical_path = fs::canonicalize(&include_path).unwrap_or_else(|_| include_path.clone());
+			if include_stack.contains(&canonical_path) {
+				let include_chain = include_stack.iter().collect::<Vec<_>>();
+				let diag = project_data::include_cycle(&include_chain, &canonical_path, &origin);
+				ctx.report_warning(diag);
+				continue;
+			}
+			if parsed_files.contains(&canonical_path) {
+				continue;
+			}
+			// NOTE: the config file itself is not counted, like project files
+			if include_stack.len() > parse_options.max_include_depth {
+				let diag = project_data::include_depth_exceeded(
+					&include_stack[1..],
+					&canonical_path,
+					&origin,
+					parse_options.max_include_depth,
+				);
+				return Err(diag.into());
+			}
+			pending_files.push((include_path, Some(origin), include_stack.clone()));
+		}
+	}
+	let banner = get_banner(banner, banner_text, banner_font)?;
+	let default_spacing = tui::Spacing::default();
+	let spacing = tui::Spacing {
+		center_vertically: center_vertically
+			.get_value()
+			.unwrap_or(default_spacing.center_vertically),
+		top_margin: top_margin.get_value().unwrap_or(default_spacing.top_margin),
+		section_gap: section_spacing
+			.get_value()
+			.unwrap_or(default_spacing.section_gap),
+		button_gap: button_spacing
+			.get_value()
+			.unwrap_or(default_spacing.button_gap),
+	};
+	let shell_cmd = match shell_cmd.get_value_with_loc() {
+		Some((cmd, loc)) if cmd.is_empty() => {
+			let label = loc
+				.get_primary_label()
+				.with_message("command must not be empty");
+			return Err(
+				Diagnostic::new(parse_lib::Severity::Error)
+					.with_message("empty shell command")
+					.with_labels(vec![label])
+					.into(),
+			);
+		}
+		shell_cmd => shell_cmd.map(|(cmd, _)| cmd),
+	};
+	let parse_options = ParseOptions {
+		project_local_config: project_local_config.get_value().unwrap_or_default(),
+		warn_missing_paths: warn_missing_paths.get_value().unwrap_or_default(),
+		sandbox_fallback: sandbox_fallback.get_value().unwrap_or_default(),
+		systemd_scope: systemd_scope.get_value().unwrap_or(true),
+		reopen_policy: reopen_policy.get_value().unwrap_or_default(),
+		..parse_options
+	};
 
 	Ok(GlobalConfig {
 		commands: commands.get_value().unwrap_or_default(),
-		global_project_data: global_project_data.get_value(),
+		global_project_data,
 		colorscheme: colorscheme.get_value().unwrap_or(DEFAULT_COLORSCHEME),
-		banner: banner.get_value().unwrap_or(DEFAULT_BANNER.to_string()),
-		disable_help_text: disable_help_text.get_value().unwrap_or_default(),
+		banner,
+		footer: footer.get_value().unwrap_or(true),
+		icons: icons.get_value().unwrap_or_default(),
+		layout: layout.get_value().unwrap_or(tui::Layout::Boxes),
+		spacing,
+		selection_marker: selection_marker.get_value().unwrap_or_default(),
+		inline: inline.get_value().unwrap_or_default(),
+		max_height: max_height.get_value().unwrap_or(DEFAULT_MAX_HEIGHT),
+		project_sort: project_sort.get_value().unwrap_or(ProjectSort::Name),
+		resume_on_start: resume_on_start.get_value().unwrap_or_default(),
+		edit_keybind: edit_keybind
+			.get_value()
+			.unwrap_or(DEFAULT_EDIT_KEYBIND.to_string()),
+		yank_keybind: yank_keybind
+			.get_value()
+			.unwrap_or(DEFAULT_YANK_KEYBIND.to_string()),
+		cd_keybind: cd_keybind
+			.get_value()
+			.unwrap_or(DEFAULT_CD_KEYBIND.to_string()),
+		actions_keybind: actions_keybind
+			.get_value()
+			.unwrap_or(DEFAULT_ACTIONS_KEYBIND.to_string()),
+		shell_keybind: shell_keybind
+			.get_value()
+			.unwrap_or(DEFAULT_SHELL_KEYBIND.to_string()),
+		kill_keybind: kill_keybind
+			.get_value()
+			.unwrap_or(DEFAULT_KILL_KEYBIND.to_string()),
+		move_top_keybind: move_top_keybind
+			.get_value()
+			.unwrap_or(DEFAULT_MOVE_TOP_KEYBIND.to_string()),
+		move_bottom_keybind: move_bottom_keybind
+			.get_value()
+			.unwrap_or(DEFAULT_MOVE_BOTTOM_KEYBIND.to_string()),
+		next_section_keybind: next_section_keybind
+			.get_value()
+			.unwrap_or(DEFAULT_NEXT_SECTION_KEYBIND.to_string()),
+		prev_section_keybind: prev_section_keybind
+			.get_value()
+			.unwrap_or(DEFAULT_PREV_SECTION_KEYBIND.to_string()),
+		wrap_navigation: wrap_navigation.get_value().unwrap_or_default(),
+		quit_on_escape: quit_on_escape.get_value().unwrap_or_default(),
+		shell_cmd,
+		sandbox_edit: sandbox_edit.get_value().unwrap_or_default(),
+		double_click_interval: double_click_interval
+			.get_value()
+			.unwrap_or(DEFAULT_DOUBLE_CLICK_INTERVAL),
+		log_config: log_config.get_value().unwrap_or_default(),
+		parse_options,
+	})
+}
+// NOTE: the include depth and the merge policy have to be known before
+//       the global project data is parsed
+fn preparse_options(table: &TomlTable, parse_options: &mut ParseOptions) -> ModResult<()> {
+	let mut max_include_depth = BaseOption::new("max-include-depth", parse_max_include_depth);
+	let mut merge_policy = BaseOption::new("merge-policy", parse_merge_policy);
+	for (key, value) in table.iter() {
+		max_include_depth.try_eat(&key, &value)?;
+		merge_policy.try_eat(&key, &value)?;
+	}
+	if let Some(max_include_depth) = max_include_depth.get_value() {
+		parse_options.max_include_depth = max_include_depth;
+	}
+	if let Some(merge_policy) = merge_policy.get_value() {
+		parse_options.merge_policy = merge_policy;
+	}
+	Ok(())
+}
+// NOTE: the preparsed options are only supported in the config file itself,
+//       as included files are parsed after the global project data has been started
+fn preparsed_option_in_include(name: &str, loc: &parse_lib::Location) -> Diagnostic {
+	let label = loc
+		.get_primary_label()
+		.with_message("only supported in the config file itself");
+	Diagnostic::new(parse_lib::Severity::Error)
+		.with_message(format!("unsupported option `{name}` in included file"))
+		.with_labels(vec![label])
+}
+// NOTE: 'banner-text' is rendered with 'banner-font' at startup
+fn get_banner(
+	banner: StringOption,
+	banner_text: StringOption,
+	banner_font: BaseOption<figlet::Font>,
+) -> ModResult<String> {
+	let (text, text_loc) = match (
+		banner.get_value_with_loc(),
+		banner_text.get_value_with_loc(),
+	) {
+		(Some((_, banner_loc)), Some((_, text_loc))) => {
+			return Err(
+				Diagnostic::new(parse_lib::Severity::Error)
+					.with_message("conflicting banner options")
+					.with_labels(vec![
+						banner_loc
+							.get_secondary_label()
+							.with_message("banner defined here"),
+						text_loc
+							.get_primary_label()
+							.with_message("but here a text to render is specified"),
+					])
+					.with_notes(vec![
+						"only one of `banner` and `banner-text` can be set".to_string()
+					])
+					.into(),
+			);
+		}
+		(Some((banner, _)), None) => return Ok(banner),
+		(None, None) => return Ok(DEFAULT_BANNER.to_string()),
+		(None, Some(text)) => text,
+	};
+	let font = banner_font
+		.get_value()
+		.unwrap_or_else(|| figlet::Font::builtin(DEFAULT_BANNER_FONT).unwrap());
+	font.render(&text).map_err(|ch| {
+		let label = text_loc
+			.get_primary_label()
+			.with_message(format!("the font has no character `{ch}`"));
+		Diagnostic::new(parse_lib::Severity::Error)
+			.with_message("unsupported character in the banner text")
+			.with_labels(vec![label])
+			.into()
 	})
 }
-fn parse_command_data(value: &TomlValue) -> ModResult<CommandData> {
+// NOTE: the font is either the name of a builtin font or the path to a '.flf' file
+fn parse_banner_font(value: &TomlValue, dirs: &dyn Dirs) -> ModResult<figlet::Font> {
+	let raw_value = value.as_str()?;
+	if let Some(font) = figlet::Font::builtin(raw_value) {
+		return Ok(font);
+	}
+	let path = path::canonicalize_path(raw_value, dirs).map_err(|err| {
+		let mut diagnostic = diagnostics::failed_canonicalization(value, &err);
+		let builtin_fonts = figlet::Font::builtin_names()
+			.map(|name| format!("`{name}`"))
+			.collect::<Vec<_>>()
+			.join(", ");
+		diagnostic
+			.notes
+			.push(format!("builtin fonts are {builtin_fonts}"));
+		diagnostic
+	})?;
+	let invalid_font = |msg: String| {
+		let label = value.loc().get_primary_label().with_message(msg);
+		Diagnostic::new(parse_lib::Severity::Error)
+			.with_message("invalid banner font")
+			.with_labels(vec![label])
+			.into()
+	};
+	let contents = std::fs::read_to_string(&path)
+		.map_err(|err| invalid_font(format!("failed to read `{}`: {err}", path.display())))?;
+	figlet::Font::parse(&contents).map_err(invalid_font)
+}
+fn parse_max_include_depth(value: &TomlValue) -> ModResult<usize> {
+	let depth = value.as_int()?;
+	match usize::try_from(depth) {
+		Ok(depth) if depth > 0 => Ok(depth),
+		_ => {
+			let label = value
+				.loc()
+				.get_primary_label()
+				.with_message("must be a positive integer");
+			Err(
+				Diagnostic::new(parse_lib::Severity::Error)
+					.with_message("invalid include depth")
+					.with_labels(vec![label])
+					.into(),
+			)
+		}
+	}
+}
+fn parse_merge_policy(value: &TomlValue) -> ModResult<MergePolicy> {
+	match value.as_str()? {
+		"error" => Ok(MergePolicy::Error),
+		"last-wins" => Ok(MergePolicy::LastWins),
+		"first-wins" => Ok(MergePolicy::FirstWins),
+		_ => {
+			let label = value
+				.loc()
+				.get_primary_label()
+				.with_message("expected `error`, `last-wins` or `first-wins`");
+			Err(
+				Diagnostic::new(parse_lib::Severity::Error)
+					.with_message("invalid merge policy")
+					.with_labels(vec![label])
+					.into(),
+			)
+		}
+	}
+}
+fn parse_layout(value: &TomlValue) -> ModResult<tui::Layout> {
+	match value.as_str()? {
+		"boxes" => Ok(tui::Layout::Boxes),
+		"list" => Ok(tui::Layout::List),
+		"grid" => Ok(tui::Layout::Grid),
+		_ => {
+			let label = value
+				.loc()
+				.get_primary_label()
+				.with_message("expected `boxes`, `list` or `grid`");
+			Err(
+				Diagnostic::new(parse_lib::Severity::Error)
+					.with_message("invalid layout")
+					.with_labels(vec![label])
+					.into(),
+			)
+		}
+	}
+}
+fn parse_project_sort(value: &TomlValue) -> ModResult<ProjectSort> {
+	match value.as_str()? {
+		"name" => Ok(ProjectSort::Name),
+		"recent" => Ok(ProjectSort::Recent),
+		"frecency" => Ok(ProjectSort::Frecency),
+		_ => {
+			let label = value
+				.loc()
+				.get_primary_label()
+				.with_message("expected `name`, `recent` or `frecency`");
+			Err(
+				Diagnostic::new(parse_lib::Severity::Error)
+					.with_message("invalid project sort")
+					.with_labels(vec![label])
+					.into(),
+			)
+		}
+	}
+}
+fn parse_sandbox_fallback(value: &TomlValue) -> ModResult<SandboxFallback> {
+	match value.as_str()? {
+		"error" => Ok(SandboxFallback::Error),
+		"warn-and-run-unsandboxed" => Ok(SandboxFallback::WarnAndRunUnsandboxed),
+		_ => {
+			let label = value
+				.loc()
+				.get_primary_label()
+				.with_message("expected `error` or `warn-and-run-unsandboxed`");
+			Err(
+				Diagnostic::new(parse_lib::Severity::Error)
+					.with_message("invalid sandbox fallback")
+					.with_labels(vec![label])
+					.into(),
+			)
+		}
+	}
+}
+fn parse_reopen_policy(value: &TomlValue) -> ModResult<ReopenPolicy> {
+	match value.as_str()? {
+		"allow" => Ok(ReopenPolicy::Allow),
+		"warn" => Ok(ReopenPolicy::Warn),
+		"refuse" => Ok(ReopenPolicy::Refuse),
+		_ => {
+			let label = value
+				.loc()
+				.get_primary_label()
+				.with_message("expected `allow`, `warn` or `refuse`");
+			Err(
+				Diagnostic::new(parse_lib::Severity::Error)
+					.with_message("invalid reopen policy")
+					.with_labels(vec![label])
+					.into(),
+			)
+		}
+	}
+}
+fn parse_command_data(value: &TomlValue, dirs: &Arc<dyn Dirs>) -> ModResult<CommandData> {
 	let table = value.as_table()?;
 
 	let mut name = StringOption::new("name");
 	let mut keybind = StringOption::new("keybind");
-	let mut command = ArrayOption::new("command", false, |raw_value| {
+	let dirs = dirs.clone();
+	let mut command = ArrayOption::new("command", false, move |raw_value| {
 		let value = raw_value.as_str()?;
-		path::substitute_placeholder(value, false)
-			.map_err(|err| diagnostics::failed_canonicalization(raw_value, &err).into())
+		path::substitute_placeholder(
+			value,
+			&[LateVariable::ProjectDir, LateVariable::ProjectName],
+			&*dirs,
+		)
+		.map_err(|err| diagnostics::failed_canonicalization(raw_value, &err).into())
 	});
 	let mut detach = BoolOption::new("detach");
+	let mut sandbox = BoolOption::new("sandbox");
 
 	let docs_pref = "configuration";
 	parse_lib::parse_table!(
-		&table => [name, keybind, command, detach],
+		&table => [name, keybind, command, detach, sandbox],
 		docs-pref: docs_pref,
 	)?;
 	let name = name
@@ -111,10 +650,131 @@ fn parse_command_data(value: &TomlValue) -> ModResult<CommandData> {
 			.ok_or_else(|| diagnostics::missing_option(value.loc(), "detach", docs_pref))?
 	};
 
+	let sandbox = sandbox.get_value().unwrap_or(false);
+	// NOTE: commands that refer to the selected project (or run in its sandbox)
+	//       are not shown as buttons, but triggered while a project is selected
+	let on_selection = sandbox
+		|| command.iter().any(|arg| {
+			[LateVariable::ProjectDir, LateVariable::ProjectName]
+				.iter()
+				.any(|var| arg.contains(&format!("$({})", var.name())))
+		});
+
 	Ok(CommandData {
 		name,
 		keybind,
-		command: Command { command, detach },
+		command: Command {
+			command,
+			detach,
+			sandbox,
+		},
+		on_selection,
+	})
+}
+fn parse_log_config(value: &TomlValue, dirs: &Arc<dyn Dirs>) -> ModResult<LogConfig> {
+	let table = value.as_table()?;
+
+	let dir_dirs = dirs.clone();
+	let mut dir = PathBufOption::new("dir", move |str| path::canonicalize_path(str, &*dir_dirs));
+	let filename_dirs = dirs.clone();
+	let mut filename = BaseOption::new("filename", move |value| {
+		parse_logfile_name(value, &*filename_dirs)
+	});
+	// in hours
+	let mut max_age = BaseOption::new("max-age", parse_non_negative_int);
+	// in MiB
+	let mut max_total_size = BaseOption::new("max-total-size", parse_non_negative_int);
+
+	parse_lib::parse_table!(
+		&table => [dir, filename, max_age, max_total_size],
+		docs-pref: "configuration",
+	)?;
+
+	let default_config = LogConfig::default();
+	// NOTE: a value of 0 disables the respective limit
+	let max_age = match max_age.get_value() {
+		Some(0) => None,
+		Some(hours) => Some(Duration::from_secs(hours.saturating_mul(60 * 60))),
+		None => default_config.max_age,
+	};
+	let max_total_size = match max_total_size.get_value() {
+		Some(0) => None,
+		Some(mib) => Some(mib.saturating_mul(1024 * 1024)),
+		None => default_config.max_total_size,
+	};
+	Ok(LogConfig {
+		dir: dir.get_value(),
+		filename_template: filename
+			.get_value()
+			.unwrap_or(default_config.filename_template),
+		max_age,
+		max_total_size,
+		quiet: false,
+	})
+}
+fn parse_logfile_name(value: &TomlValue, dirs: &dyn Dirs) -> ModResult<String> {
+	let str = value.as_str()?;
+	let log_vars = [
+		LateVariable::Name,
+		LateVariable::Time,
+		LateVariable::LogNumber,
+	];
+	let filename = path::substitute_placeholder(str, &log_vars, dirs)
+		.map_err(|err| diagnostics::failed_canonicalization(value, &err))?;
+	if filename.is_empty() || filename.contains('/') {
+		let label = value
+			.loc()
+			.get_primary_label()
+			.with_message("must be a non-empty file name without `/`");
+		return Err(
+			Diagnostic::new(parse_lib::Severity::Error)
+				.with_message("invalid logfile name")
+				.with_labels(vec![label])
+				.into(),
+		);
+	}
+	Ok(filename)
+}
+fn parse_line_count(value: &TomlValue) -> ModResult<u16> {
+	let int = value.as_int()?;
+	u16::try_from(int).map_err(|_| {
+		let label = value
+			.loc()
+			.get_primary_label()
+			.with_message("must be between 0 and 65535");
+		Diagnostic::new(parse_lib::Severity::Error)
+			.with_message("invalid number of lines")
+			.with_labels(vec![label])
+			.into()
+	})
+}
+fn parse_max_height(value: &TomlValue) -> ModResult<u16> {
+	let int = value.as_int()?;
+	u16::try_from(int)
+		.ok()
+		.filter(|int| *int > 0)
+		.ok_or_else(|| {
+			let label = value
+				.loc()
+				.get_primary_label()
+				.with_message("must be between 1 and 65535");
+			Diagnostic::new(parse_lib::Severity::Error)
+				.with_message("invalid height")
+				.with_labels(vec![label])
+				.into()
+		})
+}
+fn parse_non_negative_int(value: &TomlValue) -> ModResult<u64> {
+	let int = value.as_int()?;
+	u64::try_from(int).map_err(|_| {
+		let label = value
+			.loc()
+			.get_primary_label()
+			.with_message("must not be negative");
+		Diagnostic::new(parse_lib::Severity::Error)
+			.with_message("invalid integer")
+			.with_labels(vec![label])
+			.into()
 	})
 }
 
@@ -142,9 +802,34 @@ fn parse_colorscheme(value: &TomlValue) -> ModResult<tui::Colorscheme> {
 	let mut heading = create_color_option("heading");
 	let mut keybind = create_color_option("keybind");
 	let mut button_label = create_color_option("label");
-	let mut background = create_color_option("background");
+	let mut background = BaseOption::new("background", parse_background_color);
+	let mut footer = create_color_option("footer");
+	let mut footer_background = create_color_option("footer-background");
+	let mut selected_border = create_color_option("selected-border");
+	let mut selected_keybind = create_color_option("selected-keybind");
+	let mut selected_label = create_color_option("selected-label");
+	let mut selected_background = create_color_option("selected-background");
+	let mut section_options = SECTION_NAMES.map(|name| BaseOption::new(name, parse_section_colors));
+	let [commands, bookmarks, projects, stdin_projects] = &mut section_options;
 	parse_lib::parse_table!(
-		table => [normal, banner, heading, keybind, button_label, background],
+		table => [
+			normal,
+			banner,
+			heading,
+			keybind,
+			button_label,
+			background,
+			footer,
+			footer_background,
+			selected_border,
+			selected_keybind,
+			selected_label,
+			selected_background,
+			commands,
+			bookmarks,
+			projects,
+			stdin_projects
+		],
 		docs-pref: "configuration",
 	)?;
 
@@ -162,8 +847,39 @@ fn parse_colorscheme(value: &TomlValue) -> ModResult<tui::Colorscheme> {
 	handle_color_option!(keybind);
 	handle_color_option!(button_label);
 	handle_color_option!(background);
+	handle_color_option!(footer);
+	handle_color_option!(footer_background);
+	resulting_colorscheme.selected_border = selected_border.get_value();
+	resulting_colorscheme.selected_keybind = selected_keybind.get_value();
+	resulting_colorscheme.selected_label = selected_label.get_value();
+	resulting_colorscheme.selected_background = selected_background.get_value();
+	resulting_colorscheme.sections = SECTION_NAMES
+		.into_iter()
+		.zip(section_options)
+		.filter_map(|(name, option)| Some((name.to_string(), option.get_value()?)))
+		.collect();
 	Ok(resulting_colorscheme)
 }
+// the sections of the tui whose colors can be overridden
+const SECTION_NAMES: [&str; 4] = ["commands", "bookmarks", "projects", "stdin-projects"];
+fn parse_section_colors(value: &TomlValue) -> ModResult<tui::SectionColors> {
+	let table = value.as_table()?;
+
+	let create_color_option = |name| BaseOption::<tui::Color>::new(name, parse_tui_color);
+	let mut heading = create_color_option("heading");
+	let mut keybind = create_color_option("keybind");
+	let mut button_label = create_color_option("label");
+	parse_lib::parse_table!(
+		table => [heading, keybind, button_label],
+		docs-pref: "configuration",
+	)?;
+
+	Ok(tui::SectionColors {
+		heading: heading.get_value(),
+		keybind: keybind.get_value(),
+		button_label: button_label.get_value(),
+	})
+}
 fn parse_tui_color(value: &TomlValue) -> ModResult<tui::Color> {
 	if let Ok(str) = value.as_str() {
 		parse_hex_color(str).ok_or_else(|| {
@@ -203,6 +919,23 @@ fn parse_tui_color(value: &TomlValue) -> ModResult<tui::Color> {
 		)
 	}
 }
+// returns None for a transparent background
+fn parse_background_color(value: &TomlValue) -> ModResult<Option<tui::Color>> {
+	match value.as_str() {
+		Ok("transparent") => Ok(None),
+		Ok(str) => parse_hex_color(str).map(Some).ok_or_else(|| {
+			let label = value
+				.loc()
+				.get_primary_label()
+				.with_message("expected format is #RRGGBB or \"transparent\"");
+			Diagnostic::new(parse_lib::Severity::Error)
+				.with_message("invalid background color")
+				.with_labels(vec![label])
+				.into()
+		}),
+		Err(_) => parse_tui_color(value).map(Some),
+	}
+}
 fn parse_hex_color(str: &str) -> Option<tui::Color> {
 	if str.len() != 7 || !str.starts_with('#') || str.chars().nth(1).unwrap() == '+' {
 		return None;
@@ -0,0 +1,326 @@
+// printing of the merged configuration, to debug the layering of files
+use std::time::Duration;
+
+use codespan_reporting::files::Files as _;
+
+use super::{
+	lib::{FileDatabase, Location, MergePolicy},
+	toml_util::{toml_path, toml_string_array, toml_string_escape},
+	ModResult, ParseContext,
+};
+use crate::{
+	open_stats::ProjectSort, project::ReopenPolicy, sandbox::SandboxFallback, ui_subcommand::tui,
+	GlobalConfig,
+};
+
+pub(super) enum DumpEntry {
+	// `key = value`, with the location of the definition
+	Single {
+		key: &'static str,
+		value: String,
+		loc: Option<Location>,
+	},
+	// array whose elements may come from different files
+	List {
+		key: &'static str,
+		values: Vec<(String, Location)>,
+	},
+}
+
+// NOTE: if 'project_name' is given, its project data is merged
+//       with the global project data
+pub(super) fn dump_config(
+	ctx: &mut ParseContext,
+	global_config: &GlobalConfig,
+	project_name: Option<&str>,
+) -> ModResult<String> {
+	let config_file = ctx
+		.dirs
+		.get_skeld_config_dir()
+		.map_err(|err| format!("Failed to determine the skeld config dir:\n  {err}"))?
+		.join("config.toml");
+
+	let mut output = String::new();
+	if config_file.exists() {
+		output += &format!("# configuration file: {}\n", config_file.display());
+		output += "# (options not set there have their default value)\n";
+	} else {
+		output += "# no configuration file, all options have their default value\n";
+	}
+	output += &dump_global_options(global_config);
+
+	let project_data = global_config.global_project_data.clone();
+	let (project_data, heading) = match project_name {
+		Some(name) => {
+			let project = find_project(ctx, name)?;
+			let (project_data, ..) = project.load_parse_state(project_data, ctx)?;
+			let heading = format!("project data of `{name}` merged with the global project data");
+			(project_data, heading)
+		}
+		None => {
+			let mut project_data = project_data;
+			project_data
+				.apply_presets(ctx)
+				.map_err(|err| ctx.add_include_chains(err))?;
+			(project_data, "global project data".to_string())
+		}
+	};
+	let entries = project_data
+		.dump_entries(ctx)
+		.map_err(|err| ctx.add_include_chains(err))?;
+	output += &format!("\n# {heading}\n[project]\n");
+	output += &format_entries(&entries, ctx.file_database);
+	Ok(output)
+}
+fn find_project(ctx: &mut ParseContext, name: &str) -> ModResult<super::ProjectDataFuture> {
+	let projects = ctx.get_projects()?;
+	if let Some(project) = projects.into_iter().find(|project| project.name == name) {
+		return Ok(project.project_data);
+	}
+	let bookmarks = ctx.get_bookmarks()?;
+	if let Some(bookmark) = bookmarks.into_iter().find(|bookmark| bookmark.name == name) {
+		return Ok(bookmark.project_data);
+	}
+	Err(format!("Unknown project or bookmark `{name}`").into())
+}
+
+fn dump_global_options(global_config: &GlobalConfig) -> String {
+	let parse_options = &global_config.parse_options;
+	let merge_policy = match parse_options.merge_policy {
+		MergePolicy::Error => "error",
+		MergePolicy::LastWins => "last-wins",
+		MergePolicy::FirstWins => "first-wins",
+	};
+	let layout = match global_config.layout {
+		tui::Layout::Boxes => "boxes",
+		tui::Layout::List => "list",
+		tui::Layout::Grid => "grid",
+	};
+	let project_sort = match global_config.project_sort {
+		ProjectSort::Name => "name",
+		ProjectSort::Recent => "recent",
+		ProjectSort::Frecency => "frecency",
+	};
+	let mut output = String::new();
+	let mut add_option = |key: &str, value: String| output += &format!("{key} = {value}\n");
+	// NOTE: literal strings keep the banner readable
+	let banner = &global_config.banner;
+	if banner.contains("'''") || banner.contains('\r') {
+		add_option("banner", toml_string_escape(banner));
+	} else {
+		// the first newline after the opening delimiter is trimmed by toml
+		add_option("banner", format!("'''\n{banner}'''"));
+	}
+	add_option("footer", global_config.footer.to_string());
+	add_option("icons", global_config.icons.to_string());
+	add_option("layout", toml_string_escape(layout));
+	let spacing = &global_config.spacing;
+	add_option("center-vertically", spacing.center_vertically.to_string());
+	add_option("top-margin", spacing.top_margin.to_string());
+	add_option("section-spacing", spacing.section_gap.to_string());
+	add_option("button-spacing", spacing.button_gap.to_string());
+	add_option(
+		"selection-marker",
+		toml_string_escape(&global_config.selection_marker),
+	);
+	add_option("inline", global_config.inline.to_string());
+	add_option("max-height", global_config.max_height.to_string());
+	add_option("project-sort", toml_string_escape(project_sort));
+	add_option("resume-on-start", global_config.resume_on_start.to_string());
+	add_option(
+		"edit-keybind",
+		toml_string_escape(&global_config.edit_keybind),
+	);
+	add_option(
+		"yank-keybind",
+		toml_string_escape(&global_config.yank_keybind),
+	);
+	add_option("cd-keybind", toml_string_escape(&global_config.cd_keybind));
+	add_option(
+		"actions-keybind",
+		toml_string_escape(&global_config.actions_keybind),
+	);
+	add_option(
+		"shell-keybind",
+		toml_string_escape(&global_config.shell_keybind),
+	);
+	add_option(
+		"kill-keybind",
+		toml_string_escape(&global_config.kill_keybind),
+	);
+	add_option(
+		"move-top-keybind",
+		toml_string_escape(&global_config.move_top_keybind),
+	);
+	add_option(
+		"move-bottom-keybind",
+		toml_string_escape(&global_config.move_bottom_keybind),
+	);
+	add_option(
+		"next-section-keybind",
+		toml_string_escape(&global_config.next_section_keybind),
+	);
+	add_option(
+		"prev-section-keybind",
+		toml_string_escape(&global_config.prev_section_keybind),
+	);
+	add_option("wrap-navigation", global_config.wrap_navigation.to_string());
+	add_option("quit-on-escape", global_config.quit_on_escape.to_string());
+	if let Some(shell_cmd) = &global_config.shell_cmd {
+		add_option("shell-cmd", toml_string_array(shell_cmd));
+	}
+	add_option("sandbox-edit", global_config.sandbox_edit.to_string());
+	add_option(
+		"double-click-interval",
+		toml_string_escape(&format_duration(global_config.double_click_interval)),
+	);
+	add_option(
+		"max-include-depth",
+		parse_options.max_include_depth.to_string(),
+	);
+	add_option("merge-policy", toml_string_escape(merge_policy));
+	add_option(
+		"project-local-config",
+		parse_options.project_local_config.to_string(),
+	);
+	add_option(
+		"warn-missing-paths",
+		parse_options.warn_missing_paths.to_string(),
+	);
+	let sandbox_fallback = match parse_options.sandbox_fallback {
+		SandboxFallback::Error => "error",
+		SandboxFallback::WarnAndRunUnsandboxed => "warn-and-run-unsandboxed",
+	};
+	add_option("sandbox-fallback", toml_string_escape(sandbox_fallback));
+	add_option("systemd-scope", parse_options.systemd_scope.to_string());
+	let reopen_policy = match parse_options.reopen_policy {
+		ReopenPolicy::Allow => "allow",
+		ReopenPolicy::Warn => "warn",
+		ReopenPolicy::Refuse => "refuse",
+	};
+	add_option("reopen-policy", toml_string_escape(reopen_policy));
+
+	// NOTE: the terminal default cannot be specified, so it is omitted
+	output += "\n[colorscheme]\n";
+	let colorscheme = &global_config.colorscheme;
+	for (key, color) in [
+		("normal", Some(colorscheme.normal)),
+		("banner", Some(colorscheme.banner)),
+		("heading", Some(colorscheme.heading)),
+		("keybind", Some(colorscheme.keybind)),
+		("label", Some(colorscheme.button_label)),
+		("background", colorscheme.background),
+		("footer", Some(colorscheme.footer)),
+		("footer-background", Some(colorscheme.footer_background)),
+		("selected-border", colorscheme.selected_border),
+		("selected-keybind", colorscheme.selected_keybind),
+		("selected-label", colorscheme.selected_label),
+		("selected-background", colorscheme.selected_background),
+	] {
+		if let Some(value) = color.and_then(toml_color) {
+			output += &format!("{key} = {value}\n");
+		}
+	}
+	if colorscheme.background.is_none() {
+		output += "background = \"transparent\"\n";
+	}
+	for (name, colors) in &colorscheme.sections {
+		output += &format!("\n[colorscheme.{name}]\n");
+		for (key, color) in [
+			("heading", colors.heading),
+			("keybind", colors.keybind),
+			("label", colors.button_label),
+		] {
+			if let Some(value) = color.and_then(toml_color) {
+				output += &format!("{key} = {value}\n");
+			}
+		}
+	}
+
+	let log_config = &global_config.log_config;
+	output += "\n[logs]\n";
+	if let Some(dir) = &log_config.dir {
+		output += &format!("dir = {}\n", toml_path(dir));
+	}
+	output += &format!(
+		"filename = {}\n",
+		toml_string_escape(&log_config.filename_template)
+	);
+	let max_age = log_config
+		.max_age
+		.map_or(0, |max_age| max_age.as_secs() / (60 * 60));
+	output += &format!("max-age = {max_age}\n");
+	let max_total_size = log_config
+		.max_total_size
+		.map_or(0, |size| size / (1024 * 1024));
+	output += &format!("max-total-size = {max_total_size}\n");
+
+	for command in &global_config.commands {
+		output += "\n[[commands]]\n";
+		output += &format!("name = {}\n", toml_string_escape(&command.name));
+		output += &format!("keybind = {}\n", toml_string_escape(&command.keybind));
+		output += &format!(
+			"command = {}\n",
+			toml_string_array(&command.command.command)
+		);
+		output += &format!("detach = {}\n", command.command.detach);
+		output += &format!("sandbox = {}\n", command.command.sandbox);
+	}
+	output
+}
+
+fn format_entries(entries: &[DumpEntry], file_database: &FileDatabase) -> String {
+	let mut output = String::new();
+	for entry in entries {
+		match entry {
+			DumpEntry::Single { key, value, loc } => {
+				output += &format!("# {}\n", format_origin(loc.as_ref(), file_database));
+				output += &format!("{key} = {value}\n");
+			}
+			DumpEntry::List { key, values } => {
+				output += &format!("{key} = [\n");
+				for (value, loc) in values {
+					output += &format!("\t# {}\n", format_origin(Some(loc), file_database));
+					output += &format!("\t{value},\n");
+				}
+				output += "]\n";
+			}
+		}
+	}
+	output
+}
+fn format_origin(loc: Option<&Location>, file_database: &FileDatabase) -> String {
+	let Some(loc) = loc else {
+		return "set by skeld".to_string();
+	};
+	let Ok(file) = file_database.get(loc.file.0) else {
+		return "unknown origin".to_string();
+	};
+	let line = file_database
+		.line_index(loc.file.0, loc.span.start)
+		.map_or(0, |line| line + 1);
+	format!("from {}:{line}", file.name())
+}
+
+fn format_duration(duration: Duration) -> String {
+	let millis = duration.as_millis();
+	for (unit, unit_millis) in [
+		("d", 24 * 60 * 60 * 1000),
+		("h", 60 * 60 * 1000),
+		("m", 60 * 1000),
+		("s", 1000),
+	] {
+		if millis != 0 && millis % unit_millis == 0 {
+			return format!("{}{unit}", millis / unit_millis);
+		}
+	}
+	format!("{millis}ms")
+}
+// NOTE: the terminal default cannot be specified, so None is returned for it
+fn toml_color(color: tui::Color) -> Option<String> {
+	match color {
+		tui::Color::Rgb { r, g, b } => Some(format!("\"#{r:02x}{g:02x}{b:02x}\"")),
+		tui::Color::AnsiValue(value) => Some(value.to_string()),
+		_ => None,
+	}
+}
@@ -5,6 +5,7 @@ use std::{
 	ops::Range,
 	path::{Path, PathBuf},
 	rc::Rc,
+	time::Duration,
 };
 
 use codespan_reporting::{
@@ -13,7 +14,10 @@ use codespan_reporting::{
 };
 use toml_span::Span;
 
-use super::ModResult;
+use super::{
+	migration::{self, FileKind},
+	ModResult,
+};
 use crate::DOCS_URL;
 
 pub use codespan_reporting::diagnostic::Severity;
@@ -31,35 +35,97 @@ pub trait ConfigOption {
 // =================================================================================================
 // Wrappers around 'toml_span' adding file location info
 // =================================================================================================
+// NOTE: files of older versions are migrated, the returned warnings
+//       indicate the outdated definitions
 pub fn parse_toml_file<'v>(
 	path: impl AsRef<Path>,
+	kind: FileKind,
 	file_database: &mut FileDatabase,
 	// file contents and root toml value need to outlive the return value
 	outlivers: &'v mut (Option<String>, Option<toml_span::Value<'v>>),
-) -> ModResult<TomlTable<'v>> {
+) -> ModResult<(TomlTable<'v>, Vec<Diagnostic>)> {
 	let path = path.as_ref();
 	assert!(path.is_absolute());
 
-	outlivers.0 = Some(
-		fs::read_to_string(path)
-			.map_err(|err| format!("Failed to read file `{}`: {err}", path.display()))?,
-	);
-	let file_contents = outlivers.0.as_ref().unwrap();
+	let file_contents = fs::read_to_string(path)
+		.map_err(|err| format!("Failed to read file `{}`: {err}", path.display()))?;
+	parse_toml_str(
+		path.to_string_lossy().to_string(),
+		file_contents,
+		kind,
+		file_database,
+		outlivers,
+	)
+}
+// like 'parse_toml_file', but for contents that are not read from a file
+// NOTE: 'name' is shown in diagnostics instead of the path
+pub fn parse_toml_str<'v>(
+	name: String,
+	file_contents: String,
+	kind: FileKind,
+	file_database: &mut FileDatabase,
+	outlivers: &'v mut (Option<String>, Option<toml_span::Value<'v>>),
+) -> ModResult<(TomlTable<'v>, Vec<Diagnostic>)> {
+	let (contents_outliver, root_value) = outlivers;
+	let file_contents = contents_outliver.insert(file_contents);
+	let parse_result = toml_span::parse(file_contents);
+	add_parsed_toml(
+		name,
+		file_contents,
+		parse_result,
+		kind,
+		file_database,
+		root_value,
+	)
+}
+// like 'parse_toml_str', but for contents that have already been parsed
+// (e.g. on another thread), so that only the file database is updated here
+pub fn add_parsed_toml<'v>(
+	name: String,
+	file_contents: &'v str,
+	parse_result: Result<toml_span::Value<'v>, toml_span::Error>,
+	kind: FileKind,
+	file_database: &mut FileDatabase,
+	// root toml value needs to outlive the return value
+	root_value: &'v mut Option<toml_span::Value<'v>>,
+) -> ModResult<(TomlTable<'v>, Vec<Diagnostic>)> {
+	let file_id = FileId(file_database.add(name, file_contents.to_string()));
 
-	let file_id =
-		FileId(file_database.add(path.to_string_lossy().to_string(), file_contents.clone()));
+	let root_value = root_value.insert(parse_result.map_err(|err| err.to_diagnostic(file_id.0))?);
+	let migration_result =
+		migration::migrate(root_value, kind, file_contents).map_err(|(span, msg)| {
+			let label = DiagLabel::primary(file_id.0, span).with_message(msg);
+			Diagnostic::new(Severity::Error)
+				.with_message("invalid config version")
+				.with_labels(vec![label])
+		})?;
+	let warnings = migration_result
+		.changes
+		.into_iter()
+		.map(|change| {
+			let label = DiagLabel::primary(file_id.0, change.span).with_message(change.description);
+			let note = if change.edit.is_some() {
+				"the file can be updated with `skeld migrate`"
+			} else {
+				"the file has to be updated manually"
+			};
+			Diagnostic::new(Severity::Warning)
+				.with_message(format!("deprecated option `{}`", change.old_option))
+				.with_labels(vec![label])
+				.with_notes(vec![note.to_string()])
+		})
+		.collect();
 
-	outlivers.1 = Some(toml_span::parse(file_contents).map_err(|err| err.to_diagnostic(file_id.0))?);
-	let parsed_contents = outlivers.1.as_ref().unwrap();
+	let parsed_contents = &*root_value;
 	let table = parsed_contents.as_table().unwrap();
-
-	Ok(TomlTable {
+	let table = TomlTable {
 		table,
 		loc: Location {
 			span: parsed_contents.span,
 			file: file_id,
 		},
-	})
+	};
+	Ok((table, warnings))
 }
 #[derive(Clone)]
 pub struct TomlKey<'a> {
@@ -126,7 +192,7 @@ impl<'a> TomlValue<'a> {
 				.collect(),
 		)
 	}
-	pub fn as_table(&self) -> ModResult<TomlTable<'_>> {
+	pub fn as_table(&self) -> ModResult<TomlTable<'a>> {
 		let table = self
 			.value
 			.as_table()
@@ -156,7 +222,7 @@ impl<'a> TomlTable<'a> {
 	}
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Location {
 	pub file: FileId,
 	pub span: Span,
@@ -169,19 +235,64 @@ impl Location {
 		DiagLabel::secondary(self.file.0, self.span)
 	}
 }
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct FileId(usize);
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FileId(pub(super) usize);
 
 // ====================================================================================================
 // Basic config options
 // ====================================================================================================
+// definitions with a higher priority override definitions with a lower priority,
+// definitions with the same priority must not conflict
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+	// builtin presets (e.g. 'use-base-preset')
+	Preset,
+	// defaults that are overridden by any other definition (e.g. project-local config files)
+	Defaults,
+	Normal,
+	// platform-specific overrides (e.g. [linux])
+	Platform,
+	// definitions in [forced] tables, that override everything else
+	Forced,
+}
+// resolution of conflicting definitions with the same priority
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum MergePolicy {
+	#[default]
+	Error,
+	LastWins,
+	FirstWins,
+}
+impl MergePolicy {
+	// returns whether the new definition replaces the previous one
+	// NOTE: 'policy' is None for options that 'merge-policy' does not apply to
+	pub fn resolve(policy: Option<Self>, conflict: impl FnOnce() -> Diagnostic) -> ModResult<bool> {
+		match policy {
+			None => Err(conflict().into()),
+			Some(Self::Error) => Err(
+				conflict()
+					.with_notes(vec![
+						"the config option `merge-policy` can resolve such conflicts automatically".to_string(),
+					])
+					.into(),
+			),
+			Some(Self::LastWins) => Ok(true),
+			Some(Self::FirstWins) => Ok(false),
+		}
+	}
+}
+
 // helper config option that eats only a specific key using a specified parse function
 #[derive(Clone)]
 pub struct BaseOption<T> {
 	#[allow(clippy::type_complexity)]
 	parse_fn: Rc<dyn Fn(&TomlValue) -> ModResult<T>>,
 	name: String,
-	value: Option<(T, Location)>,
+	value: Option<(T, Location, Priority)>,
+	// priority of subsequently eaten values
+	priority: Priority,
+	// None if conflicts are always an error
+	merge_policy: Option<MergePolicy>,
 }
 impl<T> BaseOption<T> {
 	pub fn new(name: &str, parse_fn: impl Fn(&TomlValue) -> ModResult<T> + 'static) -> Self {
@@ -189,10 +300,25 @@ impl<T> BaseOption<T> {
 			parse_fn: Rc::new(parse_fn),
 			name: name.to_string(),
 			value: None,
+			priority: Priority::Normal,
+			merge_policy: None,
 		}
 	}
 	pub fn get_value(self) -> Option<T> {
-		self.value.map(|(value, _)| value)
+		self.value.map(|(value, _, _)| value)
+	}
+	pub fn get_value_with_loc(self) -> Option<(T, Location)> {
+		self.value.map(|(value, loc, _)| (value, loc))
+	}
+	// NOTE: overrides any previous definition
+	pub fn set_value(&mut self, value: T, loc: Location) {
+		self.value = Some((value, loc, self.priority));
+	}
+	pub fn set_priority(&mut self, priority: Priority) {
+		self.priority = priority;
+	}
+	pub fn set_merge_policy(&mut self, merge_policy: MergePolicy) {
+		self.merge_policy = Some(merge_policy);
 	}
 }
 impl<T: PartialEq> ConfigOption for BaseOption<T> {
@@ -203,12 +329,18 @@ impl<T: PartialEq> ConfigOption for BaseOption<T> {
 
 		let value = (self.parse_fn)(value)?;
 		match &self.value {
-			Some(prev_val) if prev_val.0 != value => {
-				return Err(diagnostics::multiple_definitions(&prev_val.1, key.loc(), &self.name).into());
+			Some(prev_val) if prev_val.2 > self.priority => return Ok(true),
+			Some(prev_val) if prev_val.2 == self.priority && prev_val.0 != value => {
+				let is_replaced = MergePolicy::resolve(self.merge_policy, || {
+					diagnostics::multiple_definitions(&prev_val.1, key.loc(), &self.name, self.priority)
+				})?;
+				if !is_replaced {
+					return Ok(true);
+				}
 			}
 			_ => (),
 		}
-		self.value = Some((value, key.loc().clone()));
+		self.value = Some((value, key.loc().clone(), self.priority));
 		Ok(true)
 	}
 }
@@ -222,6 +354,15 @@ impl BoolOption {
 	pub fn get_value(self) -> Option<bool> {
 		self.0.get_value()
 	}
+	pub fn get_value_with_loc(self) -> Option<(bool, Location)> {
+		self.0.get_value_with_loc()
+	}
+	pub fn set_priority(&mut self, priority: Priority) {
+		self.0.set_priority(priority);
+	}
+	pub fn set_merge_policy(&mut self, merge_policy: MergePolicy) {
+		self.0.set_merge_policy(merge_policy);
+	}
 }
 impl ConfigOption for BoolOption {
 	fn try_eat(&mut self, key: &TomlKey, value: &TomlValue) -> ModResult<bool> {
@@ -229,6 +370,77 @@ impl ConfigOption for BoolOption {
 	}
 }
 
+// durations are specified as strings like "30s", "5m" or "1.5h"
+#[derive(Clone)]
+pub struct DurationOption(BaseOption<Duration>);
+impl DurationOption {
+	pub fn new(name: &str) -> Self {
+		Self(BaseOption::new(name, |value| {
+			parse_duration(value.as_str()?)
+				.map_err(|err| diagnostics::failed_canonicalization(value, &err).into())
+		}))
+	}
+	pub fn get_value(self) -> Option<Duration> {
+		self.0.get_value()
+	}
+}
+impl ConfigOption for DurationOption {
+	fn try_eat(&mut self, key: &TomlKey, value: &TomlValue) -> ModResult<bool> {
+		self.0.try_eat(key, value)
+	}
+}
+fn parse_duration(str: &str) -> Result<Duration, CanonicalizationError> {
+	const UNITS: &[(&str, f64)] = &[
+		("ms", 0.001),
+		("s", 1.0),
+		("m", 60.0),
+		("h", 60.0 * 60.0),
+		("d", 24.0 * 60.0 * 60.0),
+	];
+
+	let number_len = str
+		.find(|ch: char| !ch.is_ascii_digit() && ch != '.')
+		.unwrap_or(str.len());
+	let (number, unit) = str.split_at(number_len);
+	let Ok(number) = number.parse::<f64>() else {
+		let label = if number.is_empty() {
+			CanonicalizationLabel::primary_with_span(0..str.len(), "expected a number followed by a unit")
+		} else {
+			CanonicalizationLabel::primary_with_span(0..number_len, "not a valid number")
+		};
+		return Err(CanonicalizationError {
+			labels: vec![label],
+			notes: vec!["examples: \"500ms\", \"30s\", \"5m\", \"2h\"".to_string()],
+			..CanonicalizationError::main_message("invalid duration")
+		});
+	};
+	let Some((_, factor)) = UNITS.iter().find(|(name, _)| *name == unit) else {
+		let label = if unit.is_empty() {
+			CanonicalizationLabel::primary_with_span(0..number_len, "missing unit after this number")
+		} else {
+			CanonicalizationLabel::primary_with_span(number_len..str.len(), "unknown unit")
+		};
+		let units = UNITS
+			.iter()
+			.map(|(name, _)| format!("`{name}`"))
+			.collect::<Vec<_>>()
+			.join(", ");
+		return Err(CanonicalizationError {
+			labels: vec![label],
+			notes: vec![format!("supported units: {units}")],
+			..CanonicalizationError::main_message("invalid duration")
+		});
+	};
+
+	Duration::try_from_secs_f64(number * factor).map_err(|_| CanonicalizationError {
+		labels: vec![CanonicalizationLabel::primary_with_span(
+			0..str.len(),
+			"this duration is too long",
+		)],
+		..CanonicalizationError::main_message("invalid duration")
+	})
+}
+
 #[derive(Clone)]
 pub struct PathBufOption(BaseOption<PathBuf>);
 impl PathBufOption {
@@ -245,6 +457,18 @@ impl PathBufOption {
 	pub fn get_value(self) -> Option<PathBuf> {
 		self.0.get_value()
 	}
+	pub fn get_value_with_loc(self) -> Option<(PathBuf, Location)> {
+		self.0.get_value_with_loc()
+	}
+	pub fn set_value(&mut self, value: PathBuf, loc: Location) {
+		self.0.set_value(value, loc);
+	}
+	pub fn set_priority(&mut self, priority: Priority) {
+		self.0.set_priority(priority);
+	}
+	pub fn set_merge_policy(&mut self, merge_policy: MergePolicy) {
+		self.0.set_merge_policy(merge_policy);
+	}
 }
 impl ConfigOption for PathBufOption {
 	fn try_eat(&mut self, key: &TomlKey, value: &TomlValue) -> ModResult<bool> {
@@ -270,6 +494,15 @@ impl StringOption {
 	pub fn get_value(self) -> Option<String> {
 		self.0.get_value()
 	}
+	pub fn get_value_with_loc(self) -> Option<(String, Location)> {
+		self.0.get_value_with_loc()
+	}
+	pub fn set_priority(&mut self, priority: Priority) {
+		self.0.set_priority(priority);
+	}
+	pub fn set_merge_policy(&mut self, merge_policy: MergePolicy) {
+		self.0.set_merge_policy(merge_policy);
+	}
 }
 impl ConfigOption for StringOption {
 	fn try_eat(&mut self, key: &TomlKey, value: &TomlValue) -> ModResult<bool> {
@@ -350,25 +583,45 @@ impl CanonicalizationLabel {
 #[derive(Clone)]
 pub struct ArrayOption<V> {
 	name: String,
-	// value: Option<(_, key location, value location)>
-	value: Option<(Vec<V>, Location, Location)>,
-	parse_entry_fn: fn(&TomlValue) -> ModResult<V>,
+	// value: Option<(_, key location, value location, priority)>
+	value: Option<(Vec<V>, Location, Location, Priority)>,
+	#[allow(clippy::type_complexity)]
+	parse_entry_fn: Rc<dyn Fn(&TomlValue) -> ModResult<V>>,
+	// NOTE: mergable arrays are merged regardless of their priority,
+	//       unless some definitions are forced
 	mergable: bool,
+	// priority of subsequently eaten values
+	priority: Priority,
+	// None if conflicts are always an error
+	// NOTE: only used for arrays that are not mergable
+	merge_policy: Option<MergePolicy>,
 }
 impl<V> ArrayOption<V> {
-	pub fn new(name: &str, mergable: bool, parse_entry_fn: fn(&TomlValue) -> ModResult<V>) -> Self {
+	pub fn new(
+		name: &str,
+		mergable: bool,
+		parse_entry_fn: impl Fn(&TomlValue) -> ModResult<V> + 'static,
+	) -> Self {
 		Self {
 			name: name.to_string(),
 			value: None,
-			parse_entry_fn,
+			parse_entry_fn: Rc::new(parse_entry_fn),
 			mergable,
+			priority: Priority::Normal,
+			merge_policy: None,
 		}
 	}
 	pub fn get_value(self) -> Option<Vec<V>> {
-		self.value.map(|(v, _, _)| v)
+		self.value.map(|(v, _, _, _)| v)
 	}
 	pub fn get_value_with_loc(self) -> Option<(Vec<V>, Location)> {
-		self.value.map(|(v, _, loc)| (v, loc))
+		self.value.map(|(v, _, loc, _)| (v, loc))
+	}
+	pub fn set_priority(&mut self, priority: Priority) {
+		self.priority = priority;
+	}
+	pub fn set_merge_policy(&mut self, merge_policy: MergePolicy) {
+		self.merge_policy = Some(merge_policy);
 	}
 }
 impl<V> ConfigOption for ArrayOption<V> {
@@ -378,16 +631,39 @@ impl<V> ConfigOption for ArrayOption<V> {
 		}
 		let array = value.as_array()?;
 
+		// NOTE: mergable arrays only distinguish between forced and other definitions
+		let effective_priority = |priority: Priority| match self.mergable {
+			true => (priority == Priority::Forced) as u8,
+			false => priority as u8,
+		};
 		match &self.value {
-			Some((_, prev_loc, _)) if !self.mergable => {
-				return Err(diagnostics::multiple_definitions(key.loc(), prev_loc, &self.name).into());
+			Some((_, _, _, prev_prio))
+				if effective_priority(*prev_prio) > effective_priority(self.priority) =>
+			{
+				return Ok(true);
+			}
+			Some((_, _, _, prev_prio))
+				if effective_priority(*prev_prio) < effective_priority(self.priority) =>
+			{
+				self.value = None;
+			}
+			Some((_, prev_loc, _, _)) if !self.mergable => {
+				let is_replaced = MergePolicy::resolve(self.merge_policy, || {
+					diagnostics::multiple_definitions(prev_loc, key.loc(), &self.name, self.priority)
+				})?;
+				if !is_replaced {
+					return Ok(true);
+				}
+				self.value = None;
 			}
 			_ => (),
 		}
-		let (values, _, _) =
-			self
-				.value
-				.get_or_insert((Vec::new(), key.loc().clone(), value.loc().clone()));
+		let (values, _, _, _) = self.value.get_or_insert((
+			Vec::new(),
+			key.loc().clone(),
+			value.loc().clone(),
+			self.priority,
+		));
 
 		for inner_value in array {
 			values.push((self.parse_entry_fn)(&inner_value)?);
@@ -397,6 +673,93 @@ impl<V> ConfigOption for ArrayOption<V> {
 	}
 }
 
+// option for a table of named entries (e.g. `[NAME.ENTRY]`),
+// entries from multiple definitions are merged
+#[derive(Clone)]
+pub struct TableOption<V> {
+	name: String,
+	// entries: Vec<(entry name, _, key location, priority)>
+	entries: Vec<(String, V, Location, Priority)>,
+	#[allow(clippy::type_complexity)]
+	parse_entry_fn: Rc<dyn Fn(&TomlValue) -> ModResult<V>>,
+	// priority of subsequently eaten values
+	priority: Priority,
+	// None if conflicts are always an error
+	merge_policy: Option<MergePolicy>,
+}
+// NOTE: only used by the tests until the first table of named entries is added
+#[cfg_attr(not(test), expect(dead_code))]
+impl<V> TableOption<V> {
+	pub fn new(name: &str, parse_entry_fn: impl Fn(&TomlValue) -> ModResult<V> + 'static) -> Self {
+		Self {
+			name: name.to_string(),
+			entries: Vec::new(),
+			parse_entry_fn: Rc::new(parse_entry_fn),
+			priority: Priority::Normal,
+			merge_policy: None,
+		}
+	}
+	// NOTE: the entries are in the order of their first definition
+	pub fn get_value(self) -> Vec<(String, V)> {
+		self
+			.entries
+			.into_iter()
+			.map(|(name, value, _, _)| (name, value))
+			.collect()
+	}
+	pub fn get_value_with_loc(self) -> Vec<(String, V, Location)> {
+		self
+			.entries
+			.into_iter()
+			.map(|(name, value, loc, _)| (name, value, loc))
+			.collect()
+	}
+	pub fn set_priority(&mut self, priority: Priority) {
+		self.priority = priority;
+	}
+	pub fn set_merge_policy(&mut self, merge_policy: MergePolicy) {
+		self.merge_policy = Some(merge_policy);
+	}
+}
+impl<V> ConfigOption for TableOption<V> {
+	fn try_eat(&mut self, key: &TomlKey, value: &TomlValue) -> ModResult<bool> {
+		if key.name != self.name {
+			return Ok(false);
+		}
+
+		for (entry_key, entry_value) in value.as_table()?.iter() {
+			let entry = (self.parse_entry_fn)(&entry_value)?;
+			let prev_entry = self
+				.entries
+				.iter_mut()
+				.find(|(name, ..)| name == entry_key.name());
+			match prev_entry {
+				Some((_, _, _, prev_prio)) if *prev_prio > self.priority => (),
+				Some((_, prev_value, prev_loc, prev_prio)) => {
+					let is_replaced = *prev_prio < self.priority
+						|| MergePolicy::resolve(self.merge_policy, || {
+							let name = format!("{}.{}", self.name, entry_key.name());
+							diagnostics::multiple_definitions(prev_loc, entry_key.loc(), &name, self.priority)
+						})?;
+					if is_replaced {
+						*prev_value = entry;
+						*prev_loc = entry_key.loc().clone();
+						*prev_prio = self.priority;
+					}
+				}
+				None => self.entries.push((
+					entry_key.name().to_string(),
+					entry,
+					entry_key.loc().clone(),
+					self.priority,
+				)),
+			}
+		}
+
+		Ok(true)
+	}
+}
+
 macro_rules! parse_table {
 	($table:expr => [$($opt:expr),*], docs-pref: $docs_pref:expr $(,)?) => {'blk: {
 		use $crate::parse::lib::*;
@@ -465,7 +828,13 @@ pub mod diagnostics {
 			.with_labels(vec![key.loc().get_primary_label()])
 			.with_notes(vec![format!("(see {docs_url} for supported options)")])
 	}
-	pub fn multiple_definitions(loc1: &Location, loc2: &Location, name: &str) -> Diagnostic {
+	// NOTE: 'priority' is the priority of both definitions
+	pub fn multiple_definitions(
+		loc1: &Location,
+		loc2: &Location,
+		name: &str,
+		priority: Priority,
+	) -> Diagnostic {
 		let label1 = loc2.get_primary_label().with_message("redefined here");
 		let label2 = loc1
 			.get_secondary_label()
@@ -473,6 +842,32 @@ pub mod diagnostics {
 		Diagnostic::new(Severity::Error)
 			.with_message(format!("`{name}` is defined multiple times"))
 			.with_labels(vec![label1, label2])
+			.with_notes(same_priority_notes(priority))
+	}
+	pub fn priority_name(priority: Priority) -> &'static str {
+		match priority {
+			Priority::Preset => "builtin preset",
+			Priority::Defaults => "default",
+			Priority::Normal => "normal",
+			Priority::Platform => "platform-specific",
+			Priority::Forced => "forced",
+		}
+	}
+	pub fn same_priority_notes(priority: Priority) -> Vec<String> {
+		let priority_str = match priority {
+			Priority::Preset => "from builtin presets",
+			Priority::Defaults => "defaults (from a `[defaults]` table or a project-local file)",
+			Priority::Normal => "normal definitions",
+			Priority::Platform => "platform-specific definitions",
+			Priority::Forced => "forced definitions",
+		};
+		let mut notes = vec![format!(
+			"both definitions are {priority_str}, so neither takes precedence"
+		)];
+		if priority != Priority::Forced {
+			notes.push("a definition in a `[forced]` table overrides all other definitions".to_string());
+		}
+		notes
 	}
 	pub fn wrong_type(got: &TomlValue, expected: &[TomlInnerValue]) -> Diagnostic {
 		assert!(!expected.is_empty());
@@ -502,3 +897,150 @@ pub mod diagnostics {
 		.to_string()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::GenericError;
+
+	type Outlivers<'v> = (Option<String>, Option<toml_span::Value<'v>>);
+
+	fn parse_str<'v>(
+		name: &str,
+		contents: &str,
+		file_database: &mut FileDatabase,
+		outlivers: &'v mut Outlivers<'v>,
+	) -> TomlTable<'v> {
+		let contents = format!("config-version = {}\n{contents}", migration::CONFIG_VERSION);
+		let (table, _) = parse_toml_str(
+			name.to_string(),
+			contents,
+			FileKind::ProjectData,
+			file_database,
+			outlivers,
+		)
+		.unwrap_or_else(|_| panic!("`{name}` is not valid"));
+		table
+	}
+	// parses entries of the form `[actions.NAME]` with a `cmd` string
+	fn actions_option() -> TableOption<String> {
+		TableOption::new("actions", |value| {
+			let mut cmd = BaseOption::new("cmd", |value| value.as_str().map(str::to_string));
+			parse_table!(value.as_table()? => [cmd], docs-pref: "actions")?;
+			Ok(cmd.get_value().unwrap_or_default())
+		})
+	}
+	fn eat(option: &mut TableOption<String>, table: &TomlTable) -> ModResult<()> {
+		parse_table!(table => [option], docs-pref: "")
+	}
+	fn label_text<'f>(file_database: &'f FileDatabase, label: &DiagLabel<usize>) -> &'f str {
+		&file_database.get(label.file_id).unwrap().source()[label.range.clone()]
+	}
+
+	#[test]
+	fn table_option_merges_entries_by_priority() {
+		let mut file_database = FileDatabase::new();
+		let (mut outlivers1, mut outlivers2) = ((None, None), (None, None));
+		let table1 = parse_str(
+			"first",
+			"[actions.build]\ncmd = \"make\"\n[actions.test]\ncmd = \"make test\"",
+			&mut file_database,
+			&mut outlivers1,
+		);
+		let table2 = parse_str(
+			"second",
+			"[actions.lint]\ncmd = \"make lint\"\n[actions.test]\ncmd = \"cargo test\"",
+			&mut file_database,
+			&mut outlivers2,
+		);
+
+		let mut option = actions_option();
+		eat(&mut option, &table1).unwrap();
+		option.set_priority(Priority::Platform);
+		eat(&mut option, &table2).unwrap();
+		let entries = option.get_value();
+		let expected = [
+			("build", "make"),
+			("test", "cargo test"),
+			("lint", "make lint"),
+		];
+		assert_eq!(entries.len(), expected.len());
+		for ((name, cmd), (expected_name, expected_cmd)) in entries.iter().zip(expected) {
+			assert_eq!((name.as_str(), cmd.as_str()), (expected_name, expected_cmd));
+		}
+	}
+
+	#[test]
+	fn table_option_reports_duplicate_entries() {
+		let mut file_database = FileDatabase::new();
+		let (mut outlivers1, mut outlivers2) = ((None, None), (None, None));
+		let table1 = parse_str(
+			"first",
+			"[actions.build]\ncmd = \"make\"",
+			&mut file_database,
+			&mut outlivers1,
+		);
+		let table2 = parse_str(
+			"second",
+			"[actions.build]\ncmd = \"cargo build\"",
+			&mut file_database,
+			&mut outlivers2,
+		);
+
+		let mut option = actions_option();
+		eat(&mut option, &table1).unwrap();
+		let Err(GenericError::Diagnostic(diagnostic)) = eat(&mut option, &table2) else {
+			panic!("expected a diagnostic for the duplicate entry");
+		};
+		assert_eq!(
+			diagnostic.message,
+			"`actions.build` is defined multiple times"
+		);
+		let [redefined, first_defined] = diagnostic.labels.as_slice() else {
+			panic!("expected two labels");
+		};
+		assert_eq!(redefined.style, DiagLabelStyle::Primary);
+		assert_eq!(redefined.message, "redefined here");
+		assert_eq!(
+			file_database.get(redefined.file_id).unwrap().name(),
+			"second"
+		);
+		assert_eq!(label_text(&file_database, redefined), "build");
+		assert_eq!(first_defined.style, DiagLabelStyle::Secondary);
+		assert_eq!(first_defined.message, "first defined here");
+		assert_eq!(
+			file_database.get(first_defined.file_id).unwrap().name(),
+			"first"
+		);
+		assert_eq!(label_text(&file_database, first_defined), "build");
+	}
+
+	#[test]
+	fn table_option_resolves_duplicate_entries_with_merge_policy() {
+		let mut file_database = FileDatabase::new();
+		let (mut outlivers1, mut outlivers2) = ((None, None), (None, None));
+		let table1 = parse_str(
+			"first",
+			"[actions.build]\ncmd = \"make\"",
+			&mut file_database,
+			&mut outlivers1,
+		);
+		let table2 = parse_str(
+			"second",
+			"[actions.build]\ncmd = \"cargo build\"",
+			&mut file_database,
+			&mut outlivers2,
+		);
+
+		let mut option = actions_option();
+		option.set_merge_policy(MergePolicy::LastWins);
+		eat(&mut option, &table1).unwrap();
+		eat(&mut option, &table2).unwrap();
+		let entries = option.get_value_with_loc();
+		let [(name, cmd, loc)] = entries.as_slice() else {
+			panic!("expected a single entry");
+		};
+		assert_eq!((name.as_str(), cmd.as_str()), ("build", "cargo build"));
+		assert_eq!(file_database.get(loc.file.0).unwrap().name(), "second");
+	}
+}
@@ -0,0 +1,377 @@
+// translation of files written for older versions of skeld
+use std::{borrow::Cow, ops::Range};
+
+use toml_span::{
+	value::{Key, Table, ValueInner},
+	Span, Value,
+};
+
+use super::toml_util::toml_string_escape;
+
+// NOTE: files without 'config-version' have version 1
+pub const CONFIG_VERSION: i64 = 4;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum FileKind {
+	Config,
+	// project, include and project-local files
+	ProjectData,
+	Bookmark,
+}
+
+pub struct Change {
+	// location of the outdated definition
+	pub span: Span,
+	pub old_option: String,
+	pub description: String,
+	// replacement in the file contents,
+	// None if the file has to be updated manually
+	pub edit: Option<(Range<usize>, String)>,
+}
+pub struct MigrationResult {
+	pub version: i64,
+	// span of the value of 'config-version', if present
+	pub version_span: Option<Span>,
+	pub changes: Vec<Change>,
+}
+
+// translate 'value' in place to the current version,
+// the 'config-version' key is removed
+pub fn migrate(
+	value: &mut Value,
+	kind: FileKind,
+	contents: &str,
+) -> Result<MigrationResult, (Span, String)> {
+	let span = value.span;
+	let ValueInner::Table(mut table) = value.take() else {
+		unreachable!("the root of a toml file is always a table");
+	};
+
+	let version_value = table.remove("config-version");
+	let version = match &version_value {
+		None => 1,
+		Some(version_value) => match version_value.as_integer() {
+			Some(version) if (1..=CONFIG_VERSION).contains(&version) => version,
+			_ => {
+				let msg = format!("must be an integer between 1 and {CONFIG_VERSION}");
+				return Err((version_value.span, msg));
+			}
+		},
+	};
+
+	let mut changes = Vec::new();
+	if version < 2 {
+		match kind {
+			FileKind::Config => migrate_config_v1(&mut table, contents, &mut changes),
+			FileKind::ProjectData => migrate_project_data_v1(&mut table, &mut changes),
+			FileKind::Bookmark => {
+				with_subtable(&mut table, "project", |table| {
+					migrate_project_data_v1(table, &mut changes)
+				});
+			}
+		}
+	}
+	if version < 3 && kind == FileKind::Config {
+		migrate_config_v2(&mut table, &mut changes);
+	}
+	if version < 4 {
+		migrate_escapes_v3(&mut table, contents, &mut changes);
+	}
+
+	*value = Value::with_span(ValueInner::Table(table), span);
+	Ok(MigrationResult {
+		version,
+		version_span: version_value.map(|value| value.span),
+		changes,
+	})
+}
+
+fn migrate_config_v1(table: &mut Table<'_>, contents: &str, changes: &mut Vec<Change>) {
+	with_subtable(table, "project", |table| {
+		migrate_project_data_v1(table, changes)
+	});
+
+	// commands used to be a table with the names as keys
+	let Some((key, commands)) = table.remove_entry("commands") else {
+		return;
+	};
+	let ValueInner::Table(command_table) = commands.as_ref() else {
+		table.insert(key, commands);
+		return;
+	};
+	// NOTE: only commands defined with table headers
+	//       (`[commands.NAME]`) can be updated automatically
+	let header_lines = command_table
+		.keys()
+		.map(|name| find_header_line(contents, name.span))
+		.collect::<Option<Vec<_>>>();
+
+	let mut command_array = Vec::new();
+	let mut commands = commands;
+	let ValueInner::Table(command_table) = commands.take() else {
+		unreachable!();
+	};
+	for (i, (name, mut command)) in command_table.into_iter().enumerate() {
+		let edit = header_lines.as_ref().map(|lines| {
+			let name_str = toml_string_escape(&name.name);
+			(lines[i].clone(), format!("[[commands]]\nname = {name_str}"))
+		});
+		changes.push(Change {
+			span: name.span,
+			old_option: format!("commands.{}", name.name),
+			description: "commands are now an array of tables with a `name` option".to_string(),
+			edit,
+		});
+		if let ValueInner::Table(mut command_table) = command.take() {
+			let name_value = Value::with_span(ValueInner::String(name.name.clone()), name.span);
+			command_table.insert(
+				Key {
+					name: Cow::Borrowed("name"),
+					span: name.span,
+				},
+				name_value,
+			);
+			command.set(ValueInner::Table(command_table));
+		} else {
+			// NOTE: the type error is reported by the parser
+			command.set(ValueInner::Boolean(false));
+		}
+		command_array.push(command);
+	}
+	table.insert(
+		key,
+		Value::with_span(ValueInner::Array(command_array), commands.span),
+	);
+}
+// the footer replaced the help text
+fn migrate_config_v2(table: &mut Table<'_>, changes: &mut Vec<Change>) {
+	// NOTE: if both are present, the parser reports the old name as unknown
+	if table.contains_key("footer") {
+		return;
+	}
+	let Some((key, mut value)) = table.remove_entry("disable-help") else {
+		return;
+	};
+	// NOTE: the type error of a non-boolean value is reported by the parser
+	let edit = value.as_bool().map(|disable_help| {
+		value.set(ValueInner::Boolean(!disable_help));
+		(
+			key.span.start..value.span.end,
+			format!("footer = {}", !disable_help),
+		)
+	});
+	changes.push(Change {
+		span: key.span,
+		old_option: "disable-help".to_string(),
+		description: "replaced by `footer` (with the inverse value)".to_string(),
+		edit,
+	});
+	let new_key = Key {
+		name: Cow::Borrowed("footer"),
+		span: key.span,
+	};
+	table.insert(new_key, value);
+}
+// backslashes in interpolated strings used to be literal,
+// but now they start escape sequences
+fn migrate_escapes_v3(table: &mut Table<'_>, contents: &str, changes: &mut Vec<Change>) {
+	// NOTE: the options are matched by name in all tables, as their
+	//       names are not used for options without interpolation
+	const INTERPOLATED_OPTIONS: &[&str] = &[
+		"project-dir",
+		"private-home",
+		"include",
+		"initial-file",
+		"whitelist-ro",
+		"whitelist-rw",
+		"whitelist-cow",
+		"whitelist-dev",
+		"whitelist-ln",
+		"add-tmpfs",
+		"blacklist",
+		"src",
+		"dest",
+		"cmd-with-file",
+		"cmd-without-file",
+		"working-dir",
+		"command",
+		"banner-font",
+		"dir",
+		"filename",
+	];
+	for (key, value) in table.iter_mut() {
+		if INTERPOLATED_OPTIONS.contains(&&*key.name) {
+			let mut inner = value.take();
+			if let ValueInner::Array(array) = &mut inner {
+				for entry in array {
+					escape_backslashes(entry, &key.name, contents, changes);
+				}
+			}
+			value.set(inner);
+			escape_backslashes(value, &key.name, contents, changes);
+		} else {
+			visit_tables(value, |table| migrate_escapes_v3(table, contents, changes));
+		}
+	}
+}
+// NOTE: does nothing if 'value' is not a string containing a backslash
+fn escape_backslashes(
+	value: &mut Value<'_>,
+	option: &str,
+	contents: &str,
+	changes: &mut Vec<Change>,
+) {
+	let Some(str) = value.as_str().filter(|str| str.contains('\\')) else {
+		return;
+	};
+	let escaped_str = str.replace('\\', "\\\\");
+	let raw_contents = &contents[value.span.start..value.span.end];
+	// NOTE: the contents of literal strings are not escaped,
+	//       whereas basic strings are rewritten completely
+	let edit = if raw_contents == str {
+		Some((value.span.start..value.span.end, escaped_str.clone()))
+	} else {
+		let is_basic_string = contents[..value.span.start].ends_with('"')
+			&& !contents[..value.span.start].ends_with("\"\"\"");
+		is_basic_string.then(|| {
+			(
+				value.span.start - 1..value.span.end + 1,
+				toml_string_escape(&escaped_str),
+			)
+		})
+	};
+	changes.push(Change {
+		span: value.span,
+		old_option: option.to_string(),
+		description: "backslashes start escape sequences now, so they are doubled".to_string(),
+		edit,
+	});
+	value.set(ValueInner::String(Cow::Owned(escaped_str)));
+}
+// apply 'f' to all tables in 'value', including tables in arrays
+fn visit_tables<'de>(value: &mut Value<'de>, mut f: impl FnMut(&mut Table<'de>)) {
+	let mut inner = value.take();
+	match &mut inner {
+		ValueInner::Table(table) => f(table),
+		ValueInner::Array(array) => {
+			for entry in array {
+				with_table(entry, &mut f);
+			}
+		}
+		_ => (),
+	}
+	value.set(inner);
+}
+fn migrate_project_data_v1(table: &mut Table<'_>, changes: &mut Vec<Change>) {
+	const RENAMED_OPTIONS: &[(&str, &str)] = &[
+		("whitelists-rw", "whitelist-rw"),
+		("whitelists-ro", "whitelist-ro"),
+		("whitelists-cow", "whitelist-cow"),
+		("whitelists-dev", "whitelist-dev"),
+		("whitelists-ln", "whitelist-ln"),
+		("whitelists-envvar", "whitelist-envvar"),
+	];
+	for (old_name, new_name) in RENAMED_OPTIONS {
+		// NOTE: if both are present, the parser reports the old name as unknown
+		if table.contains_key(*new_name) {
+			continue;
+		}
+		let Some((key, value)) = table.remove_entry(*old_name) else {
+			continue;
+		};
+		changes.push(Change {
+			span: key.span,
+			old_option: old_name.to_string(),
+			description: format!("renamed to `{new_name}`"),
+			edit: Some((key.span.start..key.span.end, new_name.to_string())),
+		});
+		let new_key = Key {
+			name: Cow::Borrowed(*new_name),
+			span: key.span,
+		};
+		table.insert(new_key, value);
+	}
+
+	// the editor commands used to be top-level options
+	let editor_existed = table.contains_key("editor");
+	for option in ["cmd-with-file", "cmd-without-file"] {
+		// NOTE: conflicts are reported by the parser
+		let is_conflicting = table.get("editor").is_some_and(|editor| {
+			editor
+				.as_table()
+				.map_or(true, |editor| editor.contains_key(option))
+		});
+		if is_conflicting {
+			continue;
+		}
+		let Some((key, value)) = table.remove_entry(option) else {
+			continue;
+		};
+		// NOTE: a dotted key would conflict with an existing `[editor]` table
+		let edit =
+			(!editor_existed).then(|| (key.span.start..key.span.end, format!("editor.{option}")));
+		changes.push(Change {
+			span: key.span,
+			old_option: option.to_string(),
+			description: format!("moved to `editor.{option}`"),
+			edit,
+		});
+		let editor = table
+			.entry(Key {
+				name: Cow::Borrowed("editor"),
+				span: key.span,
+			})
+			.or_insert_with(|| Value::with_span(ValueInner::Table(Table::new()), key.span));
+		with_table(editor, |editor| {
+			editor.insert(key, value);
+		});
+	}
+
+	for platform in ["linux", "macos"] {
+		with_subtable(table, platform, |table| {
+			migrate_project_data_v1(table, changes)
+		});
+	}
+}
+
+fn with_subtable<'de>(table: &mut Table<'de>, name: &str, f: impl FnOnce(&mut Table<'de>)) {
+	if let Some(value) = table.get_mut(name) {
+		with_table(value, f);
+	}
+}
+// NOTE: does nothing if 'value' is not a table
+fn with_table<'de>(value: &mut Value<'de>, f: impl FnOnce(&mut Table<'de>)) {
+	let mut inner = value.take();
+	if let ValueInner::Table(table) = &mut inner {
+		f(table);
+	}
+	value.set(inner);
+}
+// returns the range of the table header line containing 'key_span'
+fn find_header_line(contents: &str, key_span: Span) -> Option<Range<usize>> {
+	let line_start = contents[..key_span.start]
+		.rfind('\n')
+		.map_or(0, |pos| pos + 1);
+	let line_end = contents[key_span.start..]
+		.find('\n')
+		.map_or(contents.len(), |pos| key_span.start + pos);
+	let line = contents[line_start..line_end].trim();
+	let is_header = line.starts_with('[') && !line.starts_with("[[") && line.ends_with(']');
+	is_header.then_some(line_start..line_end)
+}
+// apply non-overlapping edits to 'contents'
+pub fn apply_edits(contents: &str, edits: &[(Range<usize>, String)]) -> String {
+	let mut edits = edits.to_vec();
+	// NOTE: insertions (empty ranges) precede a replacement at the same position
+	edits.sort_by_key(|(range, _)| (range.start, range.end));
+
+	let mut new_contents = String::new();
+	let mut last_end = 0;
+	for (range, replacement) in edits {
+		new_contents.push_str(&contents[last_end..range.start]);
+		new_contents.push_str(&replacement);
+		last_end = range.end;
+	}
+	new_contents.push_str(&contents[last_end..]);
+	new_contents
+}
@@ -1,26 +1,51 @@
 mod config;
+mod dump;
 mod lib;
+pub mod migration;
 mod path;
 mod project_data;
+pub mod toml_util;
 
 use std::{
-	fs, io,
+	collections::{HashMap, HashSet},
+	env, fs,
+	io::{self, Read as _},
+	num::NonZeroUsize,
 	path::{Path, PathBuf},
+	sync::Arc,
+	thread,
 };
 
-use self::lib::{self as parse_lib, diagnostics, StringOption, TomlKey, TomlValue};
-use crate::{dirs, GlobalConfig};
+use crossterm::tty::IsTty as _;
+use unicode_width::UnicodeWidthStr as _;
+
+use self::{
+	lib::{
+		self as parse_lib, diagnostics, BaseOption, FileId, Location, StringOption, TomlKey, TomlTable,
+		TomlValue,
+	},
+	migration::FileKind,
+};
+use crate::{
+	dirs::Dirs, sandbox::SandboxParameters, ui_subcommand::tui, ColorMode, GenericError, GlobalConfig,
+};
 
 pub use self::{
+	config::ParseOptions,
 	lib::{Diagnostic, FileDatabase},
+	path::canonicalize_include_path,
 	project_data::{PrelimParseState, ProjectDataFuture},
 };
 
 type ModResult<T> = crate::GenericResult<T>;
+// a shadowed project together with the file of the project shadowing it
+pub type ShadowedProject = (ProjectButtonData, PathBuf);
 
 #[derive(Clone)]
 pub struct ProjectButtonData {
 	pub name: String,
+	pub description: Option<String>,
+	pub icon: Option<String>,
 	pub project_data: ProjectDataFuture,
 }
 #[derive(Clone)]
@@ -28,55 +53,314 @@ pub struct BookmarkData {
 	pub project_data: ProjectDataFuture,
 	pub keybind: String,
 	pub name: String,
+	pub description: Option<String>,
+	pub icon: Option<String>,
 }
 
 // NOTE: FileDatabase is required for displaying errors,
 //       therefore it is stored globally
 pub struct ParseContext<'a> {
 	pub file_database: &'a mut FileDatabase,
+	// NOTE: shared, so that option parsers can capture it
+	pub dirs: Arc<dyn Dirs>,
+	// location of the include entry through which a file was parsed
+	include_origins: HashMap<FileId, Location>,
+	// NOTE: replaced as a whole by 'get_global_config',
+	//       so a failed parse keeps the previous options
+	options: ParseOptions,
+	// whether untrusted files may be confirmed interactively (see 'trust::ensure_trusted')
+	trust_prompt: bool,
+	// used for printing warnings
+	color: ColorMode,
+	// reported_warnings: HashSet<(path, position, message)>
+	// NOTE: files may be parsed multiple times (e.g. bookmarks),
+	//       but warnings should only be reported once
+	reported_warnings: HashSet<(String, usize, String)>,
+}
+impl<'a> ParseContext<'a> {
+	pub fn new(file_database: &'a mut FileDatabase, dirs: Arc<dyn Dirs>, color: ColorMode) -> Self {
+		Self {
+			file_database,
+			dirs,
+			include_origins: HashMap::new(),
+			options: ParseOptions::default(),
+			trust_prompt: true,
+			color,
+			reported_warnings: HashSet::new(),
+		}
+	}
 }
 impl ParseContext<'_> {
+	// NOTE: without a prompt, untrusted files are rejected
+	pub fn disable_trust_prompt(&mut self) {
+		self.trust_prompt = false;
+	}
+	// parse with a fresh context, which replaces this one only if 'parse' succeeds,
+	// so that a failed reload keeps the previous state
+	// NOTE: the error refers to the files of the discarded context,
+	//       therefore it is converted with 'render_err'
+	pub fn reload<T, E>(
+		&mut self,
+		parse: impl FnOnce(&mut ParseContext) -> ModResult<T>,
+		render_err: impl FnOnce(GenericError, &FileDatabase) -> E,
+	) -> Result<T, E> {
+		let mut file_database = FileDatabase::new();
+		let mut ctx = ParseContext::new(&mut file_database, self.dirs.clone(), self.color);
+		ctx.trust_prompt = self.trust_prompt;
+		let result = parse(&mut ctx);
+		let ParseContext {
+			include_origins,
+			options,
+			reported_warnings,
+			..
+		} = ctx;
+		match result {
+			Ok(value) => {
+				*self.file_database = file_database;
+				self.include_origins = include_origins;
+				self.options = options;
+				self.reported_warnings = reported_warnings;
+				Ok(value)
+			}
+			Err(err) => Err(render_err(err, &file_database)),
+		}
+	}
+	fn parse_toml_file<'v>(
+		&mut self,
+		path: impl AsRef<Path>,
+		kind: FileKind,
+		outlivers: &'v mut (Option<String>, Option<toml_span::Value<'v>>),
+	) -> ModResult<TomlTable<'v>> {
+		let (table, warnings) =
+			parse_lib::parse_toml_file(path.as_ref(), kind, self.file_database, outlivers)?;
+		for warning in warnings {
+			self.report_warning(warning);
+		}
+		Ok(table)
+	}
+	fn report_warning(&mut self, warning: Diagnostic) {
+		let label = warning.labels.first();
+		let file_name = label
+			.and_then(|label| self.file_database.get(label.file_id).ok())
+			.map(|file| file.name().clone())
+			.unwrap_or_default();
+		let position = label.map_or(0, |label| label.range.start);
+		let key = (file_name, position, warning.message.clone());
+		if self.reported_warnings.insert(key) {
+			GenericError::Diagnostic(warning).print(self.file_database, self.color);
+		}
+	}
+	// like 'parse_toml_file', but for a file that has already been read and parsed
+	fn add_parsed_toml_file<'v>(
+		&mut self,
+		path: &Path,
+		file_contents: &'v str,
+		parse_result: Result<toml_span::Value<'v>, toml_span::Error>,
+		kind: FileKind,
+		root_value: &'v mut Option<toml_span::Value<'v>>,
+	) -> ModResult<TomlTable<'v>> {
+		let (table, warnings) = parse_lib::add_parsed_toml(
+			path.to_string_lossy().to_string(),
+			file_contents,
+			parse_result,
+			kind,
+			self.file_database,
+			root_value,
+		)?;
+		for warning in warnings {
+			self.report_warning(warning);
+		}
+		Ok(table)
+	}
+	// NOTE: warnings are not expected in builtin files
+	fn parse_builtin_toml<'v>(
+		&mut self,
+		name: &str,
+		contents: &str,
+		kind: FileKind,
+		outlivers: &'v mut (Option<String>, Option<toml_span::Value<'v>>),
+	) -> ModResult<TomlTable<'v>> {
+		let (table, _) = parse_lib::parse_toml_str(
+			name.to_string(),
+			contents.to_string(),
+			kind,
+			self.file_database,
+			outlivers,
+		)?;
+		Ok(table)
+	}
+	// the merged configuration as toml, annotated with the origin of each value
+	pub fn dump_config(
+		&mut self,
+		global_config: &GlobalConfig,
+		project_name: Option<&str>,
+	) -> ModResult<String> {
+		dump::dump_config(self, global_config, project_name)
+	}
 	pub fn get_global_config(&mut self) -> ModResult<GlobalConfig> {
-		let global_config_file_path = dirs::get_skeld_config_dir()
+		let global_config_file_path = self
+			.dirs
+			.get_skeld_config_dir()
 			.map_err(|err| format!("Failed to determine the skeld config dir:\n  {err}"))?
 			.join("config.toml");
 		if !global_config_file_path.exists() {
-			return Ok(config::default_config());
+			let global_config = config::default_config(self.dirs.clone());
+			self.options = global_config.parse_options;
+			return Ok(global_config);
+		}
+		let prev_options = self.options;
+		match config::parse_config_file(&global_config_file_path, self) {
+			Ok(global_config) => {
+				self.options = global_config.parse_options;
+				Ok(global_config)
+			}
+			Err(err) => {
+				self.options = prev_options;
+				Err(self.add_include_chains(err))
+			}
+		}
+	}
+	// like 'report_warning', but with the include chains of the referenced files
+	fn report_warning_with_include_chains(&mut self, warning: Diagnostic) {
+		if let GenericError::Diagnostic(warning) = self.add_include_chains(warning.into()) {
+			self.report_warning(warning);
+		}
+	}
+	fn record_include_origin(&mut self, file: FileId, origin: Location) {
+		self.include_origins.insert(file, origin);
+	}
+	// label the include entries through which the files
+	// referenced by 'err' were parsed
+	fn add_include_chains(&self, err: GenericError) -> GenericError {
+		let GenericError::Diagnostic(mut diag) = err else {
+			return err;
+		};
+
+		let mut visited_files = Vec::new();
+		let mut include_labels = Vec::new();
+		for label in &diag.labels {
+			let mut file = FileId(label.file_id);
+			while let Some(origin) = self.include_origins.get(&file) {
+				if visited_files.contains(&file) {
+					break;
+				}
+				visited_files.push(file);
+				include_labels.push(
+					origin
+						.get_secondary_label()
+						.with_message("included from here"),
+				);
+				file = origin.file;
+			}
 		}
-		config::parse_config_file(&global_config_file_path, self)
+		diag.labels.append(&mut include_labels);
+
+		GenericError::Diagnostic(diag)
 	}
+	// see 'project_data::get_base_sandbox_params'
+	pub fn get_base_sandbox_params(&mut self) -> ModResult<SandboxParameters> {
+		project_data::get_base_sandbox_params(self)
+	}
+	// NOTE: a project shadows the projects of the same name in data
+	//       directories of lower priority (see 'Dirs::get_skeld_data_dirs')
 	pub fn get_projects(&mut self) -> ModResult<Vec<ProjectButtonData>> {
+		Ok(self.read_projects_by_priority()?.0)
+	}
+	// like 'get_projects', but additionally returns the shadowed projects
+	// together with the file of the project shadowing them
+	// NOTE: a note is reported for each shadowed project file
+	pub fn get_projects_with_shadowed(
+		&mut self,
+	) -> ModResult<(Vec<ProjectButtonData>, Vec<ShadowedProject>)> {
+		let (projects, shadowed_projects) = self.read_projects_by_priority()?;
+		for (data, shadowing_file) in &shadowed_projects {
+			let diag = Diagnostic::new(parse_lib::Severity::Note)
+				.with_message(format!(
+					"the project file `{}` is shadowed",
+					data.project_data.path().display()
+				))
+				.with_notes(vec![format!(
+					"the project `{}` of `{}` takes precedence",
+					data.name,
+					shadowing_file.display()
+				)]);
+			self.report_warning(diag);
+		}
+		Ok((projects, shadowed_projects))
+	}
+	fn read_projects_by_priority(
+		&mut self,
+	) -> ModResult<(Vec<ProjectButtonData>, Vec<ShadowedProject>)> {
 		let mut projects = Vec::new();
 
-		let skeld_data_dirs = dirs::get_skeld_data_dirs()
+		let skeld_data_dirs = self
+			.dirs
+			.get_skeld_data_dirs()
 			.map_err(|err| format!("Failed to determine the skeld data directories:\n  {err}"))?;
 		for data_root_dir in skeld_data_dirs {
-			let projects_root_dir = data_root_dir.join("projects");
-			projects.append(&mut self.read_projects_from_dir(projects_root_dir)?);
+			let entries = self.get_toml_files_from_data_dir(&data_root_dir, "projects")?;
+			projects.append(&mut self.read_projects(entries)?);
 		}
 
-		let projects =
-			sort_vec_and_check_dup(projects, |v| v.1.name.clone()).map_err(|duplicates| {
-				let duplicates_str = duplicates
-					.iter()
-					.map(|(path, _)| format!("- {}", path.display()))
-					.collect::<Vec<_>>()
-					.join("\n");
-				format!(
-					"Found conflicting projects with the same name `{}`:\n{duplicates_str}",
-					duplicates[0].1.name
-				)
-			})?;
+		// NOTE: the sort is stable, so the project of the
+		//       highest priority comes first among its namesakes
+		projects.sort_by(|(_, a), (_, b)| a.name.cmp(&b.name));
+		let mut visible_projects = Vec::<(PathBuf, ProjectButtonData)>::new();
+		let mut shadowed_projects = Vec::new();
+		for (path, data) in projects {
+			match visible_projects.last() {
+				Some((visible_path, visible_data)) if visible_data.name == data.name => {
+					shadowed_projects.push((data, visible_path.clone()));
+				}
+				_ => visible_projects.push((path, data)),
+			}
+		}
 
-		let projects = projects.into_iter().map(|(_, data)| data).collect();
-		Ok(projects)
+		let visible_projects = visible_projects.into_iter().map(|(_, data)| data).collect();
+		Ok((visible_projects, shadowed_projects))
+	}
+	// the toml files in the directory 'subdir' of the skeld data directory 'data_root_dir'
+	// NOTE: unreadable system-wide directories (e.g. because of missing permissions)
+	//       are skipped with a warning, so that they do not prevent using skeld
+	fn get_toml_files_from_data_dir(
+		&mut self,
+		data_root_dir: &Path,
+		subdir: &str,
+	) -> ModResult<Vec<PathBuf>> {
+		let dir = data_root_dir.join(subdir);
+		match get_toml_files_from_dir(&dir) {
+			Ok(entries) => Ok(entries),
+			Err(err)
+				if self
+					.dirs
+					.get_skeld_system_data_dirs()
+					.iter()
+					.any(|d| d == data_root_dir) =>
+			{
+				let diag = Diagnostic::new(parse_lib::Severity::Warning)
+					.with_message(format!(
+						"skipped the unreadable directory `{}`",
+						dir.display()
+					))
+					.with_notes(vec![
+						err.to_string(),
+						"the directory is part of the system-wide data directories (`$XDG_DATA_DIRS`)"
+							.to_string(),
+					]);
+				self.report_warning(diag);
+				Ok(Vec::new())
+			}
+			Err(err) => Err(format!("Failed to traverse directory `{}`:\n  {err}", dir.display()).into()),
+		}
 	}
-	fn read_projects_from_dir(
+	fn read_projects(
 		&mut self,
-		projects_dir: impl AsRef<Path>,
+		entries: Vec<PathBuf>,
 	) -> ModResult<Vec<(PathBuf, ProjectButtonData)>> {
+		let stage1_data = map_parallel(&entries, |path| read_project_stage1(path));
+
 		let mut projects = Vec::new();
-		for entry in get_toml_files_from_dir(projects_dir)? {
+		for (entry, (description, icon)) in entries.into_iter().zip(stage1_data) {
 			let project_data = ProjectDataFuture::Project(entry.clone());
 			let file_stem = entry.file_stem().unwrap();
 			let project_name = file_stem
@@ -94,21 +378,77 @@ impl ParseContext<'_> {
 			let project_button_data = ProjectButtonData {
 				project_data,
 				name: project_name,
+				description,
+				icon,
 			};
 
 			projects.push((entry, project_button_data));
 		}
 		Ok(projects)
 	}
+	// reads lines of the form `name<TAB>path` from stdin
+	pub fn get_stdin_projects(&mut self) -> ModResult<Vec<ProjectButtonData>> {
+		if io::stdin().is_tty() {
+			return Err("Expected a list of projects on stdin, but stdin is a tty".into());
+		}
+		let mut contents = String::new();
+		io::stdin()
+			.read_to_string(&mut contents)
+			.map_err(|err| format!("Failed to read projects from stdin: {err}"))?;
+		let file = FileId(
+			self
+				.file_database
+				.add("<stdin>".to_string(), contents.clone()),
+		);
+		let current_dir = env::current_dir()
+			.map_err(|err| format!("Failed to determine the current directory: {err}"))?;
+
+		let mut projects = Vec::new();
+		let mut line_start = 0;
+		for line in contents.split_inclusive('\n') {
+			let loc = Location {
+				file,
+				span: (line_start..line_start + line.trim_end().len()).into(),
+			};
+			line_start += line.len();
+
+			let line = line.trim_end();
+			if line.is_empty() {
+				continue;
+			}
+			let Some((name, path)) = line.split_once('\t') else {
+				let label = loc
+					.get_primary_label()
+					.with_message("expected `name<TAB>path`");
+				let diag = Diagnostic::new(lib::Severity::Error)
+					.with_message("invalid project entry")
+					.with_labels(vec![label]);
+				return Err(diag.into());
+			};
+			projects.push(ProjectButtonData {
+				name: name.to_string(),
+				description: None,
+				icon: None,
+				project_data: ProjectDataFuture::AdHoc {
+					name: name.to_string(),
+					project_dir: current_dir.join(path),
+					loc,
+				},
+			});
+		}
+		Ok(projects)
+	}
 	pub fn get_bookmarks(&mut self) -> ModResult<Vec<BookmarkData>> {
-		let mut bookmarks = Vec::new();
+		let mut bookmark_files = Vec::new();
 
-		let skeld_data_dirs = dirs::get_skeld_data_dirs()
+		let skeld_data_dirs = self
+			.dirs
+			.get_skeld_data_dirs()
 			.map_err(|err| format!("Failed to determine the skeld data directories:\n  {err}"))?;
 		for data_root_dir in skeld_data_dirs {
-			let bookmarks_dir = data_root_dir.join("bookmarks/");
-			bookmarks.append(&mut self.read_bookmarks_from_dir(bookmarks_dir)?);
+			bookmark_files.append(&mut self.get_toml_files_from_data_dir(&data_root_dir, "bookmarks")?);
 		}
+		let bookmarks = self.read_bookmark_files(bookmark_files)?;
 
 		let bookmarks =
 			sort_vec_and_check_dup(bookmarks, |v| v.1.keybind.clone()).map_err(|duplicates| {
@@ -126,24 +466,46 @@ impl ParseContext<'_> {
 		let bookmarks = bookmarks.into_iter().map(|(_, data)| data).collect();
 		Ok(bookmarks)
 	}
-	fn read_bookmarks_from_dir(
+	// NOTE: the files are read and parsed in parallel, as this dominates
+	//       the startup time on slow file systems (e.g. NFS)
+	fn read_bookmark_files(
 		&mut self,
-		bookmarks_dir: impl AsRef<Path>,
+		paths: Vec<PathBuf>,
 	) -> ModResult<Vec<(PathBuf, BookmarkData)>> {
+		let file_contents = map_parallel(&paths, |path| {
+			fs::read_to_string(path)
+				.map_err(|err| format!("Failed to read file `{}`: {err}", path.display()))
+		});
+		let parse_results = map_parallel(&file_contents, |contents| {
+			let contents = contents.as_ref().map_err(String::clone)?;
+			Ok::<_, String>((contents.as_str(), toml_span::parse(contents)))
+		});
+
 		let mut bookmarks = Vec::new();
-		for entry in get_toml_files_from_dir(bookmarks_dir)? {
-			let bookmark_data = self.parse_bookmark_file_stage1(&entry)?;
-			bookmarks.push((entry, bookmark_data));
+		for (path, parse_result) in paths.into_iter().zip(parse_results) {
+			let (contents, parse_result) = parse_result?;
+			let mut root_value = None;
+			let parsed_contents = self.add_parsed_toml_file(
+				&path,
+				contents,
+				parse_result,
+				FileKind::Bookmark,
+				&mut root_value,
+			)?;
+			let bookmark_data = self.parse_bookmark_stage1(&path, &parsed_contents)?;
+			bookmarks.push((path, bookmark_data));
 		}
 		Ok(bookmarks)
 	}
-	pub fn parse_bookmark_file_stage1(&mut self, path: impl AsRef<Path>) -> ModResult<BookmarkData> {
-		let mut outlivers = (None, None);
-		let parsed_contents =
-			parse_lib::parse_toml_file(path.as_ref(), self.file_database, &mut outlivers)?;
-
+	fn parse_bookmark_stage1(
+		&mut self,
+		path: &Path,
+		parsed_contents: &TomlTable,
+	) -> ModResult<BookmarkData> {
 		let mut name = StringOption::new("name");
 		let mut keybind = StringOption::new("keybind");
+		let mut description = StringOption::new("description");
+		let mut icon = BaseOption::new("icon", parse_icon);
 		// mock the project data option, so there is not an "unknown option" error
 		struct ProjectDataMockOption;
 		impl parse_lib::ConfigOption for ProjectDataMockOption {
@@ -155,7 +517,7 @@ impl ParseContext<'_> {
 
 		let docs_pref = "bookmarks";
 		parse_lib::parse_table!(
-			&parsed_contents => [name, keybind, project_data],
+			parsed_contents => [name, keybind, description, icon, project_data],
 			docs-pref: docs_pref,
 		)?;
 		Ok(BookmarkData {
@@ -165,27 +527,24 @@ impl ParseContext<'_> {
 			keybind: keybind
 				.get_value()
 				.ok_or_else(|| diagnostics::missing_option(parsed_contents.loc(), "keybind", docs_pref))?,
-			project_data: ProjectDataFuture::Bookmark(path.as_ref().to_path_buf()),
+			description: description.get_value(),
+			icon: icon.get_value(),
+			project_data: ProjectDataFuture::Bookmark(path.to_path_buf()),
 		})
 	}
 }
 
-fn get_toml_files_from_dir(dir: impl AsRef<Path>) -> ModResult<Vec<PathBuf>> {
-	let dir = dir.as_ref();
-
+// NOTE: a missing directory has no files
+fn get_toml_files_from_dir(dir: &Path) -> io::Result<Vec<PathBuf>> {
 	let dir_iter = match fs::read_dir(dir) {
 		Ok(iter) => iter,
-		Err(err) => match err.kind() {
-			io::ErrorKind::NotFound => return Ok(Vec::new()),
-			_ => {
-				return Err(format!("Failed to traverse directory `{}`:\n  {err}", dir.display()).into())
-			}
-		},
+		Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(err) => return Err(err),
 	};
 
 	let mut entries = Vec::new();
 	for entry in dir_iter {
-		let entry_path = entry.unwrap().path();
+		let entry_path = entry?.path();
 		if !entry_path.is_file() || !entry_path.extension().is_some_and(|ext| ext == "toml") {
 			continue;
 		}
@@ -194,6 +553,56 @@ fn get_toml_files_from_dir(dir: impl AsRef<Path>) -> ModResult<Vec<PathBuf>> {
 
 	Ok(entries)
 }
+// the id of a project file, read like in 'read_project_stage1'
+fn read_project_id(path: &Path) -> Option<String> {
+	let contents = fs::read_to_string(path).ok()?;
+	let value = toml_span::parse(&contents).ok()?;
+	value.pointer("/id")?.as_str().map(str::to_string)
+}
+// returns the description and the icon of a project file
+// NOTE: project files are only parsed completely when they are opened,
+//       so errors are ignored here and reported at that point
+fn read_project_stage1(path: &Path) -> (Option<String>, Option<String>) {
+	let Ok(contents) = fs::read_to_string(path) else {
+		return (None, None);
+	};
+	let Ok(value) = toml_span::parse(&contents) else {
+		return (None, None);
+	};
+	let get_string = |pointer| value.pointer(pointer)?.as_str().map(str::to_string);
+	let icon = get_string("/icon").filter(|icon| icon.width() <= tui::ICON_WIDTH);
+	(get_string("/description"), icon)
+}
+fn parse_icon(value: &TomlValue) -> ModResult<String> {
+	let icon = value.as_str()?;
+	if icon.width() > tui::ICON_WIDTH {
+		let label = value.loc().get_primary_label().with_message(format!(
+			"expected at most {} columns, found {}",
+			tui::ICON_WIDTH,
+			icon.width()
+		));
+		let diag = Diagnostic::new(lib::Severity::Error)
+			.with_message("icon is too wide")
+			.with_labels(vec![label]);
+		return Err(diag.into());
+	}
+	Ok(icon.to_string())
+}
+// applies 'f' to all 'items' on multiple threads, preserving the order
+fn map_parallel<'a, T: Sync, R: Send>(items: &'a [T], f: impl Fn(&'a T) -> R + Sync) -> Vec<R> {
+	let num_threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+	let chunk_size = items.len().div_ceil(num_threads).max(1);
+	thread::scope(|scope| {
+		let handles = items
+			.chunks(chunk_size)
+			.map(|chunk| scope.spawn(|| chunk.iter().map(&f).collect::<Vec<_>>()))
+			.collect::<Vec<_>>();
+		handles
+			.into_iter()
+			.flat_map(|handle| handle.join().unwrap())
+			.collect()
+	})
+}
 // if 'vec' has no duplicates, the sorted 'vec' is returned;
 // otherwise a group of duplicates is returned as an error
 fn sort_vec_and_check_dup<T, K: Eq + Ord>(
@@ -1,17 +1,19 @@
-use std::{env, iter, ops::Range, path::PathBuf};
+use std::{env, ffi::OsString, iter, ops::Range, path::PathBuf};
+
+use nix::unistd::{self, User};
 
 use crate::{
-	dirs,
+	dirs::{self, Dirs},
 	parse::lib::{CanonicalizationError, CanonicalizationLabel},
 	DOCS_URL,
 };
 
 type ModResult<T> = Result<T, CanonicalizationError>;
 
-pub fn canonicalize_path(path: impl Into<String>) -> ModResult<PathBuf> {
+pub fn canonicalize_path(path: impl Into<String>, dirs: &dyn Dirs) -> ModResult<PathBuf> {
 	let path = path.into();
 
-	let substituted_path_str = substitute_placeholder(&path, false)?;
+	let substituted_path_str = substitute_placeholder(&path, &[], dirs)?;
 	let substituted_path = PathBuf::from(&substituted_path_str);
 
 	if substituted_path.is_relative() {
@@ -32,18 +34,20 @@ pub fn canonicalize_path(path: impl Into<String>) -> ModResult<PathBuf> {
 
 	Ok(substituted_path)
 }
-pub fn canonicalize_include_path(path: impl Into<String>) -> ModResult<PathBuf> {
-	let path = PathBuf::from(substitute_placeholder(path, false)?);
+pub fn canonicalize_include_path(path: impl Into<String>, dirs: &dyn Dirs) -> ModResult<PathBuf> {
+	let path = PathBuf::from(substitute_placeholder(path, &[], dirs)?);
 
 	if path.is_absolute() {
 		return Ok(path);
 	};
 
 	let mut matching_files = Vec::new();
-	let skeld_data_dirs = dirs::get_skeld_data_dirs().map_err(|err| CanonicalizationError {
-		notes: vec![err.to_string()],
-		..CanonicalizationError::main_message("could not determine the skeld data directories")
-	})?;
+	let skeld_data_dirs = dirs
+		.get_skeld_data_dirs()
+		.map_err(|err| CanonicalizationError {
+			notes: vec![err.to_string()],
+			..CanonicalizationError::main_message("could not determine the skeld data directories")
+		})?;
 	for data_root_dir in skeld_data_dirs {
 		let include_root_dir = data_root_dir.join("include");
 		let mut possible_file_path = include_root_dir.join(&path);
@@ -85,17 +89,85 @@ pub fn canonicalize_include_path(path: impl Into<String>) -> ModResult<PathBuf>
 		Ok(matching_files.into_iter().next().unwrap())
 	}
 }
+// NOTE: config fragments are only searched in the config directory
+pub fn canonicalize_config_include_path(
+	path: impl Into<String>,
+	dirs: &dyn Dirs,
+) -> ModResult<PathBuf> {
+	let path = PathBuf::from(substitute_placeholder(path, &[], dirs)?);
 
-// resolves all placeholders except $(FILE),
-// allow_file_var determines whether the $(FILE) placeholder is allowed
-pub fn substitute_placeholder(str: impl Into<String>, allow_file_var: bool) -> ModResult<String> {
+	if path.is_absolute() {
+		return Ok(path);
+	};
+
+	let config_dir = dirs
+		.get_skeld_config_dir()
+		.map_err(|err| CanonicalizationError {
+			notes: vec![err.to_string()],
+			..CanonicalizationError::main_message("could not determine the skeld config directory")
+		})?;
+	let mut file_path = config_dir.join("conf.d").join(&path);
+	file_path.as_mut_os_string().push(".toml");
+	if file_path.exists() {
+		return Ok(file_path);
+	}
+
+	let mut notes = vec![format!(
+		"included config files are searched in `<SKELD-CONFIG>/conf.d`\n(see {DOCS_URL}#file-locations)"
+	)];
+	if path.extension().is_some_and(|ext| ext == "toml") {
+		notes.push(format!(
+			"Note that an extra `toml` extension is appended, so the file `{}.toml` is actually searched.",
+			path.display()
+		));
+	}
+	Err(CanonicalizationError {
+		notes,
+		..CanonicalizationError::main_message("config file not found")
+	})
+}
+
+// variables that can only be resolved when the project is opened
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum LateVariable {
+	File,
+	ProjectState,
+	// variables of commands operating on the selected project
+	ProjectDir,
+	ProjectName,
+	// variables of logfile names
+	Name,
+	Time,
+	LogNumber,
+}
+impl LateVariable {
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::File => "FILE",
+			Self::ProjectState => "PROJECT_STATE",
+			Self::ProjectDir => "PROJECT_DIR",
+			Self::ProjectName => "PROJECT_NAME",
+			Self::Name => "NAME",
+			Self::Time => "TIME",
+			Self::LogNumber => "N",
+		}
+	}
+}
+
+// resolves all placeholders except late variables,
+// late_vars determines which late variables are allowed
+pub fn substitute_placeholder(
+	str: impl Into<String>,
+	late_vars: &[LateVariable],
+	dirs: &dyn Dirs,
+) -> ModResult<String> {
 	let str = str.into();
 
 	let resolve_placeholder = |placeholder| {
 		Ok(match placeholder {
 			Placeholder::Tilde { idx: pos } => {
 				let resolved_expr =
-					resolve_homedir_expr(&str[pos..pos + 1]).map_err(|err| err.shift(pos))?;
+					resolve_homedir_expr(&str[pos..pos + 1], dirs).map_err(|err| err.shift(pos))?;
 				(pos..pos + 1, resolved_expr)
 			}
 			Placeholder::BracketPair {
@@ -103,7 +175,7 @@ pub fn substitute_placeholder(str: impl Into<String>, allow_file_var: bool) -> M
 				span,
 				inner_span,
 			} => {
-				let resolved_expr = resolve_envvar_expr(&str[inner_span.clone()], allow_file_var)
+				let resolved_expr = resolve_envvar_expr(&str[inner_span.clone()], late_vars, dirs)
 					.map_err(|err| err.shift(inner_span.start))?;
 				(span, resolved_expr)
 			}
@@ -112,12 +184,36 @@ pub fn substitute_placeholder(str: impl Into<String>, allow_file_var: bool) -> M
 				span,
 				inner_span,
 			} => {
-				let resolved_expr = resolve_variable_expr(&str[inner_span.clone()], allow_file_var)
+				let resolved_expr = resolve_variable_expr(&str[inner_span.clone()], late_vars, dirs)
 					.map_err(|err| err.shift(inner_span.start))?
 					// preserve variables that need to be resolved later
 					.unwrap_or_else(|| str[span.clone()].to_string());
 				(span, resolved_expr)
 			}
+			Placeholder::Escape { span } => {
+				let escaped_str = &str[span.start + 1..span.end];
+				// NOTE: late variables are substituted by plain string replacement,
+				//       so an escaped '$' would not survive that stage
+				if escaped_str == "$" {
+					if let Some(var) = late_vars
+						.iter()
+						.find(|var| str[span.end..].starts_with(&format!("({}\\)", var.name())))
+					{
+						return Err(CanonicalizationError {
+							labels: vec![CanonicalizationLabel::primary_with_span(
+								span.start..span.end + var.name().len() + 3,
+								"escaped variable",
+							)],
+							notes: vec![format!(
+								"$({}) is substituted after the escape sequences have been resolved,\nso it cannot be escaped",
+								var.name()
+							)],
+							..CanonicalizationError::main_message("unsupported escape sequence")
+						});
+					}
+				}
+				(span, escaped_str.to_string())
+			}
 		})
 	};
 
@@ -128,9 +224,10 @@ pub fn substitute_placeholder(str: impl Into<String>, allow_file_var: bool) -> M
 	let substituted_str = replace_multiple_ranges(&str, replacements);
 	Ok(substituted_str)
 }
-fn resolve_homedir_expr(expr: &str) -> ModResult<String> {
-	let home_dir_path =
-		dirs::get_home_dir().map_err(|err| convert_dirs_err(err, 0..expr.len(), None))?;
+fn resolve_homedir_expr(expr: &str, dirs: &dyn Dirs) -> ModResult<String> {
+	let home_dir_path = dirs
+		.get_home_dir()
+		.map_err(|err| convert_dirs_err(err, 0..expr.len(), None))?;
 	let home_dir_str = home_dir_path
 		.to_str()
 		.ok_or_else(|| CanonicalizationError {
@@ -147,72 +244,79 @@ fn resolve_homedir_expr(expr: &str) -> ModResult<String> {
 		.to_string();
 	Ok(home_dir_str)
 }
-fn resolve_envvar_expr(expr: &str, allow_file_var: bool) -> ModResult<String> {
-	let first_colon = expr.find(':');
-	let env_var_name = first_colon.map(|pos| &expr[..pos]).unwrap_or(expr);
+fn resolve_envvar_expr(
+	expr: &str,
+	late_vars: &[LateVariable],
+	dirs: &dyn Dirs,
+) -> ModResult<String> {
+	// NOTE: colons inside of nested placeholders do not separate the alternative value
+	let placeholders = find_toplevel_placeholders(expr)?;
+	let first_colon = expr.match_indices(':').map(|(pos, _)| pos).find(|pos| {
+		!placeholders
+			.iter()
+			.any(|placeholder| placeholder.span().contains(pos))
+	});
+	let env_var_expr = first_colon.map(|pos| &expr[..pos]).unwrap_or(expr);
 	let env_var_alt = first_colon.map(|pos| &expr[pos + 1..]);
 
-	if let Some(placeholder) = find_next_placeholder_poi(env_var_name) {
-		return Err(CanonicalizationError {
-			labels: vec![CanonicalizationLabel::primary_with_span(
-				placeholder.0,
-				"placeholders are not allowed here",
-			)],
-			..CanonicalizationError::main_message("invalid environment variable expression")
-		});
+	let env_var_name = substitute_nested_placeholder(env_var_expr, late_vars, dirs)?;
+	let mut notes = Vec::new();
+	if env_var_name != env_var_expr {
+		notes.push(format!(
+			"after the placeholders have been resolved: `{env_var_name}`"
+		));
 	}
 
-	match env::var(env_var_name) {
+	match env::var(&env_var_name) {
 		Ok(value) => Ok(value),
 		Err(env::VarError::NotPresent) if env_var_alt.is_some() => {
 			let env_var_alt = env_var_alt.unwrap();
-			substitute_placeholder(env_var_alt, allow_file_var)
-				.map_err(|err| err.shift(env_var_name.len() + 1))
+			substitute_placeholder(env_var_alt, late_vars, dirs)
+				.map_err(|err| err.shift(env_var_expr.len() + 1))
 		}
 		Err(env::VarError::NotPresent) => Err(CanonicalizationError {
 			labels: vec![CanonicalizationLabel::primary_with_span(
-				0..env_var_name.len(),
+				0..env_var_expr.len(),
 				"",
 			)],
+			notes,
 			..CanonicalizationError::main_message("environment variable not found")
 		}),
 		Err(env::VarError::NotUnicode(raw)) => Err(CanonicalizationError {
 			labels: vec![CanonicalizationLabel::primary_with_span(
-				0..env_var_name.len(),
+				0..env_var_expr.len(),
 				format!("raw value: `{}`", raw.to_string_lossy()),
 			)],
+			notes,
 			..CanonicalizationError::main_message("environment variable was not valid UTF-8")
 		}),
 	}
 }
 // NOTE: returns None if the variable needs to be resolved
 //       at a later stage (e.g. $(FILE))
-fn resolve_variable_expr(expr: &str, allow_file_var: bool) -> ModResult<Option<String>> {
-	if let Some(placeholder) = find_next_placeholder_poi(expr) {
-		return Err(CanonicalizationError {
-			labels: vec![CanonicalizationLabel::primary_with_span(
-				placeholder.0,
-				"placeholders are not allowed inside variable expressions",
-			)],
-			..CanonicalizationError::main_message("invalid variable expression")
-		});
-	}
+fn resolve_variable_expr(
+	expr: &str,
+	late_vars: &[LateVariable],
+	dirs: &dyn Dirs,
+) -> ModResult<Option<String>> {
+	let var_name = substitute_nested_placeholder(expr, late_vars, dirs)?;
+	let is_nested = var_name != expr;
 
-	type XdgDirFn = fn() -> Result<PathBuf, dirs::Error>;
-	let dir_exprs = [
-		("CONFIG", dirs::get_xdg_config_dir as XdgDirFn),
-		("CACHE", dirs::get_xdg_cache_dir),
-		("DATA", dirs::get_xdg_data_dir),
-		("STATE", dirs::get_xdg_state_dir),
+	type XdgDirFn = fn(&dyn Dirs) -> Result<PathBuf, dirs::Error>;
+	let dir_exprs: [(_, XdgDirFn); 4] = [
+		("CONFIG", |dirs| dirs.get_xdg_config_dir()),
+		("CACHE", |dirs| dirs.get_xdg_cache_dir()),
+		("DATA", |dirs| dirs.get_xdg_data_dir()),
+		("STATE", |dirs| dirs.get_xdg_state_dir()),
 	];
 	for (varname, resolve_fn) in dir_exprs {
-		if expr != varname {
+		if var_name != varname {
 			continue;
 		}
 
 		let dirname = varname.to_lowercase();
 		let resolved_expr_path =
-			resolve_fn().map_err(|err| convert_dirs_err(err, 0..expr.len(), Some(&dirname)))?;
+			resolve_fn(dirs).map_err(|err| convert_dirs_err(err, 0..expr.len(), Some(&dirname)))?;
 		let resolved_expr_str = resolved_expr_path
 			.to_str()
 			.ok_or_else(|| CanonicalizationError {
@@ -229,16 +333,35 @@ fn resolve_variable_expr(expr: &str, allow_file_var: bool) -> ModResult<Option<S
 		return Ok(Some(resolved_expr_str.to_string()));
 	}
 
-	if allow_file_var && expr == "FILE" {
+	match var_name.as_str() {
+		"USER" => return resolve_user_expr(expr).map(Some),
+		"HOSTNAME" => return resolve_hostname_expr(expr).map(Some),
+		"UID" => return Ok(Some(unistd::getuid().to_string())),
+		_ => (),
+	}
+
+	if let Some(var) = late_vars.iter().find(|var| var.name() == var_name) {
+		// NOTE: late variables are substituted by plain string replacement,
+		//       which only recognizes their literal form
+		if is_nested {
+			return Err(CanonicalizationError {
+				labels: vec![CanonicalizationLabel::primary_with_span(
+					0..expr.len(),
+					format!("resolves to `{}`", var.name()),
+				)],
+				notes: vec![format!("$({}) has to be written literally", var.name())],
+				..CanonicalizationError::main_message("unsupported nested placeholder")
+			});
+		}
 		return Ok(None);
 	}
 
 	// unknown variable
 	{
-		let mut valid_variables = vec!["CONFIG", "CACHE", "DATA", "STATE"];
-		if allow_file_var {
-			valid_variables.push("FILE");
-		}
+		let mut valid_variables = vec![
+			"CONFIG", "CACHE", "DATA", "STATE", "USER", "HOSTNAME", "UID",
+		];
+		valid_variables.extend(late_vars.iter().map(LateVariable::name));
 		let valid_variables_str = valid_variables
 			.into_iter()
 			.map(|str| format!("`$({str})`"))
@@ -248,9 +371,28 @@ fn resolve_variable_expr(expr: &str, allow_file_var: bool) -> ModResult<Option<S
 		let mut notes = vec![format!(
 			"supported variables are {valid_variables_str}\n(see {DOCS_URL}#string-interpolation)"
 		)];
-		if !allow_file_var && expr == "FILE" {
+		if is_nested {
+			notes.push(format!(
+				"after the placeholders have been resolved: `{var_name}`"
+			));
+		}
+		if !late_vars.contains(&LateVariable::File) && var_name == "FILE" {
 			notes.push("$(FILE) can only be used in 'editor.cmd-with-file'".to_string());
 		}
+		if !late_vars.contains(&LateVariable::ProjectState) && var_name == "PROJECT_STATE" {
+			notes.push("$(PROJECT_STATE) can only be used in editor commands".to_string());
+		}
+		let log_vars = [
+			LateVariable::Name,
+			LateVariable::Time,
+			LateVariable::LogNumber,
+		];
+		if log_vars
+			.iter()
+			.any(|var| !late_vars.contains(var) && var.name() == var_name)
+		{
+			notes.push(format!("$({var_name}) can only be used in 'logs.filename'"));
+		}
 
 		Err(CanonicalizationError {
 			labels: vec![CanonicalizationLabel::primary_with_span(
@@ -262,6 +404,100 @@ fn resolve_variable_expr(expr: &str, allow_file_var: bool) -> ModResult<Option<S
 		})
 	}
 }
+fn resolve_user_expr(expr: &str) -> ModResult<String> {
+	let user_name = env::var_os("USER")
+		.filter(|user_name| !user_name.is_empty())
+		.or_else(get_user_name_from_passwd)
+		.ok_or_else(|| CanonicalizationError {
+			labels: vec![CanonicalizationLabel::primary_with_span(
+				0..expr.len(),
+				"required from here",
+			)],
+			notes: vec![concat!(
+				"The user name is first looked up in `$USER`,\n",
+				"and then in `/etc/passwd` if `$USER` does not exist."
+			)
+			.to_string()],
+			..CanonicalizationError::main_message("could not determine the user name")
+		})?;
+	let user_name_str = user_name
+		.to_str()
+		.ok_or_else(|| CanonicalizationError {
+			labels: vec![CanonicalizationLabel::primary_with_span(
+				0..expr.len(),
+				"required from here",
+			)],
+			notes: vec![format!(
+				"user name contains invalid UTF-8: `{}`",
+				user_name.to_string_lossy()
+			)],
+			..CanonicalizationError::main_message("invalid user name")
+		})?
+		.to_string();
+	Ok(user_name_str)
+}
+fn get_user_name_from_passwd() -> Option<OsString> {
+	let user = User::from_uid(unistd::getuid()).ok()??;
+	Some(user.name.into())
+}
+fn resolve_hostname_expr(expr: &str) -> ModResult<String> {
+	let error_labels = vec![CanonicalizationLabel::primary_with_span(
+		0..expr.len(),
+		"required from here",
+	)];
+	let hostname = unistd::gethostname().map_err(|err| CanonicalizationError {
+		labels: error_labels.clone(),
+		notes: vec![err.desc().to_string()],
+		..CanonicalizationError::main_message("could not determine the hostname")
+	})?;
+	let hostname = hostname
+		.into_string()
+		.map_err(|hostname| CanonicalizationError {
+			labels: error_labels,
+			notes: vec![format!(
+				"hostname contains invalid UTF-8: `{}`",
+				hostname.to_string_lossy()
+			)],
+			..CanonicalizationError::main_message("invalid hostname")
+		})?;
+	Ok(hostname)
+}
+// resolves the placeholders inside of a variable name
+// NOTE: late variables are substituted after the variable has been resolved,
+//       therefore they cannot be part of its name
+fn substitute_nested_placeholder(
+	expr: &str,
+	late_vars: &[LateVariable],
+	dirs: &dyn Dirs,
+) -> ModResult<String> {
+	for placeholder in find_toplevel_placeholders(expr)? {
+		let Placeholder::BracketPair {
+			ty: BracketType::Round,
+			span,
+			inner_span,
+		} = placeholder
+		else {
+			continue;
+		};
+		if let Some(var) = late_vars
+			.iter()
+			.find(|var| var.name() == &expr[inner_span.clone()])
+		{
+			return Err(CanonicalizationError {
+				labels: vec![CanonicalizationLabel::primary_with_span(
+					span,
+					"not allowed inside of a variable name",
+				)],
+				notes: vec![format!(
+					"$({}) is substituted after the surrounding variable has been resolved",
+					var.name()
+				)],
+				..CanonicalizationError::main_message("unsupported nested placeholder")
+			});
+		}
+	}
+	substitute_placeholder(expr, &[], dirs)
+}
 fn convert_dirs_err(
 	err: dirs::Error,
 	span: Range<usize>,
@@ -287,6 +523,8 @@ fn convert_dirs_err(
 			notes: vec![dirs::Error::RelativeHomeDir { dir }.to_string()],
 			..CanonicalizationError::main_message("invalid home directory")
 		},
+		// NOTE: the runtime directory is not used in placeholders
+		dirs::Error::UnknownRuntimeDir => unreachable!(),
 		dirs::Error::RelativeXdgBaseDir { .. } if xdg_dirname.is_none() => unreachable!(),
 		dirs::Error::RelativeXdgBaseDir { varname, dir } => {
 			let xdg_dirname = xdg_dirname.unwrap();
@@ -309,6 +547,19 @@ enum Placeholder {
 		span: Range<usize>,
 		inner_span: Range<usize>,
 	},
+	Escape {
+		span: Range<usize>,
+	},
+}
+// characters that can be escaped with a backslash
+const ESCAPABLE_CHARS: [char; 5] = ['~', '$', ')', ']', '\\'];
+impl Placeholder {
+	fn span(&self) -> Range<usize> {
+		match self {
+			Self::Tilde { idx } => *idx..*idx + 1,
+			Self::BracketPair { span, .. } | Self::Escape { span } => span.clone(),
+		}
+	}
 }
 #[derive(Copy, Clone, PartialEq, Eq)]
 enum BracketType {
@@ -328,6 +579,33 @@ fn find_toplevel_placeholders(str: &str) -> ModResult<Vec<Placeholder>> {
 				placeholders.push(Placeholder::Tilde { idx });
 			}
 			PlaceholderPoI::Tilde => (),
+			PlaceholderPoI::Escape => {
+				let escaped_char = str[idx + 1..].chars().next();
+				let Some(escaped_char) = escaped_char.filter(|ch| ESCAPABLE_CHARS.contains(ch)) else {
+					let escape_end = idx + 1 + escaped_char.map_or(0, char::len_utf8);
+					let escapable_chars_str = ESCAPABLE_CHARS
+						.iter()
+						.map(|ch| format!("`\\{ch}`"))
+						.collect::<Vec<_>>()
+						.join(", ");
+					return Err(CanonicalizationError {
+						labels: vec![CanonicalizationLabel::primary_with_span(
+							idx..escape_end,
+							"unknown escape sequence",
+						)],
+						notes: vec![format!(
+							"supported escape sequences are {escapable_chars_str}\n(see {DOCS_URL}#string-interpolation)"
+						)],
+						..CanonicalizationError::main_message("invalid escape sequence")
+					});
+				};
+				str_pointer = idx + 1 + escaped_char.len_utf8();
+				if bracket_stack.is_empty() {
+					placeholders.push(Placeholder::Escape {
+						span: idx..str_pointer,
+					});
+				}
+			}
 			PlaceholderPoI::Bracket { ty, opening: true } => bracket_stack.push((idx, ty)),
 			PlaceholderPoI::Bracket { ty, opening: false } => {
 				let Some(matching_opening_bracket) = bracket_stack.pop() else {
@@ -336,6 +614,10 @@ fn find_toplevel_placeholders(str: &str) -> ModResult<Vec<Placeholder>> {
 							idx..idx + 1,
 							"unmatched closing bracket",
 						)],
+						notes: vec![format!(
+							"a literal `{}` has to be escaped with a backslash",
+							&str[idx..idx + 1]
+						)],
 						..CanonicalizationError::main_message("mismatched brackets")
 					});
 				};
@@ -378,6 +660,7 @@ fn find_toplevel_placeholders(str: &str) -> ModResult<Vec<Placeholder>> {
 enum PlaceholderPoI {
 	Bracket { ty: BracketType, opening: bool },
 	Tilde,
+	Escape,
 }
 fn find_next_placeholder_poi(str: &str) -> Option<(Range<usize>, PlaceholderPoI)> {
 	#[rustfmt::skip]
@@ -387,6 +670,7 @@ fn find_next_placeholder_poi(str: &str) -> Option<(Range<usize>, PlaceholderPoI)
 		("]", PlaceholderPoI::Bracket {ty: BracketType::Square, opening: false }),
 		(")", PlaceholderPoI::Bracket {ty: BracketType::Round, opening: false }),
 		("~", PlaceholderPoI::Tilde),
+		("\\", PlaceholderPoI::Escape),
 	];
 	poi_types
 		.into_iter()
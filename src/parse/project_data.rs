@@ -1,71 +1,179 @@
-use std::path::{Path, PathBuf};
+use std::{
+	env, fs,
+	path::{Component, Path, PathBuf},
+	sync::Arc,
+};
 
 use super::{
+	dump::DumpEntry,
 	lib::{
-		self as parse_lib, diagnostics, ArrayOption, BoolOption, PathBufOption, StringOption, TomlKey,
-		TomlTable, TomlValue,
+		self as parse_lib, diagnostics, ArrayOption, BaseOption, BoolOption, MergePolicy,
+		PathBufOption, Priority, StringOption, TomlKey, TomlTable, TomlValue,
 	},
-	path, ModResult, ParseContext,
+	migration::FileKind,
+	path::{self, LateVariable},
+	toml_util::{self, toml_string_escape},
+	ModResult, ParseContext,
 };
 use crate::{
-	project::{EditorCommand, ProjectData},
-	sandbox::{EnvVarWhitelist, FSTreeError, SandboxParameters, VirtualFSEntryType, VirtualFSTree},
+	container::{ContainerEngine, ContainerParameters},
+	dirs::Dirs,
+	project::{EditorCommand, ProjectAction, ProjectData},
+	sandbox::{
+		EnvVarWhitelist, FSTreeError, ResourceLimits, SandboxParameters, SeccompProfile,
+		VirtualFSEntryType, VirtualFSTree,
+	},
+	trust, GenericError,
 };
 
 #[derive(Clone, Debug)]
 pub enum ProjectDataFuture {
 	Project(PathBuf),
 	Bookmark(PathBuf),
+	// project without a project file, only consisting of the global project data
+	AdHoc {
+		name: String,
+		project_dir: PathBuf,
+		loc: parse_lib::Location,
+	},
 }
 impl ProjectDataFuture {
+	// the file defining the project, or its directory for projects without a file
+	pub fn path(&self) -> &Path {
+		match self {
+			Self::Project(path) | Self::Bookmark(path) => path,
+			Self::AdHoc { project_dir, .. } => project_dir,
+		}
+	}
+	// key of the project in the state databases (e.g. the open stats),
+	// which is the id of a project file if it has one
+	pub fn state_key(&self) -> String {
+		match self {
+			Self::Project(path) => get_state_key(super::read_project_id(path), path),
+			Self::Bookmark(path) => get_state_key(None, path),
+			Self::AdHoc { project_dir, .. } => get_state_key(None, project_dir),
+		}
+	}
 	pub fn load(
 		self,
 		parse_state: PrelimParseState,
 		ctx: &mut ParseContext,
 	) -> ModResult<ProjectData> {
-		match self {
+		let path = match &self {
+			Self::Project(path) | Self::Bookmark(path) => Some(path.clone()),
+			Self::AdHoc { .. } => None,
+		};
+		let state_key = self.state_key();
+		let (mut parse_state, name, loc, docs_pref) = self.load_parse_state(parse_state, ctx)?;
+		parse_state.source_files.extend(path);
+		if ctx.options.warn_missing_paths {
+			let warnings = parse_state
+				.missing_path_warnings()
+				.map_err(|err| ctx.add_include_chains(err))?;
+			for warning in warnings {
+				ctx.report_warning(warning);
+			}
+		}
+		parse_state
+			.into_project_data(name, state_key, &loc, docs_pref, ctx)
+			.map_err(|err| ctx.add_include_chains(err))
+	}
+	// returns the parse state with all files applied,
+	// together with the name and the location for errors about missing options
+	pub(super) fn load_parse_state(
+		self,
+		parse_state: PrelimParseState,
+		ctx: &mut ParseContext,
+	) -> ModResult<(PrelimParseState, String, parse_lib::Location, &'static str)> {
+		let result = match self {
 			Self::Project(path) => Self::parse_project_data_file(path, parse_state, ctx),
 			Self::Bookmark(path) => Self::parse_bookmark_file_stage2(path, parse_state, ctx),
-		}
+			Self::AdHoc {
+				name,
+				project_dir,
+				loc,
+			} => Self::load_ad_hoc_project(name, project_dir, loc, parse_state, ctx),
+		};
+		// NOTE: presets are applied last, as any file may enable them
+		let result = result.and_then(|(mut parse_state, name, loc, docs_pref)| {
+			parse_state.apply_presets(ctx)?;
+			Ok((parse_state, name, loc, docs_pref))
+		});
+		result.map_err(|err| ctx.add_include_chains(err))
 	}
 	fn parse_project_data_file(
 		path: impl AsRef<Path>,
 		mut parse_state: PrelimParseState,
 		ctx: &mut ParseContext,
-	) -> ModResult<ProjectData> {
+	) -> ModResult<(PrelimParseState, String, parse_lib::Location, &'static str)> {
 		let mut outlivers = (None, None);
-		let parsed_contents = parse_lib::parse_toml_file(path, ctx.file_database, &mut outlivers)?;
+		let parsed_contents =
+			ctx.parse_toml_file(path.as_ref(), FileKind::ProjectData, &mut outlivers)?;
+		if trust::is_foreign_file(path.as_ref(), &*ctx.dirs) {
+			// NOTE: the trust of a project file with an id survives renaming it
+			let state_key = get_state_key(super::read_project_id(path.as_ref()), path.as_ref());
+			parse_state.record_foreign_file(path.as_ref(), state_key, &parsed_contents);
+		}
+		let canonical_path =
+			fs::canonicalize(path.as_ref()).unwrap_or_else(|_| path.as_ref().to_path_buf());
+		parse_state.parsed_files.push(canonical_path.clone());
+		parse_state.root_file = Some(canonical_path);
 		parse_state.parse_table(&parsed_contents, ctx)?;
+		parse_state.parse_project_local_file(ctx)?;
+		parse_state.check_foreign_files(ctx)?;
+
+		let name = path.as_ref().file_stem().unwrap().to_string_lossy();
+		Ok((
+			parse_state,
+			name.to_string(),
+			parsed_contents.loc().clone(),
+			"project-data-format",
+		))
+	}
+	fn load_ad_hoc_project(
+		name: String,
+		project_dir: PathBuf,
+		loc: parse_lib::Location,
+		mut parse_state: PrelimParseState,
+		ctx: &mut ParseContext,
+	) -> ModResult<(PrelimParseState, String, parse_lib::Location, &'static str)> {
+		parse_state.project_dir.set_value(project_dir, loc.clone());
+		parse_state.parse_project_local_file(ctx)?;
+		parse_state.check_foreign_files(ctx)?;
 
-		let project_data = parse_state.into_project_data().map_err(|missing| {
-			diagnostics::missing_option(parsed_contents.loc(), &missing, "project-data-format")
-		})?;
-		Ok(project_data)
+		Ok((parse_state, name, loc, "project-data-format"))
 	}
 	fn parse_bookmark_file_stage2(
 		path: impl AsRef<Path>,
 		parse_state: PrelimParseState,
 		ctx: &mut ParseContext,
-	) -> ModResult<ProjectData> {
+	) -> ModResult<(PrelimParseState, String, parse_lib::Location, &'static str)> {
 		let mut outlivers = (None, None);
-		let parsed_contents =
-			parse_lib::parse_toml_file(path.as_ref(), ctx.file_database, &mut outlivers)?;
+		let parsed_contents = ctx.parse_toml_file(path.as_ref(), FileKind::Bookmark, &mut outlivers)?;
 
 		let mut name = StringOption::new("name");
 		let mut keybind = StringOption::new("keybind");
+		let mut description = StringOption::new("description");
+		let mut icon = BaseOption::new("icon", super::parse_icon);
 		let mut project_data = ProjectDataOption::new("project", parse_state, ctx);
 
 		let docs_pref = "bookmarks";
 		parse_lib::parse_table!(
-			&parsed_contents => [name, keybind, project_data],
+			&parsed_contents => [name, keybind, description, icon, project_data],
 			docs-pref: docs_pref,
 		)?;
-		let project_data = project_data
+		let name = name
 			.get_value()
-			.into_project_data()
-			.map_err(|missing| diagnostics::missing_option(parsed_contents.loc(), &missing, docs_pref))?;
+			.ok_or_else(|| diagnostics::missing_option(parsed_contents.loc(), "name", docs_pref))?;
+		let mut project_data = project_data.get_value();
+		if trust::is_foreign_file(path.as_ref(), &*ctx.dirs) {
+			let state_key = get_state_key(None, path.as_ref());
+			project_data.record_foreign_file(path.as_ref(), state_key, &parsed_contents);
+		}
+		project_data.parse_project_local_file(ctx)?;
+		project_data.check_foreign_files(ctx)?;
 
-		Ok(project_data)
+		Ok((project_data, name, parsed_contents.loc().clone(), docs_pref))
 	}
 }
 
@@ -102,121 +210,1305 @@ impl parse_lib::ConfigOption for ProjectDataOption<'_, '_> {
 #[derive(Clone)]
 pub struct PrelimParseState {
 	project_dir: PathBufOption,
-	initial_file: StringOption,
+	// only shown in the tui, see 'read_project_stage1'
+	description: StringOption,
+	// only used as state key, see 'ProjectDataFuture::state_key'
+	id: StringOption,
+	icon: BaseOption<String>,
+	// NOTE: a single file may be specified as string
+	initial_file: BaseOption<Vec<String>>,
 	editor: EditorCommandOption,
 	virtual_fs: VirtualFSOption,
-	whitelist_envvars: ArrayOption<String>,
+	whitelist_envvars: ArrayOption<(String, parse_lib::Location)>,
 	whitelist_all_envvars: BoolOption,
 	auto_nixshell: BoolOption,
 	disable_sandbox: BoolOption,
+	private_home: PathBufOption,
+	use_base_preset: BoolOption,
+	share_audio: BoolOption,
+	share_gpu: BoolOption,
+	share_gpg: BoolOption,
+	container: BaseOption<ContainerParameters>,
+	seccomp_profile: BaseOption<SeccompProfile>,
+	limits: BaseOption<ResourceLimits>,
+	remote: StringOption,
+	terminal: BoolOption,
+	terminal_cmd: ArrayOption<String>,
+	actions: ArrayOption<(ProjectAction, parse_lib::Location)>,
 
+	// NOTE: paths are canonicalized, so that different
+	//       forms of the same path are recognized
 	parsed_files: Vec<PathBuf>,
+	// files whose changes affect the options, including
+	// files that do not exist (e.g. a project-local file)
+	source_files: Vec<PathBuf>,
+	// files that are currently being parsed,
+	// starting with the outermost include
+	include_stack: Vec<PathBuf>,
+	// the project file itself, which is not part of 'include_stack'
+	root_file: Option<PathBuf>,
+	// priority of the currently parsed table
+	priority: Priority,
+	// files not written by the user together with
+	// their state key and sandbox-weakening options
+	foreign_files: Vec<(PathBuf, String, Vec<SandboxWeakening>)>,
+	// used for the canonicalization of paths
+	dirs: Arc<dyn Dirs>,
 }
 impl PrelimParseState {
-	pub fn empty() -> Self {
+	pub fn empty(dirs: Arc<dyn Dirs>) -> Self {
+		let project_dir_dirs = dirs.clone();
+		let private_home_dirs = dirs.clone();
+		let initial_file_dirs = dirs.clone();
+		let actions_dirs = dirs.clone();
 		Self {
-			project_dir: PathBufOption::new("project-dir", |str| path::canonicalize_path(str)),
-			initial_file: StringOption::new_with_canonicalization("initial-file", |str| {
-				path::substitute_placeholder(str, false)
+			project_dir: PathBufOption::new("project-dir", move |str| {
+				path::canonicalize_path(str, &*project_dir_dirs)
 			}),
-			editor: EditorCommandOption::new(),
-			virtual_fs: VirtualFSOption::new(),
-			whitelist_envvars: ArrayOption::new("whitelist-envvar", true, |value| {
-				let value = value.as_str()?;
-				Ok(value.to_string())
+			description: StringOption::new("description"),
+			id: StringOption::new("id"),
+			icon: BaseOption::new("icon", super::parse_icon),
+			initial_file: BaseOption::new("initial-file", move |value| {
+				parse_initial_files(value, &*initial_file_dirs)
+			}),
+			editor: EditorCommandOption::new(dirs.clone()),
+			virtual_fs: VirtualFSOption::new(dirs.clone()),
+			whitelist_envvars: ArrayOption::new("whitelist-envvar", true, |raw_value| {
+				let value = raw_value.as_str()?;
+				Ok((value.to_string(), raw_value.loc().clone()))
 			}),
 			whitelist_all_envvars: BoolOption::new("whitelist-all-envvars"),
 			auto_nixshell: BoolOption::new("auto-nixshell"),
 			disable_sandbox: BoolOption::new("no-sandbox"),
+			private_home: PathBufOption::new("private-home", move |str| {
+				path::canonicalize_path(str, &*private_home_dirs)
+			}),
+			use_base_preset: BoolOption::new("use-base-preset"),
+			share_audio: BoolOption::new("share-audio"),
+			share_gpu: BoolOption::new("share-gpu"),
+			share_gpg: BoolOption::new("share-gpg"),
+			container: BaseOption::new("container", parse_container_table),
+			seccomp_profile: BaseOption::new("seccomp-profile", parse_seccomp_profile),
+			limits: BaseOption::new("limits", parse_limits_table),
+			remote: StringOption::new("remote"),
+			terminal: BoolOption::new("terminal"),
+			terminal_cmd: ArrayOption::new("terminal-cmd", false, |raw_value| {
+				Ok(raw_value.as_str()?.to_string())
+			}),
+			actions: ArrayOption::new("actions", true, move |raw_value| {
+				Ok((
+					parse_action(raw_value, &actions_dirs)?,
+					raw_value.loc().clone(),
+				))
+			}),
 
 			parsed_files: Vec::new(),
+			source_files: Vec::new(),
+			include_stack: Vec::new(),
+			root_file: None,
+			priority: Priority::Normal,
+			foreign_files: Vec::new(),
+			dirs,
 		}
 	}
-	// if a required config option is missing, the name of this option is returned as an error
-	fn into_project_data(self) -> Result<ProjectData, String> {
-		let project_dir = self.project_dir.get_value().ok_or("project-dir")?;
-		let initial_file = self.initial_file.get_value();
-		let editor = self.editor.value.ok_or("editor")?.0;
-		let fs_tree = self.virtual_fs.tree;
+	fn set_priority(&mut self, priority: Priority) {
+		self.priority = priority;
+		self.project_dir.set_priority(priority);
+		self.description.set_priority(priority);
+		self.id.set_priority(priority);
+		self.icon.set_priority(priority);
+		self.initial_file.set_priority(priority);
+		self.editor.priority = priority;
+		self.virtual_fs.priority = priority;
+		self.whitelist_envvars.set_priority(priority);
+		self.whitelist_all_envvars.set_priority(priority);
+		self.auto_nixshell.set_priority(priority);
+		self.disable_sandbox.set_priority(priority);
+		self.private_home.set_priority(priority);
+		self.use_base_preset.set_priority(priority);
+		self.share_audio.set_priority(priority);
+		self.share_gpu.set_priority(priority);
+		self.share_gpg.set_priority(priority);
+		self.container.set_priority(priority);
+		self.seccomp_profile.set_priority(priority);
+		self.limits.set_priority(priority);
+		self.remote.set_priority(priority);
+		self.terminal.set_priority(priority);
+		self.terminal_cmd.set_priority(priority);
+		self.actions.set_priority(priority);
+	}
+	fn set_merge_policy(&mut self, merge_policy: MergePolicy) {
+		self.project_dir.set_merge_policy(merge_policy);
+		self.description.set_merge_policy(merge_policy);
+		self.id.set_merge_policy(merge_policy);
+		self.icon.set_merge_policy(merge_policy);
+		self.initial_file.set_merge_policy(merge_policy);
+		self.editor.merge_policy = merge_policy;
+		self.whitelist_all_envvars.set_merge_policy(merge_policy);
+		self.auto_nixshell.set_merge_policy(merge_policy);
+		self.disable_sandbox.set_merge_policy(merge_policy);
+		self.private_home.set_merge_policy(merge_policy);
+		self.use_base_preset.set_merge_policy(merge_policy);
+		self.share_audio.set_merge_policy(merge_policy);
+		self.share_gpu.set_merge_policy(merge_policy);
+		self.share_gpg.set_merge_policy(merge_policy);
+		self.container.set_merge_policy(merge_policy);
+		self.seccomp_profile.set_merge_policy(merge_policy);
+		self.limits.set_merge_policy(merge_policy);
+		self.remote.set_merge_policy(merge_policy);
+		self.terminal.set_merge_policy(merge_policy);
+		self.terminal_cmd.set_merge_policy(merge_policy);
+	}
+	// NOTE: must be called after all files have been parsed,
+	//       since the presets may be enabled by any of them
+	pub(super) fn apply_presets(&mut self, ctx: &mut ParseContext) -> ModResult<()> {
+		let is_enabled = |option: &BoolOption| option.clone().get_value().unwrap_or_default();
+		let presets = [
+			(&self.use_base_preset, BASE_PRESET),
+			(&self.share_audio, AUDIO_PRESET),
+			(&self.share_gpu, GPU_PRESET),
+			(&self.share_gpg, GPG_PRESET),
+		];
+		let enabled_presets = presets
+			.into_iter()
+			.filter(|(option, _)| is_enabled(option))
+			.map(|(_, preset)| preset)
+			.collect::<Vec<_>>();
+
+		let prev_priority = self.priority;
+		self.set_priority(Priority::Preset);
+		let result = enabled_presets
+			.into_iter()
+			.try_for_each(|(name, contents)| {
+				let mut outlivers = (None, None);
+				let parsed_contents =
+					ctx.parse_builtin_toml(name, contents, FileKind::ProjectData, &mut outlivers)?;
+				self.parse_table(&parsed_contents, ctx)
+			});
+		self.set_priority(prev_priority);
+		result
+	}
+	// the options that are currently set, with the location of their definition
+	pub(super) fn dump_entries(&self, ctx: &mut ParseContext) -> ModResult<Vec<DumpEntry>> {
+		let mut entries = Vec::new();
+		let mut add_entry = |key, value: Option<(String, parse_lib::Location)>| {
+			if let Some((value, loc)) = value {
+				entries.push(DumpEntry::Single {
+					key,
+					value,
+					loc: Some(loc),
+				});
+			}
+		};
+		let bool_value = |option: &BoolOption| {
+			let (value, loc) = option.clone().get_value_with_loc()?;
+			Some((value.to_string(), loc))
+		};
+		let path_value = |option: &PathBufOption| {
+			let (path, loc) = option.clone().get_value_with_loc()?;
+			Some((toml_util::toml_path(&path), loc))
+		};
+
+		add_entry("project-dir", path_value(&self.project_dir));
+		add_entry(
+			"description",
+			self
+				.description
+				.clone()
+				.get_value_with_loc()
+				.map(|(description, loc)| (toml_string_escape(&description), loc)),
+		);
+		add_entry(
+			"id",
+			self
+				.id
+				.clone()
+				.get_value_with_loc()
+				.map(|(id, loc)| (toml_string_escape(&id), loc)),
+		);
+		add_entry(
+			"icon",
+			self
+				.icon
+				.clone()
+				.get_value_with_loc()
+				.map(|(icon, loc)| (toml_string_escape(&icon), loc)),
+		);
+		add_entry(
+			"initial-file",
+			self
+				.initial_file
+				.clone()
+				.get_value_with_loc()
+				.map(|(files, loc)| match files.as_slice() {
+					[file] => (toml_string_escape(file), loc),
+					_ => (toml_util::toml_string_array(&files), loc),
+				}),
+		);
+		add_entry("auto-nixshell", bool_value(&self.auto_nixshell));
+		add_entry("no-sandbox", bool_value(&self.disable_sandbox));
+		add_entry("private-home", path_value(&self.private_home));
+		add_entry("use-base-preset", bool_value(&self.use_base_preset));
+		add_entry("share-audio", bool_value(&self.share_audio));
+		add_entry("share-gpu", bool_value(&self.share_gpu));
+		add_entry("share-gpg", bool_value(&self.share_gpg));
+		add_entry(
+			"remote",
+			self
+				.remote
+				.clone()
+				.get_value_with_loc()
+				.map(|(remote, loc)| (toml_string_escape(&remote), loc)),
+		);
+		add_entry("terminal", bool_value(&self.terminal));
+		add_entry(
+			"terminal-cmd",
+			self
+				.terminal_cmd
+				.clone()
+				.get_value_with_loc()
+				.map(|(cmd, loc)| (toml_util::toml_string_array(&cmd), loc)),
+		);
+		add_entry(
+			"container",
+			self
+				.container
+				.clone()
+				.get_value_with_loc()
+				.map(|(container, loc)| {
+					let value = format!(
+						"{{ image = {}, engine = \"{}\" }}",
+						toml_string_escape(&container.image),
+						container.engine.program()
+					);
+					(value, loc)
+				}),
+		);
+		add_entry(
+			"seccomp-profile",
+			self
+				.seccomp_profile
+				.clone()
+				.get_value_with_loc()
+				.map(|(profile, loc)| (toml_string_escape(profile.name()), loc)),
+		);
+		add_entry(
+			"limits",
+			self
+				.limits
+				.clone()
+				.get_value_with_loc()
+				.map(|(limits, loc)| {
+					let entries = [
+						("memory", limits.memory),
+						("cpu-weight", limits.cpu_weight),
+						("pids-max", limits.pids_max),
+						("nofile", limits.nofile),
+					]
+					.into_iter()
+					.filter_map(|(key, value)| Some(format!("{key} = {}", value?)))
+					.collect::<Vec<_>>();
+					(format!("{{ {} }}", entries.join(", ")), loc)
+				}),
+		);
+		add_entry(
+			"whitelist-all-envvars",
+			bool_value(&self.whitelist_all_envvars),
+		);
+		add_entry(
+			"editor",
+			self.editor.value.as_ref().map(|(editor, loc, _)| {
+				let flatpak = editor
+					.flatpak
+					.as_ref()
+					.map(|app_id| format!(", flatpak = {}", toml_string_escape(app_id)))
+					.unwrap_or_default();
+				let working_dir = editor
+					.working_dir
+					.as_ref()
+					.map(|dir| format!(", working-dir = {}", toml_string_escape(dir)))
+					.unwrap_or_default();
+				let value = format!(
+					"{{ cmd-with-file = {}, cmd-without-file = {}, detach = {}{flatpak}{working_dir} }}",
+					toml_util::toml_string_array(&editor.cmd_with_file),
+					toml_util::toml_string_array(&editor.cmd_without_file),
+					editor.detach
+				);
+				(value, loc.clone())
+			}),
+		);
+
+		let envvars = self
+			.whitelist_envvars
+			.clone()
+			.get_value()
+			.unwrap_or_default()
+			.into_iter()
+			.map(|(name, loc)| (toml_string_escape(&name), loc))
+			.collect::<Vec<_>>();
+		if !envvars.is_empty() {
+			entries.push(DumpEntry::List {
+				key: "whitelist-envvar",
+				values: envvars,
+			});
+		}
+		let actions = self
+			.actions
+			.clone()
+			.get_value()
+			.unwrap_or_default()
+			.into_iter()
+			.map(|(action, loc)| {
+				let value = format!(
+					"{{ name = {}, command = {}, sandbox = {} }}",
+					toml_string_escape(&action.name),
+					toml_util::toml_string_array(&action.command),
+					action.sandbox
+				);
+				(value, loc)
+			})
+			.collect::<Vec<_>>();
+		if !actions.is_empty() {
+			entries.push(DumpEntry::List {
+				key: "actions",
+				values: actions,
+			});
+		}
+
+		// NOTE: only entries that are not overridden by higher priorities are shown
+		let (_, fs_entries, overridden_notes) = self.virtual_fs.build_tree()?;
+		for note in overridden_notes {
+			ctx.report_warning_with_include_chains(note);
+		}
+		let fs_entry_keys = [
+			(VirtualFSEntryType::ReadWrite, "whitelist-rw"),
+			(VirtualFSEntryType::ReadOnly, "whitelist-ro"),
+			(VirtualFSEntryType::CopyOnWrite, "whitelist-cow"),
+			(VirtualFSEntryType::AllowDev, "whitelist-dev"),
+			(VirtualFSEntryType::Symlink, "whitelist-ln"),
+			(VirtualFSEntryType::Tmpfs, "add-tmpfs"),
+			(VirtualFSEntryType::Deny, "blacklist"),
+		];
+		for (fs_entry_type, key) in fs_entry_keys {
+			let values = fs_entries
+				.iter()
+				.filter(|entry| entry.source.is_none() && entry.fs_entry_type == fs_entry_type)
+				.map(|entry| (toml_util::toml_path(&entry.path), entry.loc.clone()))
+				.collect::<Vec<_>>();
+			if !values.is_empty() {
+				entries.push(DumpEntry::List { key, values });
+			}
+		}
+		let binds = fs_entries
+			.iter()
+			.filter_map(|entry| {
+				let source = entry.source.as_ref()?;
+				let mode = match entry.fs_entry_type {
+					VirtualFSEntryType::ReadWrite => "rw",
+					VirtualFSEntryType::AllowDev => "dev",
+					_ => "ro",
+				};
+				let value = format!(
+					"{{ src = {}, dest = {}, mode = \"{mode}\" }}",
+					toml_util::toml_path(source),
+					toml_util::toml_path(&entry.path)
+				);
+				Some((value, entry.loc.clone()))
+			})
+			.collect::<Vec<_>>();
+		if !binds.is_empty() {
+			entries.push(DumpEntry::List {
+				key: "bind",
+				values: binds,
+			});
+		}
+
+		Ok(entries)
+	}
+	// whitelisted paths that do not exist on the host,
+	// as they are skipped silently by the sandbox
+	fn missing_path_warnings(&self) -> ModResult<Vec<parse_lib::Diagnostic>> {
+		let (_, fs_entries, _) = self.virtual_fs.build_tree()?;
+		let warnings = fs_entries
+			.into_iter()
+			.filter_map(|entry| {
+				let is_whitelist = entry.fs_entry_type >= VirtualFSEntryType::Symlink;
+				// NOTE: presets contain paths that only exist on some distributions
+				let is_preset = entry.priority == Priority::Preset;
+				let host_path = entry.source.as_ref().unwrap_or(&entry.path);
+				// NOTE: symlinks are checked without following them
+				if !is_whitelist || is_preset || host_path.symlink_metadata().is_ok() {
+					return None;
+				}
+				let label = entry
+					.loc
+					.get_primary_label()
+					.with_message(format!("`{}` does not exist", host_path.display()));
+				let diag = parse_lib::Diagnostic::new(parse_lib::Severity::Warning)
+					.with_message("whitelisted path does not exist")
+					.with_labels(vec![label])
+					.with_notes(vec![
+						"the path is skipped by the sandbox".to_string(),
+						"this warning can be disabled with the config option `warn-missing-paths`".to_string(),
+					]);
+				Some(diag)
+			})
+			.collect();
+		Ok(warnings)
+	}
+	// NOTE: missing options are reported at 'loc'
+	fn into_project_data(
+		self,
+		name: String,
+		state_key: String,
+		loc: &parse_lib::Location,
+		docs_pref: &str,
+		ctx: &mut ParseContext,
+	) -> ModResult<ProjectData> {
+		let missing_option = |name| diagnostics::missing_option(loc, name, docs_pref);
+		let project_dir = self
+			.project_dir
+			.get_value()
+			.ok_or_else(|| missing_option("project-dir"))?;
+		let initial_files = self.initial_file.get_value().unwrap_or_default();
+		let editor = self.editor.value.ok_or_else(|| missing_option("editor"))?.0;
+		let (fs_tree, _, overridden_notes) = self.virtual_fs.build_tree()?;
+		for note in overridden_notes {
+			ctx.report_warning_with_include_chains(note);
+		}
 		let whitelist_all_envvars = self.whitelist_all_envvars.get_value().unwrap_or_default();
 		let whitelist_envvars = self.whitelist_envvars.get_value().unwrap_or_default();
 		let auto_nixshell = self.auto_nixshell.get_value().unwrap_or_default();
 		let disable_sandbox = self.disable_sandbox.get_value().unwrap_or_default();
+		let private_home = self.private_home.get_value();
+		let source_files = self.source_files;
+		let container = self.container.get_value();
+		let seccomp_profile = self.seccomp_profile.get_value().unwrap_or_default();
+		let limits = self.limits.get_value().unwrap_or_default();
+		let remote = self.remote.get_value();
+		let terminal = self.terminal.get_value().unwrap_or_default();
+		let actions = self
+			.actions
+			.get_value()
+			.unwrap_or_default()
+			.into_iter()
+			.map(|(action, _)| action)
+			.collect();
+		let terminal_cmd = match self.terminal_cmd.get_value_with_loc() {
+			Some((cmd, loc)) if cmd.is_empty() => {
+				let label = loc
+					.get_primary_label()
+					.with_message("command must not be empty");
+				let diag = parse_lib::Diagnostic::new(parse_lib::Severity::Error)
+					.with_message("empty terminal command")
+					.with_labels(vec![label]);
+				return Err(diag.into());
+			}
+			Some((cmd, _)) => cmd,
+			None => DEFAULT_TERMINAL_CMD
+				.iter()
+				.map(|arg| arg.to_string())
+				.collect(),
+		};
 
 		let whitelist_envvars = if whitelist_all_envvars {
 			EnvVarWhitelist::All
 		} else {
-			let os_string_list = whitelist_envvars.into_iter().map(Into::into).collect();
+			let os_string_list = whitelist_envvars
+				.into_iter()
+				.map(|(name, _)| name.into())
+				.collect();
 			EnvVarWhitelist::List(os_string_list)
 		};
 		Ok(ProjectData {
+			name,
+			state_key,
 			project_dir,
 			auto_nixshell,
 			disable_sandbox,
-			initial_file,
+			sandbox_fallback: ctx.options.sandbox_fallback,
+			systemd_scope: ctx.options.systemd_scope,
+			reopen_policy: ctx.options.reopen_policy,
+			container,
+			remote,
+			terminal_cmd: terminal.then_some(terminal_cmd),
+			source_files,
+			initial_files,
 			editor,
+			actions,
+			limits,
 			sandbox_params: SandboxParameters {
 				envvar_whitelist: whitelist_envvars,
-				fs_tree: fs_tree.remove_user_data(),
+				fs_tree,
+				private_home,
+				overlay_dir: None,
+				seccomp_profile,
 			},
 		})
 	}
-	fn parse_path(&mut self, path: impl AsRef<Path>, ctx: &mut ParseContext) -> ModResult<()> {
+	// 'origin' is the location of the include entry referencing 'path'
+	fn parse_path(
+		&mut self,
+		path: impl AsRef<Path>,
+		origin: parse_lib::Location,
+		ctx: &mut ParseContext,
+	) -> ModResult<()> {
 		let path = path.as_ref();
+		// NOTE: if the path cannot be canonicalized,
+		//       reading the file will fail anyway
+		let canonical_path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
 
-		if self.parsed_files.iter().any(|p| p == path) {
+		let include_chain = self
+			.root_file
+			.iter()
+			.chain(&self.include_stack)
+			.collect::<Vec<_>>();
+		if include_chain.contains(&&canonical_path) {
+			ctx.report_warning(include_cycle(&include_chain, &canonical_path, &origin));
+			return Ok(());
+		}
+		// NOTE: files included multiple times are only parsed once
+		if self.parsed_files.contains(&canonical_path) {
 			return Ok(());
 		}
-		self.parsed_files.push(path.to_path_buf());
+		if self.include_stack.len() >= ctx.options.max_include_depth {
+			return Err(
+				include_depth_exceeded(
+					&self.include_stack,
+					&canonical_path,
+					&origin,
+					ctx.options.max_include_depth,
+				)
+				.into(),
+			);
+		}
+		self.parsed_files.push(canonical_path.clone());
+		self.source_files.push(path.to_path_buf());
 
 		let mut outlivers = (None, None);
-		let parsed_contents = parse_lib::parse_toml_file(path, ctx.file_database, &mut outlivers)?;
+		let parsed_contents = ctx
+			.parse_toml_file(path, FileKind::ProjectData, &mut outlivers)
+			.map_err(|err| add_include_label(err, &origin))?;
+		ctx.record_include_origin(parsed_contents.loc().file, origin);
+		if trust::is_foreign_file(path, &*ctx.dirs) {
+			self.record_foreign_file(path, get_state_key(None, path), &parsed_contents);
+		}
+
+		self.include_stack.push(canonical_path);
+		let result = self.parse_table(&parsed_contents, ctx);
+		self.include_stack.pop();
+		result
+	}
+	// NOTE: project-local files are controlled by the project itself,
+	//       so sandbox-weakening options have to be confirmed
+	fn parse_project_local_file(&mut self, ctx: &mut ParseContext) -> ModResult<()> {
+		if !ctx.options.project_local_config {
+			return Ok(());
+		}
+		let Some(project_dir) = self.project_dir.clone().get_value() else {
+			// NOTE: the missing option is reported later
+			return Ok(());
+		};
+		// without a sandbox, even the editor command would be a security risk
+		if self.disable_sandbox.clone().get_value().unwrap_or_default() {
+			return Ok(());
+		}
+		let path = project_dir.join(PROJECT_LOCAL_FILE_NAME);
+		self.source_files.push(path.clone());
+		if !path.is_file() {
+			return Ok(());
+		}
 
-		self.parse_table(&parsed_contents, ctx)?;
+		let mut outlivers = (None, None);
+		let parsed_contents = ctx.parse_toml_file(&path, FileKind::ProjectData, &mut outlivers)?;
+		self.record_foreign_file(&path, get_state_key(None, &path), &parsed_contents);
+
+		self.set_merge_policy(ctx.options.merge_policy);
+		let prev_priority = self.priority;
+		self.set_priority(Priority::Defaults);
+		let mut unsupported_options = ProjectLocalBlocker;
+		let result = parse_lib::parse_table!(
+			&parsed_contents => [
+				self.initial_file,
+				self.editor,
+				self.virtual_fs,
+				self.whitelist_envvars,
+				self.whitelist_all_envvars,
+				self.auto_nixshell,
+				self.disable_sandbox,
+				self.private_home,
+				self.use_base_preset,
+				self.share_audio,
+				self.share_gpu,
+				self.share_gpg,
+				self.container,
+				self.seccomp_profile,
+				self.limits,
+				self.remote,
+				self.terminal,
+				self.terminal_cmd,
+				self.actions,
+				unsupported_options
+			],
+			docs-pref: "project-local-configuration",
+		);
+		self.set_priority(prev_priority);
+		result
+	}
+	fn record_foreign_file(&mut self, path: &Path, state_key: String, table: &TomlTable) {
+		let mut weakenings = Vec::new();
+		find_sandbox_weakenings(table, &mut weakenings, &self.dirs);
+		self
+			.foreign_files
+			.push((path.to_path_buf(), state_key, weakenings));
+	}
+	// NOTE: must be called after all files have been parsed,
+	//       since the weakenings depend on the final project directory
+	fn check_foreign_files(&self, ctx: &ParseContext) -> ModResult<()> {
+		if self.foreign_files.is_empty() {
+			return Ok(());
+		}
+		let home_dir = ctx
+			.dirs
+			.get_home_dir()
+			.map_err(|err| format!("Failed to determine the home directory:\n  {err}"))?;
+		// NOTE: the runtime directories contain the sockets of the session
+		//       (e.g. of d-bus or the ssh-agent), that are usable even if
+		//       they are only mounted read-only
+		let mut sensitive_dirs = vec![
+			home_dir,
+			PathBuf::from("/run"),
+			PathBuf::from("/var/run"),
+			PathBuf::from("/tmp"),
+		];
+		sensitive_dirs.extend(ctx.dirs.get_xdg_runtime_dir().ok());
+		let project_dir = self.project_dir.clone().get_value();
+		for (path, state_key, weakenings) in &self.foreign_files {
+			let descriptions = weakenings
+				.iter()
+				.filter_map(|weakening| weakening.describe(&sensitive_dirs, project_dir.as_deref()))
+				.collect::<Vec<_>>();
+			trust::ensure_trusted(path, state_key, &descriptions, ctx.trust_prompt, &*ctx.dirs)?;
+		}
 		Ok(())
 	}
 	fn parse_table(&mut self, table: &TomlTable, ctx: &mut ParseContext) -> ModResult<()> {
-		let mut include_option = ArrayOption::new("include", false, |raw_value| {
+		// NOTE: the merge policy is only known after the config file has been
+		//       read, but the initial parse state is created before
+		self.set_merge_policy(ctx.options.merge_policy);
+		let mut platform_overrides = PlatformOverridesOption;
+		let mut priority_tables = PriorityTablesOption {
+			// NOTE: otherwise e.g. [defaults] inside [linux] would be ambiguous
+			allowed: self.priority == Priority::Normal,
+		};
+		let dirs = self.dirs.clone();
+		let mut include_option = ArrayOption::new("include", false, move |raw_value| {
 			let value = raw_value.as_str()?;
-			path::canonicalize_include_path(value)
-				.map_err(|err| diagnostics::failed_canonicalization(raw_value, &err).into())
+			let include_path = path::canonicalize_include_path(value, &*dirs)
+				.map_err(|err| diagnostics::failed_canonicalization(raw_value, &err))?;
+			Ok((include_path, raw_value.loc().clone()))
 		});
 
 		parse_lib::parse_table!(
 			table => [
 				include_option,
 				self.project_dir,
+				self.description,
+				self.id,
+				self.icon,
 				self.initial_file,
 				self.editor,
 				self.virtual_fs,
 				self.whitelist_envvars,
 				self.whitelist_all_envvars,
 				self.auto_nixshell,
-				self.disable_sandbox
+				self.disable_sandbox,
+				self.private_home,
+				self.use_base_preset,
+				self.share_audio,
+				self.share_gpu,
+				self.share_gpg,
+				self.container,
+				self.seccomp_profile,
+				self.limits,
+				self.remote,
+				self.terminal,
+				self.terminal_cmd,
+				self.actions,
+				platform_overrides,
+				priority_tables
 			],
 			docs-pref: "project-data-format",
 		)?;
 
-		for include_path in include_option.get_value().unwrap_or_default() {
-			self.parse_path(include_path, ctx)?;
+		for (include_path, origin) in include_option.get_value().unwrap_or_default() {
+			self.parse_path(include_path, origin, ctx)?;
 		}
+
+		// platform-specific tables override the generic options
+		let prev_priority = self.priority;
+		// NOTE: in files included from [forced] or [defaults] tables,
+		//       platform-specific tables keep the priority of the file
+		let platform_priority = match prev_priority {
+			Priority::Normal => Priority::Platform,
+			priority => priority,
+		};
+		let platform_tables = get_matching_platform_tables(table)?
+			.into_iter()
+			.map(|table| (table, platform_priority));
+		for (table, priority) in platform_tables.chain(get_priority_tables(table)?) {
+			self.set_priority(priority);
+			if let Err(err) = self.parse_table(&table, ctx) {
+				self.set_priority(prev_priority);
+				return Err(err);
+			}
+		}
+		self.set_priority(prev_priority);
+
 		Ok(())
 	}
 }
 
+// the id of the project prefixed with `id:`, or otherwise the canonical
+// path of 'path' (which cannot collide, as it starts with a slash)
+fn get_state_key(id: Option<String>, path: &Path) -> String {
+	match id {
+		Some(id) => format!("id:{id}"),
+		None => fs::canonicalize(path)
+			.unwrap_or_else(|_| path.to_path_buf())
+			.to_string_lossy()
+			.into_owned(),
+	}
+}
+
+const PROJECT_LOCAL_FILE_NAME: &str = ".skeld.toml";
+// NOTE: 'x-terminal-emulator' is the configured terminal emulator on Debian
+const DEFAULT_TERMINAL_CMD: [&str; 2] = ["x-terminal-emulator", "-e"];
+// builtin presets: (name, contents)
+const BASE_PRESET: (&str, &str) = ("<builtin base preset>", include_str!("presets/base.toml"));
+const AUDIO_PRESET: (&str, &str) = ("<builtin audio preset>", include_str!("presets/audio.toml"));
+const GPU_PRESET: (&str, &str) = ("<builtin gpu preset>", include_str!("presets/gpu.toml"));
+const GPG_PRESET: (&str, &str) = ("<builtin gpg preset>", include_str!("presets/gpg.toml"));
+// rejects options that make no sense in project-local files
+struct ProjectLocalBlocker;
+impl parse_lib::ConfigOption for ProjectLocalBlocker {
+	fn try_eat(&mut self, key: &TomlKey, _: &TomlValue) -> ModResult<bool> {
+		if !["project-dir", "description", "id", "icon", "include"].contains(&key.name()) {
+			return Ok(false);
+		}
+		let label = key
+			.loc()
+			.get_primary_label()
+			.with_message("not supported in project-local config files");
+		let diag = parse_lib::Diagnostic::new(parse_lib::Severity::Error)
+			.with_message(format!(
+				"unsupported option `{}` in project-local config file",
+				key.name()
+			))
+			.with_labels(vec![label]);
+		Err(diag.into())
+	}
+}
+
+// options of foreign files that may weaken the sandbox
+#[derive(Clone)]
+enum SandboxWeakening {
+	// boolean option that is set to true (e.g. 'no-sandbox')
+	Flag(String),
+	EnvVar(String),
+	Path { path: PathBuf, writable: bool },
+	ProjectDir(PathBuf),
+	RuntimeDirEntry(String),
+	Remote(String),
+	// name of an action that is run without a sandbox
+	UnsandboxedAction(String),
+	// directory that is mounted read-write as home directory
+	PrivateHome(PathBuf),
+	// id of the flatpak app that runs the editor
+	Flatpak(String),
+	// image of the container that runs the editor
+	Container(String),
+}
+impl SandboxWeakening {
+	// returns None if the option turns out to be harmless
+	// NOTE: 'sensitive_dirs' must not be exposed as project directory
+	fn describe(&self, sensitive_dirs: &[PathBuf], project_dir: Option<&Path>) -> Option<String> {
+		let exposes = |path: &Path| {
+			sensitive_dirs
+				.iter()
+				.any(|dir| dir.starts_with(path) || path.starts_with(dir))
+		};
+		// NOTE: symlinks are checked both unresolved and resolved
+		let exposes_sensitive_dir =
+			|path: &Path| exposes(path) || fs::canonicalize(path).is_ok_and(|path| exposes(&path));
+		let in_project_dir =
+			|path: &Path| project_dir.is_some_and(|dir| is_resolved_subpath(path, dir));
+		match self {
+			Self::Flag(name) => Some(format!("sets `{name}`")),
+			Self::EnvVar(name) => Some(format!("exposes the environment variable `{name}`")),
+			Self::Path { path, .. } if in_project_dir(path) => None,
+			Self::Path {
+				path,
+				writable: true,
+			} => Some(format!("grants write access to `{}`", path.display())),
+			Self::Path { path, .. } => Some(format!("grants read access to `{}`", path.display())),
+			Self::ProjectDir(path) if exposes_sensitive_dir(path) => {
+				Some(format!("uses `{}` as project directory", path.display()))
+			}
+			Self::ProjectDir(_) => None,
+			Self::RuntimeDirEntry(name) => Some(format!("shares `{name}` of the runtime directory")),
+			Self::Remote(host) => Some(format!(
+				"runs the editor on the remote host `{host}` without a sandbox"
+			)),
+			Self::UnsandboxedAction(name) => Some(format!("runs the action `{name}` without a sandbox")),
+			Self::PrivateHome(path) if in_project_dir(path) => None,
+			Self::PrivateHome(path) => Some(format!(
+				"grants write access to `{}` as home directory",
+				path.display()
+			)),
+			Self::Flatpak(app) => Some(format!("runs the editor as the flatpak app `{app}`")),
+			Self::Container(image) => Some(format!(
+				"runs the editor in a container of the image `{image}`"
+			)),
+		}
+	}
+}
+// whether 'path' is inside of 'dir' after resolving symlinks,
+// as e.g. the project directory may contain a symlink to the home directory
+// NOTE: paths that cannot be resolved or contain `..` are not considered to be inside
+fn is_resolved_subpath(path: &Path, dir: &Path) -> bool {
+	if path.components().any(|comp| comp == Component::ParentDir) {
+		return false;
+	}
+	match (fs::canonicalize(path), fs::canonicalize(dir)) {
+		(Ok(path), Ok(dir)) => path.starts_with(dir),
+		_ => false,
+	}
+}
+// NOTE: invalid values are skipped, as the values that are
+//       actually used have already been validated
+fn find_sandbox_weakenings(
+	table: &TomlTable,
+	weakenings: &mut Vec<SandboxWeakening>,
+	dirs: &Arc<dyn Dirs>,
+) {
+	let parse_path = |value: &TomlValue| {
+		let str = value.as_str().ok()?;
+		path::canonicalize_path(str, &**dirs).ok()
+	};
+	for (key, value) in table.iter() {
+		match key.name() {
+			"no-sandbox" | "whitelist-all-envvars" | "share-audio" | "share-gpu" | "share-gpg" => {
+				if value.as_bool().unwrap_or_default() {
+					weakenings.push(SandboxWeakening::Flag(key.name().to_string()));
+				}
+			}
+			"whitelist-envvar" => {
+				for entry in value.as_array().unwrap_or_default() {
+					if let Ok(name) = entry.as_str() {
+						weakenings.push(SandboxWeakening::EnvVar(name.to_string()));
+					}
+				}
+			}
+			"whitelist-ro" | "whitelist-rw" | "whitelist-cow" | "whitelist-dev" => {
+				let writable = matches!(key.name(), "whitelist-rw" | "whitelist-dev");
+				for entry in value.as_array().unwrap_or_default() {
+					if let Some(path) = parse_path(&entry) {
+						weakenings.push(SandboxWeakening::Path { path, writable });
+					}
+				}
+			}
+			"share-runtime-dir" => {
+				for entry in value.as_array().unwrap_or_default() {
+					if let Ok(name) = entry.as_str() {
+						weakenings.push(SandboxWeakening::RuntimeDirEntry(name.to_string()));
+					}
+				}
+			}
+			"bind" => {
+				for entry in value.as_array().unwrap_or_default() {
+					if let Ok((_, src, mode, _)) = parse_bind_entry(&entry, dirs) {
+						weakenings.push(SandboxWeakening::Path {
+							path: src,
+							writable: mode != VirtualFSEntryType::ReadOnly,
+						});
+					}
+				}
+			}
+			// NOTE: the terminal emulator is run outside of the sandbox
+			"terminal-cmd" => {
+				weakenings.push(SandboxWeakening::Flag(key.name().to_string()));
+			}
+			"remote" => {
+				if let Ok(host) = value.as_str() {
+					weakenings.push(SandboxWeakening::Remote(host.to_string()));
+				}
+			}
+			"seccomp-profile" => {
+				if value.as_str().is_ok_and(|profile| profile == "none") {
+					weakenings.push(SandboxWeakening::Flag(
+						"seccomp-profile = \"none\"".to_string(),
+					));
+				}
+			}
+			"project-dir" => {
+				if let Some(path) = parse_path(&value) {
+					weakenings.push(SandboxWeakening::ProjectDir(path));
+				}
+			}
+			"private-home" => {
+				if let Some(path) = parse_path(&value) {
+					weakenings.push(SandboxWeakening::PrivateHome(path));
+				}
+			}
+			// NOTE: part of the editor table
+			"flatpak" => {
+				if let Ok(app) = value.as_str() {
+					weakenings.push(SandboxWeakening::Flatpak(app.to_string()));
+				}
+			}
+			"container" => {
+				if let Ok(container) = parse_container_table(&value) {
+					weakenings.push(SandboxWeakening::Container(container.image));
+				}
+			}
+			"actions" => {
+				for entry in value.as_array().unwrap_or_default() {
+					if let Ok(action) = parse_action(&entry, dirs) {
+						if !action.sandbox {
+							weakenings.push(SandboxWeakening::UnsandboxedAction(action.name));
+						}
+					}
+				}
+			}
+			// e.g. platform-specific tables or the project table of bookmarks
+			_ => {
+				if let Ok(subtable) = value.as_table() {
+					find_sandbox_weakenings(&subtable, weakenings, dirs);
+				}
+			}
+		}
+	}
+}
+
+// tables like [linux] or [when.arch.x86_64], that only apply to specific platforms
+struct PlatformOverridesOption;
+impl parse_lib::ConfigOption for PlatformOverridesOption {
+	fn try_eat(&mut self, key: &TomlKey, value: &TomlValue) -> ModResult<bool> {
+		if !PLATFORM_OVERRIDE_KEYS.contains(&key.name()) {
+			return Ok(false);
+		}
+		// NOTE: the contents are parsed in 'get_matching_platform_tables'
+		value.as_table()?;
+		Ok(true)
+	}
+}
+const PLATFORM_OVERRIDE_KEYS: [&str; 3] = ["linux", "macos", "when"];
+
+// tables like [forced] or [defaults], whose options have a different priority
+struct PriorityTablesOption {
+	allowed: bool,
+}
+impl parse_lib::ConfigOption for PriorityTablesOption {
+	fn try_eat(&mut self, key: &TomlKey, value: &TomlValue) -> ModResult<bool> {
+		if get_table_priority(key.name()).is_none() {
+			return Ok(false);
+		}
+		if !self.allowed {
+			let label = key
+				.loc()
+				.get_primary_label()
+				.with_message("must not be nested in platform-specific, forced or defaults tables");
+			let diag = parse_lib::Diagnostic::new(parse_lib::Severity::Error)
+				.with_message(format!("nested `{}` table", key.name()))
+				.with_labels(vec![label]);
+			return Err(diag.into());
+		}
+		// NOTE: the contents are parsed in 'get_priority_tables'
+		value.as_table()?;
+		Ok(true)
+	}
+}
+fn get_table_priority(name: &str) -> Option<Priority> {
+	match name {
+		"forced" => Some(Priority::Forced),
+		"defaults" => Some(Priority::Defaults),
+		_ => None,
+	}
+}
+fn get_priority_tables<'a>(table: &TomlTable<'a>) -> ModResult<Vec<(TomlTable<'a>, Priority)>> {
+	let mut priority_tables = Vec::new();
+	for (key, value) in table.iter() {
+		if let Some(priority) = get_table_priority(key.name()) {
+			priority_tables.push((value.as_table()?, priority));
+		}
+	}
+	Ok(priority_tables)
+}
+fn get_matching_platform_tables<'a>(table: &TomlTable<'a>) -> ModResult<Vec<TomlTable<'a>>> {
+	let mut matching_tables = Vec::new();
+	for (key, value) in table.iter() {
+		if key.name() == "when" {
+			for (when_key, when_value) in value.as_table()?.iter() {
+				if when_key.name() != "arch" {
+					return Err(diagnostics::unknown_option(&when_key, "platform-specific-options").into());
+				}
+				for (arch, arch_value) in when_value.as_table()?.iter() {
+					let arch_table = arch_value.as_table()?;
+					if arch.name() == env::consts::ARCH {
+						matching_tables.push(arch_table);
+					}
+				}
+			}
+		} else if key.name() == env::consts::OS && PLATFORM_OVERRIDE_KEYS.contains(&key.name()) {
+			matching_tables.push(value.as_table()?);
+		}
+	}
+	Ok(matching_tables)
+}
+
+// sandbox parameters that only allow running the programs of the system,
+// as configured by the base preset
+pub(super) fn get_base_sandbox_params(ctx: &mut ParseContext) -> ModResult<SandboxParameters> {
+	let mut parse_state = PrelimParseState::empty(ctx.dirs.clone());
+	let (name, contents) = BASE_PRESET;
+	let mut outlivers = (None, None);
+	let parsed_contents =
+		ctx.parse_builtin_toml(name, contents, FileKind::ProjectData, &mut outlivers)?;
+	parse_state.parse_table(&parsed_contents, ctx)?;
+
+	let (fs_tree, ..) = parse_state.virtual_fs.build_tree()?;
+	let envvar_whitelist = parse_state
+		.whitelist_envvars
+		.get_value()
+		.unwrap_or_default()
+		.into_iter()
+		.map(|(name, _)| name.into())
+		.collect();
+	Ok(SandboxParameters {
+		envvar_whitelist: EnvVarWhitelist::List(envvar_whitelist),
+		fs_tree,
+		private_home: None,
+		overlay_dir: None,
+		seccomp_profile: SeccompProfile::default(),
+	})
+}
+// NOTE: required for errors in included files that occur
+//       before their include origin is recorded
+// 'include_stack' are the files currently being parsed, starting with the outermost include
+pub(super) fn include_depth_exceeded(
+	include_stack: &[PathBuf],
+	path: &Path,
+	origin: &parse_lib::Location,
+	max_include_depth: usize,
+) -> parse_lib::Diagnostic {
+	let include_chain = include_stack
+		.iter()
+		.map(PathBuf::as_path)
+		.chain([path])
+		.map(|path| format!("- {}", path.display()))
+		.collect::<Vec<_>>()
+		.join("\n");
+	let label = origin
+		.get_primary_label()
+		.with_message("this include exceeds the maximum include depth");
+	parse_lib::Diagnostic::new(parse_lib::Severity::Error)
+		.with_message(format!(
+			"maximum include depth of {max_include_depth} exceeded"
+		))
+		.with_labels(vec![label])
+		.with_notes(vec![
+			format!("include chain:\n{include_chain}"),
+			"the limit can be changed with the config option `max-include-depth`".to_string(),
+		])
+}
+// 'include_chain' are the files currently being parsed, starting with the outermost file
+pub(super) fn include_cycle(
+	include_chain: &[&PathBuf],
+	path: &Path,
+	origin: &parse_lib::Location,
+) -> parse_lib::Diagnostic {
+	let cycle_start = include_chain
+		.iter()
+		.position(|file| *file == path)
+		.unwrap_or_default();
+	let cycle = include_chain[cycle_start..]
+		.iter()
+		.map(|file| file.as_path())
+		.chain([path])
+		.map(|file| format!("- {}", file.display()))
+		.collect::<Vec<_>>()
+		.join("\n");
+	let label = origin
+		.get_primary_label()
+		.with_message("this file is already being parsed");
+	parse_lib::Diagnostic::new(parse_lib::Severity::Warning)
+		.with_message(format!("circular include of `{}`", path.display()))
+		.with_labels(vec![label])
+		.with_notes(vec![
+			format!("include chain:\n{cycle}"),
+			"the include is skipped".to_string(),
+		])
+}
+pub(super) fn add_include_label(err: GenericError, origin: &parse_lib::Location) -> GenericError {
+	match err {
+		GenericError::Diagnostic(diag) => {
+			let label = origin
+				.get_secondary_label()
+				.with_message("included from here");
+			diag.with_labels(vec![label]).into()
+		}
+		other => other,
+	}
+}
+
 #[derive(Clone)]
 struct VirtualFSOption {
-	tree: VirtualFSTree<parse_lib::Location>,
+	entries: Vec<VirtualFSOptionEntry>,
+	// priority of subsequently eaten values
+	priority: Priority,
+	dirs: Arc<dyn Dirs>,
+}
+#[derive(Clone)]
+struct VirtualFSOptionEntry {
+	path: PathBuf,
+	source: Option<PathBuf>,
+	fs_entry_type: VirtualFSEntryType,
+	loc: parse_lib::Location,
+	priority: Priority,
 }
 impl VirtualFSOption {
-	fn new() -> Self {
+	fn new(dirs: Arc<dyn Dirs>) -> Self {
 		Self {
-			tree: VirtualFSTree::new(),
+			entries: Vec::new(),
+			priority: Priority::Normal,
+			dirs,
+		}
+	}
+	fn add_entry(
+		&mut self,
+		path: PathBuf,
+		source: Option<PathBuf>,
+		fs_entry_type: VirtualFSEntryType,
+		loc: parse_lib::Location,
+	) {
+		self.entries.push(VirtualFSOptionEntry {
+			path,
+			source,
+			fs_entry_type,
+			loc,
+			priority: self.priority,
+		});
+	}
+	// returns the tree, the entries that were added to it
+	// and a note for each skipped entry
+	// NOTE: entries are added in the order of their priority, so that an
+	//       entry conflicting with one of a higher priority is skipped
+	fn build_tree(
+		&self,
+	) -> ModResult<(
+		VirtualFSTree<()>,
+		Vec<&VirtualFSOptionEntry>,
+		Vec<parse_lib::Diagnostic>,
+	)> {
+		let mut entries = self.entries.iter().collect::<Vec<_>>();
+		entries.sort_by_key(|entry| std::cmp::Reverse(entry.priority));
+		let mut tree = VirtualFSTree::new();
+		let mut added_entries = Vec::new();
+		let mut overridden_notes = Vec::new();
+		for entry in entries {
+			let prev_tree = tree.clone();
+			let result = tree.add_bind(
+				&entry.path,
+				entry.source.clone(),
+				entry.fs_entry_type,
+				(entry.loc.clone(), entry.priority),
+			);
+			match result {
+				Err(
+					FSTreeError::IllegalChildren {
+						inner_path: first,
+						invalid_child: second,
+					}
+					| FSTreeError::ConflictingEntries(first, second),
+				) if first.1 != second.1 => {
+					tree = prev_tree;
+					// NOTE: the skipped entry is the one with the lower priority
+					let winner = if first.1 > second.1 { first } else { second };
+					overridden_notes.push(Self::overridden_note(entry, winner));
+				}
+				result => {
+					result.map_err(Self::fs_tree_error)?;
+					added_entries.push(entry);
+				}
+			}
+		}
+		Ok((tree.remove_user_data(), added_entries, overridden_notes))
+	}
+	fn overridden_note(
+		entry: &VirtualFSOptionEntry,
+		(winner_loc, winner_priority): (parse_lib::Location, Priority),
+	) -> parse_lib::Diagnostic {
+		let entry_label = entry
+			.loc
+			.get_primary_label()
+			.with_message("this entry is skipped");
+		let winner_label = winner_loc.get_secondary_label().with_message(format!(
+			"as it conflicts with this {} definition",
+			diagnostics::priority_name(winner_priority)
+		));
+		parse_lib::Diagnostic::new(parse_lib::Severity::Note)
+			.with_message(format!("`{}` is overridden", entry.path.display()))
+			.with_labels(vec![entry_label, winner_label])
+			.with_notes(vec![
+				"definitions of a higher priority take precedence".to_string()
+			])
+	}
+	fn fs_tree_error(err: FSTreeError<(parse_lib::Location, Priority)>) -> GenericError {
+		match err {
+			FSTreeError::IllegalChildren {
+				inner_path: (inner_path, priority),
+				invalid_child: (invalid_child, _),
+			} => {
+				let inner_path_label = inner_path
+					.get_primary_label()
+					.with_message("subpaths of symlink/tmpfs/blacklist entries must not be whitelisted");
+				let child_label = invalid_child
+					.get_secondary_label()
+					.with_message("but here a subpath is whitelisted");
+				parse_lib::Diagnostic::new(parse_lib::Severity::Error)
+					.with_message("subpath of symlink/tmpfs/blacklist entry is whitelisted")
+					.with_labels(vec![inner_path_label, child_label])
+					.with_notes(diagnostics::same_priority_notes(priority))
+					.into()
+			}
+			FSTreeError::ConflictingEntries((first, priority), (second, _)) => {
+				let first_label = first
+					.get_primary_label()
+					.with_message("path whitelisted here");
+				let second_label = second.get_secondary_label().with_message("and here again");
+				parse_lib::Diagnostic::new(parse_lib::Severity::Error)
+					.with_message("conflicting whitelists")
+					.with_labels(vec![first_label, second_label])
+					.with_notes(diagnostics::same_priority_notes(priority))
+					.into()
+			}
 		}
 	}
 }
 impl parse_lib::ConfigOption for VirtualFSOption {
 	fn try_eat(&mut self, key: &TomlKey, value: &TomlValue) -> ModResult<bool> {
+		if key.name() == "bind" {
+			let dirs = self.dirs.clone();
+			let mut bind_option =
+				ArrayOption::new("bind", false, move |value| parse_bind_entry(value, &dirs));
+			bind_option.try_eat(key, value)?;
+			for (dest, source, fs_entry_type, loc) in bind_option.get_value().unwrap_or_default() {
+				self.add_entry(dest, Some(source), fs_entry_type, loc);
+			}
+			return Ok(true);
+		}
+
 		let fs_entry_type;
 		if key.name() == "whitelist-dev" {
 			fs_entry_type = VirtualFSEntryType::AllowDev;
@@ -224,62 +1516,342 @@ impl parse_lib::ConfigOption for VirtualFSOption {
 			fs_entry_type = VirtualFSEntryType::ReadWrite;
 		} else if key.name() == "whitelist-ro" {
 			fs_entry_type = VirtualFSEntryType::ReadOnly;
+		} else if key.name() == "whitelist-cow" {
+			fs_entry_type = VirtualFSEntryType::CopyOnWrite;
 		} else if key.name() == "whitelist-ln" {
 			fs_entry_type = VirtualFSEntryType::Symlink;
 		} else if key.name() == "add-tmpfs" {
 			fs_entry_type = VirtualFSEntryType::Tmpfs;
+		} else if key.name() == "blacklist" {
+			fs_entry_type = VirtualFSEntryType::Deny;
+		} else if key.name() == "share-runtime-dir" {
+			return self.eat_runtime_dir_entries(key, value);
 		} else {
 			return Ok(false);
 		}
 
-		let mut patharray_option = ArrayOption::new(key.name(), false, |raw_value| {
+		let dirs = self.dirs.clone();
+		let mut patharray_option = ArrayOption::new(key.name(), false, move |raw_value| {
 			let value = raw_value.as_str()?;
-			let parsed_value = path::canonicalize_path(value)
+			let parsed_value = path::canonicalize_path(value, &*dirs)
 				.map_err(|err| diagnostics::failed_canonicalization(raw_value, &err))?;
 			Ok((parsed_value, raw_value.loc().clone()))
 		});
 		patharray_option.try_eat(key, value)?;
 		for (path, loc) in patharray_option.get_value().unwrap_or_default() {
-			match self.tree.add_path(path, fs_entry_type, loc) {
-				Ok(()) => (),
-				Err(FSTreeError::IllegalChildren {
-					inner_path,
-					invalid_child,
-				}) => {
-					let inner_path_label = inner_path
-						.get_primary_label()
-						.with_message("subpaths of symlink/tmpfs whitelists must not be whitelisted");
-					let child_label = invalid_child
-						.get_secondary_label()
-						.with_message("but here a subpath is whitelisted");
-					let diag = parse_lib::Diagnostic::new(parse_lib::Severity::Error)
-						.with_message("subpath of symlink/tmpfs is whitelisted")
-						.with_labels(vec![inner_path_label, child_label]);
-					return Err(diag.into());
-				}
-				Err(FSTreeError::ConflictingEntries(first, second)) => {
-					let first_label = first
-						.get_primary_label()
-						.with_message("path whitelisted here");
-					let second_label = second.get_secondary_label().with_message("and here again");
-					let diag = parse_lib::Diagnostic::new(parse_lib::Severity::Error)
-						.with_message("conflicting whitelists")
-						.with_labels(vec![first_label, second_label]);
-					return Err(diag.into());
-				}
-			}
+			self.add_entry(path, None, fs_entry_type, loc);
 		}
 
 		Ok(true)
 	}
 }
+impl VirtualFSOption {
+	// entries of the host runtime directory (e.g. sockets) that are
+	// mounted into the private runtime directory of the sandbox
+	// NOTE: sockets can be connected to through read-only mounts
+	fn eat_runtime_dir_entries(&mut self, key: &TomlKey, value: &TomlValue) -> ModResult<bool> {
+		let mut entries_option = ArrayOption::new(key.name(), false, |raw_value| {
+			let value = raw_value.as_str()?;
+			parse_runtime_dir_entry(value)
+				.map(|entry| (entry, raw_value.loc().clone()))
+				.ok_or_else(|| {
+					let label = raw_value
+						.loc()
+						.get_primary_label()
+						.with_message("expected a relative path like `pipewire-0`");
+					parse_lib::Diagnostic::new(parse_lib::Severity::Error)
+						.with_message("invalid runtime directory entry")
+						.with_labels(vec![label])
+						.into()
+				})
+		});
+		parse_lib::ConfigOption::try_eat(&mut entries_option, key, value)?;
+		// NOTE: without a runtime directory there is nothing to share
+		let Ok(runtime_dir) = self.dirs.get_xdg_runtime_dir() else {
+			return Ok(true);
+		};
+		for (entry, loc) in entries_option.get_value().unwrap_or_default() {
+			self.add_entry(
+				runtime_dir.join(entry),
+				None,
+				VirtualFSEntryType::ReadOnly,
+				loc,
+			);
+		}
+		Ok(true)
+	}
+}
+// only plain relative paths are accepted, so that entries
+// cannot escape the runtime directory
+fn parse_runtime_dir_entry(value: &str) -> Option<PathBuf> {
+	let path = PathBuf::from(value);
+	let is_plain = path
+		.components()
+		.all(|comp| matches!(comp, std::path::Component::Normal(_)));
+	(is_plain && !value.is_empty()).then_some(path)
+}
+// returns (dest, src, type, location)
+fn parse_bind_entry(
+	value: &TomlValue,
+	dirs: &Arc<dyn Dirs>,
+) -> ModResult<(PathBuf, PathBuf, VirtualFSEntryType, parse_lib::Location)> {
+	let table = value.as_table()?;
+
+	let src_dirs = dirs.clone();
+	let mut src = PathBufOption::new("src", move |str| path::canonicalize_path(str, &*src_dirs));
+	let dest_dirs = dirs.clone();
+	let mut dest = PathBufOption::new("dest", move |str| path::canonicalize_path(str, &*dest_dirs));
+	let mut mode = BaseOption::new("mode", |value| match value.as_str()? {
+		"ro" => Ok(VirtualFSEntryType::ReadOnly),
+		"rw" => Ok(VirtualFSEntryType::ReadWrite),
+		"dev" => Ok(VirtualFSEntryType::AllowDev),
+		_ => {
+			let label = value
+				.loc()
+				.get_primary_label()
+				.with_message("expected `ro`, `rw` or `dev`");
+			Err(
+				parse_lib::Diagnostic::new(parse_lib::Severity::Error)
+					.with_message("invalid bind mode")
+					.with_labels(vec![label])
+					.into(),
+			)
+		}
+	});
+
+	let docs_pref = "project-data-format";
+	parse_lib::parse_table!(
+		&table => [src, dest, mode],
+		docs-pref: docs_pref,
+	)?;
+	let src = src
+		.get_value()
+		.ok_or_else(|| diagnostics::missing_option(value.loc(), "src", docs_pref))?;
+	let dest = dest
+		.get_value()
+		.ok_or_else(|| diagnostics::missing_option(value.loc(), "dest", docs_pref))?;
+	let mode = mode.get_value().unwrap_or(VirtualFSEntryType::ReadOnly);
+
+	Ok((dest, src, mode, value.loc().clone()))
+}
+
+fn parse_container_table(value: &TomlValue) -> ModResult<ContainerParameters> {
+	let table = value.as_table()?;
+
+	let mut image = StringOption::new("image");
+	let mut engine = BaseOption::new("engine", |value| match value.as_str()? {
+		"podman" => Ok(ContainerEngine::Podman),
+		"docker" => Ok(ContainerEngine::Docker),
+		_ => {
+			let label = value
+				.loc()
+				.get_primary_label()
+				.with_message("expected `podman` or `docker`");
+			Err(
+				parse_lib::Diagnostic::new(parse_lib::Severity::Error)
+					.with_message("invalid container engine")
+					.with_labels(vec![label])
+					.into(),
+			)
+		}
+	});
+
+	let docs_pref = "container-backend";
+	parse_lib::parse_table!(
+		&table => [image, engine],
+		docs-pref: docs_pref,
+	)?;
+	let image = image
+		.get_value()
+		.ok_or_else(|| diagnostics::missing_option(value.loc(), "image", docs_pref))?;
+	let engine = engine.get_value().unwrap_or(ContainerEngine::Podman);
+
+	Ok(ContainerParameters { image, engine })
+}
+fn parse_action(value: &TomlValue, dirs: &Arc<dyn Dirs>) -> ModResult<ProjectAction> {
+	let table = value.as_table()?;
+
+	let mut name = StringOption::new("name");
+	let dirs = dirs.clone();
+	let mut command = ArrayOption::new("command", false, move |raw_value| {
+		let value = raw_value.as_str()?;
+		let late_vars = [
+			LateVariable::ProjectState,
+			LateVariable::ProjectDir,
+			LateVariable::ProjectName,
+		];
+		path::substitute_placeholder(value, &late_vars, &*dirs)
+			.map_err(|err| diagnostics::failed_canonicalization(raw_value, &err).into())
+	});
+	let mut sandbox = BoolOption::new("sandbox");
+
+	let docs_pref = "project-data-format";
+	parse_lib::parse_table!(
+		&table => [name, command, sandbox],
+		docs-pref: docs_pref,
+	)?;
+	let name = name
+		.get_value()
+		.ok_or_else(|| diagnostics::missing_option(value.loc(), "name", docs_pref))?;
+	let command = match command.get_value_with_loc() {
+		Some((command, loc)) if command.is_empty() => {
+			let label = loc
+				.get_primary_label()
+				.with_message("command must not be empty");
+			let diag = parse_lib::Diagnostic::new(parse_lib::Severity::Error)
+				.with_message("empty action command")
+				.with_labels(vec![label]);
+			return Err(diag.into());
+		}
+		Some((command, _)) => command,
+		None => return Err(diagnostics::missing_option(value.loc(), "command", docs_pref).into()),
+	};
+	let sandbox = sandbox.get_value().unwrap_or(true);
+
+	Ok(ProjectAction {
+		name,
+		command,
+		sandbox,
+	})
+}
+fn parse_limits_table(value: &TomlValue) -> ModResult<ResourceLimits> {
+	let table = value.as_table()?;
+
+	let mut memory = BaseOption::new("memory", |value| {
+		let mib = parse_positive_int(value)?;
+		// NOTE: the limit is passed to systemd in bytes
+		if mib.checked_mul(1024 * 1024).is_none() {
+			let label = value
+				.loc()
+				.get_primary_label()
+				.with_message(format!("must be at most {}", u64::MAX / (1024 * 1024)));
+			return Err(
+				parse_lib::Diagnostic::new(parse_lib::Severity::Error)
+					.with_message("invalid memory limit")
+					.with_labels(vec![label])
+					.into(),
+			);
+		}
+		Ok(mib)
+	});
+	let mut cpu_weight = BaseOption::new("cpu-weight", |value| {
+		let weight = parse_positive_int(value)?;
+		if weight > 10000 {
+			let label = value
+				.loc()
+				.get_primary_label()
+				.with_message("must be between 1 and 10000");
+			return Err(
+				parse_lib::Diagnostic::new(parse_lib::Severity::Error)
+					.with_message("invalid cpu weight")
+					.with_labels(vec![label])
+					.into(),
+			);
+		}
+		Ok(weight)
+	});
+	let mut pids_max = BaseOption::new("pids-max", parse_positive_int);
+	let mut nofile = BaseOption::new("nofile", parse_positive_int);
+
+	parse_lib::parse_table!(
+		&table => [memory, cpu_weight, pids_max, nofile],
+		docs-pref: "project-data-format",
+	)?;
+	Ok(ResourceLimits {
+		memory: memory.get_value(),
+		cpu_weight: cpu_weight.get_value(),
+		pids_max: pids_max.get_value(),
+		nofile: nofile.get_value(),
+	})
+}
+fn parse_positive_int(value: &TomlValue) -> ModResult<u64> {
+	let int = value.as_int()?;
+	u64::try_from(int)
+		.ok()
+		.filter(|int| *int > 0)
+		.ok_or_else(|| {
+			let label = value
+				.loc()
+				.get_primary_label()
+				.with_message("must be positive");
+			parse_lib::Diagnostic::new(parse_lib::Severity::Error)
+				.with_message("invalid integer")
+				.with_labels(vec![label])
+				.into()
+		})
+}
+fn parse_seccomp_profile(value: &TomlValue) -> ModResult<SeccompProfile> {
+	match value.as_str()? {
+		"none" => Ok(SeccompProfile::None),
+		"default" => Ok(SeccompProfile::Default),
+		"strict" => Ok(SeccompProfile::Strict),
+		"allow-list" => Ok(SeccompProfile::AllowList),
+		_ => {
+			let label = value
+				.loc()
+				.get_primary_label()
+				.with_message("expected `none`, `default`, `strict` or `allow-list`");
+			Err(
+				parse_lib::Diagnostic::new(parse_lib::Severity::Error)
+					.with_message("invalid seccomp profile")
+					.with_labels(vec![label])
+					.into(),
+			)
+		}
+	}
+}
+
+fn parse_initial_files(value: &TomlValue, dirs: &dyn Dirs) -> ModResult<Vec<String>> {
+	let substitute_placeholder = |value: &TomlValue| -> ModResult<String> {
+		path::substitute_placeholder(value.as_str()?, &[], dirs)
+			.map_err(|err| diagnostics::failed_canonicalization(value, &err).into())
+	};
+	if value.as_str().is_ok() {
+		return Ok(vec![substitute_placeholder(value)?]);
+	}
+	let Ok(array) = value.as_array() else {
+		return Err(
+			diagnostics::wrong_type(
+				value,
+				&[
+					parse_lib::TomlInnerValue::String(Default::default()),
+					parse_lib::TomlInnerValue::Array(Default::default()),
+				],
+			)
+			.into(),
+		);
+	};
+	if array.is_empty() {
+		let label = value
+			.loc()
+			.get_primary_label()
+			.with_message("at least one file is required");
+		return Err(
+			parse_lib::Diagnostic::new(parse_lib::Severity::Error)
+				.with_message("empty initial-file")
+				.with_labels(vec![label])
+				.into(),
+		);
+	}
+	array.iter().map(substitute_placeholder).collect()
+}
+
 #[derive(Clone)]
 struct EditorCommandOption {
-	value: Option<(EditorCommand, parse_lib::Location)>,
+	value: Option<(EditorCommand, parse_lib::Location, Priority)>,
+	// priority of subsequently eaten values
+	priority: Priority,
+	merge_policy: MergePolicy,
+	dirs: Arc<dyn Dirs>,
 }
 impl EditorCommandOption {
-	fn new() -> Self {
-		Self { value: None }
+	fn new(dirs: Arc<dyn Dirs>) -> Self {
+		Self {
+			value: None,
+			priority: Priority::Normal,
+			merge_policy: MergePolicy::default(),
+			dirs,
+		}
 	}
 }
 impl parse_lib::ConfigOption for EditorCommandOption {
@@ -287,37 +1859,74 @@ impl parse_lib::ConfigOption for EditorCommandOption {
 		if key.name() != "editor" {
 			return Ok(false);
 		}
-		if let Some((_, prev_loc)) = &self.value {
-			return Err(diagnostics::multiple_definitions(key.loc(), prev_loc, "editor").into());
+		match &self.value {
+			Some((_, _, prev_prio)) if prev_prio > &self.priority => return Ok(true),
+			Some((_, prev_loc, prev_prio)) if prev_prio == &self.priority => {
+				let is_replaced = MergePolicy::resolve(Some(self.merge_policy), || {
+					diagnostics::multiple_definitions(prev_loc, key.loc(), "editor", self.priority)
+				})?;
+				if !is_replaced {
+					return Ok(true);
+				}
+			}
+			_ => (),
 		}
 		let table = value.as_table()?;
 
-		let mut cmd_with_file = ArrayOption::new("cmd-with-file", false, |raw_value| {
-			let value = raw_value.as_str()?;
-			path::substitute_placeholder(value, true)
-				.map_err(|err| diagnostics::failed_canonicalization(raw_value, &err).into())
-		});
-		let mut cmd_without_file = ArrayOption::new("cmd-without-file", false, |raw_value| {
-			let value = raw_value.as_str()?;
-			path::substitute_placeholder(value, false)
-				.map_err(|err| diagnostics::failed_canonicalization(raw_value, &err).into())
-		});
+		let substitute_placeholder = |late_vars: &'static [LateVariable]| {
+			let dirs = self.dirs.clone();
+			move |raw_value: &TomlValue| {
+				let value = raw_value.as_str()?;
+				path::substitute_placeholder(value, late_vars, &*dirs)
+					.map_err(|err| diagnostics::failed_canonicalization(raw_value, &err).into())
+			}
+		};
+		let mut cmd_with_file = ArrayOption::new(
+			"cmd-with-file",
+			false,
+			substitute_placeholder(&[LateVariable::File, LateVariable::ProjectState]),
+		);
+		let mut cmd_without_file = ArrayOption::new(
+			"cmd-without-file",
+			false,
+			substitute_placeholder(&[LateVariable::ProjectState]),
+		);
 		let mut detach = BoolOption::new("detach");
+		let mut flatpak = StringOption::new("flatpak");
+		let mut working_dir = BaseOption::new(
+			"working-dir",
+			substitute_placeholder(&[LateVariable::ProjectState]),
+		);
 
 		let docs_pref = "project-data-format";
 		parse_lib::parse_table!(
-			&table => [cmd_with_file, cmd_without_file, detach],
+			&table => [cmd_with_file, cmd_without_file, detach, flatpak, working_dir],
 			docs-pref: docs_pref,
 		)?;
+		let detach = detach
+			.get_value()
+			.ok_or_else(|| diagnostics::missing_option(key.loc(), "detach", docs_pref))?;
+		let working_dir = working_dir.get_value();
+		// NOTE: for flatpak apps, the commands are only the (optional) arguments
+		if let Some(flatpak) = flatpak.get_value() {
+			let editor_cmd = EditorCommand {
+				cmd_with_file: cmd_with_file
+					.get_value()
+					.unwrap_or_else(|| vec!["$(FILE)".to_string()]),
+				cmd_without_file: cmd_without_file.get_value().unwrap_or_default(),
+				detach,
+				flatpak: Some(flatpak),
+				working_dir,
+			};
+			self.value = Some((editor_cmd, key.loc().clone(), self.priority));
+			return Ok(true);
+		}
 		let cmd_with_file = cmd_with_file
 			.get_value_with_loc()
 			.ok_or_else(|| diagnostics::missing_option(key.loc(), "cmd-with-file", docs_pref))?;
 		let cmd_without_file = cmd_without_file
 			.get_value_with_loc()
 			.ok_or_else(|| diagnostics::missing_option(key.loc(), "cmd-without-file", docs_pref))?;
-		let detach = detach
-			.get_value()
-			.ok_or_else(|| diagnostics::missing_option(key.loc(), "detach", docs_pref))?;
 
 		let diagnostics_empty_command = |loc: parse_lib::Location| {
 			let label = loc
@@ -338,8 +1947,10 @@ impl parse_lib::ConfigOption for EditorCommandOption {
 			cmd_with_file: cmd_with_file.0,
 			cmd_without_file: cmd_without_file.0,
 			detach,
+			flatpak: None,
+			working_dir,
 		};
-		self.value = Some((editor_cmd, key.loc().clone()));
+		self.value = Some((editor_cmd, key.loc().clone(), self.priority));
 		Ok(true)
 	}
 }
@@ -0,0 +1,31 @@
+// helpers for writing toml, shared by the subcommands that edit files
+use std::path::Path;
+
+pub fn toml_string_escape(str: &str) -> String {
+	let escaped_str = str
+		.chars()
+		.map(|char| match char {
+			'\x08' => "\\b".to_string(),
+			'\t' => "\\t".to_string(),
+			'\n' => "\\n".to_string(),
+			'\x0c' => "\\f".to_string(),
+			'\r' => "\\r".to_string(),
+			'\\' => "\\\\".to_string(),
+			'"' => "\\\"".to_string(),
+			ch if ch.is_ascii_graphic() || ch == ' ' => ch.to_string(),
+			ch => format!("\\U{:08X}", ch as u32),
+		})
+		.collect::<String>();
+	format!("\"{escaped_str}\"")
+}
+pub fn toml_path(path: &Path) -> String {
+	toml_string_escape(&path.to_string_lossy())
+}
+pub fn toml_string_array(strings: &[String]) -> String {
+	let strings = strings
+		.iter()
+		.map(|str| toml_string_escape(str))
+		.collect::<Vec<_>>()
+		.join(", ");
+	format!("[{strings}]")
+}
@@ -1,41 +1,207 @@
 use std::{
+	env,
 	error::Error,
+	fs,
 	path::{Path, PathBuf},
 	process::ExitCode,
 };
 
-use crate::sandbox::{Command, SandboxParameters};
+use crate::{
+	container::ContainerParameters,
+	dirs::Dirs,
+	error::{exit_code, ExitError, WithExitCode as _},
+	open_stats, running,
+	sandbox::{
+		Command, EnvVarWhitelist, LaunchPlan, LogConfig, ResourceLimits, SandboxFallback,
+		SandboxParameters, SeccompProfile, VirtualFSEntryType,
+	},
+};
 
 #[derive(Clone)]
 pub struct ProjectData {
+	pub name: String,
+	// key in the state databases, see 'ProjectDataFuture::state_key'
+	pub state_key: String,
 	pub project_dir: PathBuf,
-	pub initial_file: Option<String>,
+	// NOTE: empty if the project is opened without a file
+	pub initial_files: Vec<String>,
 	pub editor: EditorCommand,
 	pub auto_nixshell: bool,
 	pub sandbox_params: SandboxParameters,
 	pub disable_sandbox: bool,
+	// what happens if bwrap is not available
+	pub sandbox_fallback: SandboxFallback,
+	// run the editor in a container instead of the bwrap sandbox
+	pub container: Option<ContainerParameters>,
+	// ssh destination (e.g. 'user@host') where the editor is run,
+	// 'project_dir' is then a path on the remote host
+	pub remote: Option<String>,
+	// terminal emulator command, in whose new window the editor is run
+	pub terminal_cmd: Option<Vec<String>>,
+	// files the project data has been read from
+	pub source_files: Vec<PathBuf>,
+	// commands offered in the action menu of the project
+	pub actions: Vec<ProjectAction>,
+	// NOTE: not applied to remote projects
+	pub limits: ResourceLimits,
+	// whether the editor is run in a systemd scope named after the project
+	pub systemd_scope: bool,
+	pub reopen_policy: ReopenPolicy,
+}
+// behavior when a project is opened that is already running
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReopenPolicy {
+	Allow,
+	#[default]
+	Warn,
+	Refuse,
+}
+#[derive(Clone, Debug)]
+pub struct ProjectAction {
+	pub name: String,
+	pub command: Vec<String>,
+	// run inside the sandbox of the project
+	pub sandbox: bool,
 }
 #[derive(Clone, Debug)]
 pub struct EditorCommand {
 	pub cmd_with_file: Vec<String>,
 	pub cmd_without_file: Vec<String>,
 	pub detach: bool,
+	// id of a flatpak app (e.g. 'org.gnome.Builder'), that is run with
+	// the commands as arguments instead of inside the sandbox
+	pub flatpak: Option<String>,
+	// directory the editor is started in, relative to the project directory
+	// (default: the project directory)
+	pub working_dir: Option<String>,
 }
 
 impl ProjectData {
-	pub fn open(mut self) -> Result<ExitCode, Box<dyn Error>> {
+	pub fn open(self, log_config: &LogConfig, dirs: &dyn Dirs) -> Result<ExitCode, ExitError> {
+		let running_pids = running::find_running(&self.name, dirs);
+		if let Some(pid) = running_pids.first() {
+			match self.reopen_policy {
+				ReopenPolicy::Allow => (),
+				ReopenPolicy::Warn => eprintln!(
+					"Warning: The project `{}` is already open (pid {pid}).",
+					self.name
+				),
+				ReopenPolicy::Refuse => {
+					let err = format!(
+						concat!(
+							"The project `{}` is already open (pid {}).",
+							"\n  NOTE: Opening it again can be allowed with `reopen-policy` in the configuration."
+						),
+						self.name, pid
+					);
+					return Err(err).with_exit_code(exit_code::PROJECT_ERROR);
+				}
+			}
+		}
+		let state_key = self.state_key.clone();
+		let mut plan = self
+			.plan_launch(dirs)
+			.with_exit_code(exit_code::PROJECT_ERROR)?;
+		plan.track_running = true;
+		// NOTE: the project is opened nevertheless
+		if let Err(err) = open_stats::record_open(&state_key, &plan.name, dirs) {
+			eprintln!("{err}");
+		}
+		plan
+			.execute(log_config, dirs)
+			.with_exit_code(exit_code::LAUNCH_ERROR)
+	}
+	// run 'cmd' instead of the editor, inside of the sandbox if 'sandboxed'
+	// NOTE: the open is not recorded, as the project is not edited
+	pub fn run_command(
+		mut self,
+		cmd: Vec<String>,
+		detach: bool,
+		sandboxed: bool,
+		log_config: &LogConfig,
+		dirs: &dyn Dirs,
+	) -> Result<ExitCode, ExitError> {
+		self.editor = EditorCommand {
+			cmd_with_file: Vec::new(),
+			cmd_without_file: cmd,
+			detach,
+			flatpak: None,
+			working_dir: None,
+		};
+		self.initial_files = Vec::new();
+		self.terminal_cmd = None;
+		if !sandboxed {
+			self.disable_sandbox = true;
+			self.container = None;
+		}
+		self
+			.plan_launch(dirs)
+			.with_exit_code(exit_code::PROJECT_ERROR)?
+			.execute(log_config, dirs)
+			.with_exit_code(exit_code::LAUNCH_ERROR)
+	}
+	// NOTE: required directories (e.g. the project state directory) are created
+	pub fn plan_launch(mut self, dirs: &dyn Dirs) -> Result<LaunchPlan, Box<dyn Error>> {
+		if let Some(remote) = self.remote.take() {
+			return self.plan_remote_launch(&remote, dirs);
+		}
+
 		// NOTE: if the user gives the project directory higher permsission
 		//       or tmpfs/symlinks it, 'add_path' returns an error,
 		//       but it should be ignored
-		_ = self.sandbox_params.fs_tree.add_path(
-			&self.project_dir,
-			crate::sandbox::VirtualFSEntryType::ReadWrite,
-			(),
+		let project_dir_ty = self.sandbox_params.fs_tree.lookup(&self.project_dir);
+		if project_dir_ty != Some(VirtualFSEntryType::CopyOnWrite) {
+			_ =
+				self
+					.sandbox_params
+					.fs_tree
+					.add_path(&self.project_dir, VirtualFSEntryType::ReadWrite, ());
+		}
+		let skeld_state_dir = dirs
+			.get_skeld_state_dir()
+			.map_err(|err| format!("Failed to determine the skeld state directory:\n  {err}"))?;
+		let overlay_dir = skeld_state_dir
+			.join("overlays")
+			.join(escape_path_component(&self.name));
+		self.sandbox_params.overlay_dir = Some(overlay_dir);
+
+		let project_state_dir = create_project_state_dir(&skeld_state_dir, &self.name)?;
+		// NOTE: errors are ignored for the same reason as above
+		_ = self
+			.sandbox_params
+			.fs_tree
+			.add_path(&project_state_dir, VirtualFSEntryType::ReadWrite, ());
+
+		let is_flatpak = self.editor.flatpak.is_some();
+		let uses_bwrap = !self.disable_sandbox && self.container.is_none() && !is_flatpak;
+		if uses_bwrap
+			&& self.sandbox_fallback == SandboxFallback::WarnAndRunUnsandboxed
+			&& !SandboxParameters::is_available()
+		{
+			eprintln!(
+				"Warning: Bubblewrap (`bwrap`) is not usable, the project `{}` is opened without a sandbox.",
+				self.name
+			);
+			self.disable_sandbox = true;
+		}
+		let mut project_cmd = self.editor.get_command(
+			self.name.clone(),
+			self.project_dir.clone(),
+			self.initial_files,
+			&project_state_dir,
 		);
+		project_cmd.terminal = self.terminal_cmd;
+		// NOTE: bwrap does not work inside of flatpak,
+		//       so flatpak itself is used as sandbox
+		if is_flatpak {
+			let project_cmd = add_flatpak_permissions(project_cmd, &self.sandbox_params)?;
+			return Ok(
+				project_cmd
+					.plan()
+					.with_scope(&self.limits, self.systemd_scope, dirs),
+			);
+		}
 
-		let project_cmd = self
-			.editor
-			.get_command(self.project_dir.clone(), self.initial_file);
 		let use_nix_shell = self.auto_nixshell && detect_nix_shell_file(&self.project_dir);
 		let project_cmd = if use_nix_shell {
 			wrap_cmd_with_nix_shell(project_cmd)
@@ -43,31 +209,233 @@ impl ProjectData {
 			project_cmd
 		};
 
-		if self.disable_sandbox {
-			project_cmd.run()
+		let plan = if self.disable_sandbox {
+			project_cmd.plan()
+		} else if let Some(container) = &self.container {
+			container.plan_cmd(&self.sandbox_params, project_cmd, dirs)?
 		} else {
-			self.sandbox_params.run_cmd(project_cmd)
+			self.sandbox_params.plan_cmd(project_cmd, dirs)?
+		};
+		Ok(plan.with_scope(&self.limits, self.systemd_scope, dirs))
+	}
+	// NOTE: the editor runs on the remote host without a sandbox,
+	//       so only the ssh process is started locally
+	fn plan_remote_launch(self, remote: &str, dirs: &dyn Dirs) -> Result<LaunchPlan, Box<dyn Error>> {
+		let uses_project_state = self
+			.editor
+			.cmd_with_file
+			.iter()
+			.chain(&self.editor.cmd_without_file)
+			.chain(&self.editor.working_dir)
+			.any(|arg| arg.contains("$(PROJECT_STATE)"));
+		if uses_project_state {
+			return Err(
+				"The placeholder `$(PROJECT_STATE)` is not supported for remote projects.".into(),
+			);
 		}
+		let home_dir = dirs
+			.get_home_dir()
+			.map_err(|err| format!("Failed to determine the home directory:\n  {err}"))?;
+
+		let mut remote_cmd =
+			self
+				.editor
+				.get_command(self.name, self.project_dir.clone(), self.initial_files, "");
+		remote_cmd.terminal = self.terminal_cmd;
+		let escaped_cmd = remote_cmd.cmd.iter().map(posix_string_escape);
+		let remote_shell_cmd = format!(
+			"cd {} && exec {}",
+			posix_string_escape(remote_cmd.working_dir.to_string_lossy()),
+			escaped_cmd.collect::<Vec<_>>().join(" ")
+		);
+		// NOTE: a terminal is only allocated for editors running in it
+		let tty_arg = if remote_cmd.detach { "-T" } else { "-t" };
+		let ssh_cmd = Command {
+			cmd: vec![
+				"ssh".to_string(),
+				tty_arg.to_string(),
+				"--".to_string(),
+				remote.to_string(),
+				remote_shell_cmd,
+			],
+			working_dir: home_dir,
+			..remote_cmd
+		};
+		Ok(ssh_cmd.plan())
 	}
 }
 impl EditorCommand {
-	fn get_command(self, working_dir: PathBuf, initial_file: Option<String>) -> Command {
-		let command = if let Some(initial_file) = initial_file {
+	// NOTE: the editor is started in 'project_dir', unless 'working_dir' is set
+	fn get_command(
+		self,
+		name: String,
+		project_dir: PathBuf,
+		initial_files: Vec<String>,
+		project_state_dir: &str,
+	) -> Command {
+		let project_dir_str = project_dir.to_string_lossy().into_owned();
+		let placeholders = [
+			("$(PROJECT_DIR)", project_dir_str.as_str()),
+			("$(PROJECT_NAME)", name.as_str()),
+			("$(PROJECT_STATE)", project_state_dir),
+		];
+		let working_dir = match &self.working_dir {
+			Some(dir) => project_dir.join(replace_placeholders(dir, &placeholders)),
+			None => project_dir,
+		};
+		let command = if !initial_files.is_empty() {
+			// NOTE: arguments containing '$(FILE)' are repeated for each file
 			self
 				.cmd_with_file
 				.into_iter()
-				.map(|arg| arg.replace("$(FILE)", &initial_file))
+				.flat_map(|arg| {
+					if arg.contains("$(FILE)") {
+						initial_files
+							.iter()
+							.map(|file| {
+								let [dir, name, state] = placeholders;
+								replace_placeholders(&arg, &[dir, name, state, ("$(FILE)", file)])
+							})
+							.collect()
+					} else {
+						vec![replace_placeholders(&arg, &placeholders)]
+					}
+				})
 				.collect()
 		} else {
-			self.cmd_without_file.into_iter().map(Into::into).collect()
+			self
+				.cmd_without_file
+				.into_iter()
+				.map(|arg| replace_placeholders(&arg, &placeholders))
+				.collect()
+		};
+
+		let command = match self.flatpak {
+			Some(app_id) => ["flatpak".to_string(), "run".to_string(), app_id]
+				.into_iter()
+				.chain(command)
+				.collect(),
+			None => command,
 		};
 
 		Command {
+			name,
 			cmd: command,
 			working_dir,
 			detach: self.detach,
+			terminal: None,
+		}
+	}
+}
+// persistent directory for caches etc. of sandboxed tools,
+// that is always accessible inside the sandbox
+fn create_project_state_dir(skeld_state_dir: &Path, project_name: &str) -> Result<String, String> {
+	let project_state_dir = skeld_state_dir
+		.join("projects")
+		.join(escape_path_component(project_name));
+	fs::create_dir_all(&project_state_dir).map_err(|err| {
+		format!(
+			"Failed to create the project state directory `{}`:\n  {err}",
+			project_state_dir.display()
+		)
+	})?;
+	let project_state_dir = project_state_dir
+		.into_os_string()
+		.into_string()
+		.map_err(|dir| {
+			format!(
+				"The project state directory path contains invalid UTF-8: `{}`",
+				dir.to_string_lossy()
+			)
+		})?;
+	Ok(project_state_dir)
+}
+// restrict the flatpak app of 'cmd' to the whitelisted paths and environment variables
+// NOTE: flatpak cannot mount paths at a different location, so binds are
+//       rejected, and copy-on-write paths are only readable
+fn add_flatpak_permissions(
+	cmd: Command,
+	sandbox_params: &SandboxParameters,
+) -> Result<Command, String> {
+	// NOTE: options that flatpak cannot enforce are rejected,
+	//       instead of opening the project with a weaker sandbox
+	if sandbox_params.private_home.is_some() {
+		return Err("The option `private-home` is not supported for flatpak editors.".to_string());
+	}
+	if matches!(
+		sandbox_params.seccomp_profile,
+		SeccompProfile::Strict | SeccompProfile::AllowList
+	) {
+		return Err(format!(
+			"The seccomp profile `{}` is not supported for flatpak editors.",
+			sandbox_params.seccomp_profile.name()
+		));
+	}
+
+	// NOTE: the filesystem permissions of the app manifest are revoked,
+	//       so that only the whitelisted paths are accessible
+	let mut permission_args = ["host", "host-os", "host-etc", "home"]
+		.into_iter()
+		.map(|filesystem| format!("--nofilesystem={filesystem}"))
+		.collect::<Vec<_>>();
+	for (path, ty, source) in sandbox_params.fs_tree.flatten() {
+		if source.is_some() {
+			return Err(format!(
+				"The bind mount at `{}` is not supported for flatpak editors.",
+				path.display()
+			));
 		}
+		let path = path.to_string_lossy();
+		let arg = match ty {
+			VirtualFSEntryType::AllowDev | VirtualFSEntryType::ReadWrite => {
+				format!("--filesystem={path}")
+			}
+			VirtualFSEntryType::CopyOnWrite | VirtualFSEntryType::ReadOnly => {
+				format!("--filesystem={path}:ro")
+			}
+			VirtualFSEntryType::Deny => format!("--nofilesystem={path}"),
+			VirtualFSEntryType::Tmpfs | VirtualFSEntryType::Symlink => continue,
+		};
+		permission_args.push(arg);
+	}
+	// NOTE: the app inherits the environment of 'flatpak run'
+	if let EnvVarWhitelist::List(list) = &sandbox_params.envvar_whitelist {
+		for (name, _) in env::vars_os() {
+			if !list.contains(&name) {
+				permission_args.push(format!("--unset-env={}", name.to_string_lossy()));
+			}
+		}
+	}
+
+	// NOTE: the options have to be inserted before the app id
+	let mut cmd_args = cmd.cmd.into_iter();
+	let flatpak_run = cmd_args.by_ref().take(2).collect::<Vec<_>>();
+	let flatpak_cmd = flatpak_run
+		.into_iter()
+		.chain(permission_args)
+		.chain(cmd_args)
+		.collect();
+	Ok(Command {
+		cmd: flatpak_cmd,
+		..cmd
+	})
+}
+// replace all 'placeholders' in a single pass,
+// so that the substituted values are not searched for placeholders again
+fn replace_placeholders(str: &str, placeholders: &[(&str, &str)]) -> String {
+	let mut result = String::new();
+	let mut rest = str;
+	while let Some((pos, placeholder, value)) = placeholders
+		.iter()
+		.filter_map(|(placeholder, value)| Some((rest.find(placeholder)?, placeholder, value)))
+		.min_by_key(|(pos, ..)| *pos)
+	{
+		result += &rest[..pos];
+		result += value;
+		rest = &rest[pos + placeholder.len()..];
 	}
+	result += rest;
+	result
 }
 fn detect_nix_shell_file(project_path: impl AsRef<Path>) -> bool {
 	let project_path = project_path.as_ref();
@@ -89,3 +457,106 @@ fn wrap_cmd_with_nix_shell(cmd: Command) -> Command {
 fn bash_string_escape(str: impl Into<String>) -> String {
 	format!("$'{}'", str.into().as_bytes().escape_ascii())
 }
+// NOTE: the login shell of a remote host is not necessarily bash
+fn posix_string_escape(str: impl Into<String>) -> String {
+	format!("'{}'", str.into().replace('\'', r"'\''"))
+}
+// make 'str' usable as a single path component
+pub fn escape_path_component(str: &str) -> String {
+	match str {
+		"." | ".." => str.replace('.', "_"),
+		_ => str.replace('/', "_"),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn strings(strs: &[&str]) -> Vec<String> {
+		strs.iter().map(|str| str.to_string()).collect()
+	}
+
+	#[test]
+	fn replace_placeholders_substitutes_all_occurrences() {
+		let placeholders = [("$(A)", "1"), ("$(B)", "22")];
+		assert_eq!(
+			replace_placeholders("$(B)-$(A)/$(A)", &placeholders),
+			"22-1/1"
+		);
+		assert_eq!(
+			replace_placeholders("no placeholders", &placeholders),
+			"no placeholders"
+		);
+		assert_eq!(replace_placeholders("$(C) $(A", &placeholders), "$(C) $(A");
+	}
+
+	#[test]
+	fn replace_placeholders_ignores_placeholders_in_values() {
+		let placeholders = [
+			("$(FILE)", "$(PROJECT_NAME).txt"),
+			("$(PROJECT_NAME)", "demo"),
+		];
+		assert_eq!(
+			replace_placeholders("$(FILE) $(PROJECT_NAME)", &placeholders),
+			"$(PROJECT_NAME).txt demo"
+		);
+		let placeholders = [("$(PROJECT_NAME)", "$(PROJECT_NAME)$(PROJECT_NAME)")];
+		assert_eq!(
+			replace_placeholders("$(PROJECT_NAME)", &placeholders),
+			"$(PROJECT_NAME)$(PROJECT_NAME)"
+		);
+	}
+
+	#[test]
+	fn editor_command_substitutes_placeholders() {
+		let editor = EditorCommand {
+			cmd_with_file: strings(&["edit", "--state=$(PROJECT_STATE)", "$(PROJECT_DIR)/$(FILE)"]),
+			cmd_without_file: Vec::new(),
+			detach: false,
+			flatpak: None,
+			working_dir: Some("$(PROJECT_NAME)".to_string()),
+		};
+		let command = editor.get_command(
+			"demo".to_string(),
+			PathBuf::from("/projects/$(PROJECT_NAME)"),
+			strings(&["a.rs", "$(PROJECT_STATE).rs"]),
+			"/state/demo",
+		);
+		assert_eq!(
+			command.cmd,
+			strings(&[
+				"edit",
+				"--state=/state/demo",
+				"/projects/$(PROJECT_NAME)/a.rs",
+				"/projects/$(PROJECT_NAME)/$(PROJECT_STATE).rs",
+			])
+		);
+		assert_eq!(
+			command.working_dir,
+			PathBuf::from("/projects/$(PROJECT_NAME)/demo")
+		);
+	}
+
+	#[test]
+	fn editor_command_without_files() {
+		let editor = EditorCommand {
+			cmd_with_file: strings(&["edit", "$(FILE)"]),
+			cmd_without_file: strings(&["run", "$(PROJECT_NAME)", "$(PROJECT_DIR)", "$(FILE)"]),
+			detach: false,
+			flatpak: None,
+			working_dir: None,
+		};
+		let command = editor.get_command(
+			"demo".to_string(),
+			PathBuf::from("/projects/demo"),
+			Vec::new(),
+			"/state/demo",
+		);
+		assert_eq!(
+			command.cmd,
+			strings(&["run", "demo", "/projects/demo", "$(FILE)"])
+		);
+		assert_eq!(command.working_dir, PathBuf::from("/projects/demo"));
+	}
+}
@@ -0,0 +1,279 @@
+use std::{
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+use toml_span::value::ValueInner;
+
+use crate::{
+	add_subcommand, atomic_file,
+	dirs::Dirs,
+	lock::{FileLock, DATA_LOCK},
+	parse::{self, toml_util::toml_string_escape},
+	trust, GenericResult, RenameArgs,
+};
+
+pub fn run(args: RenameArgs, dirs: &dyn Dirs) -> GenericResult<()> {
+	if args.new_name.is_empty() || args.new_name.contains('/') || args.new_name.starts_with('.') {
+		return Err(format!("Invalid name `{}`", args.new_name).into());
+	}
+
+	// NOTE: system-wide files are not renamed
+	let user_dirs = [dirs.get_skeld_config_dir(), dirs.get_skeld_data_dir()]
+		.into_iter()
+		.collect::<Result<Vec<_>, _>>()
+		.map_err(|err| format!("Failed to determine the skeld data directories:\n  {err}"))?;
+
+	let _data_lock = FileLock::acquire(DATA_LOCK, dirs)?;
+	let (old_path, is_bookmark) = find_file(&user_dirs, &args.old_name)?;
+	let new_path = old_path
+		.with_file_name(&args.new_name)
+		.with_extension("toml");
+
+	// NOTE: the old file is removed after the references have been updated,
+	//       so that they never reference a missing file
+	fs::hard_link(&old_path, &new_path).map_err(|err| match err.kind() {
+		io::ErrorKind::AlreadyExists => {
+			format!("The file `{}` already exists", new_path.display())
+		}
+		_ => format!(
+			"Failed to rename `{}` to `{}`:\n  {err}",
+			old_path.display(),
+			new_path.display()
+		),
+	})?;
+
+	if is_bookmark {
+		let contents = read_file(&new_path)?;
+		let value = parse_file(&new_path, &contents)?;
+		let name_span = value
+			.as_table()
+			.and_then(|table| table.get("name"))
+			.filter(|name| name.as_str() == Some(&args.old_name))
+			.map(|name| name.span);
+		if let Some(span) = name_span {
+			let contents = replace_strings(&contents, &[(span.start..span.end, &args.new_name)]);
+			write_file(&new_path, &contents)?;
+			println!("Updated the name in `{}`", new_path.display());
+		}
+	}
+
+	update_references(&user_dirs, &old_path, &new_path, dirs)?;
+	trust::move_import_mark(&old_path, &new_path, dirs)?;
+	fs::remove_file(&old_path)
+		.map_err(|err| format!("Failed to remove `{}`:\n  {err}", old_path.display()))?;
+	println!(
+		"Renamed `{}` to `{}`",
+		old_path.display(),
+		new_path.display()
+	);
+	Ok(())
+}
+
+// returns the path of the project or bookmark file and whether it is a bookmark
+fn find_file(user_dirs: &[PathBuf], name: &str) -> GenericResult<(PathBuf, bool)> {
+	for user_dir in user_dirs {
+		let project_file = user_dir.join("projects").join(format!("{name}.toml"));
+		if project_file.is_file() {
+			return Ok((project_file, false));
+		}
+	}
+	for user_dir in user_dirs {
+		for bookmark_file in get_toml_files(&user_dir.join("bookmarks")) {
+			// NOTE: unrelated invalid bookmarks are reported by the parser when they are used
+			let Ok(contents) = fs::read_to_string(&bookmark_file) else {
+				println!(
+					"Skipped `{}`, because it cannot be read",
+					bookmark_file.display()
+				);
+				continue;
+			};
+			let Ok(value) = toml_span::parse(&contents) else {
+				println!(
+					"Skipped `{}`, because it is not valid toml",
+					bookmark_file.display()
+				);
+				continue;
+			};
+			let bookmark_name = value
+				.as_table()
+				.and_then(|table| table.get("name"))
+				.and_then(|name| name.as_str());
+			if bookmark_name == Some(name) {
+				return Ok((bookmark_file, true));
+			}
+		}
+	}
+	Err(format!("Unknown project or bookmark `{name}`").into())
+}
+
+// update include entries referencing 'old_path'
+fn update_references(
+	user_dirs: &[PathBuf],
+	old_path: &Path,
+	new_path: &Path,
+	dirs: &dyn Dirs,
+) -> GenericResult<()> {
+	let mut files = Vec::new();
+	for user_dir in user_dirs {
+		let config_file = user_dir.join("config.toml");
+		if config_file.is_file() {
+			files.push(config_file);
+		}
+		for subdir in ["projects", "bookmarks", "include"] {
+			files.append(&mut get_toml_files(&user_dir.join(subdir)));
+		}
+	}
+
+	let old_file_name = old_path.file_name().unwrap().to_string_lossy();
+	let new_file_name = new_path.file_name().unwrap().to_string_lossy();
+	for file in files {
+		let contents = read_file(&file)?;
+		let Ok(value) = toml_span::parse(&contents) else {
+			// NOTE: the file is reported by the parser when it is used
+			println!("Skipped `{}`, because it is not valid toml", file.display());
+			continue;
+		};
+
+		let mut include_entries = Vec::new();
+		find_include_entries(&value, &mut include_entries);
+		let replacements = include_entries
+			.into_iter()
+			.filter(|(_, include)| {
+				parse::canonicalize_include_path(*include, dirs).is_ok_and(|path| path == old_path)
+			})
+			.map(|(span, include)| {
+				// NOTE: the form of the path (e.g. placeholders) is preserved if possible
+				let new_include = match include.strip_suffix(old_file_name.as_ref()) {
+					Some(dir) => format!("{dir}{new_file_name}"),
+					None => add_subcommand::normalize_path_prefix(new_path, dirs)
+						.to_string_lossy()
+						.into_owned(),
+				};
+				(span, new_include)
+			})
+			.collect::<Vec<_>>();
+		if replacements.is_empty() {
+			continue;
+		}
+
+		for (span, _) in &replacements {
+			let line = contents[..span.start].lines().count().max(1);
+			println!("Updated the include in `{}:{line}`", file.display());
+		}
+		let replacements = replacements
+			.iter()
+			.map(|(span, new)| (span.clone(), new.as_str()))
+			.collect::<Vec<_>>();
+		write_file(&file, &replace_strings(&contents, &replacements))?;
+	}
+	Ok(())
+}
+// find_include_entries(.., entries: Vec<(span, path)>)
+pub fn find_include_entries<'a>(
+	value: &'a toml_span::Value<'a>,
+	entries: &mut Vec<(std::ops::Range<usize>, &'a str)>,
+) {
+	match value.as_ref() {
+		ValueInner::Table(table) => {
+			for (key, value) in table {
+				if key.name != "include" {
+					find_include_entries(value, entries);
+					continue;
+				}
+				let includes = match value.as_ref() {
+					ValueInner::Array(array) => array.iter().collect(),
+					_ => vec![value],
+				};
+				for include in includes {
+					if let Some(path) = include.as_str() {
+						entries.push((include.span.start..include.span.end, path));
+					}
+				}
+			}
+		}
+		ValueInner::Array(array) => {
+			for value in array {
+				find_include_entries(value, entries);
+			}
+		}
+		_ => (),
+	}
+}
+// replace the toml strings at the given spans
+// NOTE: the spans of toml strings only cover their contents, so the
+//       delimiters (and thereby the kind of the string) are preserved
+pub fn replace_strings(contents: &str, replacements: &[(std::ops::Range<usize>, &str)]) -> String {
+	let mut replacements = replacements.to_vec();
+	replacements.sort_by_key(|(span, _)| span.start);
+
+	let mut new_contents = String::new();
+	let mut last_end = 0;
+	for (span, new) in replacements {
+		let (span, new) = encode_string_contents(contents, span, new);
+		new_contents.push_str(&contents[last_end..span.start]);
+		new_contents.push_str(&new);
+		last_end = span.end;
+	}
+	new_contents.push_str(&contents[last_end..]);
+	new_contents
+}
+// returns the span to replace and its replacement for setting the string
+// with the contents 'span' to 'new'
+fn encode_string_contents(
+	contents: &str,
+	span: std::ops::Range<usize>,
+	new: &str,
+) -> (std::ops::Range<usize>, String) {
+	// NOTE: a newline directly after the opening delimiter
+	//       of a multi-line string is not part of its contents
+	let prefix = contents[..span.start]
+		.strip_suffix('\n')
+		.map(|prefix| prefix.strip_suffix('\r').unwrap_or(prefix))
+		.unwrap_or(&contents[..span.start]);
+	let delimiter = ["'''", "\"\"\"", "'", "\""]
+		.into_iter()
+		.find(|delimiter| prefix.ends_with(delimiter))
+		.expect("toml strings are delimited by quotes");
+	let escaped_str = toml_string_escape(new);
+
+	let is_representable = |forbidden: &[&str]| {
+		let has_control_chars = new.chars().any(|ch| ch.is_control() && ch != '\t');
+		!has_control_chars && !forbidden.iter().any(|str| new.contains(str))
+	};
+	match delimiter {
+		"'" if is_representable(&["'"]) => (span, new.to_string()),
+		"'''" if is_representable(&["'''"]) => (span, new.to_string()),
+		// the contents of basic strings are escaped like the whole string
+		"\"" | "\"\"\"" => (span, escaped_str[1..escaped_str.len() - 1].to_string()),
+		// literal strings that cannot contain 'new' are replaced by basic strings
+		_ => (
+			prefix.len() - delimiter.len()..span.end + delimiter.len(),
+			escaped_str,
+		),
+	}
+}
+
+pub fn get_toml_files(dir: &Path) -> Vec<PathBuf> {
+	let Ok(dir_iter) = fs::read_dir(dir) else {
+		return Vec::new();
+	};
+	let mut files = dir_iter
+		.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+		.filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "toml"))
+		.collect::<Vec<_>>();
+	files.sort();
+	files
+}
+fn read_file(path: &Path) -> GenericResult<String> {
+	fs::read_to_string(path)
+		.map_err(|err| format!("Failed to read file `{}`: {err}", path.display()).into())
+}
+fn write_file(path: &Path, contents: &str) -> GenericResult<()> {
+	atomic_file::write(path, contents)
+		.map_err(|err| format!("Failed to write file `{}`: {err}", path.display()).into())
+}
+fn parse_file<'a>(path: &Path, contents: &'a str) -> GenericResult<toml_span::Value<'a>> {
+	toml_span::parse(contents)
+		.map_err(|err| format!("Failed to parse `{}`: {err}", path.display()).into())
+}
@@ -0,0 +1,93 @@
+use std::{fs, path::PathBuf, thread, time::Duration};
+
+use nix::{
+	errno::Errno,
+	sys::signal::{self, Signal},
+	unistd::Pid,
+};
+
+use crate::{dirs::Dirs, project::escape_path_component};
+
+// marks a process as a running launch of a project, until this is dropped
+// NOTE: each process is tracked with a file `<SKELD-RUNTIME>/running/<name>/<pid>`,
+//       so that multiple instances of the same project can be tracked
+pub struct RunningGuard {
+	path: PathBuf,
+}
+impl RunningGuard {
+	// NOTE: failures are ignored, as the tracking is only informational
+	pub fn register(name: &str, pid: u32, dirs: &dyn Dirs) -> Option<Self> {
+		let path = get_running_dir(name, dirs)?.join(pid.to_string());
+		fs::create_dir_all(path.parent()?).ok()?;
+		fs::write(&path, "").ok()?;
+		Some(Self { path })
+	}
+}
+impl Drop for RunningGuard {
+	fn drop(&mut self) {
+		let _ = fs::remove_file(&self.path);
+		// NOTE: fails if other instances are still running
+		let _ = fs::remove_dir(self.path.parent().unwrap());
+	}
+}
+
+// the pids of the running launches of the project 'name'
+// NOTE: stale entries (e.g. if skeld has been killed) are removed,
+//       but a reused pid may be mistaken for a running launch
+pub fn find_running(name: &str, dirs: &dyn Dirs) -> Vec<u32> {
+	let Some(dir) = get_running_dir(name, dirs) else {
+		return Vec::new();
+	};
+	let Ok(entries) = fs::read_dir(&dir) else {
+		return Vec::new();
+	};
+	let pids = entries
+		.filter_map(Result::ok)
+		.filter_map(|entry| {
+			let pid = entry.file_name().to_str()?.parse().ok()?;
+			if is_alive(pid) {
+				Some(pid)
+			} else {
+				let _ = fs::remove_file(entry.path());
+				None
+			}
+		})
+		.collect::<Vec<_>>();
+	if pids.is_empty() {
+		let _ = fs::remove_dir(dir);
+	}
+	pids
+}
+fn is_alive(pid: u32) -> bool {
+	// NOTE: EPERM means that the process exists, but belongs to another user
+	matches!(
+		signal::kill(Pid::from_raw(pid as i32), None),
+		Ok(()) | Err(Errno::EPERM)
+	)
+}
+// NOTE: the runtime directory is cleared on logout,
+//       so that entries of a previous session do not remain
+fn get_running_dir(name: &str, dirs: &dyn Dirs) -> Option<PathBuf> {
+	let runtime_dir = dirs.get_xdg_runtime_dir().ok()?;
+	Some(
+		runtime_dir
+			.join("skeld/running")
+			.join(escape_path_component(name)),
+	)
+}
+// terminate the running launches of the project 'name' and wait (at most one second)
+// for them to exit, returns the number of terminated launches
+pub fn stop(name: &str, dirs: &dyn Dirs) -> Result<usize, String> {
+	let pids = find_running(name, dirs);
+	for pid in &pids {
+		signal::kill(Pid::from_raw(*pid as i32), Signal::SIGTERM)
+			.map_err(|err| format!("Failed to terminate the process {pid}:\n  {err}"))?;
+	}
+	for _ in 0..10 {
+		if !pids.iter().any(|pid| is_alive(*pid)) {
+			break;
+		}
+		thread::sleep(Duration::from_millis(100));
+	}
+	Ok(pids.len())
+}
@@ -3,15 +3,28 @@ use std::{
 	env,
 	error::Error,
 	ffi::OsString,
-	fs::{self, File},
-	io,
-	os::fd::IntoRawFd as _,
+	fs::{self, File, OpenOptions},
+	io, iter,
+	os::{
+		fd::IntoRawFd as _,
+		unix::{
+			fs::{symlink, MetadataExt as _},
+			process::ExitStatusExt as _,
+		},
+	},
 	path::{Component as PathComponents, Path, PathBuf},
-	process::{Command as OsCommand, ExitCode, ExitStatus},
+	process::{Child, Command as OsCommand, ExitCode, ExitStatus},
 	time::Duration,
 };
 
 use nix::{errno::Errno, unistd};
+
+use crate::{
+	dirs::Dirs,
+	launch_history::{self, Launch, LaunchStatus},
+	running::RunningGuard,
+	signals::SignalGuard,
+};
 use seccompiler::{
 	BpfProgram, SeccompAction, SeccompCmpArgLen, SeccompCmpOp, SeccompCondition, SeccompFilter,
 	SeccompRule, TargetArch as SeccompArch,
@@ -21,6 +34,42 @@ use seccompiler::{
 pub struct SandboxParameters {
 	pub fs_tree: VirtualFSTree<()>,
 	pub envvar_whitelist: EnvVarWhitelist,
+	// persistent directory that is mounted as the home directory
+	pub private_home: Option<PathBuf>,
+	// directory where changes to copy-on-write paths are stored
+	pub overlay_dir: Option<PathBuf>,
+	pub seccomp_profile: SeccompProfile,
+}
+// syscalls that are blocked inside of the sandbox
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SeccompProfile {
+	None,
+	// block TIOCSTI, which allows injecting input into the terminal
+	#[default]
+	Default,
+	// additionally block syscalls to inspect other processes,
+	// access the kernel keyring or load kernel code
+	Strict,
+	// only allow the syscalls required by common programs (and bwrap),
+	// all other syscalls fail with EPERM
+	AllowList,
+}
+impl SeccompProfile {
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::None => "none",
+			Self::Default => "default",
+			Self::Strict => "strict",
+			Self::AllowList => "allow-list",
+		}
+	}
+}
+// behavior if the sandbox (i.e. bwrap) is not available
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum SandboxFallback {
+	#[default]
+	Error,
+	WarnAndRunUnsandboxed,
 }
 #[derive(Clone)]
 pub enum EnvVarWhitelist {
@@ -29,47 +78,373 @@ pub enum EnvVarWhitelist {
 }
 #[derive(Clone, Debug)]
 pub struct Command {
+	// name in the launch history
+	pub name: String,
 	pub cmd: Vec<String>,
 	pub working_dir: PathBuf,
 	pub detach: bool,
+	// terminal emulator command (e.g. '["foot", "--"]'), in whose new window
+	// the command is run, while this process detaches from the terminal
+	pub terminal: Option<Vec<String>>,
+}
+// configuration of the logfiles of detached processes
+#[derive(Clone, PartialEq)]
+pub struct LogConfig {
+	// defaults to the skeld state directory
+	pub dir: Option<PathBuf>,
+	// may contain the variables $(NAME), $(TIME) and $(N)
+	pub filename_template: String,
+	pub max_age: Option<Duration>,
+	// in bytes
+	pub max_total_size: Option<u64>,
+	// whether the notice about detaching is omitted (set by `--quiet`)
+	pub quiet: bool,
+}
+impl Default for LogConfig {
+	fn default() -> Self {
+		Self {
+			dir: None,
+			filename_template: "$(NAME)-$(TIME).log".to_string(),
+			max_age: Some(Duration::from_secs(60 * 60 * 24)),
+			max_total_size: None,
+			quiet: false,
+		}
+	}
+}
+
+// resource limits of a launched command and its children
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+	// in MiB
+	pub memory: Option<u64>,
+	// relative share of the cpu time (1-10000, default: 100)
+	pub cpu_weight: Option<u64>,
+	// maximum number of processes and threads
+	pub pids_max: Option<u64>,
+	// maximum number of open files per process
+	pub nofile: Option<u64>,
+}
+impl ResourceLimits {
+	// options of the transient systemd scope (e.g. 'MemoryMax=...'),
+	// as these limits require a cgroup
+	fn get_scope_properties(&self) -> Vec<String> {
+		let properties = [
+			(
+				"MemoryMax",
+				self.memory.map(|mib| mib.saturating_mul(1024 * 1024)),
+			),
+			("CPUWeight", self.cpu_weight),
+			("TasksMax", self.pids_max),
+		];
+		properties
+			.into_iter()
+			.filter_map(|(name, value)| Some(format!("{name}={}", value?)))
+			.collect()
+	}
 }
-impl SandboxParameters {
-	pub fn run_cmd(&self, command: Command) -> Result<ExitCode, Box<dyn Error>> {
-		assert!(!command.cmd.is_empty());
 
-		let bwrap_args = self.get_bwrap_args(&command)?;
-		let mut bwrap_command = OsCommand::new("bwrap");
-		bwrap_command.args(bwrap_args);
-		bwrap_command.arg("--");
-		bwrap_command.args(command.cmd);
+// fully resolved launch of a command (including the sandbox arguments and the
+// values of whitelisted environment variables), so that it can be computed
+// ahead of time and executed later
+#[derive(Clone, Debug)]
+pub struct LaunchPlan {
+	// name in the launch history
+	pub name: String,
+	pub program: OsString,
+	// e.g. the bwrap arguments followed by the command
+	pub args: Vec<OsString>,
+	// None if the program sets the working directory itself (e.g. 'bwrap --chdir')
+	pub working_dir: Option<PathBuf>,
+	// whether this process detaches from the terminal
+	pub detach: bool,
+	// whether TIOCSTI is blocked, as the controlling terminal is inherited
+	pub restrict_tty: bool,
+	// additional filter of the seccomp profile, applied even if detached
+	pub seccomp_filter: Option<BpfProgram>,
+	pub terminal: Option<Vec<String>>,
+	// appended to the error if the program could not be found
+	pub not_found_note: Option<&'static str>,
+	// RLIMIT_NOFILE of the program
+	pub nofile_limit: Option<u64>,
+	// whether the program is tracked as a running launch of 'name'
+	pub track_running: bool,
+}
+impl LaunchPlan {
+	// run the program in a transient systemd scope, which is required for the
+	// cgroup limits and named after the launch if 'named_scope' is set
+	// NOTE: the scope only exists as long as the program is running
+	// NOTE: without systemd, a named scope is silently omitted
+	pub fn with_scope(self, limits: &ResourceLimits, named_scope: bool, dirs: &dyn Dirs) -> Self {
+		let scope_properties = limits.get_scope_properties();
+		let plan = Self {
+			nofile_limit: limits.nofile,
+			..self
+		};
+		let unit_name =
+			(named_scope && is_systemd_available(dirs)).then(|| get_scope_unit_name(&plan.name, dirs));
+		if scope_properties.is_empty() && unit_name.is_none() {
+			return plan;
+		}
 
-		if command.detach {
-			detach_process(false)?;
+		let mut systemd_run_args = vec![
+			"--user".into(),
+			"--scope".into(),
+			"--quiet".into(),
+			"--collect".into(),
+		];
+		if let Some(unit_name) = unit_name {
+			systemd_run_args.push(format!("--unit={unit_name}").into());
+			systemd_run_args.push(format!("--description=skeld: {}", plan.name).into());
+		}
+		for property in scope_properties {
+			systemd_run_args.extend(["--property".into(), property.into()]);
+		}
+		systemd_run_args.push("--".into());
+		systemd_run_args.push(plan.program);
+		systemd_run_args.extend(plan.args);
+		Self {
+			program: "systemd-run".into(),
+			args: systemd_run_args,
+			not_found_note: Some(concat!(
+				"\n  NOTE: The resource limits `memory`, `cpu-weight` and `pids-max`",
+				"\n        require systemd (`systemd-run`)."
+			)),
+			..plan
+		}
+	}
+	pub fn execute(
+		self,
+		log_config: &LogConfig,
+		dirs: &dyn Dirs,
+	) -> Result<ExitCode, Box<dyn Error>> {
+		let mut os_command = OsCommand::new(&self.program);
+		os_command.args(&self.args);
+		if let Some(working_dir) = &self.working_dir {
+			os_command.current_dir(working_dir);
+		}
+
+		let logfile = if self.detach {
+			Some(detach_process(false, log_config, &self.name, dirs)?)
 		} else {
-			// prevent TIOCSTI injections if controlling terminal is inherited
-			seccompiler::apply_filter(&get_bpf_program()).unwrap();
-		}
-		let mut bwrap_process = bwrap_command.spawn().map_err(|err| {
-			let mut error_string = format!("Failed to execute bwrap: {err}");
-			if err.kind() == io::ErrorKind::NotFound {
-				error_string.push_str(concat!(
-					"\n  NOTE: This may be because Bubblewrap is not installed.",
-					"\n        Install Bubblewrap (https://github.com/containers/bubblewrap)",
-					"\n        and make sure `bwrap` is available in `$PATH`.",
-				));
+			if self.restrict_tty {
+				apply_seccomp_filter(&get_tty_bpf_program()?)?;
 			}
-			error_string
-		})?;
+			None
+		};
+		if let Some(seccomp_filter) = &self.seccomp_filter {
+			apply_seccomp_filter(seccomp_filter)?;
+		}
+		// NOTE: the limit is inherited by the program, while this process
+		//       only waits for it afterwards
+		if let Some(nofile_limit) = self.nofile_limit {
+			let limit = libc::rlimit {
+				rlim_cur: nofile_limit,
+				rlim_max: nofile_limit,
+			};
+			// SAFETY: 'limit' is a valid rlimit struct
+			if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &limit) } != 0 {
+				let err = io::Error::last_os_error();
+				return Err(format!("Failed to limit the number of open files:\n  {err}").into());
+			}
+		}
+		let start_time = launch_history::current_time();
+		// NOTE: without detaching, the editor is terminated together with skeld
+		//       (e.g. when the terminal is closed), instead of being killed by bwrap
+		let signal_guard = (!self.detach).then(SignalGuard::new);
+		let mut child = match &self.terminal {
+			Some(terminal_cmd) => spawn_in_terminal(&os_command, terminal_cmd)?,
+			None => os_command.spawn().map_err(|err| {
+				let mut error_string = format!(
+					"Failed to execute `{}`: {err}",
+					self.program.to_string_lossy()
+				);
+				if let Some(note) = self
+					.not_found_note
+					.filter(|_| err.kind() == io::ErrorKind::NotFound)
+				{
+					error_string.push_str(note);
+				}
+				error_string
+			})?,
+		};
+
+		if let Some(logfile) = &logfile {
+			record_detached_launch(&self.name, start_time, LaunchStatus::Running, logfile, dirs);
+		}
+		if let Some(signal_guard) = &signal_guard {
+			signal_guard.forward_to(child.id());
+		}
+		let running_guard = self
+			.track_running
+			.then(|| RunningGuard::register(&self.name, child.id(), dirs))
+			.flatten();
+		let child_status = child.wait().unwrap();
+		drop(running_guard);
+		drop(signal_guard);
+		if let Some(logfile) = &logfile {
+			let status = LaunchStatus::from_exit_status(child_status);
+			record_detached_launch(&self.name, start_time, status, logfile, dirs);
+		}
+		Ok(convert_exit_status_to_code(child_status))
+	}
+}
+
+impl SandboxParameters {
+	pub fn run_cmd(
+		&self,
+		command: Command,
+		log_config: &LogConfig,
+		dirs: &dyn Dirs,
+	) -> Result<ExitCode, Box<dyn Error>> {
+		self.plan_cmd(command, dirs)?.execute(log_config, dirs)
+	}
+	pub fn plan_cmd(&self, command: Command, dirs: &dyn Dirs) -> Result<LaunchPlan, Box<dyn Error>> {
+		assert!(!command.cmd.is_empty());
 
-		if command.detach {
-			Ok(ExitCode::SUCCESS)
+		if let Some(private_home) = &self.private_home {
+			self.check_private_home_overlap(dirs)?;
+			fs::create_dir_all(private_home).map_err(|err| {
+				format!(
+					"Failed to create the private home directory `{}`:\n  {err}",
+					private_home.display()
+				)
+			})?;
+		}
+
+		// NOTE: without user namespaces, bwrap only fails with an opaque error
+		if let Some(restriction) = find_bwrap().and_then(|bwrap| get_userns_restriction(&bwrap)) {
+			return Err(restriction.into());
+		}
+		self.check_program_access(&command, dirs)?;
+		let seccomp_filter = get_bpf_program(self.seccomp_profile)?;
+		let mut bwrap_args = self.get_bwrap_args(&command, dirs)?;
+		bwrap_args.push("--".into());
+		bwrap_args.extend(command.cmd.iter().map(OsString::from));
+
+		Ok(LaunchPlan {
+			program: "bwrap".into(),
+			args: bwrap_args,
+			working_dir: None,
+			detach: command.is_detached(),
+			// NOTE: a new terminal window does not inherit the controlling terminal
+			restrict_tty: self.seccomp_profile != SeccompProfile::None,
+			seccomp_filter,
+			not_found_note: Some(concat!(
+				"\n  NOTE: This may be because Bubblewrap is not installed.",
+				"\n        Install Bubblewrap (https://github.com/containers/bubblewrap)",
+				"\n        and make sure `bwrap` is available in `$PATH`.",
+			)),
+			nofile_limit: None,
+			track_running: false,
+			name: command.name,
+			terminal: command.terminal,
+		})
+	}
+
+	// reject entries that would be mounted over the private home directory,
+	// as the real home directory would silently be accessible again
+	fn check_private_home_overlap(&self, dirs: &dyn Dirs) -> Result<(), String> {
+		let home_dir = dirs
+			.get_home_dir()
+			.map_err(|err| format!("Failed to determine the home directory:\n  {err}"))?;
+		let overlapping_entry = self
+			.fs_tree
+			.flatten()
+			.into_iter()
+			.find(|(path, ..)| home_dir.starts_with(path));
+		if let Some((path, ..)) = overlapping_entry {
+			return Err(format!(
+				concat!(
+					"The sandbox entry `{}` would be mounted over the private home directory.",
+					"\n  NOTE: With `private-home`, only subpaths of the home directory `{}` can be whitelisted."
+				),
+				path.display(),
+				home_dir.display()
+			));
+		}
+		Ok(())
+	}
+	// whether 'bwrap' is found in '$PATH' and is able to create a sandbox
+	pub fn is_available() -> bool {
+		find_bwrap().is_some_and(|bwrap| get_userns_restriction(&bwrap).is_none())
+	}
+	// report a program that exists on the host but is not accessible inside
+	// the sandbox, as bwrap only fails with a cryptic "No such file" error
+	// NOTE: programs that are not found on the host are left to bwrap,
+	//       as they may be provided by a mount with a different source
+	fn check_program_access(&self, command: &Command, dirs: &dyn Dirs) -> Result<(), String> {
+		let program = Path::new(&command.cmd[0]);
+		let candidates = if program.components().count() > 1 {
+			vec![command.working_dir.join(program)]
 		} else {
-			let sandbox_status = bwrap_process.wait().unwrap();
-			Ok(convert_exit_status_to_code(sandbox_status))
+			self
+				.get_sandbox_path_var()
+				.iter()
+				.map(|dir| dir.join(program))
+				.collect()
+		};
+
+		let mut inaccessible_program = None;
+		for candidate in candidates {
+			if !candidate.is_absolute() || !candidate.is_file() {
+				continue;
+			}
+			// NOTE: symlinks (e.g. '/bin' -> '/usr/bin') have to be resolved,
+			//       as the target has to be accessible as well
+			let resolved_path = fs::canonicalize(&candidate).unwrap_or_else(|_| candidate.clone());
+			let is_accessible = |path: &Path| {
+				self
+					.fs_tree
+					.lookup(path)
+					.is_some_and(|ty| ty >= VirtualFSEntryType::Symlink)
+			};
+			if is_accessible(&candidate) && is_accessible(&resolved_path) {
+				return Ok(());
+			}
+			inaccessible_program.get_or_insert(resolved_path);
 		}
+		let Some(program_path) = inaccessible_program else {
+			return Ok(());
+		};
+
+		// NOTE: system directories are suggested as a whole,
+		//       as the program most likely needs other files from them
+		let home_dir = dirs.get_home_dir().ok();
+		let suggested_dir = match program_path.components().nth(1) {
+			Some(top_level_dir)
+				if !home_dir.is_some_and(|home_dir| program_path.starts_with(home_dir)) =>
+			{
+				Path::new("/").join(top_level_dir)
+			}
+			_ => program_path.parent().unwrap().to_path_buf(),
+		};
+		Err(format!(
+			concat!(
+				"The program `{}` at `{}` is not whitelisted in the sandbox.",
+				"\n  NOTE: Whitelist it with e.g. `whitelist-ro = [\"{}\"]`.",
+			),
+			program.display(),
+			program_path.display(),
+			suggested_dir.display(),
+		))
+	}
+	// the search path of programs inside the sandbox
+	fn get_sandbox_path_var(&self) -> Vec<PathBuf> {
+		let is_path_whitelisted = match &self.envvar_whitelist {
+			EnvVarWhitelist::All => true,
+			EnvVarWhitelist::List(list) => list.iter().any(|envvar| envvar == "PATH"),
+		};
+		let path_var = env::var_os("PATH").filter(|_| is_path_whitelisted);
+		// NOTE: without 'PATH', execvp uses the default search path of glibc
+		let path_var = path_var.unwrap_or_else(|| "/bin:/usr/bin".into());
+		env::split_paths(&path_var).collect()
 	}
 
-	fn get_bwrap_args(&self, command: &Command) -> Result<Vec<OsString>, Box<dyn Error>> {
+	fn get_bwrap_args(
+		&self,
+		command: &Command,
+		dirs: &dyn Dirs,
+	) -> Result<Vec<OsString>, Box<dyn Error>> {
 		let mut bwrap_args = Vec::new();
 
 		match &self.envvar_whitelist {
@@ -87,7 +462,35 @@ impl SandboxParameters {
 		//      it is possible to whitelist subpaths of /dev
 		bwrap_args.extend_from_slice(&["--dev".into(), "/dev".into()]);
 
-		bwrap_args.append(&mut get_virtual_fs_args(&self.fs_tree)?);
+		//NOTE: as this argument appears before the virtual fs arguments,
+		//      whitelisted subpaths of the home directory (e.g. the project
+		//      directory) are still mounted on top of the private home
+		//      (entries containing the home directory are rejected, see
+		//      'check_private_home_overlap')
+		if let Some(private_home) = &self.private_home {
+			let home_dir = dirs
+				.get_home_dir()
+				.map_err(|err| format!("Failed to determine the home directory:\n  {err}"))?;
+			bwrap_args.extend_from_slice(&[
+				"--tmpfs".into(),
+				home_dir.clone().into(),
+				"--bind".into(),
+				private_home.clone().into(),
+				home_dir.clone().into(),
+				"--setenv".into(),
+				"HOME".into(),
+				home_dir.into(),
+			]);
+		}
+
+		//NOTE: as this argument appears before the virtual fs arguments,
+		//      shared entries of the runtime directory are mounted on top
+		bwrap_args.append(&mut self.get_runtime_dir_args(dirs));
+
+		bwrap_args.append(&mut get_virtual_fs_args(
+			&self.fs_tree,
+			self.overlay_dir.as_deref(),
+		)?);
 
 		bwrap_args.extend_from_slice(&[
 			"--unshare-user".into(),
@@ -103,20 +506,65 @@ impl SandboxParameters {
 
 		Ok(bwrap_args)
 	}
+	// a private runtime directory, as programs expect '$XDG_RUNTIME_DIR'
+	// to exist and the host one contains sockets like the skeld daemon
+	// NOTE: skipped if the runtime directory or a parent is
+	//       already configured, e.g. by whitelisting it
+	fn get_runtime_dir_args(&self, dirs: &dyn Dirs) -> Vec<OsString> {
+		let is_envvar_whitelisted = match &self.envvar_whitelist {
+			EnvVarWhitelist::All => true,
+			EnvVarWhitelist::List(list) => list.iter().any(|envvar| envvar == "XDG_RUNTIME_DIR"),
+		};
+		let Ok(runtime_dir) = dirs.get_xdg_runtime_dir() else {
+			return Vec::new();
+		};
+		if !is_envvar_whitelisted || self.fs_tree.lookup(&runtime_dir).is_some() {
+			return Vec::new();
+		}
+		vec![
+			"--perms".into(),
+			"0700".into(),
+			"--tmpfs".into(),
+			runtime_dir.into(),
+		]
+	}
 }
-fn get_virtual_fs_args(fs_tree: &VirtualFSTree<()>) -> Result<Vec<OsString>, Box<dyn Error>> {
+fn get_virtual_fs_args(
+	fs_tree: &VirtualFSTree<()>,
+	overlay_dir: Option<&Path>,
+) -> Result<Vec<OsString>, Box<dyn Error>> {
 	let mut args = Vec::new();
-	for (path, ty) in fs_tree.flatten() {
+	for (path, ty, source) in fs_tree.flatten() {
 		assert!(path.is_absolute());
+		let source = source.unwrap_or_else(|| path.clone());
+		let backing_path = match ty {
+			VirtualFSEntryType::Deny => fs_tree.backing_path(&path),
+			_ => source.clone(),
+		};
 		let mut path_args = match ty {
 			VirtualFSEntryType::AllowDev => {
-				vec!["--dev-bind-try".into(), path.clone().into(), path.into()]
+				vec!["--dev-bind-try".into(), source.into(), path.into()]
 			}
 			VirtualFSEntryType::ReadWrite => {
-				vec!["--bind-try".into(), path.clone().into(), path.into()]
+				vec!["--bind-try".into(), source.into(), path.into()]
 			}
 			VirtualFSEntryType::ReadOnly => {
-				vec!["--ro-bind-try".into(), path.clone().into(), path.into()]
+				vec!["--ro-bind-try".into(), source.into(), path.into()]
+			}
+			// mirror the semantics of '--*-bind-try'
+			VirtualFSEntryType::CopyOnWrite if !source.exists() => Vec::new(),
+			VirtualFSEntryType::CopyOnWrite => {
+				let overlay_dir =
+					overlay_dir.expect("overlay directory is required for copy-on-write paths");
+				let (upper_dir, work_dir) = create_overlay_dirs(overlay_dir, &path)?;
+				vec![
+					"--overlay-src".into(),
+					source.into(),
+					"--overlay".into(),
+					upper_dir.into(),
+					work_dir.into(),
+					path.into(),
+				]
 			}
 			VirtualFSEntryType::Symlink => {
 				let target_path = path
@@ -128,11 +576,39 @@ fn get_virtual_fs_args(fs_tree: &VirtualFSTree<()>) -> Result<Vec<OsString>, Box
 			VirtualFSEntryType::Tmpfs => {
 				vec!["--tmpfs".into(), path.as_os_str().to_owned()]
 			}
+			// NOTE: a tmpfs can only be mounted on directories,
+			//       so files are masked with '/dev/null' instead
+			VirtualFSEntryType::Deny if backing_path.is_dir() => vec![
+				"--tmpfs".into(),
+				path.clone().into(),
+				"--remount-ro".into(),
+				path.into(),
+			],
+			VirtualFSEntryType::Deny if backing_path.exists() => {
+				vec!["--ro-bind".into(), "/dev/null".into(), path.into()]
+			}
+			// nothing to hide if the path does not exist
+			VirtualFSEntryType::Deny => Vec::new(),
 		};
 		args.append(&mut path_args);
 	}
 	Ok(args)
 }
+// returns the (upper, work) directories of the overlay mounted at 'path'
+fn create_overlay_dirs(overlay_dir: &Path, path: &Path) -> Result<(PathBuf, PathBuf), String> {
+	let relative_path = path.strip_prefix("/").unwrap();
+	let upper_dir = overlay_dir.join("upper").join(relative_path);
+	let work_dir = overlay_dir.join("work").join(relative_path);
+	for dir in [&upper_dir, &work_dir] {
+		fs::create_dir_all(dir).map_err(|err| {
+			format!(
+				"Failed to create the overlay directory `{}`:\n  {err}",
+				dir.display()
+			)
+		})?;
+	}
+	Ok((upper_dir, work_dir))
+}
 fn get_envvar_whitelist_args(envvar_whitelists: &[OsString]) -> Vec<OsString> {
 	let mut args = Vec::new();
 	for envvar in envvar_whitelists {
@@ -143,65 +619,213 @@ fn get_envvar_whitelist_args(envvar_whitelists: &[OsString]) -> Vec<OsString> {
 	}
 	args
 }
-fn convert_exit_status_to_code(status: ExitStatus) -> ExitCode {
+// run 'command' in a new window of the terminal emulator 'terminal_cmd'
+// NOTE: the exit status is the one of the terminal emulator
+fn spawn_in_terminal(command: &OsCommand, terminal_cmd: &[String]) -> Result<Child, String> {
+	assert!(!terminal_cmd.is_empty());
+	let mut terminal_command = OsCommand::new(&terminal_cmd[0]);
+	terminal_command
+		.args(&terminal_cmd[1..])
+		.arg(command.get_program())
+		.args(command.get_args());
+	if let Some(working_dir) = command.get_current_dir() {
+		terminal_command.current_dir(working_dir);
+	}
+	terminal_command.spawn().map_err(|err| {
+		format!(
+			"Failed to execute the terminal emulator `{}`: {err}",
+			terminal_cmd[0]
+		)
+	})
+}
+// NOTE: like in shells, a process killed by a signal results in 128 + signal
+pub fn convert_exit_status_to_code(status: ExitStatus) -> ExitCode {
 	if let Some(code) = status.code() {
 		(code as u8).into()
-	} else if status.success() {
-		ExitCode::SUCCESS
+	} else if let Some(signal) = status.signal() {
+		(128 + signal as u8).into()
 	} else {
 		ExitCode::FAILURE
 	}
 }
 
 impl Command {
+	// whether this process detaches from the terminal
+	pub fn is_detached(&self) -> bool {
+		self.detach || self.terminal.is_some()
+	}
 	// run command without a sandbox
-	pub fn run(&self) -> Result<ExitCode, Box<dyn Error>> {
+	pub fn run(&self, log_config: &LogConfig, dirs: &dyn Dirs) -> Result<ExitCode, Box<dyn Error>> {
+		self.plan().execute(log_config, dirs)
+	}
+	pub fn plan(&self) -> LaunchPlan {
 		assert!(!self.cmd.is_empty());
-
-		if self.detach {
-			detach_process(false)?;
-		};
-
-		let mut child = OsCommand::new(&self.cmd[0])
-			.args(self.cmd.iter().skip(1))
-			.current_dir(&self.working_dir)
-			.spawn()
-			.map_err(|err| format!("Failed to execute command `{}`: {err}", &self.cmd[0]))?;
-
-		if self.detach {
-			Ok(ExitCode::SUCCESS)
-		} else {
-			let child_status = child.wait().unwrap();
-			Ok(convert_exit_status_to_code(child_status))
+		LaunchPlan {
+			name: self.name.clone(),
+			program: self.cmd[0].clone().into(),
+			args: self.cmd[1..].iter().map(OsString::from).collect(),
+			working_dir: Some(self.working_dir.clone()),
+			detach: self.is_detached(),
+			restrict_tty: false,
+			seccomp_filter: None,
+			terminal: self.terminal.clone(),
+			not_found_note: None,
+			nofile_limit: None,
+			track_running: false,
 		}
 	}
 }
+// whether a systemd user instance is running, to which transient units can be added
+fn is_systemd_available(dirs: &dyn Dirs) -> bool {
+	let path_var = env::var_os("PATH").unwrap_or_default();
+	let has_systemd_run = env::split_paths(&path_var).any(|dir| dir.join("systemd-run").is_file());
+	has_systemd_run
+		&& dirs
+			.get_xdg_runtime_dir()
+			.is_ok_and(|dir| dir.join("systemd").is_dir())
+}
+// 'skeld-<name>.scope', with a number appended if a scope
+// of the same name is still running (e.g. a second instance)
+fn get_scope_unit_name(name: &str, dirs: &dyn Dirs) -> String {
+	let escaped_name = escape_unit_name(name);
+	let transient_dir = dirs
+		.get_xdg_runtime_dir()
+		.map(|dir| dir.join("systemd/transient"))
+		.ok();
+	let is_taken = |unit_name: &str| {
+		transient_dir
+			.as_ref()
+			.is_some_and(|dir| dir.join(unit_name).exists())
+	};
+	iter::once(format!("skeld-{escaped_name}.scope"))
+		.chain((2..).map(|n| format!("skeld-{escaped_name}-{n}.scope")))
+		.find(|unit_name| !is_taken(unit_name))
+		.unwrap()
+}
+// like `systemd-escape`
+fn escape_unit_name(str: &str) -> String {
+	str
+		.bytes()
+		.map(|byte| match byte {
+			b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b':' | b'_' | b'.' => (byte as char).to_string(),
+			b'/' => "-".to_string(),
+			_ => format!("\\x{byte:02x}"),
+		})
+		.collect()
+}
+fn find_bwrap() -> Option<PathBuf> {
+	let path_var = env::var_os("PATH").unwrap_or_default();
+	env::split_paths(&path_var)
+		.map(|dir| dir.join("bwrap"))
+		.find(|path| path.is_file())
+}
+// the reason why 'bwrap' cannot create unprivileged user namespaces,
+// together with instructions to fix it
+// NOTE: a setuid bwrap does not require unprivileged user namespaces
+fn get_userns_restriction(bwrap: &Path) -> Option<String> {
+	let is_setuid = fs::metadata(bwrap).is_ok_and(|metadata| metadata.mode() & 0o4000 != 0);
+	if is_setuid {
+		return None;
+	}
+	let read_sysctl = |path: &str| {
+		fs::read_to_string(path)
+			.ok()
+			.map(|value| value.trim().to_string())
+	};
+
+	// NOTE: only present on Debian-based kernels
+	let reason = if read_sysctl("/proc/sys/kernel/unprivileged_userns_clone").as_deref() == Some("0")
+	{
+		"unprivileged user namespaces are disabled (`kernel.unprivileged_userns_clone = 0`)"
+	} else if read_sysctl("/proc/sys/user/max_user_namespaces").as_deref() == Some("0") {
+		"user namespaces are disabled (`user.max_user_namespaces = 0`)"
+	} else if read_sysctl("/proc/sys/kernel/apparmor_restrict_unprivileged_userns").as_deref()
+		== Some("1")
+		&& !has_bwrap_apparmor_profile()
+	{
+		"AppArmor restricts unprivileged user namespaces and there is no AppArmor profile for `bwrap`"
+	} else {
+		return None;
+	};
+	Some(format!(
+		concat!(
+			"Bubblewrap cannot create a sandbox, since {}.",
+			"\n  NOTE: Allow unprivileged user namespaces (see the documentation of your distribution),",
+			"\n        install a setuid `bwrap` (`chmod u+s {}`) or set `sandbox-fallback` in the configuration.",
+		),
+		reason,
+		bwrap.display()
+	))
+}
+// NOTE: e.g. Ubuntu ships the profile 'bwrap-userns-restrict'
+fn has_bwrap_apparmor_profile() -> bool {
+	let Ok(entries) = fs::read_dir("/etc/apparmor.d") else {
+		return false;
+	};
+	entries
+		.filter_map(Result::ok)
+		.any(|entry| entry.file_name().to_string_lossy().contains("bwrap"))
+}
+// NOTE: after detaching, this process remains as a supervisor
+//       that records the exit status of the command
+fn record_detached_launch(
+	name: &str,
+	start_time: u64,
+	status: LaunchStatus,
+	logfile: &Path,
+	dirs: &dyn Dirs,
+) {
+	let launch = Launch {
+		name: name.to_string(),
+		start_time,
+		status,
+		logfile: logfile.to_path_buf(),
+	};
+	if let Err(err) = launch_history::record(&launch, dirs) {
+		// NOTE: stderr is redirected to the logfile
+		eprintln!("{err}");
+	}
+}
 // detach this process from the controlling terminal and
-// redirect stdout/stderr to a logfile
-pub fn detach_process(keep_working_dir: bool) -> Result<(), String> {
-	let logdir = crate::dirs::get_skeld_state_dir()
-		.map_err(|err| format!("Failed to determine the skeld state directory:\n  {err}"))?;
+// redirect stdout/stderr to a logfile, whose path is returned
+// 'name' is used for the logfile name
+pub fn detach_process(
+	keep_working_dir: bool,
+	log_config: &LogConfig,
+	name: &str,
+	dirs: &dyn Dirs,
+) -> Result<PathBuf, String> {
+	let logdir = match &log_config.dir {
+		Some(dir) => dir.clone(),
+		None => dirs
+			.get_skeld_state_dir()
+			.map_err(|err| format!("Failed to determine the skeld state directory:\n  {err}"))?,
+	};
 	fs::create_dir_all(&logdir).map_err(|err| {
 		format!(
-			"Failed to create the skeld state directory `{}`:\n  {err}",
+			"Failed to create the log directory `{}`:\n  {err}",
 			logdir.display()
 		)
 	})?;
 
-	remove_old_logfiles(&logdir);
+	remove_old_logfiles(&logdir, log_config);
 
-	let (logfile_path, logfile) =
-		create_logfile(logdir).map_err(|err| format!("Failed to create a logfile: {err}"))?;
+	let (logfile_path, logfile) = create_logfile(&logdir, log_config, name)
+		.map_err(|err| format!("Failed to create a logfile: {err}"))?;
+	// NOTE: the logfile is usable nevertheless
+	let _ = link_latest_logfile(&logdir, &logfile_path, name);
 	// leak the file descriptor
 	let logfile_fd = logfile.into_raw_fd();
 
-	println!(
-		concat!(
-			"NOTE: Detaching from terminal;\n",
-			"      further output will be redirected to `{}`",
-		),
-		logfile_path.display()
-	);
+	if !log_config.quiet {
+		println!(
+			concat!(
+				"NOTE: Detaching from terminal;\n",
+				"      further output will be redirected to `{}`",
+			),
+			logfile_path.display()
+		);
+	}
 	// wrapper of dup2 handling EINTR
 	let dup2 = |oldfd, newfd| loop {
 		match unistd::dup2(oldfd, newfd) {
@@ -216,39 +840,97 @@ pub fn detach_process(keep_working_dir: bool) -> Result<(), String> {
 	unistd::daemon(keep_working_dir, true)
 		.map_err(|err| format!("Failed to detach process: {err}"))?;
 
-	Ok(())
+	Ok(logfile_path)
 }
-// remove logfiles older than 24h, errors are silently ignored
-fn remove_old_logfiles(logdir: impl AsRef<Path>) {
+// remove logfiles older than the maximum age and the oldest logfiles
+// exceeding the maximum total size, errors are silently ignored
+fn remove_old_logfiles(logdir: impl AsRef<Path>, log_config: &LogConfig) {
 	let Ok(dir_iter) = fs::read_dir(logdir) else {
 		return;
 	};
 
-	dir_iter
+	// (path, elapsed time since last access, size)
+	let mut logfiles = dir_iter
 		.filter_map(Result::ok)
 		.filter(|dir_entry| dir_entry.path().extension().is_some_and(|ext| ext == "log"))
-		.filter(|dir_entry| {
-			let elapsed_time = dir_entry
-				.metadata()
-				.ok()
-				.and_then(|metadata| metadata.accessed().ok())
-				.and_then(|mtime| mtime.elapsed().ok());
-			let Some(elapsed_time) = elapsed_time else {
-				// remove the file in case of an error
-				return false;
-			};
-			elapsed_time > Duration::from_secs(60 * 60 * 24)
+		.filter_map(|dir_entry| {
+			let metadata = dir_entry.metadata().ok()?;
+			// NOTE: 'latest-<name>.log' links to removed logfiles are removed
+			if metadata.is_symlink() && !dir_entry.path().exists() {
+				let _ = fs::remove_file(dir_entry.path());
+			}
+			// NOTE: directories (and links) are not removed
+			if !metadata.is_file() {
+				return None;
+			}
+			let elapsed_time = metadata.accessed().ok()?.elapsed().ok()?;
+			Some((dir_entry.path(), elapsed_time, metadata.len()))
 		})
-		.for_each(|dir_entry| {
-			// NOTE: directories are not removed
-			let _ = fs::remove_file(dir_entry.path());
+		.collect::<Vec<_>>();
+
+	if let Some(max_age) = log_config.max_age {
+		logfiles.retain(|(path, elapsed_time, _)| {
+			if *elapsed_time > max_age {
+				let _ = fs::remove_file(path);
+				false
+			} else {
+				true
+			}
 		});
+	}
+
+	if let Some(max_total_size) = log_config.max_total_size {
+		// NOTE: the oldest logfiles come first
+		logfiles.sort_by_key(|(_, elapsed_time, _)| std::cmp::Reverse(*elapsed_time));
+		let mut total_size = logfiles.iter().map(|(_, _, size)| size).sum::<u64>();
+		for (path, _, size) in logfiles {
+			if total_size <= max_total_size {
+				break;
+			}
+			if fs::remove_file(path).is_ok() {
+				total_size -= size;
+			}
+		}
+	}
+}
+// point the symlink 'latest-<name>.log' in 'logdir' to 'logfile_path'
+fn link_latest_logfile(logdir: &Path, logfile_path: &Path, name: &str) -> io::Result<()> {
+	let link_name = format!("latest-{}.log", name.replace('/', "_"));
+	let link_path = logdir.join(&link_name);
+	// NOTE: the link is replaced atomically, and its target is relative,
+	//       so that the log directory can be moved
+	let tmp_link_path = logdir.join(format!(".{link_name}.{}", std::process::id()));
+	let _ = fs::remove_file(&tmp_link_path);
+	symlink(logfile_path.file_name().unwrap(), &tmp_link_path)?;
+	fs::rename(&tmp_link_path, link_path).inspect_err(|_| {
+		let _ = fs::remove_file(&tmp_link_path);
+	})
 }
-fn create_logfile(logdir: impl AsRef<Path>) -> io::Result<(PathBuf, File)> {
+fn create_logfile(
+	logdir: impl AsRef<Path>,
+	log_config: &LogConfig,
+	name: &str,
+) -> io::Result<(PathBuf, File)> {
 	let logdir = logdir.as_ref();
 
+	// NOTE: the name must not introduce subdirectories
+	let filename = log_config
+		.filename_template
+		.replace("$(NAME)", &name.replace('/', "_"))
+		.replace(
+			"$(TIME)",
+			&format_local_time(launch_history::current_time()),
+		);
+
+	if !filename.contains("$(N)") {
+		// NOTE: existing logfiles are appended to
+		let path = logdir.join(&filename);
+		let file = OpenOptions::new().create(true).append(true).open(&path)?;
+		return Ok((path, file));
+	}
+
 	for i in 1..1_440 {
-		let possible_path = logdir.join(format!("skeld.{i}.log"));
+		let possible_path = logdir.join(filename.replace("$(N)", &i.to_string()));
 		match File::create_new(&possible_path) {
 			Ok(file) => return Ok((possible_path, file)),
 			Err(err) if err.kind() == io::ErrorKind::AlreadyExists => (),
@@ -256,16 +938,35 @@ fn create_logfile(logdir: impl AsRef<Path>) -> io::Result<(PathBuf, File)> {
 		}
 	}
 
-	Err(io::Error::new(
-		io::ErrorKind::Other,
-		"all logfile names are occupied",
-	))
+	Err(io::Error::other("all logfile names are occupied"))
+}
+// formats seconds since the unix epoch like `2024-06-01T10-33-07` in local time
+fn format_local_time(secs: u64) -> String {
+	let time = secs as libc::time_t;
+	// SAFETY: 'tm' is a plain C struct, for which all-zero bytes are a valid value
+	let mut tm = unsafe { std::mem::zeroed::<libc::tm>() };
+	// SAFETY: both pointers are valid references, and 'localtime_r' is thread-safe
+	if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+		return secs.to_string();
+	}
+	let mut buf = [0u8; 64];
+	// SAFETY: 'buf' is writable for 'buf.len()' bytes, the format is nul-terminated
+	//         and 'tm' was initialized by 'localtime_r'
+	let len = unsafe {
+		libc::strftime(
+			buf.as_mut_ptr().cast(),
+			buf.len(),
+			c"%Y-%m-%dT%H-%M-%S".as_ptr(),
+			&tm,
+		)
+	};
+	String::from_utf8_lossy(&buf[..len]).into_owned()
 }
 
 // path tree of all virtual-fs-entries with the following normalization:
 // 1. All subpaths of a path can only have higher permissions.
 //    If this is not the case, the tree is silently normalized.
-// 2. Tmpfs/Symlinks cannot have any whitelists in subpaths.
+// 2. Tmpfs/Symlinks/Denies cannot have any whitelists in subpaths.
 //    If this is not the case, an error is returned.
 #[derive(Clone)]
 pub struct VirtualFSTree<U> {
@@ -276,16 +977,25 @@ pub struct VirtualFSTree<U> {
 	// U can be used for user data to identify paths
 	// in the event of an error
 	entry: Option<(VirtualFSEntryType, U)>,
+	// host path that is mounted at the current path,
+	// if it differs from the current path
+	// NOTE: entries with a source are exempt from normalization 1.,
+	//       as they do not show the same contents as their parents
+	source: Option<PathBuf>,
 }
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum VirtualFSEntryType {
 	// whitelists
 	AllowDev,
 	ReadWrite,
+	// writable view whose changes are not written back
+	CopyOnWrite,
 	ReadOnly,
 	// others (need to be leafs)
 	Tmpfs,
 	Symlink,
+	// hides the path inside a whitelisted parent directory
+	Deny,
 }
 #[derive(Clone, Debug)]
 pub enum FSTreeError<U> {
@@ -299,6 +1009,7 @@ impl<U: Clone> VirtualFSTree<U> {
 			path_component: "/".into(),
 			children: Vec::new(),
 			entry: None,
+			source: None,
 		}
 	}
 	pub fn remove_user_data(self) -> VirtualFSTree<()> {
@@ -310,6 +1021,7 @@ impl<U: Clone> VirtualFSTree<U> {
 				.map(Self::remove_user_data)
 				.collect(),
 			entry: self.entry.map(|(ty, _)| (ty, ())),
+			source: self.source,
 		}
 	}
 	pub fn add_path(
@@ -318,6 +1030,17 @@ impl<U: Clone> VirtualFSTree<U> {
 		ty: VirtualFSEntryType,
 		user_data: U,
 	) -> Result<(), FSTreeError<U>> {
+		self.add_bind(path, None, ty, user_data)
+	}
+	// mount 'source' at 'path', or 'path' itself if 'source' is None
+	pub fn add_bind(
+		&mut self,
+		path: impl AsRef<Path>,
+		source: Option<PathBuf>,
+		ty: VirtualFSEntryType,
+		user_data: U,
+	) -> Result<(), FSTreeError<U>> {
+		assert!(source.is_none() || !ty.should_be_leaf());
 		let mut path_components = path.as_ref().components();
 
 		assert_eq!(path_components.next(), Some(PathComponents::RootDir));
@@ -329,12 +1052,13 @@ impl<U: Clone> VirtualFSTree<U> {
 			.collect::<Option<Vec<_>>>()
 			.expect("unexpected path component");
 
-		self.add_path_rec(&rest_components, (ty, user_data))
+		self.add_path_rec(&rest_components, (ty, user_data), source)
 	}
 	fn add_path_rec(
 		&mut self,
 		parts: &[&std::ffi::OsStr],
 		entry: (VirtualFSEntryType, U),
+		source: Option<PathBuf>,
 	) -> Result<(), FSTreeError<U>> {
 		if let Some(next_part) = parts.first() {
 			if self.should_be_leaf() {
@@ -343,7 +1067,10 @@ impl<U: Clone> VirtualFSTree<U> {
 					invalid_child: entry.1,
 				});
 			}
-			if self.entry.as_ref().is_some_and(|(ty, _)| ty >= &entry.0) {
+			if self.source.is_none()
+				&& source.is_none()
+				&& self.entry.as_ref().is_some_and(|(ty, _)| ty >= &entry.0)
+			{
 				return Ok(());
 			}
 
@@ -358,10 +1085,11 @@ impl<U: Clone> VirtualFSTree<U> {
 					path_component: next_part.into(),
 					children: Vec::new(),
 					entry: None,
+					source: None,
 				});
 				self.children.last_mut().unwrap()
 			};
-			matching_children.add_path_rec(&parts[1..], entry)
+			matching_children.add_path_rec(&parts[1..], entry, source)
 		} else if !entry.0.should_be_leaf() {
 			if self.should_be_leaf() {
 				return Err(FSTreeError::ConflictingEntries(
@@ -369,6 +1097,17 @@ impl<U: Clone> VirtualFSTree<U> {
 					entry.1,
 				));
 			}
+			if self.source.is_some() || source.is_some() {
+				return match &self.entry {
+					Some((ty, _)) if ty == &entry.0 && self.source == source => Ok(()),
+					Some((_, u)) => Err(FSTreeError::ConflictingEntries(u.clone(), entry.1)),
+					None => {
+						self.entry = Some(entry);
+						self.source = source;
+						Ok(())
+					}
+				};
+			}
 			if self.entry.as_ref().is_some_and(|(ty, _)| ty >= &entry.0) {
 				return Ok(());
 			}
@@ -406,10 +1145,11 @@ impl<U: Clone> VirtualFSTree<U> {
 	}
 	// filter out subpaths with lower permissions
 	fn filter_subpaths(&mut self, ty: VirtualFSEntryType) -> bool {
-		if self
-			.entry
-			.as_ref()
-			.is_some_and(|(self_ty, _)| self_ty <= &ty)
+		if self.source.is_none()
+			&& self
+				.entry
+				.as_ref()
+				.is_some_and(|(self_ty, _)| self_ty <= &ty)
 		{
 			self.entry = None;
 		}
@@ -424,6 +1164,62 @@ impl<U: Clone> VirtualFSTree<U> {
 		found_subpath_whitelist |= self.entry.is_some();
 		found_subpath_whitelist
 	}
+	// the entry type that applies to 'path', i.e. the entry of 'path'
+	// itself or of its nearest parent
+	pub fn lookup(&self, path: impl AsRef<Path>) -> Option<VirtualFSEntryType> {
+		let mut path_components = path.as_ref().components();
+		assert_eq!(path_components.next(), Some(PathComponents::RootDir));
+
+		let mut node = self;
+		let mut entry_type = node.entry.as_ref().map(|(ty, _)| *ty);
+		for comp in path_components {
+			let Some(child) = node
+				.children
+				.iter()
+				.find(|child| child.path_component == comp.as_os_str())
+			else {
+				break;
+			};
+			node = child;
+			if let Some((ty, _)) = &node.entry {
+				entry_type = Some(*ty);
+			}
+		}
+		entry_type
+	}
+	// the host path that backs 'path' inside of the sandbox before its own
+	// entry is applied, i.e. the path below the source of its nearest mounted parent
+	pub fn backing_path(&self, path: impl AsRef<Path>) -> PathBuf {
+		let path = path.as_ref();
+		let components = path.components().collect::<Vec<_>>();
+		assert_eq!(components.first(), Some(&PathComponents::RootDir));
+
+		let mut node = self;
+		let mut backing_path = None;
+		for (idx, comp) in components
+			.iter()
+			.enumerate()
+			.take(components.len() - 1)
+			.skip(1)
+		{
+			let Some(child) = node
+				.children
+				.iter()
+				.find(|child| child.path_component == comp.as_os_str())
+			else {
+				break;
+			};
+			node = child;
+			// NOTE: entries without a source mount the host path itself
+			if node.entry.is_some() {
+				backing_path = node
+					.source
+					.as_ref()
+					.map(|source| source.join(components[idx + 1..].iter().collect::<PathBuf>()));
+			}
+		}
+		backing_path.unwrap_or_else(|| path.to_path_buf())
+	}
 	fn find_subpath_entry(&self) -> &(VirtualFSEntryType, U) {
 		if let Some(entry) = &self.entry {
 			return entry;
@@ -431,19 +1227,20 @@ impl<U: Clone> VirtualFSTree<U> {
 		assert!(!self.children.is_empty());
 		self.children[0].find_subpath_entry()
 	}
-	fn flatten(&self) -> Vec<(PathBuf, VirtualFSEntryType)> {
+	// returns Vec<(path, type, source)>
+	pub fn flatten(&self) -> Vec<(PathBuf, VirtualFSEntryType, Option<PathBuf>)> {
 		let mut entries = Vec::new();
 		let path: PathBuf = self.path_component.clone().into();
 
 		if let Some(entry) = &self.entry {
-			entries.push((path.clone(), entry.0));
+			entries.push((path.clone(), entry.0, self.source.clone()));
 		}
 
 		for child in &self.children {
 			let child_entries = child
 				.flatten()
 				.into_iter()
-				.map(|entry| (path.join(entry.0), entry.1));
+				.map(|entry| (path.join(entry.0), entry.1, entry.2));
 			entries.extend(child_entries);
 		}
 
@@ -453,17 +1250,18 @@ impl<U: Clone> VirtualFSTree<U> {
 impl VirtualFSEntryType {
 	fn priority(&self) -> Option<i64> {
 		match self {
-			VirtualFSEntryType::AllowDev => Some(2),
-			VirtualFSEntryType::ReadWrite => Some(1),
+			VirtualFSEntryType::AllowDev => Some(3),
+			VirtualFSEntryType::ReadWrite => Some(2),
+			VirtualFSEntryType::CopyOnWrite => Some(1),
 			VirtualFSEntryType::ReadOnly => Some(0),
 			VirtualFSEntryType::Symlink => Some(-1),
-			VirtualFSEntryType::Tmpfs => None,
+			VirtualFSEntryType::Tmpfs | VirtualFSEntryType::Deny => None,
 		}
 	}
 	fn should_be_leaf(&self) -> bool {
 		matches!(
 			self,
-			VirtualFSEntryType::Tmpfs | VirtualFSEntryType::Symlink
+			VirtualFSEntryType::Tmpfs | VirtualFSEntryType::Symlink | VirtualFSEntryType::Deny
 		)
 	}
 }
@@ -475,33 +1273,446 @@ impl PartialOrd for VirtualFSEntryType {
 	}
 }
 
-// blacklists TIOCSTI
-fn get_bpf_program() -> BpfProgram {
-	#[cfg(target_arch = "x86_64")]
-	let arch = SeccompArch::x86_64;
-	#[cfg(target_arch = "aarch64")]
-	let arch = SeccompArch::aarch64;
-	#[cfg(not(any(target_arch = "aarch64", target_arch = "x86_64")))]
-	compile_error!("only x86_64 and aarch64 are supported");
+#[cfg(target_arch = "x86_64")]
+const SECCOMP_ARCH: SeccompArch = SeccompArch::x86_64;
+#[cfg(target_arch = "aarch64")]
+const SECCOMP_ARCH: SeccompArch = SeccompArch::aarch64;
+#[cfg(not(any(target_arch = "aarch64", target_arch = "x86_64")))]
+compile_error!("only x86_64 and aarch64 are supported");
+
+// syscalls blocked by the strict seccomp profile
+// NOTE: syscalls required by bwrap itself (e.g. 'mount') must not be blocked,
+//       as the filter is installed before bwrap is started
+const STRICT_BLOCKED_SYSCALLS: [libc::c_long; 12] = [
+	libc::SYS_ptrace,
+	libc::SYS_process_vm_readv,
+	libc::SYS_process_vm_writev,
+	libc::SYS_keyctl,
+	libc::SYS_add_key,
+	libc::SYS_request_key,
+	libc::SYS_perf_event_open,
+	libc::SYS_userfaultfd,
+	libc::SYS_kexec_load,
+	libc::SYS_init_module,
+	libc::SYS_finit_module,
+	libc::SYS_delete_module,
+];
+
+// syscalls allowed by the allow-list seccomp profile
+// NOTE: this includes the syscalls required by bwrap to set up the sandbox
+//       (e.g. 'mount', 'pivot_root'), as the filter is installed before bwrap
+//       is started, while all syscalls of the strict profile are missing
+const ALLOWED_SYSCALLS: &[libc::c_long] = &[
+	// file descriptors and filesystem
+	libc::SYS_read,
+	libc::SYS_write,
+	libc::SYS_readv,
+	libc::SYS_writev,
+	libc::SYS_pread64,
+	libc::SYS_pwrite64,
+	libc::SYS_preadv,
+	libc::SYS_pwritev,
+	libc::SYS_preadv2,
+	libc::SYS_pwritev2,
+	libc::SYS_openat,
+	libc::SYS_openat2,
+	libc::SYS_close,
+	libc::SYS_close_range,
+	libc::SYS_lseek,
+	libc::SYS_fstat,
+	libc::SYS_newfstatat,
+	libc::SYS_statx,
+	libc::SYS_statfs,
+	libc::SYS_fstatfs,
+	libc::SYS_getdents64,
+	libc::SYS_ioctl,
+	libc::SYS_fcntl,
+	libc::SYS_flock,
+	libc::SYS_dup,
+	libc::SYS_dup3,
+	libc::SYS_pipe2,
+	libc::SYS_fsync,
+	libc::SYS_fdatasync,
+	libc::SYS_sync,
+	libc::SYS_syncfs,
+	libc::SYS_sync_file_range,
+	libc::SYS_truncate,
+	libc::SYS_ftruncate,
+	libc::SYS_fallocate,
+	libc::SYS_readahead,
+	libc::SYS_getcwd,
+	libc::SYS_chdir,
+	libc::SYS_fchdir,
+	libc::SYS_mkdirat,
+	libc::SYS_mknodat,
+	libc::SYS_unlinkat,
+	libc::SYS_renameat2,
+	libc::SYS_linkat,
+	libc::SYS_symlinkat,
+	libc::SYS_readlinkat,
+	libc::SYS_faccessat,
+	libc::SYS_faccessat2,
+	libc::SYS_fchmod,
+	libc::SYS_fchmodat,
+	libc::SYS_fchown,
+	libc::SYS_fchownat,
+	libc::SYS_umask,
+	libc::SYS_utimensat,
+	libc::SYS_getxattr,
+	libc::SYS_lgetxattr,
+	libc::SYS_fgetxattr,
+	libc::SYS_setxattr,
+	libc::SYS_lsetxattr,
+	libc::SYS_fsetxattr,
+	libc::SYS_listxattr,
+	libc::SYS_llistxattr,
+	libc::SYS_flistxattr,
+	libc::SYS_removexattr,
+	libc::SYS_lremovexattr,
+	libc::SYS_fremovexattr,
+	libc::SYS_splice,
+	libc::SYS_tee,
+	libc::SYS_vmsplice,
+	libc::SYS_copy_file_range,
+	libc::SYS_memfd_create,
+	libc::SYS_inotify_init1,
+	libc::SYS_inotify_add_watch,
+	libc::SYS_inotify_rm_watch,
+	// polling and events
+	libc::SYS_ppoll,
+	libc::SYS_pselect6,
+	libc::SYS_epoll_create1,
+	libc::SYS_epoll_ctl,
+	libc::SYS_epoll_pwait,
+	libc::SYS_epoll_pwait2,
+	libc::SYS_eventfd2,
+	libc::SYS_signalfd4,
+	libc::SYS_timerfd_create,
+	libc::SYS_timerfd_settime,
+	libc::SYS_timerfd_gettime,
+	libc::SYS_io_setup,
+	libc::SYS_io_destroy,
+	libc::SYS_io_submit,
+	libc::SYS_io_cancel,
+	libc::SYS_io_getevents,
+	// memory
+	libc::SYS_brk,
+	libc::SYS_mmap,
+	libc::SYS_munmap,
+	libc::SYS_mremap,
+	libc::SYS_mprotect,
+	libc::SYS_madvise,
+	libc::SYS_mincore,
+	libc::SYS_msync,
+	libc::SYS_mlock,
+	libc::SYS_mlock2,
+	libc::SYS_munlock,
+	libc::SYS_membarrier,
+	// processes and threads
+	libc::SYS_clone,
+	libc::SYS_clone3,
+	libc::SYS_execve,
+	libc::SYS_execveat,
+	libc::SYS_exit,
+	libc::SYS_exit_group,
+	libc::SYS_wait4,
+	libc::SYS_waitid,
+	libc::SYS_getpid,
+	libc::SYS_getppid,
+	libc::SYS_gettid,
+	libc::SYS_getpgid,
+	libc::SYS_setpgid,
+	libc::SYS_getsid,
+	libc::SYS_setsid,
+	libc::SYS_set_tid_address,
+	libc::SYS_set_robust_list,
+	libc::SYS_get_robust_list,
+	libc::SYS_rseq,
+	libc::SYS_futex,
+	libc::SYS_prctl,
+	libc::SYS_getrlimit,
+	libc::SYS_setrlimit,
+	libc::SYS_prlimit64,
+	libc::SYS_getrusage,
+	libc::SYS_getpriority,
+	libc::SYS_setpriority,
+	libc::SYS_sched_yield,
+	libc::SYS_sched_getaffinity,
+	libc::SYS_sched_setaffinity,
+	libc::SYS_sched_getparam,
+	libc::SYS_sched_setparam,
+	libc::SYS_sched_getscheduler,
+	libc::SYS_sched_setscheduler,
+	libc::SYS_sched_get_priority_max,
+	libc::SYS_sched_get_priority_min,
+	libc::SYS_sched_getattr,
+	libc::SYS_sched_setattr,
+	libc::SYS_getcpu,
+	libc::SYS_pidfd_open,
+	libc::SYS_pidfd_send_signal,
+	libc::SYS_seccomp,
+	libc::SYS_restart_syscall,
+	// signals
+	libc::SYS_kill,
+	libc::SYS_tkill,
+	libc::SYS_tgkill,
+	libc::SYS_rt_sigaction,
+	libc::SYS_rt_sigprocmask,
+	libc::SYS_rt_sigreturn,
+	libc::SYS_rt_sigpending,
+	libc::SYS_rt_sigsuspend,
+	libc::SYS_rt_sigtimedwait,
+	libc::SYS_rt_sigqueueinfo,
+	libc::SYS_rt_tgsigqueueinfo,
+	libc::SYS_sigaltstack,
+	// credentials
+	libc::SYS_getuid,
+	libc::SYS_geteuid,
+	libc::SYS_getgid,
+	libc::SYS_getegid,
+	libc::SYS_getresuid,
+	libc::SYS_getresgid,
+	libc::SYS_getgroups,
+	libc::SYS_setuid,
+	libc::SYS_setgid,
+	libc::SYS_setreuid,
+	libc::SYS_setregid,
+	libc::SYS_setresuid,
+	libc::SYS_setresgid,
+	libc::SYS_setgroups,
+	libc::SYS_setfsuid,
+	libc::SYS_setfsgid,
+	libc::SYS_capget,
+	libc::SYS_capset,
+	// time
+	libc::SYS_clock_gettime,
+	libc::SYS_clock_getres,
+	libc::SYS_clock_nanosleep,
+	libc::SYS_gettimeofday,
+	libc::SYS_nanosleep,
+	libc::SYS_getitimer,
+	libc::SYS_setitimer,
+	libc::SYS_timer_create,
+	libc::SYS_timer_settime,
+	libc::SYS_timer_gettime,
+	libc::SYS_timer_getoverrun,
+	libc::SYS_timer_delete,
+	libc::SYS_times,
+	// sockets
+	libc::SYS_socket,
+	libc::SYS_socketpair,
+	libc::SYS_bind,
+	libc::SYS_listen,
+	libc::SYS_accept,
+	libc::SYS_accept4,
+	libc::SYS_connect,
+	libc::SYS_shutdown,
+	libc::SYS_getsockname,
+	libc::SYS_getpeername,
+	libc::SYS_getsockopt,
+	libc::SYS_setsockopt,
+	libc::SYS_sendto,
+	libc::SYS_recvfrom,
+	libc::SYS_sendmsg,
+	libc::SYS_recvmsg,
+	libc::SYS_sendmmsg,
+	libc::SYS_recvmmsg,
+	// system information
+	libc::SYS_uname,
+	libc::SYS_sysinfo,
+	libc::SYS_getrandom,
+	// sandbox setup of bwrap
+	libc::SYS_unshare,
+	libc::SYS_mount,
+	libc::SYS_umount2,
+	libc::SYS_pivot_root,
+	libc::SYS_chroot,
+	libc::SYS_sethostname,
+];
+// syscalls with architecture-specific names or availability
+#[cfg(target_arch = "x86_64")]
+const ARCH_ALLOWED_SYSCALLS: &[libc::c_long] = &[
+	libc::SYS_sendfile,
+	libc::SYS_fadvise64,
+	// legacy syscalls that only exist on x86_64
+	libc::SYS_open,
+	libc::SYS_creat,
+	libc::SYS_stat,
+	libc::SYS_lstat,
+	libc::SYS_access,
+	libc::SYS_getdents,
+	libc::SYS_mkdir,
+	libc::SYS_rmdir,
+	libc::SYS_rename,
+	libc::SYS_renameat,
+	libc::SYS_link,
+	libc::SYS_unlink,
+	libc::SYS_symlink,
+	libc::SYS_readlink,
+	libc::SYS_chmod,
+	libc::SYS_chown,
+	libc::SYS_lchown,
+	libc::SYS_mknod,
+	libc::SYS_utime,
+	libc::SYS_utimes,
+	libc::SYS_futimesat,
+	libc::SYS_dup2,
+	libc::SYS_pipe,
+	libc::SYS_poll,
+	libc::SYS_select,
+	libc::SYS_epoll_create,
+	libc::SYS_epoll_wait,
+	libc::SYS_eventfd,
+	libc::SYS_signalfd,
+	libc::SYS_inotify_init,
+	libc::SYS_fork,
+	libc::SYS_vfork,
+	libc::SYS_getpgrp,
+	libc::SYS_arch_prctl,
+	libc::SYS_alarm,
+	libc::SYS_pause,
+	libc::SYS_time,
+];
+#[cfg(target_arch = "aarch64")]
+const ARCH_ALLOWED_SYSCALLS: &[libc::c_long] = &[
+	libc::SYS_renameat,
+	// NOTE: 'sendfile' and 'fadvise64', which are not defined by libc
+	71,
+	223,
+];
 
+// blacklists TIOCSTI
+fn get_tty_bpf_program() -> Result<BpfProgram, String> {
+	let convert_err =
+		|err: seccompiler::BackendError| format!("Failed to build the seccomp filter:\n  {err}");
+	let tiocsti_condition = SeccompCondition::new(
+		1,
+		SeccompCmpArgLen::Dword,
+		SeccompCmpOp::MaskedEq(0xFFFF_FFFF),
+		libc::TIOCSTI,
+	)
+	.map_err(convert_err)?;
 	let blacklist_syscalls = [(
 		libc::SYS_ioctl,
-		vec![SeccompRule::new(vec![SeccompCondition::new(
-			1,
-			SeccompCmpArgLen::Dword,
-			SeccompCmpOp::MaskedEq(0xFFFF_FFFF),
-			libc::TIOCSTI,
-		)
-		.unwrap()])
-		.unwrap()],
+		vec![SeccompRule::new(vec![tiocsti_condition]).map_err(convert_err)?],
 	)];
-	SeccompFilter::new(
+	let filter = SeccompFilter::new(
 		blacklist_syscalls.into_iter().collect(),
 		SeccompAction::Allow,
 		SeccompAction::Trap,
-		arch,
+		SECCOMP_ARCH,
 	)
-	.unwrap()
-	.try_into()
-	.unwrap()
+	.map_err(convert_err)?;
+	filter.try_into().map_err(convert_err)
+}
+// the additional filter of a seccomp profile, TIOCSTI is handled separately
+// NOTE: blocked syscalls fail with EPERM so that programs can handle it
+fn get_bpf_program(profile: SeccompProfile) -> Result<Option<BpfProgram>, String> {
+	let convert_err =
+		|err: seccompiler::BackendError| format!("Failed to build the seccomp filter:\n  {err}");
+	let (syscalls, mismatch_action, match_action) = match profile {
+		SeccompProfile::None | SeccompProfile::Default => return Ok(None),
+		SeccompProfile::Strict => (
+			STRICT_BLOCKED_SYSCALLS.to_vec(),
+			SeccompAction::Allow,
+			SeccompAction::Errno(libc::EPERM as u32),
+		),
+		SeccompProfile::AllowList => (
+			[ALLOWED_SYSCALLS, ARCH_ALLOWED_SYSCALLS].concat(),
+			SeccompAction::Errno(libc::EPERM as u32),
+			SeccompAction::Allow,
+		),
+	};
+	// NOTE: an empty rule list matches every invocation of the syscall
+	let rules = syscalls.into_iter().map(|syscall| (syscall, Vec::new()));
+	let filter = SeccompFilter::new(rules.collect(), mismatch_action, match_action, SECCOMP_ARCH)
+		.map_err(convert_err)?;
+	filter.try_into().map(Some).map_err(convert_err)
+}
+fn apply_seccomp_filter(filter: &BpfProgram) -> Result<(), String> {
+	seccompiler::apply_filter(filter)
+		.map_err(|err| format!("Failed to apply the seccomp filter:\n  {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn bpf_programs_build() {
+		get_tty_bpf_program().unwrap();
+		for profile in [
+			SeccompProfile::None,
+			SeccompProfile::Default,
+			SeccompProfile::Strict,
+			SeccompProfile::AllowList,
+		] {
+			let program = get_bpf_program(profile).unwrap();
+			let expects_filter = matches!(profile, SeccompProfile::Strict | SeccompProfile::AllowList);
+			assert_eq!(
+				program.is_some(),
+				expects_filter,
+				"profile `{}`",
+				profile.name()
+			);
+		}
+	}
+
+	#[test]
+	fn deny_inside_of_bind_uses_bind_source() {
+		let source_dir = env::temp_dir().join(format!("skeld-test-{}", std::process::id()));
+		fs::create_dir_all(source_dir.join("secret-dir")).unwrap();
+		fs::write(source_dir.join("secret-file"), "").unwrap();
+		// NOTE: the mount point does not exist on the host
+		let mount_point = Path::new("/nonexistent-skeld-mount-point");
+
+		let mut fs_tree = VirtualFSTree::new();
+		fs_tree
+			.add_bind(
+				mount_point,
+				Some(source_dir.clone()),
+				VirtualFSEntryType::ReadOnly,
+				(),
+			)
+			.unwrap();
+		for name in ["secret-dir", "secret-file", "missing"] {
+			fs_tree
+				.add_path(mount_point.join(name), VirtualFSEntryType::Deny, ())
+				.unwrap();
+		}
+		assert_eq!(
+			fs_tree.backing_path(mount_point.join("secret-dir")),
+			source_dir.join("secret-dir")
+		);
+		let args = get_virtual_fs_args(&fs_tree, None).unwrap();
+		fs::remove_dir_all(&source_dir).unwrap();
+
+		let args = args
+			.iter()
+			.map(|arg| arg.to_str().unwrap())
+			.collect::<Vec<_>>();
+		let dir = mount_point.join("secret-dir");
+		let file = mount_point.join("secret-file");
+		let expected = [
+			"--ro-bind-try",
+			source_dir.to_str().unwrap(),
+			mount_point.to_str().unwrap(),
+			"--tmpfs",
+			dir.to_str().unwrap(),
+			"--remount-ro",
+			dir.to_str().unwrap(),
+			"--ro-bind",
+			"/dev/null",
+			file.to_str().unwrap(),
+		];
+		assert_eq!(args, expected);
+	}
+
+	#[test]
+	fn allow_list_excludes_strict_syscalls() {
+		for syscall in STRICT_BLOCKED_SYSCALLS {
+			assert!(!ALLOWED_SYSCALLS.contains(&syscall));
+			assert!(!ARCH_ALLOWED_SYSCALLS.contains(&syscall));
+		}
+	}
 }
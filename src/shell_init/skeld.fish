@@ -0,0 +1,16 @@
+# shell integration of skeld for fish (generated by `skeld shell-init fish`)
+# `skeld ui` changes into the directory of a project chosen with the cd key
+function skeld
+	if test "$argv[1]" != ui
+		command skeld $argv
+		return
+	end
+	set -l cd_file (mktemp); or return
+	command skeld ui --cd-file $cd_file $argv[2..-1]
+	set -l exit_code $status
+	if test -s $cd_file
+		cd (cat $cd_file); or set exit_code $status
+	end
+	rm -f -- $cd_file
+	return $exit_code
+end
@@ -0,0 +1,18 @@
+# shell integration of skeld for bash and zsh (generated by `skeld shell-init`)
+# `skeld ui` changes into the directory of a project chosen with the cd key
+skeld() {
+	if [ "$1" != ui ]; then
+		command skeld "$@"
+		return
+	fi
+	shift
+	local cd_file exit_code
+	cd_file="$(mktemp)" || return
+	command skeld ui --cd-file "$cd_file" "$@"
+	exit_code=$?
+	if [ -s "$cd_file" ]; then
+		cd -- "$(cat -- "$cd_file")" || exit_code=$?
+	fi
+	rm -f -- "$cd_file"
+	return "$exit_code"
+}
@@ -0,0 +1,13 @@
+use crate::Shell;
+
+// NOTE: bash and zsh share the same script
+const POSIX_SCRIPT: &str = include_str!("shell_init/skeld.sh");
+const FISH_SCRIPT: &str = include_str!("shell_init/skeld.fish");
+
+pub fn run(shell: Shell) {
+	let script = match shell {
+		Shell::Bash | Shell::Zsh => POSIX_SCRIPT,
+		Shell::Fish => FISH_SCRIPT,
+	};
+	print!("{script}");
+}
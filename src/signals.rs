@@ -0,0 +1,109 @@
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+use nix::{
+	sys::signal::{self, SaFlags, SigAction, SigHandler, SigSet, Signal},
+	unistd::Pid,
+};
+
+// signals that terminate skeld, e.g. when the terminal is closed
+const TERMINATION_SIGNALS: [Signal; 2] = [Signal::SIGTERM, Signal::SIGHUP];
+
+// latest received termination signal (0 if none)
+static RECEIVED_SIGNAL: AtomicI32 = AtomicI32::new(0);
+// process to which the termination signals are forwarded (0 if none)
+static FORWARD_PID: AtomicI32 = AtomicI32::new(0);
+// whether SIGTSTP has been received (e.g. from `kill -TSTP`)
+static SUSPEND_REQUESTED: AtomicBool = AtomicBool::new(false);
+// whether SIGCONT has been received, e.g. after being stopped with SIGSTOP
+static CONTINUED: AtomicBool = AtomicBool::new(false);
+
+// NOTE: only async-signal-safe functions may be called here
+extern "C" fn handle_signal(signal: libc::c_int) {
+	RECEIVED_SIGNAL.store(signal, Ordering::SeqCst);
+	let pid = FORWARD_PID.load(Ordering::SeqCst);
+	if pid > 0 {
+		// SAFETY: kill is async-signal-safe
+		unsafe { libc::kill(pid, signal) };
+	}
+}
+extern "C" fn handle_job_control_signal(signal: libc::c_int) {
+	if signal == libc::SIGTSTP {
+		SUSPEND_REQUESTED.store(true, Ordering::SeqCst);
+	} else {
+		CONTINUED.store(true, Ordering::SeqCst);
+	}
+}
+
+// while this guard exists, termination signals are recorded instead of
+// terminating skeld, so that e.g. the terminal can be restored
+pub struct SignalGuard {
+	previous_actions: Vec<(Signal, SigAction)>,
+}
+impl SignalGuard {
+	pub fn new() -> Self {
+		RECEIVED_SIGNAL.store(0, Ordering::SeqCst);
+		let action = SigAction::new(
+			SigHandler::Handler(handle_signal),
+			SaFlags::SA_RESTART,
+			SigSet::empty(),
+		);
+		let mut guard = Self {
+			previous_actions: Vec::new(),
+		};
+		guard.install(&TERMINATION_SIGNALS, &action);
+		guard
+	}
+	// additionally record SIGTSTP and SIGCONT instead of stopping skeld,
+	// so that the terminal can be restored before
+	pub fn with_job_control(mut self) -> Self {
+		SUSPEND_REQUESTED.store(false, Ordering::SeqCst);
+		CONTINUED.store(false, Ordering::SeqCst);
+		let action = SigAction::new(
+			SigHandler::Handler(handle_job_control_signal),
+			SaFlags::SA_RESTART,
+			SigSet::empty(),
+		);
+		self.install(&[Signal::SIGTSTP, Signal::SIGCONT], &action);
+		self
+	}
+	fn install(&mut self, signals: &[Signal], action: &SigAction) {
+		for sig in signals {
+			// SAFETY: the handlers only use async-signal-safe functions
+			if let Ok(previous_action) = unsafe { signal::sigaction(*sig, action) } {
+				self.previous_actions.push((*sig, previous_action));
+			}
+		}
+	}
+	// the termination signal received since the creation of this guard
+	pub fn received_signal(&self) -> Option<i32> {
+		Some(RECEIVED_SIGNAL.load(Ordering::SeqCst)).filter(|signal| *signal != 0)
+	}
+	// whether SIGTSTP has been received since the last call
+	pub fn take_suspend_request(&self) -> bool {
+		SUSPEND_REQUESTED.swap(false, Ordering::SeqCst)
+	}
+	// whether SIGCONT has been received since the last call
+	pub fn take_continued(&self) -> bool {
+		CONTINUED.swap(false, Ordering::SeqCst)
+	}
+	// forward termination signals to the process 'pid' (e.g. the editor)
+	// NOTE: a signal received before is forwarded immediately
+	pub fn forward_to(&self, pid: u32) {
+		FORWARD_PID.store(pid as i32, Ordering::SeqCst);
+		if let Some(signal) = self
+			.received_signal()
+			.and_then(|sig| Signal::try_from(sig).ok())
+		{
+			let _ = signal::kill(Pid::from_raw(pid as i32), signal);
+		}
+	}
+}
+impl Drop for SignalGuard {
+	fn drop(&mut self) {
+		FORWARD_PID.store(0, Ordering::SeqCst);
+		for (sig, action) in &self.previous_actions {
+			// SAFETY: the previous action was valid before
+			let _ = unsafe { signal::sigaction(*sig, action) };
+		}
+	}
+}
@@ -0,0 +1,42 @@
+use std::error::Error;
+
+use crossterm::style::Color;
+
+use crate::{
+	cli_output::{Align, Cell, Table},
+	dirs::Dirs,
+	launch_history, open_stats,
+	status_subcommand::format_elapsed_time,
+	ColorMode,
+};
+
+type ModResult<T> = Result<T, Box<dyn Error>>;
+
+pub fn run(color: ColorMode, dirs: &dyn Dirs) -> ModResult<()> {
+	let mut stats = open_stats::read(dirs)?;
+	if stats.is_empty() {
+		println!("No opened projects have been recorded yet.");
+		return Ok(());
+	}
+
+	let now = launch_history::current_time();
+	// NOTE: the most frequently opened project is shown first
+	stats.sort_by(|a, b| {
+		b.open_count
+			.cmp(&a.open_count)
+			.then(b.last_open.cmp(&a.last_open))
+	});
+	let mut table = Table::new(&[Align::Right, Align::Right, Align::Left]);
+	for entry in stats {
+		table.add_row(vec![
+			Cell::plain(entry.open_count.to_string()),
+			Cell::colored(
+				format_elapsed_time(now.saturating_sub(entry.last_open)),
+				Color::DarkGrey,
+			),
+			Cell::plain(entry.name),
+		]);
+	}
+	table.print(color);
+	Ok(())
+}
@@ -0,0 +1,57 @@
+use std::error::Error;
+
+use crossterm::style::Color;
+
+use crate::{
+	cli_output::{Align, Cell, Table},
+	dirs::Dirs,
+	launch_history::{self, LaunchStatus},
+	ColorMode, StatusArgs,
+};
+
+type ModResult<T> = Result<T, Box<dyn Error>>;
+
+pub fn run(args: StatusArgs, color: ColorMode, dirs: &dyn Dirs) -> ModResult<()> {
+	let launches = launch_history::read(dirs)?;
+	if launches.is_empty() {
+		println!("No detached launches have been recorded yet.");
+		return Ok(());
+	}
+
+	let now = launch_history::current_time();
+	let shown_launches = &launches[launches.len().saturating_sub(args.count)..];
+	let mut table = Table::new(&[Align::Right, Align::Left, Align::Left, Align::Left]);
+	// NOTE: the newest launch is shown first
+	for launch in shown_launches.iter().rev() {
+		let status = match launch.status {
+			LaunchStatus::Running => Cell::colored("running", Color::Green),
+			LaunchStatus::Exited(0) => Cell::plain("exited with 0"),
+			LaunchStatus::Exited(code) => Cell::colored(format!("exited with {code}"), Color::Red),
+			LaunchStatus::Signaled(signal) => {
+				Cell::colored(format!("killed by signal {signal}"), Color::Red)
+			}
+		};
+		let mut row = vec![
+			Cell::colored(
+				format_elapsed_time(now.saturating_sub(launch.start_time)),
+				Color::DarkGrey,
+			),
+			Cell::plain(&launch.name),
+			status,
+		];
+		if !launch.status.success() && !matches!(launch.status, LaunchStatus::Running) {
+			row.push(Cell::plain(format!("(see {})", launch.logfile.display())));
+		}
+		table.add_row(row);
+	}
+	table.print(color);
+	Ok(())
+}
+pub fn format_elapsed_time(secs: u64) -> String {
+	match secs {
+		0..60 => "just now".to_string(),
+		60..3_600 => format!("{}m ago", secs / 60),
+		3_600..86_400 => format!("{}h ago", secs / 3_600),
+		_ => format!("{}d ago", secs / 86_400),
+	}
+}
@@ -0,0 +1,147 @@
+use std::{
+	fs,
+	io::{self, Write as _},
+	path::{Path, PathBuf},
+};
+
+use crossterm::tty::IsTty as _;
+
+use crate::{atomic_file, dirs::Dirs, GenericResult};
+
+// system-wide, imported and project-local files are not written by the user,
+// so their sandbox-weakening options have to be confirmed first
+// NOTE: project-local files are recognized by their location in the project
+pub fn is_foreign_file(path: &Path, dirs: &dyn Dirs) -> bool {
+	let Ok(path) = fs::canonicalize(path) else {
+		return false;
+	};
+	let is_system_wide = dirs
+		.get_skeld_system_data_dirs()
+		.into_iter()
+		.filter_map(|dir| fs::canonicalize(dir).ok())
+		.any(|dir| path.starts_with(dir));
+	is_system_wide || get_import_entry_path(&path, dirs).is_ok_and(|entry| entry.exists())
+}
+// record that 'path' has been added by 'skeld import'
+pub fn mark_imported(path: &Path, dirs: &dyn Dirs) -> GenericResult<()> {
+	let canonical_path = fs::canonicalize(path)
+		.map_err(|err| format!("Failed to canonicalize `{}`: {err}", path.display()))?;
+	let entry_path = get_import_entry_path(&canonical_path, dirs)?;
+	let store_entry = || -> io::Result<()> {
+		fs::create_dir_all(entry_path.parent().unwrap())?;
+		atomic_file::write(&entry_path, "")
+	};
+	store_entry().map_err(|err| {
+		format!(
+			"Failed to store the import entry `{}`: {err}",
+			entry_path.display()
+		)
+	})?;
+	Ok(())
+}
+// keep the import entry of a renamed file
+// NOTE: both paths have to exist
+pub fn move_import_mark(old_path: &Path, new_path: &Path, dirs: &dyn Dirs) -> GenericResult<()> {
+	let canonical_old_path = fs::canonicalize(old_path)
+		.map_err(|err| format!("Failed to canonicalize `{}`: {err}", old_path.display()))?;
+	let old_entry_path = get_import_entry_path(&canonical_old_path, dirs)?;
+	if !old_entry_path.exists() {
+		return Ok(());
+	}
+	mark_imported(new_path, dirs)?;
+	fs::remove_file(&old_entry_path).map_err(|err| {
+		format!(
+			"Failed to remove the import entry `{}`: {err}",
+			old_entry_path.display()
+		)
+	})?;
+	Ok(())
+}
+
+// 'weakenings' are human-readable descriptions of the sandbox-weakening options in 'path',
+// whose trust entry is stored under 'state_key' (see 'ProjectDataFuture::state_key');
+// the user is only asked if 'prompt' is set and stdin is a terminal
+pub fn ensure_trusted(
+	path: &Path,
+	state_key: &str,
+	weakenings: &[String],
+	prompt: bool,
+	dirs: &dyn Dirs,
+) -> GenericResult<()> {
+	if weakenings.is_empty() {
+		return Ok(());
+	}
+
+	let contents =
+		fs::read(path).map_err(|err| format!("Failed to read the file `{}`: {err}", path.display()))?;
+	let trust_entry_path = get_trust_entry_path(state_key, dirs)?;
+	// NOTE: a file is only trusted as long as it is not modified
+	if fs::read(&trust_entry_path).is_ok_and(|trusted_contents| trusted_contents == contents) {
+		return Ok(());
+	}
+
+	let weakenings_str = weakenings
+		.iter()
+		.map(|weakening| format!("- {weakening}"))
+		.collect::<Vec<_>>()
+		.join("\n");
+	// NOTE: untrusted files are rejected if nobody can be asked
+	if !prompt || !io::stdin().is_tty() {
+		return Err(
+			format!(
+				concat!(
+					"The file `{}` is not trusted, but weakens the sandbox:\n{}\n",
+					"Open the project from a terminal to confirm these options."
+				),
+				path.display(),
+				weakenings_str
+			)
+			.into(),
+		);
+	}
+
+	eprint!(
+		"The file `{}` weakens the sandbox:\n{weakenings_str}\nTrust this file? [y/N] ",
+		path.display()
+	);
+	let _ = io::stderr().flush();
+	let mut answer = String::new();
+	io::stdin()
+		.read_line(&mut answer)
+		.map_err(|err| format!("Failed to read the answer: {err}"))?;
+	if !matches!(answer.trim(), "y" | "Y" | "yes") {
+		return Err(format!("Refused to trust the file `{}`", path.display()).into());
+	}
+
+	let store_trust = || -> io::Result<()> {
+		fs::create_dir_all(trust_entry_path.parent().unwrap())?;
+		atomic_file::write(&trust_entry_path, &contents)
+	};
+	store_trust().map_err(|err| {
+		format!(
+			"Failed to store the trust entry `{}`: {err}",
+			trust_entry_path.display()
+		)
+	})?;
+	Ok(())
+}
+// each trusted file has an entry containing the trusted contents
+fn get_trust_entry_path(state_key: &str, dirs: &dyn Dirs) -> GenericResult<PathBuf> {
+	let trust_db_dir = dirs
+		.get_skeld_state_dir()
+		.map_err(|err| format!("Failed to determine the skeld state directory:\n  {err}"))?
+		.join("trusted-files");
+	Ok(trust_db_dir.join(escape_entry_name(state_key)))
+}
+// each imported file has an empty entry named after its canonical path
+fn get_import_entry_path(canonical_path: &Path, dirs: &dyn Dirs) -> GenericResult<PathBuf> {
+	let import_db_dir = dirs
+		.get_skeld_state_dir()
+		.map_err(|err| format!("Failed to determine the skeld state directory:\n  {err}"))?
+		.join("imported-files");
+	Ok(import_db_dir.join(escape_entry_name(&canonical_path.to_string_lossy())))
+}
+// NOTE: '%' is escaped as well, so that different keys never share an entry
+fn escape_entry_name(key: &str) -> String {
+	key.replace('%', "%25").replace('/', "%2F")
+}
@@ -0,0 +1,129 @@
+// rendering of text with FIGlet fonts (see http://www.figlet.org)
+use std::collections::HashMap;
+
+// fonts embedded into skeld: (name, contents)
+const BUILTIN_FONTS: &[(&str, &str)] = &[("block", include_str!("fonts/block.flf"))];
+
+#[derive(Clone, PartialEq)]
+pub struct Font {
+	// character rendered as a space, but not removed by kerning
+	hardblank: char,
+	height: usize,
+	// whether characters are moved together until they touch
+	kerning: bool,
+	chars: HashMap<char, Vec<Vec<char>>>,
+}
+impl Font {
+	pub fn builtin(name: &str) -> Option<Self> {
+		let (_, contents) = BUILTIN_FONTS.iter().find(|(font, _)| *font == name)?;
+		Some(Self::parse(contents).expect("builtin fonts should be valid"))
+	}
+	pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+		BUILTIN_FONTS.iter().map(|(name, _)| *name)
+	}
+	// parse the contents of a '.flf' file
+	// NOTE: only the required characters (ASCII 32-126) are supported,
+	//       the smushing rules are approximated by kerning
+	pub fn parse(contents: &str) -> Result<Self, String> {
+		let mut lines = contents.lines();
+		let header = lines.next().unwrap_or_default();
+		let mut params = header.split_whitespace();
+		let hardblank = params
+			.next()
+			.and_then(|signature| signature.strip_prefix("flf2a"))
+			.and_then(|rest| rest.chars().next())
+			.ok_or("missing FIGlet signature `flf2a`")?;
+		let mut next_param = |name: &str| {
+			params
+				.next()
+				.and_then(|param| param.parse::<i64>().ok())
+				.ok_or_else(|| format!("missing or invalid {name} in the header"))
+		};
+		let height = next_param("height")?;
+		let _baseline = next_param("baseline")?;
+		let _max_length = next_param("maximum line length")?;
+		let old_layout = next_param("layout")?;
+		let comment_line_count = next_param("number of comment lines")?;
+		let height = usize::try_from(height)
+			.ok()
+			.filter(|height| *height > 0)
+			.ok_or("the height must be positive")?;
+
+		let mut lines = lines.skip(comment_line_count.max(0) as usize);
+		let mut chars = HashMap::new();
+		for code in 32..=126u8 {
+			let ch = code as char;
+			let rows = (0..height)
+				.map(|_| lines.next().map(strip_endmarks))
+				.collect::<Option<Vec<_>>>()
+				.ok_or_else(|| format!("the character `{ch}` is incomplete"))?;
+			chars.insert(ch, rows);
+		}
+
+		Ok(Self {
+			hardblank,
+			height,
+			// NOTE: a layout of -1 means full width
+			kerning: old_layout >= 0,
+			chars,
+		})
+	}
+
+	// returns the character that is not supported by the font on failure
+	pub fn render(&self, text: &str) -> Result<String, char> {
+		let mut output = String::new();
+		for text_line in text.lines() {
+			let mut rows = vec![Vec::new(); self.height];
+			for ch in text_line.chars() {
+				let glyph = self.chars.get(&ch).ok_or(ch)?;
+				let overlap = if self.kerning {
+					self.kerning_overlap(&rows, glyph)
+				} else {
+					0
+				};
+				for (row, glyph_row) in rows.iter_mut().zip(glyph) {
+					let overlap_start = row.len() - overlap;
+					for (i, glyph_ch) in glyph_row.iter().enumerate() {
+						match row.get_mut(overlap_start + i) {
+							// NOTE: the overlap is chosen so that either is a space
+							Some(row_ch) if *glyph_ch != ' ' => *row_ch = *glyph_ch,
+							Some(_) => (),
+							None => row.push(*glyph_ch),
+						}
+					}
+				}
+			}
+			for row in rows {
+				let row = row
+					.into_iter()
+					.map(|ch| if ch == self.hardblank { ' ' } else { ch })
+					.collect::<String>();
+				output += row.trim_end();
+				output.push('\n');
+			}
+		}
+		Ok(output)
+	}
+	// number of columns 'glyph' can be moved left without two characters colliding
+	fn kerning_overlap(&self, rows: &[Vec<char>], glyph: &[Vec<char>]) -> usize {
+		let count_spaces =
+			|chars: &mut dyn Iterator<Item = &char>| chars.take_while(|ch| **ch == ' ').count();
+		rows
+			.iter()
+			.zip(glyph)
+			.map(|(row, glyph_row)| {
+				let overlap = count_spaces(&mut row.iter().rev()) + count_spaces(&mut glyph_row.iter());
+				overlap.min(row.len()).min(glyph_row.len())
+			})
+			.min()
+			.unwrap_or(0)
+	}
+}
+// remove the characters marking the end of a line
+fn strip_endmarks(line: &str) -> Vec<char> {
+	let line = line.trim_end();
+	let Some(endmark) = line.chars().last() else {
+		return Vec::new();
+	};
+	line.trim_end_matches(endmark).chars().collect()
+}
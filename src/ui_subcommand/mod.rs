@@ -1,98 +1,714 @@
+pub mod figlet;
 pub mod tui;
 
-use std::process::{Command as OsCommand, ExitCode};
+use std::{
+	collections::HashMap,
+	env, fs,
+	path::Path,
+	process::{Command as OsCommand, ExitCode},
+};
 
 use self::tui::{TuiData, UserSelection};
 use crate::{
-	parse::{ParseContext, PrelimParseState, ProjectDataFuture},
-	GenericResult,
+	dirs::Dirs,
+	error::{exit_code, ExitError, WithExitCode as _},
+	launch_history,
+	open_stats::{self, ProjectSort},
+	parse::{ParseContext, PrelimParseState, ProjectButtonData, ProjectDataFuture},
+	project::{ProjectAction, ProjectData},
+	running,
+	sandbox::{Command as SandboxCommand, EnvVarWhitelist, LogConfig, VirtualFSEntryType},
+	watch::ConfigWatcher,
+	ColorMode, GenericResult, GlobalConfig, PrintSelection, UiArgs,
 };
 
 pub fn run(
 	parse_ctx: &mut ParseContext,
-	global_config: crate::GlobalConfig,
-) -> GenericResult<ExitCode> {
-	let commands = global_config.commands.into_iter().map(|data| tui::Button {
-		keybind: data.keybind,
-		text: data.name,
-		action: Action::Run(data.command),
-	});
+	mut global_config: GlobalConfig,
+	args: UiArgs,
+	color: ColorMode,
+) -> Result<ExitCode, ExitError> {
+	let dirs = parse_ctx.dirs.clone();
+	let stdin_projects = if args.stdin_projects {
+		// NOTE: stdin has been consumed, so nobody can be asked
+		parse_ctx.disable_trust_prompt();
+		parse_ctx.get_stdin_projects()?
+	} else {
+		Vec::new()
+	};
+	let mut tui_data = get_tui_data(parse_ctx, &global_config, &args, color, &stdin_projects)
+		.with_exit_code(exit_code::CONFIG_ERROR)?;
+	// NOTE: without a watcher, changes are only picked up after editing a project
+	let watcher = ConfigWatcher::new(&*dirs).ok();
+	let is_outdated = || watcher.as_ref().is_some_and(ConfigWatcher::has_changed);
+
+	// NOTE: if no project has been opened yet, the tui is shown
+	let mut resumed_action = if args.resume || global_config.resume_on_start {
+		get_last_opened_action(&tui_data, &*dirs)
+	} else {
+		None
+	};
+	let mut selected_button = 0;
+	loop {
+		let selection = match resumed_action.take() {
+			Some(action) => UserSelection::Button(action),
+			None => tui::run(&tui_data, &mut selected_button, Some(&is_outdated))
+				.map_err(|err| err.to_string())?,
+		};
+		let action = match selection {
+			UserSelection::ControlC | UserSelection::Escape if args.print_selection.is_some() => {
+				return Ok(exit_code::USER_ABORT.into());
+			}
+			UserSelection::ControlC | UserSelection::Escape => return Ok(ExitCode::SUCCESS),
+			// NOTE: like in shells, the exit status is 128 + signal
+			UserSelection::Terminated(signal) => return Ok((128 + signal as u8).into()),
+			UserSelection::Reload => {
+				// NOTE: if the reload fails, the previous data is kept
+				let reload_result = parse_ctx.reload(
+					|parse_ctx| {
+						let config = parse_ctx.get_global_config()?;
+						let data = get_tui_data(parse_ctx, &config, &args, color, &stdin_projects)?;
+						Ok((config, data))
+					},
+					|err, files| err.to_plain_string(files),
+				);
+				match reload_result {
+					Ok((config, data)) => (global_config, tui_data) = (config, data),
+					Err(err) => tui_data.error = Some(err),
+				}
+				continue;
+			}
+			UserSelection::Edit(action) => {
+				let edit_result = action
+					.edit(
+						global_config.sandbox_edit,
+						parse_ctx,
+						&global_config.log_config,
+					)
+					.and_then(|()| {
+						let commands = get_commands(&global_config, &args);
+						tui_data.sections = get_sections(
+							parse_ctx,
+							&commands,
+							&stdin_projects,
+							global_config.project_sort,
+							args.show_shadowed,
+							&tui_data.colorscheme,
+						)?;
+						Ok(())
+					});
+				tui_data.error = edit_result
+					.err()
+					.map(|err| err.to_plain_string(parse_ctx.file_database));
+				continue;
+			}
+			UserSelection::Yank { action, file } => {
+				let parse_state = global_config.global_project_data.clone();
+				let yank_result = action
+					.project_path(file, parse_state, parse_ctx)
+					.and_then(|path| {
+						tui::copy_to_clipboard(&path)
+							.map_err(|err| format!("Failed to copy `{path}` to the clipboard: {err}"))?;
+						Ok(path)
+					});
+				tui_data.error = Some(match yank_result {
+					Ok(path) => format!("Copied `{path}` to the clipboard"),
+					Err(err) => err.to_plain_string(parse_ctx.file_database),
+				});
+				continue;
+			}
+			UserSelection::Cd(action) => {
+				let cd_file = args.cd_file.as_ref().unwrap();
+				let parse_state = global_config.global_project_data.clone();
+				// NOTE: the error is displayed in the tui to allow choosing another project
+				let cd_result = action
+					.project_path(false, parse_state, parse_ctx)
+					.and_then(|dir| {
+						fs::write(cd_file, dir)
+							.map_err(|err| format!("Failed to write to `{}`:\n  {err}", cd_file.display()).into())
+					});
+				match cd_result {
+					Ok(()) => return Ok(ExitCode::SUCCESS),
+					Err(err) => tui_data.error = Some(err.to_plain_string(parse_ctx.file_database)),
+				}
+				continue;
+			}
+			UserSelection::Actions(action) => {
+				// NOTE: the project is loaded outside of the tui,
+				//       so that the user can be asked to trust files
+				let parse_state = global_config.global_project_data.clone();
+				let project = match action.load_project(parse_state, parse_ctx) {
+					Ok(project) if project.actions.is_empty() => {
+						tui_data.error = Some(format!("The project `{}` has no actions", project.name));
+						continue;
+					}
+					Ok(project) => project,
+					Err(err) => {
+						tui_data.error = Some(err.to_plain_string(parse_ctx.file_database));
+						continue;
+					}
+				};
+				let menu_data = get_action_menu_data(&tui_data, &project, &*dirs);
+				let entry = match tui::run(&menu_data, &mut 0, None).map_err(|err| err.to_string())? {
+					UserSelection::Button(entry) => entry,
+					UserSelection::ControlC => return Ok(ExitCode::SUCCESS),
+					UserSelection::Terminated(signal) => return Ok((128 + signal as u8).into()),
+					// NOTE: escape returns to the list of projects
+					_ => continue,
+				};
+				global_config.log_config.quiet = args.quiet;
+				return match entry {
+					ActionMenuEntry::Open => project.open(&global_config.log_config, &*dirs),
+					ActionMenuEntry::Run(action) => project.run_command(
+						action.command,
+						false,
+						action.sandbox,
+						&global_config.log_config,
+						&*dirs,
+					),
+				};
+			}
+			UserSelection::Shell(action) => {
+				let parse_state = global_config.global_project_data.clone();
+				match action.load_project(parse_state, parse_ctx) {
+					Ok(project) => {
+						global_config.log_config.quiet = args.quiet;
+						let shell_cmd = global_config
+							.shell_cmd
+							.clone()
+							.unwrap_or_else(|| vec![env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string())]);
+						// NOTE: the shell is sandboxed like the editor
+						return project.run_command(shell_cmd, false, true, &global_config.log_config, &*dirs);
+					}
+					// NOTE: the error is displayed in the tui to allow choosing another project
+					Err(err) => tui_data.error = Some(err.to_plain_string(parse_ctx.file_database)),
+				}
+				continue;
+			}
+			UserSelection::Kill(name) => {
+				let kill_result = running::stop(&name, &*dirs).and_then(|count| {
+					tui_data.sections = get_sections(
+						parse_ctx,
+						&get_commands(&global_config, &args),
+						&stdin_projects,
+						global_config.project_sort,
+						args.show_shadowed,
+						&tui_data.colorscheme,
+					)
+					.map_err(|err| err.to_plain_string(parse_ctx.file_database))?;
+					Ok(count)
+				});
+				tui_data.error = Some(match kill_result {
+					Ok(0) => format!("`{name}` is not running"),
+					Ok(_) => format!("Stopped `{name}`"),
+					Err(err) => err,
+				});
+				continue;
+			}
+			UserSelection::Command { index, action } => {
+				let mut commands = get_selection_commands(&global_config, &args);
+				let command = commands.swap_remove(index);
+				let parse_state = global_config.global_project_data.clone();
+				match action.load_with_command(command, parse_state, parse_ctx) {
+					Ok(action) => {
+						global_config.log_config.quiet = args.quiet;
+						return action.execute(&global_config.log_config, &*dirs);
+					}
+					// NOTE: the error is displayed in the tui to allow choosing another project
+					Err(err) => tui_data.error = Some(err.to_plain_string(parse_ctx.file_database)),
+				}
+				continue;
+			}
+			UserSelection::Button(action) => action,
+		};
+
+		// NOTE: the project is loaded outside of the tui,
+		//       so that the user can be asked to trust files
+		let parse_state = global_config.global_project_data.clone();
+		match action.load(parse_state, parse_ctx) {
+			Ok(action) => {
+				global_config.log_config.quiet = args.quiet;
+				return match args.print_selection {
+					Some(field) => Ok(action.print(field)),
+					None => action.execute(&global_config.log_config, &*dirs),
+				};
+			}
+			// NOTE: the error is displayed in the tui to allow choosing another project
+			Err(err) => tui_data.error = Some(err.to_plain_string(parse_ctx.file_database)),
+		}
+	}
+}
+fn get_tui_data(
+	parse_ctx: &mut ParseContext,
+	global_config: &GlobalConfig,
+	args: &UiArgs,
+	color: ColorMode,
+	stdin_projects: &[ProjectButtonData],
+) -> GenericResult<TuiData<Action>> {
+	let commands = get_commands(global_config, args);
+	let colorscheme = if color.use_color(true) {
+		global_config.colorscheme.clone()
+	} else {
+		tui::Colorscheme::uncolored()
+	};
+	Ok(TuiData {
+		// NOTE: the banner would take most of the space in inline mode
+		banner: if global_config.inline {
+			String::new()
+		} else {
+			global_config.banner.clone()
+		},
+		sections: get_sections(
+			parse_ctx,
+			&commands,
+			stdin_projects,
+			global_config.project_sort,
+			args.show_shadowed,
+			&colorscheme,
+		)?,
+		colorscheme,
+		footer: global_config.footer,
+		icons: global_config.icons,
+		layout: global_config.layout,
+		spacing: global_config.spacing,
+		inline_height: global_config.inline.then_some(global_config.max_height),
+		selection_marker: global_config.selection_marker.clone(),
+		quit_on_escape: global_config.quit_on_escape || args.print_selection.is_some(),
+		error: None,
+		edit_keybind: global_config.edit_keybind.clone(),
+		yank_keybind: global_config.yank_keybind.clone(),
+		selection_commands: get_selection_commands(global_config, args)
+			.into_iter()
+			.map(|data| (data.keybind, data.name))
+			.collect(),
+		actions_keybind: if args.print_selection.is_none() {
+			global_config.actions_keybind.clone()
+		} else {
+			String::new()
+		},
+		shell_keybind: if args.print_selection.is_none() {
+			global_config.shell_keybind.clone()
+		} else {
+			String::new()
+		},
+		kill_keybind: if args.print_selection.is_none() {
+			global_config.kill_keybind.clone()
+		} else {
+			String::new()
+		},
+		move_top_keybind: global_config.move_top_keybind.clone(),
+		move_bottom_keybind: global_config.move_bottom_keybind.clone(),
+		next_section_keybind: global_config.next_section_keybind.clone(),
+		prev_section_keybind: global_config.prev_section_keybind.clone(),
+		wrap_navigation: global_config.wrap_navigation,
+		// NOTE: the cd key requires the shell integration
+		cd_keybind: if args.cd_file.is_some() {
+			global_config.cd_keybind.clone()
+		} else {
+			String::new()
+		},
+		double_click_interval: global_config.double_click_interval,
+	})
+}
+// the action menu of 'project', which looks like the main tui
+fn get_action_menu_data(
+	tui_data: &TuiData<Action>,
+	project: &ProjectData,
+	dirs: &dyn Dirs,
+) -> TuiData<ActionMenuEntry> {
+	let open_button = tui::Button {
+		keybind: "0".to_string(),
+		text: "open editor".to_string(),
+		detail: display_path(&project.project_dir, dirs),
+		description: String::new(),
+		icon: String::new(),
+		running: false,
+		action: ActionMenuEntry::Open,
+	};
+	let action_buttons = project
+		.actions
+		.iter()
+		.enumerate()
+		.map(|(i, action)| tui::Button {
+			keybind: (i + 1).to_string(),
+			text: action.name.clone(),
+			detail: action.command.join(" "),
+			description: String::new(),
+			icon: String::new(),
+			running: false,
+			action: ActionMenuEntry::Run(action.clone()),
+		});
+	TuiData {
+		banner: tui_data.banner.clone(),
+		sections: vec![tui::Section {
+			heading: format!("Actions of {}", project.name),
+			buttons: [open_button].into_iter().chain(action_buttons).collect(),
+			colors: tui::SectionColors::default(),
+		}],
+		colorscheme: tui_data.colorscheme.clone(),
+		footer: tui_data.footer,
+		icons: false,
+		layout: tui_data.layout,
+		spacing: tui_data.spacing,
+		inline_height: tui_data.inline_height,
+		selection_marker: tui_data.selection_marker.clone(),
+		quit_on_escape: true,
+		error: None,
+		edit_keybind: String::new(),
+		yank_keybind: String::new(),
+		cd_keybind: String::new(),
+		actions_keybind: String::new(),
+		shell_keybind: String::new(),
+		kill_keybind: String::new(),
+		move_top_keybind: tui_data.move_top_keybind.clone(),
+		move_bottom_keybind: tui_data.move_bottom_keybind.clone(),
+		next_section_keybind: tui_data.next_section_keybind.clone(),
+		prev_section_keybind: tui_data.prev_section_keybind.clone(),
+		wrap_navigation: tui_data.wrap_navigation,
+		selection_commands: Vec::new(),
+		double_click_interval: tui_data.double_click_interval,
+	}
+}
+// NOTE: commands do not select a project
+fn get_commands(global_config: &GlobalConfig, args: &UiArgs) -> Vec<CommandData> {
+	if args.print_selection.is_none() {
+		global_config.commands.clone()
+	} else {
+		Vec::new()
+	}
+}
+// commands that operate on the selected project instead of being buttons
+fn get_selection_commands(global_config: &GlobalConfig, args: &UiArgs) -> Vec<CommandData> {
+	get_commands(global_config, args)
+		.into_iter()
+		.filter(|data| data.on_selection)
+		.collect()
+}
+// abbreviate the home directory with '~'
+fn display_path(path: &Path, dirs: &dyn Dirs) -> String {
+	match dirs.get_home_dir() {
+		Ok(home_dir) if path.starts_with(&home_dir) => {
+			let relative_path = path.strip_prefix(&home_dir).unwrap();
+			Path::new("~").join(relative_path).display().to_string()
+		}
+		_ => path.display().to_string(),
+	}
+}
+fn get_sections(
+	parse_ctx: &mut ParseContext,
+	commands: &[CommandData],
+	stdin_projects: &[ProjectButtonData],
+	project_sort: ProjectSort,
+	show_shadowed: bool,
+	colorscheme: &tui::Colorscheme,
+) -> GenericResult<Vec<tui::Section<Action>>> {
+	let dirs = parse_ctx.dirs.clone();
+	let commands = commands
+		.iter()
+		.filter(|data| !data.on_selection)
+		.cloned()
+		.map(|data| tui::Button {
+			keybind: data.keybind,
+			text: data.name.clone(),
+			detail: data.command.command.join(" "),
+			description: String::new(),
+			icon: String::new(),
+			running: false,
+			action: Action::Run {
+				name: data.name,
+				command: data.command,
+			},
+		});
 
 	let bookmarks = parse_ctx
 		.get_bookmarks()?
 		.into_iter()
 		.map(|data| tui::Button {
 			keybind: data.keybind,
+			running: !running::find_running(&data.name, &*dirs).is_empty(),
 			text: data.name,
+			detail: display_path(data.project_data.path(), &*dirs),
+			description: data.description.unwrap_or_default(),
+			icon: data.icon.unwrap_or_default(),
 			action: Action::OpenProject(data.project_data),
 		});
 
-	let projects = parse_ctx
-		.get_projects()?
-		.into_iter()
+	let (mut projects, shadowed_projects) = if show_shadowed {
+		parse_ctx.get_projects_with_shadowed()?
+	} else {
+		(parse_ctx.get_projects()?, Vec::new())
+	};
+	sort_projects(&mut projects, project_sort, &*dirs);
+	// NOTE: shadowed projects are listed after the projects shadowing them
+	let projects = projects.into_iter().map(|data| (data, None)).chain(
+		shadowed_projects
+			.into_iter()
+			.map(|(data, shadowing_file)| (data, Some(shadowing_file))),
+	);
+	// NOTE: stdin projects continue the numbering of the projects
+	let mut project_buttons = projects
+		.chain(stdin_projects.iter().cloned().map(|data| (data, None)))
 		.enumerate()
-		.map(|(i, data)| tui::Button {
+		.map(|(i, (data, shadowing_file))| tui::Button {
 			keybind: i.to_string(),
+			running: !running::find_running(&data.name, &*dirs).is_empty(),
 			text: data.name,
+			detail: display_path(data.project_data.path(), &*dirs),
+			description: match shadowing_file {
+				Some(file) => format!("shadowed by {}", display_path(&file, &*dirs)),
+				None => data.description.unwrap_or_default(),
+			},
+			icon: data.icon.unwrap_or_default(),
 			action: Action::OpenProject(data.project_data),
-		});
+		})
+		.collect::<Vec<_>>();
+	let stdin_project_buttons =
+		project_buttons.split_off(project_buttons.len() - stdin_projects.len());
 
 	let sections = [
 		tui::Section {
 			heading: "Commands".to_string(),
 			buttons: commands.collect(),
+			colors: colorscheme.get_section_colors("commands"),
 		},
 		tui::Section {
 			heading: "Bookmarks".to_string(),
 			buttons: bookmarks.collect(),
+			colors: colorscheme.get_section_colors("bookmarks"),
 		},
 		tui::Section {
 			heading: "Projects".to_string(),
-			buttons: projects.collect(),
+			buttons: project_buttons,
+			colors: colorscheme.get_section_colors("projects"),
 		},
-	]
-	.into_iter()
-	.filter(|section| !section.buttons.is_empty());
-
-	let help_text = if global_config.disable_help_text {
-		"".to_string()
-	} else {
-		"Use J/K/Enter/Mouse to navigate".to_string()
-	};
-
-	let tui_data = TuiData {
-		banner: global_config.banner.clone(),
-		colorscheme: global_config.colorscheme.clone(),
-		sections: sections.collect(),
-		help_text,
-	};
+		tui::Section {
+			heading: "Stdin Projects".to_string(),
+			buttons: stdin_project_buttons,
+			colors: colorscheme.get_section_colors("stdin-projects"),
+		},
+	];
+	Ok(
+		sections
+			.into_iter()
+			.filter(|section| !section.buttons.is_empty())
+			.collect(),
+	)
+}
 
-	let action = tui::run(&tui_data).map_err(|err| err.to_string())?;
-	match action {
-		UserSelection::ControlC => Ok(ExitCode::SUCCESS),
-		UserSelection::Button(action) => action.execute(global_config.global_project_data, parse_ctx),
+// the most recently opened project (or bookmark) that still exists
+fn get_last_opened_action(tui_data: &TuiData<Action>, dirs: &dyn Dirs) -> Option<Action> {
+	let mut stats = open_stats::read(dirs).unwrap_or_default();
+	stats.sort_by_key(|entry| std::cmp::Reverse(entry.last_open));
+	let buttons = tui_data
+		.sections
+		.iter()
+		.flat_map(|section| &section.buttons)
+		.filter_map(|button| match &button.action {
+			Action::OpenProject(project) => Some((project.state_key(), button)),
+			Action::Run { .. } => None,
+		})
+		.collect::<Vec<_>>();
+	stats.iter().find_map(|entry| {
+		buttons
+			.iter()
+			.find(|(state_key, button)| entry.is_entry_of(state_key, &button.text))
+			.map(|(_, button)| button.action.clone())
+	})
+}
+// NOTE: projects are already sorted by name
+fn sort_projects(projects: &mut [ProjectButtonData], project_sort: ProjectSort, dirs: &dyn Dirs) {
+	if project_sort == ProjectSort::Name {
+		return;
 	}
+	// NOTE: if the stats cannot be read, the projects are sorted by name
+	let stats = open_stats::read(dirs).unwrap_or_default();
+	let now = launch_history::current_time();
+	let get_score = |project: &ProjectButtonData| {
+		// NOTE: reading the state key may require reading the project file
+		let state_key = project.project_data.state_key();
+		let Some(entry) = stats
+			.iter()
+			.find(|entry| entry.is_entry_of(&state_key, &project.name))
+		else {
+			return 0.0;
+		};
+		match project_sort {
+			ProjectSort::Name => unreachable!(),
+			ProjectSort::Recent => entry.last_open as f64,
+			ProjectSort::Frecency => entry.frecency(now),
+		}
+	};
+	// NOTE: project names are unique, so the scores can be looked up by name
+	let scores = projects
+		.iter()
+		.map(|project| (project.name.clone(), get_score(project)))
+		.collect::<HashMap<_, _>>();
+	// NOTE: the sort is stable, so projects with equal scores stay sorted by name
+	projects.sort_by(|a, b| scores[&b.name].total_cmp(&scores[&a.name]));
 }
 
 #[derive(Clone, Debug)]
 enum Action {
-	Run(Command),
+	Run { name: String, command: Command },
 	OpenProject(ProjectDataFuture),
 }
 impl Action {
-	fn execute(
+	// the project directory or (if 'file' is true) the path of the project file
+	// NOTE: the project is loaded to determine its directory
+	fn project_path(
 		self,
+		file: bool,
 		parse_state: PrelimParseState,
 		ctx: &mut ParseContext,
-	) -> GenericResult<ExitCode> {
-		match self {
-			Action::Run(cmd) => cmd.run(),
+	) -> GenericResult<String> {
+		let path =
+			match self {
+				Action::OpenProject(
+					ProjectDataFuture::Project(path) | ProjectDataFuture::Bookmark(path),
+				) if file => path,
+				Action::OpenProject(ProjectDataFuture::AdHoc { name, .. }) if file => {
+					return Err(format!("The project `{name}` has no project file").into());
+				}
+				Action::OpenProject(ProjectDataFuture::AdHoc { project_dir, .. }) => project_dir,
+				Action::OpenProject(project) => project.load(parse_state, ctx)?.project_dir,
+				Action::Run { name, .. } => {
+					return Err(format!("The command `{name}` has no path").into());
+				}
+			};
+		Ok(path.to_string_lossy().into_owned())
+	}
+	// open the file of the project in '$EDITOR'
+	fn edit(
+		self,
+		sandboxed: bool,
+		parse_ctx: &mut ParseContext,
+		log_config: &LogConfig,
+	) -> GenericResult<()> {
+		let path = match self {
+			Action::OpenProject(ProjectDataFuture::Project(path) | ProjectDataFuture::Bookmark(path)) => {
+				path
+			}
+			Action::OpenProject(ProjectDataFuture::AdHoc { name, .. }) => {
+				return Err(format!("The project `{name}` has no project file").into());
+			}
+			Action::Run { name, .. } => {
+				return Err(format!("The command `{name}` has no project file").into());
+			}
+		};
+		let working_dir = path.parent().unwrap().to_path_buf();
+		let command = SandboxCommand {
+			name: "edit".to_string(),
+			cmd: vec![
+				"sh".to_string(),
+				"-c".to_string(),
+				r#"exec ${EDITOR:-vi} "$1""#.to_string(),
+				"sh".to_string(),
+				path.to_string_lossy().into_owned(),
+			],
+			working_dir: working_dir.clone(),
+			detach: false,
+			terminal: None,
+		};
+
+		let result = if sandboxed {
+			// NOTE: apart from the system (see the base preset), only the directory
+			//       of the project file is exposed, so that the editor can create
+			//       temporary files next to it
+			let mut sandbox_params = parse_ctx.get_base_sandbox_params()?;
+			sandbox_params
+				.fs_tree
+				.add_path(&working_dir, VirtualFSEntryType::ReadWrite, ())
+				.map_err(|_| format!("Failed to whitelist `{}`", working_dir.display()))?;
+			if let EnvVarWhitelist::List(list) = &mut sandbox_params.envvar_whitelist {
+				list.extend(["EDITOR".into(), "VISUAL".into()]);
+			}
+			sandbox_params.run_cmd(command, log_config, &*parse_ctx.dirs)
+		} else {
+			command.run(log_config, &*parse_ctx.dirs)
+		};
+		result.map_err(|err| format!("Failed to edit `{}`:\n  {err}", path.display()))?;
+		Ok(())
+	}
+	fn load(
+		self,
+		parse_state: PrelimParseState,
+		ctx: &mut ParseContext,
+	) -> GenericResult<LoadedAction> {
+		Ok(match self {
+			Action::Run { name, command } => LoadedAction::Run { name, command },
 			Action::OpenProject(project) => {
-				let project_result = project.load(parse_state, ctx)?;
-				project_result.open().map_err(|err| err.to_string().into())
+				LoadedAction::OpenProject(Box::new(project.load(parse_state, ctx)?))
 			}
+		})
+	}
+	fn load_project(
+		self,
+		parse_state: PrelimParseState,
+		ctx: &mut ParseContext,
+	) -> GenericResult<ProjectData> {
+		match self {
+			Action::OpenProject(project) => Ok(project.load(parse_state, ctx)?),
+			Action::Run { name, .. } => Err(format!("The command `{name}` is not a project").into()),
 		}
 	}
+	// load the project to run 'command' on it
+	fn load_with_command(
+		self,
+		command: CommandData,
+		parse_state: PrelimParseState,
+		ctx: &mut ParseContext,
+	) -> GenericResult<LoadedAction> {
+		Ok(LoadedAction::RunInProject {
+			project: Box::new(self.load_project(parse_state, ctx)?),
+			command: command.command,
+		})
+	}
+}
+#[derive(Clone)]
+enum ActionMenuEntry {
+	// open the project like from the main tui
+	Open,
+	Run(ProjectAction),
+}
+enum LoadedAction {
+	Run {
+		name: String,
+		command: Command,
+	},
+	OpenProject(Box<ProjectData>),
+	// a command operating on the project
+	RunInProject {
+		project: Box<ProjectData>,
+		command: Command,
+	},
+}
+impl LoadedAction {
+	fn execute(self, log_config: &LogConfig, dirs: &dyn Dirs) -> Result<ExitCode, ExitError> {
+		match self {
+			LoadedAction::Run { name, command } => command
+				.run(&name, log_config, dirs)
+				.with_exit_code(exit_code::LAUNCH_ERROR),
+			LoadedAction::OpenProject(project_data) => project_data.open(log_config, dirs),
+			LoadedAction::RunInProject { project, command } => project.run_command(
+				command.command,
+				command.detach,
+				command.sandbox,
+				log_config,
+				dirs,
+			),
+		}
+	}
+	fn print(self, field: PrintSelection) -> ExitCode {
+		let LoadedAction::OpenProject(project_data) = self else {
+			// NOTE: commands are not shown when printing the selection
+			return ExitCode::FAILURE;
+		};
+		match field {
+			PrintSelection::Name => println!("{}", project_data.name),
+			PrintSelection::Path => println!("{}", project_data.project_dir.display()),
+		}
+		ExitCode::SUCCESS
+	}
 }
 
 #[derive(Clone)]
@@ -100,15 +716,20 @@ pub struct CommandData {
 	pub name: String,
 	pub keybind: String,
 	pub command: Command,
+	// whether the command operates on the selected project
+	// (i.e. it uses a project placeholder or runs in the sandbox)
+	pub on_selection: bool,
 }
 //TODO: make project's expressive enough to also handle this concept
 #[derive(Clone, Debug)]
 pub struct Command {
 	pub command: Vec<String>,
 	pub detach: bool,
+	// run in the sandbox of the selected project
+	pub sandbox: bool,
 }
 impl Command {
-	fn run(self) -> GenericResult<ExitCode> {
+	fn run(self, name: &str, log_config: &LogConfig, dirs: &dyn Dirs) -> GenericResult<ExitCode> {
 		if self.command.is_empty() {
 			return Ok(ExitCode::SUCCESS);
 		}
@@ -116,7 +737,7 @@ impl Command {
 		let cmd_args = self.command.into_iter().skip(1);
 
 		if self.detach {
-			crate::sandbox::detach_process(true)?;
+			crate::sandbox::detach_process(true, log_config, name, dirs)?;
 		}
 
 		let mut child = OsCommand::new(&cmd)
@@ -125,13 +746,6 @@ impl Command {
 			.map_err(|err| format!("Failed to execute command `{cmd}`: {err}"))?;
 
 		let exit_status = child.wait().unwrap();
-
-		if let Some(code) = exit_status.code() {
-			Ok((code as u8).into())
-		} else if exit_status.success() {
-			Ok(ExitCode::SUCCESS)
-		} else {
-			Ok(ExitCode::FAILURE)
-		}
+		Ok(crate::sandbox::convert_exit_status_to_code(exit_status))
 	}
 }
@@ -1,8 +1,13 @@
 use std::{
+	collections::{BTreeMap, BTreeSet},
 	error::Error,
-	io::{self, Write},
+	fs::OpenOptions,
+	io::{self, Read as _, Write},
 	ops::RangeInclusive,
-	panic, time,
+	os::fd::AsRawFd as _,
+	panic,
+	sync::atomic::{AtomicBool, Ordering},
+	time,
 };
 
 use crossterm::{
@@ -15,16 +20,66 @@ use crossterm::{
 	tty::IsTty as _,
 	ExecutableCommand as _, QueueableCommand as _,
 };
-use unicode_width::UnicodeWidthStr;
+use unicode_width::{UnicodeWidthChar as _, UnicodeWidthStr};
+
+use nix::sys::signal::{self, Signal};
+
+use crate::signals::SignalGuard;
 
 pub use crossterm::style::Color;
 
+// NOTE: also the interval in which received termination signals are checked
+const OUTDATED_CHECK_INTERVAL: time::Duration = time::Duration::from_millis(250);
+// whether the keyboard enhancement flags have been pushed by 'setup_terminal'
+static KEYBOARD_ENHANCED: AtomicBool = AtomicBool::new(false);
+
 #[derive(Clone)]
 pub struct TuiData<U> {
 	pub banner: String,
 	pub sections: Vec<Section<U>>,
 	pub colorscheme: Colorscheme,
-	pub help_text: String,
+	// whether a footer bar with the selected path and keybind hints is shown
+	pub footer: bool,
+	// whether a cell with the icon of each button is shown
+	pub icons: bool,
+	pub layout: Layout,
+	pub spacing: Spacing,
+	// if set, the tui is rendered below the cursor with at most this
+	// many lines instead of on the alternate screen
+	pub inline_height: Option<u16>,
+	// glyph shown in front of the selected button, may be empty
+	pub selection_marker: String,
+	// whether the escape key aborts the tui
+	pub quit_on_escape: bool,
+	// message (e.g. an error) shown at the bottom until the next user input
+	pub error: Option<String>,
+	// key to request editing the selected button (empty if disabled)
+	pub edit_keybind: String,
+	// key to request copying the path of the selected button (empty if disabled)
+	pub yank_keybind: String,
+	// key to request changing into the directory of the selected button
+	// (empty if disabled)
+	pub cd_keybind: String,
+	// key to request the action menu of the selected button (empty if disabled)
+	pub actions_keybind: String,
+	// key to request a shell in the selected button (empty if disabled)
+	pub shell_keybind: String,
+	// key to request stopping the running selected button (empty if disabled)
+	pub kill_keybind: String,
+	// keys to select the first/last button (empty if disabled)
+	pub move_top_keybind: String,
+	pub move_bottom_keybind: String,
+	// keys to select the first button of the next/previous section
+	// (empty if disabled)
+	pub next_section_keybind: String,
+	pub prev_section_keybind: String,
+	// whether moving down from the last button selects the first one
+	// and vice versa
+	pub wrap_navigation: bool,
+	// (keybind, name) of the commands operating on the selected button
+	pub selection_commands: Vec<(String, String)>,
+	// maximum time between the clicks of a double click
+	pub double_click_interval: time::Duration,
 }
 #[derive(Clone, PartialEq, Eq)]
 pub struct Colorscheme {
@@ -33,17 +88,126 @@ pub struct Colorscheme {
 	pub heading: Color,
 	pub keybind: Color,
 	pub button_label: Color,
-	pub background: Color,
+	// None if the background is transparent, i.e. only the cells with text
+	// are redrawn and no background color is set
+	pub background: Option<Color>,
+	pub footer: Color,
+	pub footer_background: Color,
+	// colors of the selected button (the border are its brackets),
+	// None if it is colored like the other buttons
+	pub selected_border: Option<Color>,
+	pub selected_keybind: Option<Color>,
+	pub selected_label: Option<Color>,
+	pub selected_background: Option<Color>,
+	// (section name, overrides) of the sections with different colors
+	// (e.g. `bookmarks`)
+	pub sections: Vec<(String, SectionColors)>,
+}
+impl Colorscheme {
+	// uses the default colors of the terminal
+	pub fn uncolored() -> Self {
+		Self {
+			normal: Color::Reset,
+			banner: Color::Reset,
+			heading: Color::Reset,
+			keybind: Color::Reset,
+			button_label: Color::Reset,
+			background: Some(Color::Reset),
+			footer: Color::Reset,
+			footer_background: Color::Reset,
+			selected_border: None,
+			selected_keybind: None,
+			selected_label: None,
+			selected_background: None,
+			sections: Vec::new(),
+		}
+	}
+	pub fn get_section_colors(&self, section_name: &str) -> SectionColors {
+		self
+			.sections
+			.iter()
+			.find(|(name, _)| name == section_name)
+			.map(|(_, colors)| *colors)
+			.unwrap_or_default()
+	}
+	// the colors of the selected button
+	fn selected(&self) -> Self {
+		Self {
+			normal: self.selected_border.unwrap_or(self.normal),
+			keybind: self.selected_keybind.unwrap_or(self.keybind),
+			button_label: self.selected_label.unwrap_or(self.button_label),
+			sections: Vec::new(),
+			..self.clone()
+		}
+	}
+	// this colorscheme with the overrides of a section applied
+	fn with_section_colors(&self, colors: SectionColors) -> Self {
+		Self {
+			heading: colors.heading.unwrap_or(self.heading),
+			keybind: colors.keybind.unwrap_or(self.keybind),
+			button_label: colors.button_label.unwrap_or(self.button_label),
+			sections: Vec::new(),
+			..self.clone()
+		}
+	}
+}
+// colors of a section overriding those of the colorscheme
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct SectionColors {
+	pub heading: Option<Color>,
+	pub keybind: Option<Color>,
+	pub button_label: Option<Color>,
+}
+// arrangement of the buttons of a section
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+	// one button per line
+	Boxes,
+	// one button per line followed by its detail (e.g. the path)
+	List,
+	// as many buttons per line as fit into the terminal
+	Grid,
+}
+// vertical spacing of the tui (in lines)
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Spacing {
+	// whether the content is centered in the space below the top margin
+	pub center_vertically: bool,
+	// empty lines above the content
+	pub top_margin: u16,
+	// empty lines between sections
+	pub section_gap: u16,
+	// empty lines between the rows of buttons of a section
+	pub button_gap: u16,
+}
+impl Default for Spacing {
+	fn default() -> Self {
+		Self {
+			center_vertically: false,
+			top_margin: 0,
+			section_gap: 2,
+			button_gap: 0,
+		}
+	}
 }
 #[derive(Clone)]
 pub struct Section<U> {
 	pub heading: String,
 	pub buttons: Vec<Button<U>>,
+	pub colors: SectionColors,
 }
 #[derive(Clone)]
 pub struct Button<U> {
 	pub keybind: String,
 	pub text: String,
+	// shown in the status line/footer (e.g. the path)
+	pub detail: String,
+	// shown dimmed beside the button if there is enough space, may be empty
+	pub description: String,
+	// glyph of at most 'ICON_WIDTH' columns, may be empty
+	pub icon: String,
+	// whether the button is marked as running (e.g. an open project)
+	pub running: bool,
 	pub action: U,
 }
 
@@ -58,77 +222,512 @@ impl Error for UiError {}
 
 pub enum UserSelection<U> {
 	Button(U),
+	// the user wants to edit the selected button
+	Edit(U),
+	// the user wants to copy the path of the selected button
+	// (with alt: the path of its file)
+	Yank { action: U, file: bool },
+	// the user wants to change into the directory of the selected button
+	Cd(U),
+	// the user wants to choose an action of the selected button
+	Actions(U),
+	// the user wants to open a shell in the directory of the selected button
+	Shell(U),
+	// the user wants to stop the selected running button (identified by its text)
+	Kill(String),
+	// the user wants to run the command 'index' of 'selection_commands'
+	// on the selected button
+	Command { index: usize, action: U },
 	ControlC,
+	Escape,
+	// the data of the tui is outdated and should be reloaded
+	Reload,
+	// skeld received a termination signal (e.g. SIGHUP if the terminal is closed)
+	Terminated(i32),
 }
-pub fn run<U: Clone>(data: &TuiData<U>) -> Result<UserSelection<U>, UiError> {
-	if !io::stdout().is_tty() {
+// NOTE: 'selected_button' is updated, so that the selection can be
+//       restored when the tui is run again
+// NOTE: 'is_outdated' is checked periodically while waiting for input
+pub fn run<U: Clone>(
+	data: &TuiData<U>,
+	selected_button: &mut usize,
+	is_outdated: Option<&dyn Fn() -> bool>,
+) -> Result<UserSelection<U>, UiError> {
+	if !io::stdout().is_tty() && !io::stderr().is_tty() {
 		return Err(UiError::NoTty);
 	}
 
-	let setup_terminal = || -> io::Result<()> {
-		terminal::enable_raw_mode()?;
-		io::stdout()
-			.queue(terminal::EnterAlternateScreen)?
-			.queue(event::EnableMouseCapture)?
-			.queue(terminal::DisableLineWrap)?
-			.queue(cursor::SavePosition)?
-			.flush()?;
-		Ok(())
+	let inline_height = match data.inline_height {
+		Some(max_height) => Some(get_inline_height(data, max_height)?),
+		None => None,
 	};
-	let restore_terminal = || {
-		let mut stdout = io::stdout();
-		let _ = terminal::disable_raw_mode();
-
-		let _ = stdout.execute(terminal::LeaveAlternateScreen);
-		let _ = stdout.execute(event::DisableMouseCapture);
-		let _ = stdout.execute(terminal::EnableLineWrap);
-		let _ = stdout.execute(cursor::RestorePosition);
-		let _ = stdout.execute(cursor::Show);
-	};
-
-	setup_terminal().inspect_err(|_| restore_terminal())?;
+	let is_inline = inline_height.is_some();
+	// NOTE: installed before the terminal is set up,
+	//       so that it is always restored
+	let signal_guard = SignalGuard::new().with_job_control();
+	let first_row = setup_terminal(inline_height).inspect_err(|_| restore_terminal(is_inline))?;
 	// restore the terminal before a panic is displayed
 	let default_panic_hook = panic::take_hook();
 	panic::set_hook(Box::new(move |info| {
-		restore_terminal();
+		restore_terminal(is_inline);
 		default_panic_hook(info);
 	}));
 
-	let result = protected_run(data);
+	let result = run_with_events(
+		data,
+		selected_button,
+		&mut TerminalEvents {
+			is_outdated,
+			signal_guard: &signal_guard,
+			inline_height,
+			first_row,
+		},
+		&mut tui_output(),
+	);
 
-	restore_terminal();
+	restore_terminal(is_inline);
 	// revert to the default panic hook
 	let _ = panic::take_hook();
+	drop(signal_guard);
 
 	result
 }
-fn protected_run<U: Clone>(data: &TuiData<U>) -> Result<UserSelection<U>, UiError> {
+// returns the first row of the terminal used by the tui
+// NOTE: in inline mode, 'inline_height' lines below the cursor are used,
+//       which are made available by scrolling the terminal if necessary
+fn setup_terminal(inline_height: Option<u16>) -> io::Result<u16> {
+	terminal::enable_raw_mode()?;
+	let mut out = tui_output();
+	let first_row = match inline_height {
+		Some(height) => {
+			// NOTE: in raw mode, a newline does not move to the first column
+			out
+				.queue(style::Print("\n".repeat(height.saturating_sub(1).into())))?
+				.queue(cursor::MoveToColumn(0))?
+				.flush()?;
+			query_cursor_row()?.saturating_sub(height.saturating_sub(1))
+		}
+		None => {
+			out.queue(terminal::EnterAlternateScreen)?;
+			0
+		}
+	};
+	// NOTE: with the kitty keyboard protocol, key combinations with modifiers
+	//       (e.g. ctrl-enter) are reported unambiguously;
+	//       legacy terminals do not respond to the query and are used as before
+	if supports_keyboard_enhancement() {
+		out.queue(event::PushKeyboardEnhancementFlags(
+			event::KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES,
+		))?;
+		KEYBOARD_ENHANCED.store(true, Ordering::SeqCst);
+	}
+	// NOTE: with bracketed paste, pasted text is reported as a whole
+	//       instead of as key presses
+	out
+		.queue(event::EnableMouseCapture)?
+		.queue(event::EnableBracketedPaste)?
+		.queue(terminal::DisableLineWrap)?
+		.queue(cursor::MoveTo(0, first_row))?
+		.queue(cursor::SavePosition)?
+		.flush()?;
+	Ok(first_row)
+}
+fn restore_terminal(is_inline: bool) {
+	let mut stdout = tui_output();
+	let _ = terminal::disable_raw_mode();
+
+	// NOTE: the flags are pushed separately for the alternate screen,
+	//       so they are popped before leaving it
+	if KEYBOARD_ENHANCED.swap(false, Ordering::SeqCst) {
+		let _ = stdout.execute(event::PopKeyboardEnhancementFlags);
+	}
+	if !is_inline {
+		let _ = stdout.execute(terminal::LeaveAlternateScreen);
+	}
+	let _ = stdout.execute(event::DisableMouseCapture);
+	let _ = stdout.execute(event::DisableBracketedPaste);
+	let _ = stdout.execute(terminal::EnableLineWrap);
+	let _ = stdout.execute(cursor::RestorePosition);
+	// NOTE: in inline mode, the prompt continues where the tui started
+	if is_inline {
+		let _ = stdout.execute(style::ResetColor);
+		let _ = stdout.execute(terminal::Clear(terminal::ClearType::FromCursorDown));
+	}
+	let _ = stdout.execute(cursor::Show);
+}
+// the number of lines of the inline tui: the lines required to show 'data'
+// (and the footer), but at most 'max_height' and the terminal height
+fn get_inline_height<U>(data: &TuiData<U>, max_height: u16) -> io::Result<u16> {
+	let (width, height) = terminal::size()?;
+	let content = RenderedContent::new(data, (width, u16::MAX));
+	// NOTE: the footer (or status line) is separated by an empty line
+	let required_height = data.spacing.top_margin as usize + content.plain_text.lines().count() + 2;
+	Ok(max_height.min(height).min(required_height as u16).max(1))
+}
+// the row of the cursor (requires raw mode)
+fn query_cursor_row() -> io::Result<u16> {
+	// the response has the format 'ESC [ <row> ; <col> R'
+	let response = query_terminal(b"\x1b[6n", b'R', 2000)
+		.map_err(|_| io::Error::other("the terminal did not report the cursor position"))?;
+	let response = String::from_utf8_lossy(&response);
+	let row = response
+		.rsplit_once('[')
+		.and_then(|(_, pos)| pos.split_once(';'))
+		.and_then(|(row, _)| row.parse::<u16>().ok())
+		.ok_or_else(|| io::Error::other("the terminal reported an invalid cursor position"))?;
+	// NOTE: the reported row is one-based
+	Ok(row.saturating_sub(1))
+}
+// whether the terminal supports the kitty keyboard protocol (requires raw mode)
+fn supports_keyboard_enhancement() -> bool {
+	// NOTE: the flags are queried ('ESC [ ? <flags> u') together with the
+	//       primary device attributes ('ESC [ ? <attrs> c'), to which all
+	//       terminals respond
+	query_terminal(b"\x1b[?u\x1b[c", b'c', 500).is_ok_and(|response| {
+		let response = String::from_utf8_lossy(&response);
+		response
+			.split('\x1b')
+			.any(|seq| seq.starts_with("[?") && seq.ends_with('u'))
+	})
+}
+// send 'query' to the terminal and read the response up to 'terminator',
+// waiting at most 'timeout_ms' for each byte (requires raw mode)
+// NOTE: crossterm would send the query to stdout, which
+//       may be redirected (e.g. to capture the printed selection)
+fn query_terminal(query: &[u8], terminator: u8, timeout_ms: i32) -> io::Result<Vec<u8>> {
+	let mut tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+	tty.write_all(query)?;
+	let mut response = Vec::new();
+	while response.last() != Some(&terminator) {
+		let mut poll_fd = libc::pollfd {
+			fd: tty.as_raw_fd(),
+			events: libc::POLLIN,
+			revents: 0,
+		};
+		if unsafe { libc::poll(&mut poll_fd, 1, timeout_ms) } <= 0 || response.len() > 64 {
+			return Err(io::Error::other("the terminal did not respond"));
+		}
+		let mut byte = [0];
+		tty.read_exact(&mut byte)?;
+		response.push(byte[0]);
+	}
+	Ok(response)
+}
+// copy 'text' to the clipboard of the terminal with the OSC 52 escape sequence
+// NOTE: the terminal has to support (and allow) this
+pub fn copy_to_clipboard(text: &str) -> io::Result<()> {
+	let mut out = tui_output();
+	write!(out, "\x1b]52;c;{}\x07", base64_encode(text.as_bytes()))?;
+	out.flush()
+}
+fn base64_encode(bytes: &[u8]) -> String {
+	const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+	let mut encoded = String::new();
+	for chunk in bytes.chunks(3) {
+		let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+			group | u32::from(*byte) << (16 - 8 * i)
+		});
+		// NOTE: each byte contributes to two characters, the rest is padded
+		for i in 0..4 {
+			if i <= chunk.len() {
+				encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+			} else {
+				encoded.push('=');
+			}
+		}
+	}
+	encoded
+}
+// NOTE: if stdout is redirected (e.g. to capture the printed selection),
+//       the tui is rendered to stderr instead
+fn tui_output() -> Box<dyn Write> {
+	if io::stdout().is_tty() {
+		Box::new(io::stdout())
+	} else {
+		Box::new(io::stderr())
+	}
+}
+// source of the events handled by the tui
+// NOTE: allows driving the tui with scripted events (e.g. for tests)
+pub trait EventSource {
+	fn read(&mut self) -> io::Result<TuiEvent>;
+	fn terminal_size(&mut self) -> io::Result<(u16, u16)>;
+	// the row of the terminal where the tui starts (e.g. in inline mode)
+	fn first_row(&mut self) -> u16 {
+		0
+	}
+	// whether 'read' would return immediately
+	fn has_pending_event(&mut self) -> io::Result<bool> {
+		Ok(false)
+	}
+	// stop this process like ctrl-z in a shell, returns after it is continued
+	fn suspend(&mut self) -> io::Result<()>;
+}
+pub enum TuiEvent {
+	Terminal(Event),
+	// the displayed data is outdated
+	Outdated,
+	// a termination signal has been received
+	Terminated(i32),
+	// SIGTSTP has been received
+	Suspend,
+	// the tui has to be redrawn, e.g. after being continued
+	Redraw,
+}
+struct TerminalEvents<'a> {
+	is_outdated: Option<&'a dyn Fn() -> bool>,
+	signal_guard: &'a SignalGuard,
+	// see 'TuiData'
+	inline_height: Option<u16>,
+	first_row: u16,
+}
+impl EventSource for TerminalEvents<'_> {
+	fn read(&mut self) -> io::Result<TuiEvent> {
+		loop {
+			// NOTE: a signal may interrupt polling
+			let poll_result = event::poll(OUTDATED_CHECK_INTERVAL);
+			if let Some(signal) = self.signal_guard.received_signal() {
+				return Ok(TuiEvent::Terminated(signal));
+			}
+			if self.signal_guard.take_suspend_request() {
+				return Ok(TuiEvent::Suspend);
+			}
+			if self.signal_guard.take_continued() {
+				return Ok(TuiEvent::Redraw);
+			}
+			if poll_result? {
+				match event::read()? {
+					// NOTE: mouse events are relative to the first row,
+					//       and ignored above it
+					Event::Mouse(mut mouse_event) => {
+						let Some(row) = mouse_event.row.checked_sub(self.first_row()) else {
+							continue;
+						};
+						mouse_event.row = row;
+						return Ok(TuiEvent::Terminal(Event::Mouse(mouse_event)));
+					}
+					event => return Ok(TuiEvent::Terminal(event)),
+				}
+			}
+			if self.is_outdated.is_some_and(|is_outdated| is_outdated()) {
+				return Ok(TuiEvent::Outdated);
+			}
+		}
+	}
+	fn terminal_size(&mut self) -> io::Result<(u16, u16)> {
+		let (width, height) = terminal::size()?;
+		Ok((width, self.inline_height.map_or(height, |h| h.min(height))))
+	}
+	// NOTE: if the terminal has been shrunk, the tui is moved up
+	fn first_row(&mut self) -> u16 {
+		let Some(inline_height) = self.inline_height else {
+			return 0;
+		};
+		let height = terminal::size().map_or(u16::MAX, |(_, height)| height);
+		self.first_row.min(height.saturating_sub(inline_height))
+	}
+	fn has_pending_event(&mut self) -> io::Result<bool> {
+		event::poll(time::Duration::ZERO)
+	}
+	fn suspend(&mut self) -> io::Result<()> {
+		restore_terminal(self.inline_height.is_some());
+		// NOTE: SIGSTOP is used, as SIGTSTP is caught while the tui is shown
+		let stop_result = signal::raise(Signal::SIGSTOP);
+		self.first_row = setup_terminal(self.inline_height)?;
+		// NOTE: the SIGCONT is handled by redrawing anyway
+		self.signal_guard.take_continued();
+		stop_result.map_err(io::Error::from)
+	}
+}
+
+// run the tui loop without setting up the terminal
+// NOTE: the tui is drawn to 'out' with ansi escape sequences
+pub fn run_with_events<U: Clone>(
+	data: &TuiData<U>,
+	selected_button: &mut usize,
+	events: &mut impl EventSource,
+	out: &mut impl Write,
+) -> Result<UserSelection<U>, UiError> {
+	let button_count = data
+		.sections
+		.iter()
+		.map(|sec| sec.buttons.len())
+		.sum::<usize>();
 	let mut state = State {
 		data,
-		rendered_content: RenderedContent::new(data)?,
-		selected_button: 0,
+		rendered_content: RenderedContent::new(data, events.terminal_size()?),
+		selected_button: (*selected_button).min(button_count.saturating_sub(1)),
 		acc_pressed_keys: String::new(),
 		prev_mouse_press: None,
+		error: data.error.as_deref(),
 	};
+	// the frame on the screen, None if the screen has to be cleared completely
+	let mut prev_frame: Option<Frame> = None;
 
 	loop {
-		if terminal::size()? != state.rendered_content.terminal_size {
-			state.rendered_content = RenderedContent::new(state.data)?;
+		let terminal_size = events.terminal_size()?;
+		if terminal_size != state.rendered_content.terminal_size {
+			state.rendered_content = RenderedContent::new(state.data, terminal_size);
+			prev_frame = None;
+		}
+		// NOTE: events that arrive in quick succession (e.g. a held key)
+		//       are handled before the tui is redrawn
+		if !events.has_pending_event()? {
+			prev_frame = Some(state.rendered_content.display(
+				out,
+				events.first_row(),
+				prev_frame.as_ref(),
+				state.selected_button,
+				state.pending_keys(),
+				state.error,
+			)?);
 		}
-		state.rendered_content.display(state.selected_button)?;
 
-		match event::read()? {
+		let event = match events.read()? {
+			TuiEvent::Terminal(event) => event,
+			TuiEvent::Outdated => {
+				*selected_button = state.selected_button;
+				return Ok(UserSelection::Reload);
+			}
+			TuiEvent::Terminated(signal) => return Ok(UserSelection::Terminated(signal)),
+			TuiEvent::Suspend => {
+				events.suspend()?;
+				prev_frame = None;
+				continue;
+			}
+			// NOTE: the tui is redrawn in every iteration
+			TuiEvent::Redraw => {
+				prev_frame = None;
+				continue;
+			}
+		};
+		// NOTE: the error is dismissed by any user input,
+		//       but the input is handled nevertheless
+		if matches!(
+			event,
+			Event::Key(_)
+				| Event::Mouse(MouseEvent {
+					kind: MouseEventKind::Down(_),
+					..
+				})
+		) {
+			state.error = None;
+		}
+		match event {
 			Event::Key(KeyEvent {
 				kind: KeyEventKind::Press,
 				code: KeyCode::Char('c'),
 				modifiers: KeyModifiers::CONTROL,
 				..
 			}) => return Ok(UserSelection::ControlC),
+			// NOTE: in raw mode, ctrl-z does not send SIGTSTP
+			Event::Key(KeyEvent {
+				kind: KeyEventKind::Press,
+				code: KeyCode::Char('z'),
+				modifiers: KeyModifiers::CONTROL,
+				..
+			}) => events.suspend()?,
+			// NOTE: escape first discards the keys of an incomplete keybind
+			Event::Key(KeyEvent {
+				kind: KeyEventKind::Press,
+				code: KeyCode::Esc,
+				..
+			}) if !state.pending_keys().is_empty() => state.acc_pressed_keys.clear(),
+			Event::Key(KeyEvent {
+				kind: KeyEventKind::Press,
+				code: KeyCode::Esc,
+				..
+			}) if data.quit_on_escape => return Ok(UserSelection::Escape),
+			// NOTE: pasted text is ignored, so that it cannot trigger keybinds
+			Event::Paste(_) => {}
 			event => {
 				let choosen_button_action = state.handle_event(&event);
 				if let Some(action) = choosen_button_action {
+					*selected_button = state.selected_button;
 					return Ok(UserSelection::Button(action));
 				}
+				// NOTE: keybinds of buttons take precedence
+				let is_key_press = matches!(
+					event,
+					Event::Key(KeyEvent {
+						kind: KeyEventKind::Press,
+						code: KeyCode::Char(_),
+						..
+					})
+				);
+				if is_key_press
+					&& !data.edit_keybind.is_empty()
+					&& state.acc_pressed_keys.ends_with(&data.edit_keybind)
+				{
+					if let Some(button) = state.buttons().nth(state.selected_button) {
+						*selected_button = state.selected_button;
+						return Ok(UserSelection::Edit(button.action.clone()));
+					}
+				}
+				if is_key_press
+					&& !data.yank_keybind.is_empty()
+					&& state.acc_pressed_keys.ends_with(&data.yank_keybind)
+				{
+					if let Some(button) = state.buttons().nth(state.selected_button) {
+						*selected_button = state.selected_button;
+						let Event::Key(KeyEvent { modifiers, .. }) = event else {
+							unreachable!();
+						};
+						return Ok(UserSelection::Yank {
+							action: button.action.clone(),
+							file: modifiers.contains(KeyModifiers::ALT),
+						});
+					}
+				}
+				if is_key_press
+					&& !data.cd_keybind.is_empty()
+					&& state.acc_pressed_keys.ends_with(&data.cd_keybind)
+				{
+					if let Some(button) = state.buttons().nth(state.selected_button) {
+						*selected_button = state.selected_button;
+						return Ok(UserSelection::Cd(button.action.clone()));
+					}
+				}
+				if is_key_press
+					&& !data.actions_keybind.is_empty()
+					&& state.acc_pressed_keys.ends_with(&data.actions_keybind)
+				{
+					if let Some(button) = state.buttons().nth(state.selected_button) {
+						*selected_button = state.selected_button;
+						return Ok(UserSelection::Actions(button.action.clone()));
+					}
+				}
+				if is_key_press
+					&& !data.shell_keybind.is_empty()
+					&& state.acc_pressed_keys.ends_with(&data.shell_keybind)
+				{
+					if let Some(button) = state.buttons().nth(state.selected_button) {
+						*selected_button = state.selected_button;
+						return Ok(UserSelection::Shell(button.action.clone()));
+					}
+				}
+				if is_key_press
+					&& !data.kill_keybind.is_empty()
+					&& state.acc_pressed_keys.ends_with(&data.kill_keybind)
+				{
+					if let Some(button) = state.buttons().nth(state.selected_button) {
+						*selected_button = state.selected_button;
+						return Ok(UserSelection::Kill(button.text.clone()));
+					}
+				}
+				let selection_command = data
+					.selection_commands
+					.iter()
+					.position(|(keybind, _)| state.acc_pressed_keys.ends_with(keybind));
+				if let Some(index) = selection_command.filter(|_| is_key_press) {
+					if let Some(button) = state.buttons().nth(state.selected_button) {
+						*selected_button = state.selected_button;
+						return Ok(UserSelection::Command {
+							index,
+							action: button.action.clone(),
+						});
+					}
+				}
 			}
 		}
 	}
@@ -138,11 +737,13 @@ struct State<'a, U> {
 	data: &'a TuiData<U>,
 	rendered_content: RenderedContent,
 	selected_button: usize,
-	// accumulated pressed keys
-	// (never cleared, only the end is checked for a match)
+	// accumulated pressed keys (only the end is checked for a match)
+	// NOTE: limited to the length of the longest keybind
 	acc_pressed_keys: String,
 	// prev_mouse_press: Option<(pressed button, _)>
 	prev_mouse_press: Option<(usize, time::Instant)>,
+	// error that has not yet been dismissed
+	error: Option<&'a str>,
 }
 
 impl<U: Clone> State<'_, U> {
@@ -165,6 +766,18 @@ impl<U: Clone> State<'_, U> {
 	fn handle_key_press(&mut self, keycode: KeyCode) -> Option<U> {
 		if let KeyCode::Char(ch) = keycode {
 			self.acc_pressed_keys.push(ch);
+			let max_keybind_len = self
+				.keybinds()
+				.iter()
+				.map(|keybind| keybind.chars().count())
+				.max()
+				.unwrap_or(0);
+			let excess_len = self
+				.acc_pressed_keys
+				.chars()
+				.count()
+				.saturating_sub(max_keybind_len);
+			self.acc_pressed_keys = self.acc_pressed_keys.chars().skip(excess_len).collect();
 		}
 
 		match keycode {
@@ -176,22 +789,78 @@ impl<U: Clone> State<'_, U> {
 			}
 			KeyCode::Char('j') | event::KeyCode::Down => {
 				let max_idx = self.buttons().count().saturating_sub(1);
-				self.selected_button = (self.selected_button + 1).min(max_idx);
+				self.selected_button = if self.data.wrap_navigation && self.selected_button == max_idx {
+					0
+				} else {
+					(self.selected_button + 1).min(max_idx)
+				};
 			}
 			KeyCode::Char('k') | event::KeyCode::Up => {
-				self.selected_button = self.selected_button.saturating_sub(1);
+				let max_idx = self.buttons().count().saturating_sub(1);
+				self.selected_button = if self.data.wrap_navigation && self.selected_button == 0 {
+					max_idx
+				} else {
+					self.selected_button.saturating_sub(1)
+				};
 			}
 			_ => (),
 		};
+		// NOTE: keybinds of buttons take precedence
+		let is_keybind_pressed =
+			|keybind: &str| !keybind.is_empty() && self.acc_pressed_keys.ends_with(keybind);
+		if is_keybind_pressed(&self.data.move_top_keybind) {
+			self.selected_button = 0;
+		} else if is_keybind_pressed(&self.data.move_bottom_keybind) {
+			self.selected_button = self.buttons().count().saturating_sub(1);
+		} else if is_keybind_pressed(&self.data.next_section_keybind) {
+			self.select_section(1);
+		} else if is_keybind_pressed(&self.data.prev_section_keybind) {
+			self.select_section(-1);
+		}
 
 		self.check_for_keybind_match()
 	}
-	fn check_for_keybind_match(&self) -> Option<U> {
-		let pressed_button = self
+	// select the first button of the section 'offset' sections
+	// after (or before) the section of the selected button
+	fn select_section(&mut self, offset: isize) {
+		// the index of the first button of each section
+		let section_starts = self
+			.data
+			.sections
+			.iter()
+			.scan(0, |start, section| {
+				let section_start = *start;
+				*start += section.buttons.len();
+				Some(section_start)
+			})
+			.collect::<Vec<_>>();
+		let Some(current_section) = section_starts
+			.iter()
+			.rposition(|start| *start <= self.selected_button)
+		else {
+			return;
+		};
+		let section_count = section_starts.len() as isize;
+		let mut target_section = current_section as isize + offset;
+		if self.data.wrap_navigation {
+			target_section = target_section.rem_euclid(section_count);
+		}
+		if let Some(start) = usize::try_from(target_section)
+			.ok()
+			.and_then(|section| section_starts.get(section))
+		{
+			self.selected_button = *start;
+		}
+	}
+	fn check_for_keybind_match(&mut self) -> Option<U> {
+		let (idx, pressed_button) = self
 			.buttons()
-			.filter(|button| self.acc_pressed_keys.ends_with(&button.keybind))
-			.max_by_key(|button| button.keybind.len());
-		pressed_button.map(|button| button.action.clone())
+			.enumerate()
+			.filter(|(_, button)| self.acc_pressed_keys.ends_with(&button.keybind))
+			.max_by_key(|(_, button)| button.keybind.len())?;
+		let action = pressed_button.action.clone();
+		self.selected_button = idx;
+		Some(action)
 	}
 
 	fn handle_mouse_press(&mut self, pos: (u16, u16)) -> Option<U> {
@@ -207,12 +876,12 @@ impl<U: Clone> State<'_, U> {
 			return None;
 		};
 
-		const DOUBLE_CLICK_TIME: f64 = 0.5;
+		let double_click_interval = self.data.double_click_interval;
 		if self
 			.prev_mouse_press
 			.as_ref()
 			.is_some_and(|(prev_button, prev_time)| {
-				prev_button == &pressed_button && (now - *prev_time).as_secs_f64() < DOUBLE_CLICK_TIME
+				prev_button == &pressed_button && now - *prev_time < double_click_interval
 			}) {
 			self.prev_mouse_press = None;
 			Some(self.buttons().nth(pressed_button).unwrap().action.clone())
@@ -223,6 +892,48 @@ impl<U: Clone> State<'_, U> {
 		}
 	}
 
+	// all keybinds of the tui (may contain empty ones)
+	fn keybinds(&self) -> Vec<&str> {
+		self
+			.buttons()
+			.map(|button| button.keybind.as_str())
+			.chain([
+				self.data.edit_keybind.as_str(),
+				self.data.yank_keybind.as_str(),
+				self.data.cd_keybind.as_str(),
+				self.data.actions_keybind.as_str(),
+				self.data.shell_keybind.as_str(),
+				self.data.kill_keybind.as_str(),
+				self.data.move_top_keybind.as_str(),
+				self.data.move_bottom_keybind.as_str(),
+				self.data.next_section_keybind.as_str(),
+				self.data.prev_section_keybind.as_str(),
+			])
+			.chain(
+				self
+					.data
+					.selection_commands
+					.iter()
+					.map(|(keybind, _)| keybind.as_str()),
+			)
+			.collect()
+	}
+	// the pressed keys that may be continued to a keybind
+	fn pending_keys(&self) -> &str {
+		let keybinds = self.keybinds();
+		// NOTE: the longest matching suffix is used
+		self
+			.acc_pressed_keys
+			.char_indices()
+			.map(|(i, _)| &self.acc_pressed_keys[i..])
+			.find(|keys| {
+				keybinds
+					.iter()
+					.any(|keybind| keybind.len() > keys.len() && keybind.starts_with(keys))
+			})
+			.unwrap_or("")
+	}
+
 	fn buttons(&self) -> impl Iterator<Item = &Button<U>> {
 		self
 			.data
@@ -232,165 +943,851 @@ impl<U: Clone> State<'_, U> {
 	}
 }
 
+// NOTE: the columns of the grid layout are separated by this many columns
+const GRID_COLUMN_GAP: usize = 4;
+// NOTE: nerd font glyphs are often rendered with two columns, although
+//       their unicode width is one, therefore the icon cell is padded
+pub const ICON_WIDTH: usize = 2;
+// NOTE: shown after the label of running buttons, preceded by a space
+const RUNNING_MARKER: &str = "●";
+impl Layout {
+	// returns (column_count, column_width) for the buttons of one section
+	// NOTE: 'icon_cell_width' is the width of the icon cell (or 0 if hidden)
+	fn columns<U>(
+		self,
+		buttons: &[Button<U>],
+		terminal_width: usize,
+		icon_cell_width: usize,
+	) -> (usize, usize) {
+		match self {
+			Self::Boxes | Self::List => (1, terminal_width),
+			Self::Grid => {
+				let column_width = buttons
+					.iter()
+					.map(|button| {
+						button.keybind.width()
+							+ 3 + icon_cell_width
+							+ button.text.width()
+							+ button.marker_width()
+					})
+					.max()
+					.unwrap_or(0)
+					.min(terminal_width);
+				let column_count =
+					(terminal_width + GRID_COLUMN_GAP) / (column_width + GRID_COLUMN_GAP).max(1);
+				(column_count.max(1), column_width)
+			}
+		}
+	}
+}
+
 // styled and layouted text of the tui
 struct RenderedContent {
 	// terminal size at the time of creation
 	terminal_size: (u16, u16),
-	background_color: Color,
+	// None if the background is transparent
+	background_color: Option<Color>,
 	text: String,
+	// 'text' without colors
+	plain_text: String,
 	left_padding: u16,
+	// the first line of the text (i.e. the top margin and the centering)
+	top_padding: u16,
 	// buttons_clickable_area: Vec<(line, col_range)>
 	buttons_clickable_area: Vec<(u16, RangeInclusive<u16>)>,
-	// - None: help text should not be visible
-	// - Some((pos, text)): render 'text' at 'pos'
-	help_text: Option<((u16, u16), String)>,
+	// for each button the text of the status line shown when it is selected,
+	// None if the label is not truncated or the footer is shown
+	status_lines: Vec<Option<String>>,
+	// for each button the styled text drawn over it when it is selected,
+	// None if it looks the same
+	selected_texts: Vec<Option<String>>,
+	selected_background: Option<Color>,
+	// styled glyph drawn in front of the selected button (with a space)
+	selection_marker: Option<(String, usize)>,
+	footer: Option<Footer>,
+}
+struct Footer {
+	color: Color,
+	background_color: Color,
+	// for each button the text describing the selection
+	selections: Vec<String>,
+	hints: String,
 }
 impl RenderedContent {
-	fn new<U>(content: &TuiData<U>) -> io::Result<Self> {
+	// NOTE: does not access the terminal, so that the layout can be
+	//       computed for arbitrary terminal sizes
+	fn new<U>(content: &TuiData<U>, terminal_size: (u16, u16)) -> Self {
 		let mut text = TextBuilder::new();
 
-		text.push_text(&content.banner, content.colorscheme.banner);
-		text.push_text("\n\n\n", Color::Reset);
+		// NOTE: an empty banner (e.g. in inline mode) takes no space
+		if !content.banner.is_empty() {
+			text.push_text(&content.banner, content.colorscheme.banner);
+			text.push_text("\n\n\n", Color::Reset);
+		}
 
 		let mut buttons_clickable_area = Vec::new();
+		let mut status_lines = Vec::new();
+		let mut selected_texts = Vec::new();
+		let mut selections = Vec::new();
+		let highlight_selection = [
+			content.colorscheme.selected_border,
+			content.colorscheme.selected_keybind,
+			content.colorscheme.selected_label,
+			content.colorscheme.selected_background,
+		]
+		.iter()
+		.any(Option::is_some);
 		for (i, section) in content.sections.iter().enumerate() {
-			text.push_text(&section.heading, content.colorscheme.heading);
+			let colorscheme = content.colorscheme.with_section_colors(section.colors);
+			text.push_text(&section.heading, colorscheme.heading);
 			text.push_text("\n\n", Color::Reset);
-			for button in &section.buttons {
-				buttons_clickable_area.push((text.line_count as u16, 0..=button.keybind.len() as u16 + 1));
-				button.render(&content.colorscheme, &mut text);
+			let terminal_width = terminal_size.0 as usize;
+			// NOTE: the icon is followed by a space
+			let icon_cell_width = if content.icons { ICON_WIDTH + 1 } else { 0 };
+			let (column_count, column_width) =
+				content
+					.layout
+					.columns(&section.buttons, terminal_width, icon_cell_width);
+			for (k, row) in section.buttons.chunks(column_count).enumerate() {
+				if k != 0 {
+					let gap = content.spacing.button_gap as usize;
+					text.push_text(&"\n".repeat(gap), Color::Reset);
+				}
+				for (j, button) in row.iter().enumerate() {
+					let col = j * (column_width + GRID_COLUMN_GAP);
+					buttons_clickable_area.push((
+						text.line_count as u16,
+						col as u16..=(col + button.keybind.len() + 1) as u16,
+					));
+					// NOTE: the keybind is followed by '] ' and preceded by '['
+					let max_label_width = column_width
+						.saturating_sub(button.keybind.width() + 3 + icon_cell_width + button.marker_width());
+					let label = truncate_with_ellipsis(&button.text, max_label_width);
+					let mut is_truncated = label != button.text;
+					// NOTE: the detail is separated from the label by two columns
+					//       and omitted if there is not enough space
+					let detail = (content.layout == Layout::List && !button.detail.is_empty())
+						.then(|| max_label_width.saturating_sub(label.width() + 2))
+						.filter(|max_detail_width| *max_detail_width > 1)
+						.map(|max_detail_width| truncate_with_ellipsis(&button.detail, max_detail_width));
+					if content.layout == Layout::List {
+						is_truncated |= detail.as_ref() != Some(&button.detail);
+					}
+					// NOTE: the description follows the label (and the detail)
+					//       and is also omitted if there is not enough space
+					let description = (content.layout != Layout::Grid && !button.description.is_empty())
+						.then(|| {
+							let detail_width = detail.as_ref().map_or(0, |detail| detail.width() + 2);
+							max_label_width.saturating_sub(label.width() + detail_width + 2)
+						})
+						.filter(|max_description_width| *max_description_width > 1)
+						.map(|max_description_width| {
+							truncate_with_ellipsis(&button.description, max_description_width)
+						});
+					if !button.description.is_empty() {
+						is_truncated |= description.as_ref() != Some(&button.description);
+					}
+					let mut selection = if is_truncated {
+						format!("{}  {}", button.text, button.detail)
+					} else {
+						button.detail.clone()
+					};
+					if !button.description.is_empty() {
+						selection = format!("{selection}  ({})", button.description);
+					}
+					let status_line = (is_truncated && !content.footer)
+						.then(|| truncate_with_ellipsis(&selection, terminal_width));
+					status_lines.push(status_line);
+					selections.push(selection);
+					let selected_text = highlight_selection.then(|| {
+						let mut selected_text = TextBuilder::new();
+						button.render(
+							content.icons,
+							&label,
+							detail.as_deref(),
+							description.as_deref(),
+							&colorscheme.selected(),
+							&mut selected_text,
+						);
+						selected_text.text
+					});
+					selected_texts.push(selected_text);
+					let button_width = button.render(
+						content.icons,
+						&label,
+						detail.as_deref(),
+						description.as_deref(),
+						&colorscheme,
+						&mut text,
+					);
+					if j != row.len() - 1 {
+						let padding = column_width.saturating_sub(button_width) + GRID_COLUMN_GAP;
+						text.push_text(&" ".repeat(padding), Color::Reset);
+					}
+				}
+				text.push_text("\n", Color::Reset);
 			}
-			// NOTE: Trailing newlines would break the overlap check of the help text.
 			if i != content.sections.len() - 1 {
-				text.push_text("\n\n", Color::Reset);
+				let gap = content.spacing.section_gap as usize;
+				text.push_text(&"\n".repeat(gap), Color::Reset);
 			}
 		}
 
-		let terminal_size = terminal::size()?;
 		let left_padding =
 			((terminal_size.0 as f32 - text.max_text_width as f32).max(0.0) * 0.5) as u16;
 
+		// NOTE: the content is centered above the last line,
+		//       which is used by the footer or the status line
+		let mut top_padding = content.spacing.top_margin;
+		if content.spacing.center_vertically {
+			let free_lines = (terminal_size.1 as usize)
+				.saturating_sub(1 + top_padding as usize + text.plain_text.lines().count());
+			top_padding += (free_lines / 2) as u16;
+		}
+
 		let buttons_clickable_area = buttons_clickable_area
 			.into_iter()
 			.map(|(line, range)| {
 				(
-					line,
+					line + top_padding,
 					*range.start() + left_padding..=*range.end() + left_padding,
 				)
 			})
 			.collect();
 
-		let help_text = (|| {
-			if content.help_text.is_empty() {
-				return None;
+		let footer = content.footer.then(|| {
+			let mut hints = vec!["j/k: navigate".to_string(), "enter: open".to_string()];
+			if !content.edit_keybind.is_empty() {
+				hints.push(format!("{}: edit", content.edit_keybind));
 			}
-
-			let is_help_text_printable_ascii = content
-				.help_text
-				.chars()
-				.all(|ch| ch.is_ascii_graphic() || ch == ' ');
-			assert!(is_help_text_printable_ascii);
-
-			let help_text_col = terminal_size
-				.0
-				.checked_sub(content.help_text.len().try_into().ok()?)?;
-			let help_text_pos = (help_text_col, terminal_size.1 - 1);
-
-			// check if the help text would overlap the main text
-			if text.line_count >= terminal_size.1 as usize
-				&& help_text_col as usize <= left_padding as usize + text.max_text_width
-			{
-				return None;
+			if !content.yank_keybind.is_empty() {
+				hints.push(format!("{}: copy path", content.yank_keybind));
 			}
+			if !content.cd_keybind.is_empty() {
+				hints.push(format!("{}: cd", content.cd_keybind));
+			}
+			if !content.actions_keybind.is_empty() {
+				hints.push(format!("{}: actions", content.actions_keybind));
+			}
+			if !content.shell_keybind.is_empty() {
+				hints.push(format!("{}: shell", content.shell_keybind));
+			}
+			if !content.kill_keybind.is_empty() {
+				hints.push(format!("{}: stop", content.kill_keybind));
+			}
+			for (keybind, name) in &content.selection_commands {
+				hints.push(format!("{keybind}: {name}"));
+			}
+			hints.push(if content.quit_on_escape {
+				"esc: quit".to_string()
+			} else {
+				"ctrl-c: quit".to_string()
+			});
+			Footer {
+				color: content.colorscheme.footer,
+				background_color: content.colorscheme.footer_background,
+				selections,
+				hints: hints.join("  "),
+			}
+		});
 
-			Some((help_text_pos, content.help_text.clone()))
-		})();
-
-		Ok(Self {
+		Self {
 			terminal_size,
 			background_color: content.colorscheme.background,
 			left_padding,
+			top_padding,
 			text: text.text,
+			plain_text: text.plain_text,
 			buttons_clickable_area,
-			help_text,
-		})
+			status_lines,
+			selected_texts,
+			selected_background: content.colorscheme.selected_background,
+			selection_marker: (!content.selection_marker.is_empty()).then(|| {
+				let marker_color =
+					(content.colorscheme.selected_keybind).unwrap_or(content.colorscheme.keybind);
+				let mut marker = TextBuilder::new();
+				marker.push_text(&content.selection_marker, marker_color);
+				(marker.text, content.selection_marker.width())
+			}),
+			footer,
+		}
+	}
+	// the position (column, line) of the selection marker,
+	// None if there is no selected button or no space in front of it
+	fn get_selection_marker_pos(&self, selected_button: usize) -> Option<(u16, u16)> {
+		let (_, marker_width) = self.selection_marker.as_ref()?;
+		let (line, range) = self.buttons_clickable_area.get(selected_button)?;
+		let col = range.start().checked_sub(*marker_width as u16 + 1)?;
+		Some((col, *line))
 	}
-	fn display(&self, selected_button: usize) -> io::Result<()> {
-		assert!(terminal::is_raw_mode_enabled()?);
+	// the screen contents as displayed by 'display', but without colors
+	// NOTE: intended for snapshot tests of the layout
+	#[cfg_attr(not(test), expect(unused))]
+	fn to_plain_string(
+		&self,
+		selected_button: usize,
+		pending_keys: &str,
+		error: Option<&str>,
+	) -> String {
+		let (width, height) = (self.terminal_size.0 as usize, self.terminal_size.1 as usize);
+		let mut screen = vec![vec![' '; width]; height];
+		let mut print = |col: usize, line: usize, text: &str| {
+			let Some(screen_line) = screen.get_mut(line) else {
+				return;
+			};
+			for (i, ch) in text.chars().enumerate() {
+				if let Some(cell) = screen_line.get_mut(col + i) {
+					*cell = ch;
+				}
+			}
+		};
 
-		let mut stdout = io::stdout();
+		for (i, line) in self.plain_text.lines().enumerate() {
+			print(
+				self.left_padding as usize,
+				self.top_padding as usize + i,
+				line,
+			);
+		}
+		if let Some((col, line)) = self.get_selection_marker_pos(selected_button) {
+			let (marker, _) = self.selection_marker.as_ref().unwrap();
+			print(col as usize, line as usize, marker);
+		}
+		if let Some(error) = error {
+			let max_line_count = height.saturating_sub(1);
+			let error_lines = error.lines().take(max_line_count).collect::<Vec<_>>();
+			let first_line = height - error_lines.len();
+			for line in first_line.saturating_sub(1)..height {
+				print(0, line, &" ".repeat(width));
+			}
+			for (i, line) in error_lines.iter().enumerate() {
+				print(0, first_line + i, line);
+			}
+		} else if let Some((_, footer_line)) = self.get_footer_line(selected_button, pending_keys) {
+			print(0, height.saturating_sub(1), &footer_line);
+		} else if let Some(status_line) = self.get_status_line(selected_button) {
+			let line = height.saturating_sub(1);
+			print(0, line, &" ".repeat(width));
+			print(0, line, status_line);
+		}
+
+		screen
+			.into_iter()
+			.map(|line| line.into_iter().collect::<String>().trim_end().to_string())
+			.collect::<Vec<_>>()
+			.join("\n")
+	}
+	fn get_status_line(&self, selected_button: usize) -> Option<&str> {
+		self.status_lines.get(selected_button)?.as_deref()
+	}
+	// the footer with the line filling the terminal width
+	fn get_footer_line(
+		&self,
+		selected_button: usize,
+		pending_keys: &str,
+	) -> Option<(&Footer, String)> {
+		let footer = self.footer.as_ref()?;
+		let width = self.terminal_size.0 as usize;
+		let selection = footer
+			.selections
+			.get(selected_button)
+			.map_or("", String::as_str);
+
+		let mut right_text = String::new();
+		if !pending_keys.is_empty() {
+			right_text = format!("keys: {pending_keys}");
+		}
+		// NOTE: the hints are omitted if they would hide the selection
+		if selection.width() + right_text.width() + footer.hints.width() + 6 <= width {
+			if !right_text.is_empty() {
+				right_text += "  ";
+			}
+			right_text += &footer.hints;
+		}
+		// NOTE: the line has a margin of one column on each side
+		//       and the texts are separated by at least one column
+		let left_text = truncate_with_ellipsis(selection, width.saturating_sub(right_text.width() + 3));
+		let gap = width.saturating_sub(left_text.width() + right_text.width() + 2);
+		let line = format!(" {left_text}{}{right_text} ", " ".repeat(gap));
+		Some((footer, truncate_with_ellipsis(&line, width)))
+	}
+	// draws the content and returns the drawn frame
+	// NOTE: the content is drawn relative to the row 'first_row' of the terminal;
+	//       if 'prev_frame' is the frame currently on the screen, only the rows
+	//       that changed are redrawn, instead of clearing the whole screen
+	fn display(
+		&self,
+		stdout: &mut impl Write,
+		first_row: u16,
+		prev_frame: Option<&Frame>,
+		selected_button: usize,
+		pending_keys: &str,
+		error: Option<&str>,
+	) -> io::Result<Frame> {
+		let frame = self.render_frame(first_row, selected_button, pending_keys, error)?;
+		let move_to = |col: u16, row: u16| cursor::MoveTo(col, first_row.saturating_add(row));
+
+		// NOTE: with a transparent background, no background color is set
+		if let Some(background_color) = self.background_color {
+			stdout.queue(style::SetBackgroundColor(background_color))?;
+		}
+		match prev_frame.filter(|prev_frame| prev_frame.first_row == first_row) {
+			Some(prev_frame) => {
+				let changed_rows = prev_frame
+					.rows
+					.keys()
+					.chain(frame.rows.keys())
+					.filter(|row| prev_frame.rows.get(row) != frame.rows.get(row))
+					.collect::<BTreeSet<_>>();
+				for row in changed_rows {
+					stdout
+						.queue(move_to(0, *row))?
+						.queue(terminal::Clear(terminal::ClearType::CurrentLine))?;
+					if let Some(content) = frame.rows.get(row) {
+						stdout.write_all(content)?;
+					}
+				}
+			}
+			None => {
+				stdout
+					.queue(move_to(0, 0))?
+					.queue(terminal::Clear(terminal::ClearType::FromCursorDown))?;
+				for content in frame.rows.values() {
+					stdout.write_all(content)?;
+				}
+			}
+		}
 
-		stdout
-			.queue(style::SetBackgroundColor(self.background_color))?
-			.queue(terminal::Clear(terminal::ClearType::All))?;
+		match frame.cursor_pos {
+			Some((col, row)) => {
+				stdout.queue(cursor::Show)?.queue(move_to(col, row))?;
+			}
+			None => {
+				stdout.queue(cursor::Hide)?;
+			}
+		}
+
+		stdout.flush()?;
+		Ok(frame)
+	}
+	// NOTE: each row is drawn independently after it has been cleared
+	fn render_frame(
+		&self,
+		first_row: u16,
+		selected_button: usize,
+		pending_keys: &str,
+		error: Option<&str>,
+	) -> io::Result<Frame> {
+		let move_to = |col: u16, row: u16| cursor::MoveTo(col, first_row.saturating_add(row));
+		let background_color = self.background_color.unwrap_or(Color::Reset);
+		let mut rows = BTreeMap::<u16, Vec<u8>>::new();
 
 		for (i, line) in self
 			.text
 			.lines()
 			.enumerate()
-			.take(self.terminal_size.1 as usize)
+			.take(self.terminal_size.1.saturating_sub(self.top_padding) as usize)
 		{
-			stdout
-				.queue(cursor::MoveTo(self.left_padding, i as u16))?
+			if line.is_empty() {
+				continue;
+			}
+			let row = self.top_padding + i as u16;
+			rows
+				.entry(row)
+				.or_default()
+				.queue(move_to(self.left_padding, row))?
 				.queue(style::Print(&line))?;
 		}
 
-		if let Some((pos, text)) = &self.help_text {
-			stdout
-				.queue(cursor::MoveTo(pos.0, pos.1))?
+		// NOTE: the selected button is drawn over its unselected version
+		if let (Some((line, range)), Some(Some(selected_text))) = (
+			self.buttons_clickable_area.get(selected_button),
+			self.selected_texts.get(selected_button),
+		) {
+			if *line < self.terminal_size.1 {
+				let content = rows.entry(*line).or_default();
+				content.queue(move_to(*range.start(), *line))?;
+				if let Some(background) = self.selected_background {
+					content.queue(style::SetBackgroundColor(background))?;
+				}
+				content
+					.queue(style::Print(selected_text))?
+					.queue(style::SetBackgroundColor(background_color))?;
+			}
+		}
+		if let Some((col, line)) = self
+			.get_selection_marker_pos(selected_button)
+			.filter(|(_, line)| *line < self.terminal_size.1)
+		{
+			let (marker, _) = self.selection_marker.as_ref().unwrap();
+			rows
+				.entry(line)
+				.or_default()
+				.queue(move_to(col, line))?
+				.queue(style::Print(marker))?;
+		}
+
+		let last_row = self.terminal_size.1.saturating_sub(1);
+		if let Some(error) = error {
+			// NOTE: the error pane is separated from the main text by an empty line
+			//       and is truncated at the end if it is too long
+			let max_line_count = (self.terminal_size.1 as usize).saturating_sub(1);
+			let error_lines = error.lines().take(max_line_count).collect::<Vec<_>>();
+			let first_line = self.terminal_size.1 as usize - error_lines.len();
+			rows.retain(|row, _| (*row as usize) < first_line.saturating_sub(1));
+			for (i, line) in error_lines.iter().enumerate() {
+				let row = (first_line + i) as u16;
+				rows
+					.entry(row)
+					.or_default()
+					.queue(move_to(0, row))?
+					.queue(style::SetForegroundColor(Color::Reset))?
+					.queue(style::Print(line))?;
+			}
+		} else if let Some((footer, footer_line)) = self.get_footer_line(selected_button, pending_keys)
+		{
+			let mut content = Vec::new();
+			content
+				.queue(move_to(0, last_row))?
+				.queue(style::SetBackgroundColor(footer.background_color))?
+				.queue(terminal::Clear(terminal::ClearType::CurrentLine))?
+				.queue(style::SetForegroundColor(footer.color))?
+				.queue(style::Print(footer_line))?
+				.queue(style::SetBackgroundColor(background_color))?;
+			rows.insert(last_row, content);
+		} else if let Some(status_line) = self.get_status_line(selected_button) {
+			let mut content = Vec::new();
+			content
+				.queue(move_to(0, last_row))?
 				.queue(style::SetForegroundColor(Color::Reset))?
-				.queue(style::Print(text))?;
+				.queue(style::Print(status_line))?;
+			rows.insert(last_row, content);
 		}
 
 		let cursor_pos = self
 			.buttons_clickable_area
 			.get(selected_button)
 			.map(|(line, range)| (*range.start() + 1, *line))
-			.unwrap_or((u16::MAX, u16::MAX));
-		if cursor_pos.0 < self.terminal_size.0 && cursor_pos.1 < self.terminal_size.1 {
-			stdout.queue(cursor::Show)?;
-			stdout.queue(cursor::MoveTo(cursor_pos.0, cursor_pos.1))?;
-		} else {
-			stdout.queue(cursor::Hide)?;
-		}
-
-		stdout.flush()?;
-		Ok(())
+			.filter(|(col, line)| *col < self.terminal_size.0 && *line < self.terminal_size.1);
+		Ok(Frame {
+			first_row,
+			rows,
+			cursor_pos,
+		})
 	}
 }
+// the escape sequences drawing the non-empty rows of the tui
+struct Frame {
+	// see 'RenderedContent::display'
+	first_row: u16,
+	// rows relative to 'first_row'
+	rows: BTreeMap<u16, Vec<u8>>,
+	cursor_pos: Option<(u16, u16)>,
+}
 
 impl<U> Button<U> {
-	fn render(&self, colorscheme: &Colorscheme, out: &mut TextBuilder) {
+	// the width of the running marker (including the space before it)
+	fn marker_width(&self) -> usize {
+		if self.running {
+			RUNNING_MARKER.width() + 1
+		} else {
+			0
+		}
+	}
+	// NOTE: 'label' replaces the text of the button (e.g. if it is truncated),
+	//       returns the width of the rendered button
+	fn render(
+		&self,
+		show_icon: bool,
+		label: &str,
+		detail: Option<&str>,
+		description: Option<&str>,
+		colorscheme: &Colorscheme,
+		out: &mut TextBuilder,
+	) -> usize {
 		out.push_text("[", colorscheme.normal);
 		out.push_text(&self.keybind, colorscheme.keybind);
 		out.push_text("] ", colorscheme.normal);
-		out.push_text(&self.text, colorscheme.button_label);
-		out.push_text("\n", Color::Reset);
+		let mut width = self.keybind.width() + 3;
+		if show_icon {
+			out.push_text(&self.icon, colorscheme.button_label);
+			let padding = ICON_WIDTH.saturating_sub(self.icon.width()) + 1;
+			out.push_text(&" ".repeat(padding), Color::Reset);
+			width += ICON_WIDTH + 1;
+		}
+		out.push_text(label, colorscheme.button_label);
+		width += label.width();
+		if self.running {
+			out.push_text(" ", Color::Reset);
+			out.push_text(RUNNING_MARKER, colorscheme.keybind);
+			width += self.marker_width();
+		}
+		if let Some(detail) = detail {
+			out.push_text("  ", Color::Reset);
+			out.push_text(detail, colorscheme.normal);
+			width += 2 + detail.width();
+		}
+		if let Some(description) = description {
+			out.push_text("  ", Color::Reset);
+			out.push_dim_text(description, colorscheme.normal);
+			width += 2 + description.width();
+		}
+		width
+	}
+}
+// shorten 'text' to 'max_width' columns, indicating the truncation with '…'
+fn truncate_with_ellipsis(text: &str, max_width: usize) -> String {
+	if text.width() <= max_width {
+		return text.to_string();
 	}
+	let mut truncated_text = String::new();
+	let mut width = 0;
+	for ch in text.chars() {
+		let ch_width = ch.width().unwrap_or(0);
+		// NOTE: one column is reserved for the ellipsis
+		if width + ch_width + 1 > max_width {
+			break;
+		}
+		truncated_text.push(ch);
+		width += ch_width;
+	}
+	if max_width > 0 {
+		truncated_text.push('…');
+	}
+	truncated_text
 }
 
 // record the maximum width of the text
 // that may be styled with ansi escape sequences
 struct TextBuilder {
 	text: String,
+	plain_text: String,
 	max_text_width: usize,
+	// width of the last line
+	line_width: usize,
 	line_count: usize,
 }
 impl TextBuilder {
 	fn new() -> Self {
 		Self {
 			text: String::new(),
+			plain_text: String::new(),
 			max_text_width: 0,
+			line_width: 0,
 			line_count: 0,
 		}
 	}
 	fn push_text(&mut self, text: &str, color: Color) {
 		use style::Stylize;
 
-		self.text.push_str(&text.with(color).to_string());
-		self.max_text_width = self
-			.max_text_width
-			.max(text.lines().map(UnicodeWidthStr::width).max().unwrap_or(0));
+		self.push_styled_text(text, style::ContentStyle::new().with(color));
+	}
+	fn push_dim_text(&mut self, text: &str, color: Color) {
+		use style::Stylize;
+
+		self.push_styled_text(text, style::ContentStyle::new().with(color).dim());
+	}
+	// NOTE: each line is styled separately, so that it can be drawn on its own
+	fn push_styled_text(&mut self, text: &str, text_style: style::ContentStyle) {
+		for (i, line) in text.split('\n').enumerate() {
+			if i != 0 {
+				self.text.push('\n');
+			}
+			if !line.is_empty() {
+				self.text.push_str(&text_style.apply(line).to_string());
+			}
+		}
+		self.plain_text.push_str(text);
+		// NOTE: a line may consist of multiple pushed texts
+		for (i, line) in text.split('\n').enumerate() {
+			if i != 0 {
+				self.line_width = 0;
+			}
+			self.line_width += line.width();
+			self.max_text_width = self.max_text_width.max(self.line_width);
+		}
 		self.line_count += text.chars().filter(|ch| ch == &'\n').count();
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn button(keybind: &str, text: &str, detail: &str) -> Button<()> {
+		Button {
+			keybind: keybind.to_string(),
+			text: text.to_string(),
+			detail: detail.to_string(),
+			description: String::new(),
+			icon: String::new(),
+			running: false,
+			action: (),
+		}
+	}
+	// buttons with the keybinds `1`, `2`, ... and their index as action
+	fn numbered_buttons(count: usize) -> Vec<Button<usize>> {
+		(0..count)
+			.map(|i| Button {
+				keybind: (i + 1).to_string(),
+				text: format!("button-{i}"),
+				detail: String::new(),
+				description: String::new(),
+				icon: String::new(),
+				running: false,
+				action: i,
+			})
+			.collect()
+	}
+	fn tui_data<U>(layout: Layout, buttons: Vec<Button<U>>) -> TuiData<U> {
+		TuiData {
+			banner: String::new(),
+			sections: vec![Section {
+				heading: "projects".to_string(),
+				buttons,
+				colors: SectionColors::default(),
+			}],
+			colorscheme: Colorscheme::uncolored(),
+			footer: false,
+			icons: false,
+			layout,
+			spacing: Spacing::default(),
+			inline_height: None,
+			selection_marker: String::new(),
+			quit_on_escape: false,
+			error: None,
+			edit_keybind: String::new(),
+			yank_keybind: String::new(),
+			cd_keybind: String::new(),
+			actions_keybind: String::new(),
+			shell_keybind: String::new(),
+			kill_keybind: String::new(),
+			move_top_keybind: String::new(),
+			move_bottom_keybind: String::new(),
+			next_section_keybind: String::new(),
+			prev_section_keybind: String::new(),
+			wrap_navigation: false,
+			selection_commands: Vec::new(),
+			double_click_interval: time::Duration::from_millis(500),
+		}
+	}
+	fn render(data: &TuiData<()>, terminal_size: (u16, u16)) -> String {
+		RenderedContent::new(data, terminal_size).to_plain_string(0, "", None)
+	}
+	// replays 'events' and fails if the tui waits for more
+	struct ScriptedEvents {
+		events: std::collections::VecDeque<Event>,
+		terminal_size: (u16, u16),
+	}
+	impl EventSource for ScriptedEvents {
+		fn read(&mut self) -> io::Result<TuiEvent> {
+			let event = self
+				.events
+				.pop_front()
+				.expect("the tui should have returned");
+			Ok(TuiEvent::Terminal(event))
+		}
+		fn terminal_size(&mut self) -> io::Result<(u16, u16)> {
+			Ok(self.terminal_size)
+		}
+		fn suspend(&mut self) -> io::Result<()> {
+			Ok(())
+		}
+	}
+	fn run_scripted(
+		data: &TuiData<usize>,
+		terminal_size: (u16, u16),
+		events: Vec<Event>,
+	) -> UserSelection<usize> {
+		let mut events = ScriptedEvents {
+			events: events.into(),
+			terminal_size,
+		};
+		let mut selected_button = 0;
+		match run_with_events(data, &mut selected_button, &mut events, &mut Vec::new()) {
+			Ok(selection) => selection,
+			Err(err) => panic!("the tui failed: {err}"),
+		}
+	}
+	fn key(code: KeyCode) -> Event {
+		Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+	}
+	fn left_click((column, row): (u16, u16)) -> Event {
+		Event::Mouse(MouseEvent {
+			kind: MouseEventKind::Down(MouseButton::Left),
+			column,
+			row,
+			modifiers: KeyModifiers::NONE,
+		})
+	}
+
+	#[test]
+	fn grid_columns_have_the_width_of_the_widest_button() {
+		let data = tui_data(
+			Layout::Grid,
+			vec![
+				button("a", "skeld", ""),
+				button("b", "a-longer-name", ""),
+				button("c", "x", ""),
+			],
+		);
+		let expected = [
+			" projects",
+			"",
+			" [a] skeld            [b] a-longer-name",
+			" [c] x",
+			"",
+			"",
+		];
+		assert_eq!(render(&data, (40, 6)), expected.join("\n"));
+	}
+	#[test]
+	fn truncated_labels_show_the_full_text_in_the_status_line() {
+		let data = tui_data(
+			Layout::List,
+			vec![button("a", "a-very-long-project-name", "/tmp/p")],
+		);
+		let expected = ["projects", "", "[a] a-very-long…", "", "a-very-long-pro…"];
+		assert_eq!(render(&data, (16, 5)), expected.join("\n"));
+	}
+	#[test]
+	fn content_is_centered() {
+		let mut data = tui_data(Layout::Boxes, vec![button("a", "skeld", "")]);
+		data.spacing.center_vertically = true;
+		let expected = ["", "", "     projects", "", "     [a] skeld", "", "", ""];
+		assert_eq!(render(&data, (20, 8)), expected.join("\n"));
+	}
+	#[test]
+	fn key_sequence_selects_a_button() {
+		let data = tui_data(Layout::Boxes, numbered_buttons(3));
+		let events = vec![
+			key(KeyCode::Char('j')),
+			key(KeyCode::Char('j')),
+			key(KeyCode::Char('k')),
+			key(KeyCode::Enter),
+		];
+		let selection = run_scripted(&data, (40, 10), events);
+		assert!(matches!(selection, UserSelection::Button(1)));
+
+		let selection = run_scripted(&data, (40, 10), vec![key(KeyCode::Char('3'))]);
+		assert!(matches!(selection, UserSelection::Button(2)));
+	}
+	#[test]
+	fn double_click_chooses_a_button() {
+		let data = tui_data(Layout::Boxes, numbered_buttons(3));
+		let rendered_content = RenderedContent::new(&data, (40, 10));
+		let (line, col_range) = &rendered_content.buttons_clickable_area[2];
+		let pos = (*col_range.start(), *line);
+		// NOTE: a single click only selects the button
+		let events = vec![left_click(pos), key(KeyCode::Enter)];
+		assert!(matches!(
+			run_scripted(&data, (40, 10), events),
+			UserSelection::Button(2)
+		));
+		let events = vec![left_click(pos), left_click(pos)];
+		assert!(matches!(
+			run_scripted(&data, (40, 10), events),
+			UserSelection::Button(2)
+		));
+	}
+}
@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+
+use crate::dirs::Dirs;
+
+// notices changes to the config and data directories of skeld,
+// so that long-running sessions can reload their state
+// NOTE: files outside of these directories (e.g. includes) are not watched
+pub struct ConfigWatcher {
+	inotify: Inotify,
+	watched_dirs: Vec<PathBuf>,
+}
+impl ConfigWatcher {
+	pub fn new(dirs: &dyn Dirs) -> Result<Self, String> {
+		let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)
+			.map_err(|err| format!("Failed to initialize inotify: {err}"))?;
+		let watcher = Self {
+			inotify,
+			watched_dirs: get_watched_dirs(dirs),
+		};
+		watcher.add_watches();
+		Ok(watcher)
+	}
+	// whether a watched file has changed since the last call
+	pub fn has_changed(&self) -> bool {
+		let mut changed = false;
+		// NOTE: reading fails with 'EAGAIN' once all events are consumed
+		while let Ok(events) = self.inotify.read_events() {
+			if events.is_empty() {
+				break;
+			}
+			changed = true;
+		}
+		if changed {
+			// directories may have been created in the meantime
+			self.add_watches();
+		}
+		changed
+	}
+	// NOTE: directories that do not exist are skipped, but the creation of
+	//       the projects/bookmarks directory is noticed in the data directory
+	fn add_watches(&self) {
+		let flags = AddWatchFlags::IN_CLOSE_WRITE
+			| AddWatchFlags::IN_CREATE
+			| AddWatchFlags::IN_DELETE
+			| AddWatchFlags::IN_MOVED_FROM
+			| AddWatchFlags::IN_MOVED_TO;
+		for dir in &self.watched_dirs {
+			let _ = self.inotify.add_watch(dir, flags);
+		}
+	}
+}
+
+fn get_watched_dirs(dirs: &dyn Dirs) -> Vec<PathBuf> {
+	let mut watched_dirs = Vec::new();
+	if let Ok(config_dir) = dirs.get_skeld_config_dir() {
+		watched_dirs.push(config_dir.join("conf.d"));
+		watched_dirs.push(config_dir);
+	}
+	for data_dir in dirs.get_skeld_data_dirs().unwrap_or_default() {
+		watched_dirs.push(data_dir.join("projects"));
+		watched_dirs.push(data_dir.join("bookmarks"));
+		watched_dirs.push(data_dir);
+	}
+	watched_dirs
+}